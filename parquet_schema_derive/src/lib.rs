@@ -0,0 +1,212 @@
+//! `#[derive(ParquetSchema)]` for the state config structs.
+//!
+//! Every field becomes one column, named after the field and in declaration order. How a field is
+//! stored is decided by its type through the `ParquetColumn` trait in
+//! `regenesis_encoding::encoding`, so adding a field only needs its type to implement that trait.
+//! `Option<T>` fields become `OPTIONAL` columns, everything else is `REQUIRED`.
+//!
+//! The codecs that store a config field by field, avro, csv and the columnar bincode, get their
+//! records from the same field list, through `AvroValue`, `CsvField` and `BinaryValue`.
+//!
+//! The generated code refers to `crate::encoding`, the derive is only meant to be used inside
+//! `regenesis_encoding`.
+
+use proc_macro::TokenStream;
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, Data, DeriveInput, Fields};
+
+/// Implements `ParquetSchema` for the struct, `ColumnEncoder` for a slice of it and
+/// `TryFrom<parquet::record::Row>` to read it back. `AvroRecord`, `CsvRecord` and `ColumnarRecord`
+/// are implemented from the same fields.
+#[proc_macro_derive(ParquetSchema)]
+pub fn derive_parquet_schema(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand(input: DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let ident = &input.ident;
+    let Data::Struct(data) = &input.data else {
+        return Err(syn::Error::new_spanned(
+            ident,
+            "ParquetSchema can only be derived for structs",
+        ));
+    };
+    let Fields::Named(fields) = &data.fields else {
+        return Err(syn::Error::new_spanned(
+            ident,
+            "ParquetSchema needs a struct with named fields",
+        ));
+    };
+
+    let names = fields
+        .named
+        .iter()
+        .map(|field| field.ident.as_ref().expect("fields are named"))
+        .collect::<Vec<_>>();
+    let column_names = names
+        .iter()
+        .map(|name| name.to_string())
+        .collect::<Vec<_>>();
+    let types = fields
+        .named
+        .iter()
+        .map(|field| &field.ty)
+        .collect::<Vec<_>>();
+    let indices = 0..names.len();
+    let struct_name = ident.to_string();
+    let columns = names
+        .iter()
+        .map(|name| format_ident!("{name}_column"))
+        .collect::<Vec<_>>();
+
+    Ok(quote! {
+        impl crate::encoding::ParquetSchema for #ident {
+            fn schema() -> ::parquet::schema::types::Type {
+                ::parquet::schema::types::Type::group_type_builder(#struct_name)
+                    .with_fields(vec![
+                        #(::std::sync::Arc::new(
+                            <#types as crate::encoding::ParquetColumn>::column_type(#column_names)
+                        )),*
+                    ])
+                    .build()
+                    .unwrap()
+            }
+        }
+
+        impl crate::encoding::ColumnEncoder for [#ident] {
+            type ElementT = #ident;
+
+            fn encode_column(
+                &self,
+                index: usize,
+                column: &mut ::parquet::file::writer::SerializedColumnWriter<'_>,
+            ) -> Result<(), crate::encoding::CodecError> {
+                match index {
+                    #(#indices => <#types as crate::encoding::ParquetColumn>::write_column(
+                        self.iter().map(|el| &el.#names),
+                        column,
+                    ),)*
+                    unknown_column => {
+                        panic!(
+                            "Unknown column {unknown_column}, doesn't index schema: {:?}",
+                            <#ident as crate::encoding::ParquetSchema>::schema()
+                        )
+                    }
+                }
+            }
+        }
+
+        impl TryFrom<::parquet::record::Row> for #ident {
+            type Error = crate::encoding::CodecError;
+
+            fn try_from(row: ::parquet::record::Row) -> Result<Self, Self::Error> {
+                let mut fields = crate::encoding::RowFields::new(&row);
+                Ok(Self {
+                    #(#names: fields.read::<#types>()?,)*
+                })
+            }
+        }
+
+        impl crate::encoding::AvroRecord for #ident {
+            fn schema() -> ::serde_json::Value {
+                ::serde_json::json!({
+                    "type": "record",
+                    "name": #struct_name,
+                    "fields": [
+                        #({
+                            "name": #column_names,
+                            "type": <#types as crate::encoding::AvroValue>::schema(#column_names),
+                        }),*
+                    ],
+                })
+            }
+
+            fn write(&self, out: &mut Vec<u8>) {
+                #(<#types as crate::encoding::AvroValue>::write(&self.#names, out);)*
+            }
+
+            fn read(input: &mut impl ::std::io::Read) -> ::std::io::Result<Self> {
+                Ok(Self {
+                    #(#names: <#types as crate::encoding::AvroValue>::read(input)?,)*
+                })
+            }
+        }
+
+        impl crate::encoding::CsvRecord for #ident {
+            fn headers() -> Vec<&'static str> {
+                vec![#(#column_names),*]
+            }
+
+            fn to_record(&self) -> Vec<String> {
+                vec![#(<#types as crate::encoding::CsvField>::to_field(&self.#names)),*]
+            }
+
+            fn from_record(
+                record: &::csv::StringRecord,
+            ) -> Result<Self, crate::encoding::CodecError> {
+                let mut cells = crate::encoding::CsvCells::new(record);
+                Ok(Self {
+                    #(#names: cells.read::<#types>(#column_names)?,)*
+                })
+            }
+        }
+
+        impl crate::encoding::ColumnarRecord for #ident {
+            fn encode_columns(
+                rows: &[Self],
+                writer: &mut impl ::std::io::Write,
+            ) -> Result<(), crate::encoding::CodecError> {
+                #(
+                    for row in rows {
+                        <#types as crate::encoding::BinaryValue>::encode_value(&row.#names, writer)?;
+                    }
+                )*
+                Ok(())
+            }
+
+            fn encode_rows(
+                rows: &[Self],
+                writer: &mut impl ::std::io::Write,
+            ) -> Result<(), crate::encoding::CodecError> {
+                for row in rows {
+                    #(<#types as crate::encoding::BinaryValue>::encode_value(&row.#names, writer)?;)*
+                }
+                Ok(())
+            }
+
+            fn decode_columns(
+                len: usize,
+                reader: &mut impl ::std::io::Read,
+            ) -> Result<Vec<Self>, crate::encoding::CodecError> {
+                #(
+                    let mut #columns = (0..len)
+                        .map(|_| <#types as crate::encoding::BinaryValue>::decode_value(reader))
+                        .collect::<Result<Vec<_>, _>>()?
+                        .into_iter();
+                )*
+                let rows = (0..len)
+                    .map(|_| Self {
+                        #(#names: #columns.next().expect("every column holds `len` values"),)*
+                    })
+                    .collect();
+                Ok(rows)
+            }
+
+            fn decode_rows(
+                len: usize,
+                reader: &mut impl ::std::io::Read,
+            ) -> Result<Vec<Self>, crate::encoding::CodecError> {
+                (0..len)
+                    .map(|_| {
+                        Ok(Self {
+                            #(#names: <#types as crate::encoding::BinaryValue>::decode_value(reader)?,)*
+                        })
+                    })
+                    .collect()
+            }
+        }
+    })
+}
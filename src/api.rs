@@ -1,22 +1,119 @@
 use std::{
+    fs::File,
     io::{BufRead, BufReader, BufWriter, Cursor, IntoInnerError, Read, Seek, Write},
+    path::Path,
     sync::{atomic::AtomicU64, Arc},
 };
 
 use bincode::config::{Configuration, LittleEndian, NoLimit, Varint};
+use flate2::{bufread::GzDecoder, write::GzEncoder, Compression};
 use itertools::Itertools;
-use serde::de::DeserializeOwned;
+use serde::{de::DeserializeOwned, Serialize};
+
+/// How a single batch is laid out in the stream. A batch has to be readable without knowing where
+/// it ends, `StateReader` only ever tells the codec where it starts.
+pub trait BatchCodec {
+    fn write_batch<T: Serialize>(&self, batch: Vec<T>, dest: &mut impl Write)
+        -> anyhow::Result<()>;
+    fn read_batch<T: DeserializeOwned>(&self, source: &mut impl BufRead) -> anyhow::Result<Vec<T>>;
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct BincodeBatchCodec;
+
+impl BatchCodec for BincodeBatchCodec {
+    fn write_batch<T: Serialize>(
+        &self,
+        batch: Vec<T>,
+        dest: &mut impl Write,
+    ) -> anyhow::Result<()> {
+        bincode::serde::encode_into_std_write(
+            batch,
+            dest,
+            Configuration::<LittleEndian, Varint, NoLimit>::default(),
+        )?;
+        Ok(())
+    }
+
+    fn read_batch<T: DeserializeOwned>(&self, source: &mut impl BufRead) -> anyhow::Result<Vec<T>> {
+        Ok(bincode::serde::decode_from_std_read(
+            source,
+            Configuration::<LittleEndian, Varint, NoLimit>::default(),
+        )?)
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct MessagePackBatchCodec;
+
+impl BatchCodec for MessagePackBatchCodec {
+    fn write_batch<T: Serialize>(
+        &self,
+        batch: Vec<T>,
+        dest: &mut impl Write,
+    ) -> anyhow::Result<()> {
+        rmp_serde::encode::write(dest, &batch)?;
+        Ok(())
+    }
+
+    fn read_batch<T: DeserializeOwned>(&self, source: &mut impl BufRead) -> anyhow::Result<Vec<T>> {
+        Ok(rmp_serde::decode::from_read(source)?)
+    }
+}
+
+/// Gzips every batch on its own so each one can still be seeked to and decompressed without
+/// touching the batches before it.
+#[derive(Debug, Clone, Copy)]
+pub struct GzipBatchCodec<C = BincodeBatchCodec> {
+    inner: C,
+    level: Compression,
+}
+
+impl Default for GzipBatchCodec {
+    fn default() -> Self {
+        Self::new(BincodeBatchCodec, Compression::default())
+    }
+}
+
+impl<C> GzipBatchCodec<C> {
+    pub fn new(inner: C, level: Compression) -> Self {
+        Self { inner, level }
+    }
+}
 
-use crate::serde_types::CoinConfig;
+impl<C: BatchCodec> BatchCodec for GzipBatchCodec<C> {
+    fn write_batch<T: Serialize>(
+        &self,
+        batch: Vec<T>,
+        dest: &mut impl Write,
+    ) -> anyhow::Result<()> {
+        let mut encoder = GzEncoder::new(dest, self.level);
+        self.inner.write_batch(batch, &mut encoder)?;
+        encoder.finish()?;
+        Ok(())
+    }
+
+    fn read_batch<T: DeserializeOwned>(&self, source: &mut impl BufRead) -> anyhow::Result<Vec<T>> {
+        // the `bufread` decoder only consumes the gzip member it decodes, leaving `source` right
+        // at the start of the next batch
+        let mut decoder = GzDecoder::new(source);
+        let batch = self.inner.read_batch(&mut BufReader::new(&mut decoder))?;
+        // the inner codec stops as soon as it has its batch, drain the gzip trailer as well
+        std::io::copy(&mut decoder, &mut std::io::sink())?;
+        Ok(batch)
+    }
+}
 
 /// So you don't have to work with files all the time. Useful for testing.
-struct InMemorySource {
+pub struct InMemorySource<C = BincodeBatchCodec> {
     // The encoded data inside a `Cursor`. Note this is not our cursor i.e. progress tracker, but
     // rather something rust provides so that you may mimic a file using only a Vec<u8>
     data: Cursor<Vec<u8>>,
     // also has a handy field containing the cursors of all batches encoded in `self.data`. Useful
     // for testing
     element_cursors: Vec<u64>,
+    // the codec the batches were written with, needed to read them back
+    codec: C,
 }
 
 /// So that we may keep track of how many bytes were written. Needed for `InMemorySource`.
@@ -62,6 +159,16 @@ impl InMemorySource {
     pub fn new<T: serde::Serialize>(
         entries: impl IntoIterator<Item = T>,
         batch_size: usize,
+    ) -> std::io::Result<Self> {
+        Self::with_codec(entries, batch_size, BincodeBatchCodec)
+    }
+}
+
+impl<C: BatchCodec + Clone> InMemorySource<C> {
+    pub fn with_codec<T: serde::Serialize>(
+        entries: impl IntoIterator<Item = T>,
+        batch_size: usize,
+        codec: C,
     ) -> std::io::Result<Self> {
         let buffer = Cursor::new(vec![]);
 
@@ -69,7 +176,7 @@ impl InMemorySource {
         // this allows us to give up ownership of `writer` but still be able to peek inside it
         let bytes_written = writer.written_bytes();
 
-        let mut writer = StateWriter::new(writer);
+        let mut writer = StateWriter::with_codec(writer, codec.clone());
         let element_cursors = entries
             .into_iter()
             .chunks(batch_size)
@@ -93,22 +200,22 @@ impl InMemorySource {
             // all the data. Also we did a bunch of flushing above
             data: writer.into_inner()?.into_inner(),
             element_cursors,
+            codec,
         })
     }
 
-    // useful for tests so we don't have to hardcode boundaries
-    pub fn batch_cursors(&self) -> &[u64] {
-        &self.element_cursors
+    pub fn codec(&self) -> C {
+        self.codec.clone()
     }
 }
 
-impl Read for InMemorySource {
+impl<C> Read for InMemorySource<C> {
     fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
         self.data.read(buf)
     }
 }
 
-impl Seek for InMemorySource {
+impl<C> Seek for InMemorySource<C> {
     fn seek(&mut self, pos: std::io::SeekFrom) -> std::io::Result<u64> {
         self.data.seek(pos)
     }
@@ -122,7 +229,10 @@ struct TrackingBuffReader<T> {
 
 impl<T: Seek> Seek for TrackingBuffReader<T> {
     fn seek(&mut self, pos: std::io::SeekFrom) -> std::io::Result<u64> {
-        self.source.seek(pos)
+        // keep `amount_read` pointing at the absolute position so cursors survive a seek
+        let position = self.source.seek(pos)?;
+        self.amount_read = position;
+        Ok(position)
     }
 }
 
@@ -149,15 +259,36 @@ impl<T: Read> Read for TrackingBuffReader<T> {
     }
 }
 
-struct StateReader<R> {
+impl<T: Read> BufRead for TrackingBuffReader<T> {
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        self.source.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.amount_read += amt as u64;
+        self.source.consume(amt)
+    }
+}
+
+pub struct StateReader<R, C = BincodeBatchCodec> {
     source: TrackingBuffReader<R>,
+    codec: C,
 }
 
 impl<R: Read + Seek> StateReader<R> {
     pub fn new(source: R, start_cursor: u64) -> std::io::Result<Self> {
+        Self::with_codec(source, start_cursor, BincodeBatchCodec)
+    }
+}
+
+impl<R: Read + Seek, C: BatchCodec> StateReader<R, C> {
+    pub fn with_codec(source: R, start_cursor: u64, codec: C) -> std::io::Result<Self> {
         let mut reader = TrackingBuffReader::new(source);
         reader.seek(std::io::SeekFrom::Start(start_cursor))?;
-        Ok(Self { source: reader })
+        Ok(Self {
+            source: reader,
+            codec,
+        })
     }
 
     pub fn batch_cursor(&self) -> u64 {
@@ -166,10 +297,7 @@ impl<R: Read + Seek> StateReader<R> {
 
     pub fn read_batch<T: DeserializeOwned>(&mut self) -> anyhow::Result<Vec<T>> {
         let coins = if self.source.has_data_left()? {
-            bincode::serde::decode_from_std_read(
-                &mut self.source,
-                Configuration::<LittleEndian, Varint, NoLimit>::default(),
-            )?
+            self.codec.read_batch(&mut self.source)?
         } else {
             vec![]
         };
@@ -178,28 +306,146 @@ impl<R: Read + Seek> StateReader<R> {
     }
 }
 
-struct StateWriter<W: Write> {
+impl<R: Read + Seek + BatchIndex, C: BatchCodec> StateReader<R, C> {
+    /// Jumps straight to the batch at `index` and reads it, no need to scan the batches before it.
+    pub fn read_batch_at<T: DeserializeOwned>(&mut self, index: usize) -> anyhow::Result<Vec<T>> {
+        let cursor = self
+            .source
+            .source
+            .get_ref()
+            .batch_cursors()
+            .get(index)
+            .copied()
+            .ok_or_else(|| anyhow::anyhow!("there is no batch with index {index}"))?;
+        self.source.seek(std::io::SeekFrom::Start(cursor))?;
+        self.read_batch()
+    }
+}
+
+impl StateReader<IndexedFile> {
+    /// Opens a snapshot written by `StateWriter::finish_with_index`.
+    pub fn open_with_index(
+        data_path: impl AsRef<Path>,
+        idx_path: impl AsRef<Path>,
+    ) -> anyhow::Result<Self> {
+        Self::open_with_index_and_codec(data_path, idx_path, BincodeBatchCodec)
+    }
+}
+
+impl<C: BatchCodec> StateReader<IndexedFile, C> {
+    pub fn open_with_index_and_codec(
+        data_path: impl AsRef<Path>,
+        idx_path: impl AsRef<Path>,
+        codec: C,
+    ) -> anyhow::Result<Self> {
+        let source = IndexedFile::open(data_path, idx_path)?;
+        Ok(Self::with_codec(source, 0, codec)?)
+    }
+}
+
+/// A source that knows where each of its batches starts.
+pub trait BatchIndex {
+    fn batch_cursors(&self) -> &[u64];
+}
+
+impl<C> BatchIndex for InMemorySource<C> {
+    fn batch_cursors(&self) -> &[u64] {
+        &self.element_cursors
+    }
+}
+
+/// A snapshot file along with the batch offsets loaded from its `.idx` sidecar.
+pub struct IndexedFile {
+    file: File,
+    batch_cursors: Vec<u64>,
+}
+
+impl IndexedFile {
+    pub fn open(data_path: impl AsRef<Path>, idx_path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let index = std::fs::read(idx_path)?;
+        if index.len() % 8 != 0 {
+            anyhow::bail!("index length {} is not a multiple of 8 bytes", index.len());
+        }
+        let batch_cursors = index
+            .chunks_exact(8)
+            .map(|offset| u64::from_le_bytes(offset.try_into().expect("chunks are 8 bytes")))
+            .collect();
+
+        Ok(Self {
+            file: File::open(data_path)?,
+            batch_cursors,
+        })
+    }
+}
+
+impl BatchIndex for IndexedFile {
+    fn batch_cursors(&self) -> &[u64] {
+        &self.batch_cursors
+    }
+}
+
+impl Read for IndexedFile {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.file.read(buf)
+    }
+}
+
+impl Seek for IndexedFile {
+    fn seek(&mut self, pos: std::io::SeekFrom) -> std::io::Result<u64> {
+        self.file.seek(pos)
+    }
+}
+
+pub struct StateWriter<W: Write, C = BincodeBatchCodec> {
     dest: BufWriter<W>,
+    codec: C,
+    // bytes written so far, `dest` is assumed to start out empty
+    position: u64,
+    batch_cursors: Vec<u64>,
 }
 
 use std::fmt::Debug;
 impl<W: Write + Debug> StateWriter<W> {
     pub fn new(dest: W) -> Self {
+        Self::with_codec(dest, BincodeBatchCodec)
+    }
+}
+
+impl<W: Write + Debug, C: BatchCodec> StateWriter<W, C> {
+    pub fn with_codec(dest: W, codec: C) -> Self {
         Self {
             dest: BufWriter::new(dest),
+            codec,
+            position: 0,
+            batch_cursors: vec![],
         }
     }
 
     pub fn write_batch(&mut self, coins: Vec<impl serde::Serialize>) -> anyhow::Result<()> {
-        bincode::serde::encode_into_std_write(
-            coins,
-            &mut self.dest,
-            Configuration::<LittleEndian, Varint, NoLimit>::default(),
-        )?;
+        let mut writer = TrackingWriter::new(&mut self.dest);
+        let written = writer.written_bytes();
+        self.codec.write_batch(coins, &mut writer)?;
 
+        self.batch_cursors.push(self.position);
+        self.position += written.load(std::sync::atomic::Ordering::Relaxed);
         Ok(())
     }
 
+    /// Flushes the data and writes the offset of every batch written so far to `idx_path` as
+    /// little endian u64s. Read it back with `StateReader::open_with_index`.
+    pub fn finish_with_index(self, idx_path: impl AsRef<Path>) -> anyhow::Result<W> {
+        let index = self
+            .batch_cursors
+            .iter()
+            .flat_map(|cursor| cursor.to_le_bytes())
+            .collect_vec();
+        std::fs::write(idx_path, index)?;
+
+        self.dest
+            .into_inner()
+            .map_err(|err| anyhow::anyhow!(err.into_error()))
+    }
+
     pub fn flush(&mut self) -> std::io::Result<()> {
         self.dest.flush()
     }
@@ -216,13 +462,12 @@ mod tests {
     use itertools::Itertools;
 
     use super::*;
+    use crate::{serde_types::CoinConfig, test_support::coins};
 
     #[test]
     fn respects_cursor() {
         // given
-        let coins = repeat_with(|| CoinConfig::random(&mut rand::thread_rng()))
-            .take(100)
-            .collect_vec();
+        let coins = coins(100, 1);
 
         let in_mem = InMemorySource::new(coins.clone(), 10).unwrap();
         let start_element_cursor = in_mem.batch_cursors()[1];
@@ -238,9 +483,7 @@ mod tests {
     #[test]
     fn batch_smaller_if_not_enough_elements() {
         // given
-        let coins = repeat_with(|| CoinConfig::random(&mut rand::thread_rng()))
-            .take(5)
-            .collect_vec();
+        let coins = coins(5, 2);
 
         let in_mem = InMemorySource::new(coins.clone(), 10).unwrap();
         let mut reader = StateReader::new(in_mem, 0).unwrap();
@@ -255,9 +498,7 @@ mod tests {
     #[test]
     fn cursor_stops_at_correct_locations() {
         // given
-        let coins = repeat_with(|| CoinConfig::random(&mut rand::thread_rng()))
-            .take(100)
-            .collect_vec();
+        let coins = coins(100, 3);
 
         let in_mem = InMemorySource::new(coins.clone(), 1).unwrap();
         let expected_cursors = in_mem.batch_cursors().to_vec();
@@ -277,11 +518,86 @@ mod tests {
     }
 
     #[test]
-    fn encodes_and_decodes() {
+    fn respects_cursor_with_another_codec() {
         // given
-        let coins = repeat_with(|| CoinConfig::random(&mut rand::thread_rng()))
-            .take(100)
+        let coins = coins(100, 4);
+
+        let in_mem = InMemorySource::with_codec(coins.clone(), 10, MessagePackBatchCodec).unwrap();
+        let start_element_cursor = in_mem.batch_cursors()[1];
+        let codec = in_mem.codec();
+        let mut reader = StateReader::with_codec(in_mem, start_element_cursor, codec).unwrap();
+
+        // when
+        let batch = reader.read_batch().unwrap();
+
+        // then
+        pretty_assertions::assert_eq!(coins[10..20], batch);
+    }
+
+    #[test]
+    fn gzipped_batches_read_back_to_back() {
+        // given
+        let coins = coins(25, 5);
+
+        let in_mem =
+            InMemorySource::with_codec(coins.clone(), 10, GzipBatchCodec::default()).unwrap();
+        let codec = in_mem.codec();
+        let mut reader = StateReader::with_codec(in_mem, 0, codec).unwrap();
+
+        // when
+        let batches = repeat_with(|| reader.read_batch::<CoinConfig>().unwrap())
+            .take_while(|batch| !batch.is_empty())
             .collect_vec();
+
+        // then
+        pretty_assertions::assert_eq!(batches.concat(), coins);
+        pretty_assertions::assert_eq!(batches.len(), 3);
+    }
+
+    #[test]
+    fn reads_batch_at_index() {
+        // given
+        let coins = coins(100, 6);
+
+        let in_mem = InMemorySource::new(coins.clone(), 10).unwrap();
+        let mut reader = StateReader::new(in_mem, 0).unwrap();
+
+        // when
+        let seventh = reader.read_batch_at::<CoinConfig>(7).unwrap();
+        let second = reader.read_batch_at::<CoinConfig>(2).unwrap();
+
+        // then
+        pretty_assertions::assert_eq!(coins[70..80], seventh);
+        pretty_assertions::assert_eq!(coins[20..30], second);
+        assert!(reader.read_batch_at::<CoinConfig>(10).is_err());
+    }
+
+    #[test]
+    fn reads_batch_through_index_file() {
+        // given
+        let dir = tempfile::tempdir().unwrap();
+        let data_path = dir.path().join("coins.bin");
+        let idx_path = dir.path().join("coins.idx");
+        let coins = coins(1000, 7);
+
+        let mut writer = StateWriter::new(File::create(&data_path).unwrap());
+        for batch in &coins.iter().chunks(50) {
+            writer.write_batch(batch.collect_vec()).unwrap();
+        }
+        writer.finish_with_index(&idx_path).unwrap();
+
+        // when
+        let mut reader = StateReader::open_with_index(&data_path, &idx_path).unwrap();
+        let batch = reader.read_batch_at::<CoinConfig>(7).unwrap();
+
+        // then
+        pretty_assertions::assert_eq!(coins[350..400], batch);
+    }
+
+    #[test]
+    fn encodes_and_decodes() {
+        // given
+        let coins = coins(100, 8);
         let mut buffer = vec![];
 
         let mut writer = StateWriter::new(&mut buffer);
@@ -298,9 +614,7 @@ mod tests {
     #[test]
     fn works_with_files() {
         let file = tempfile::tempfile().unwrap();
-        let coins = repeat_with(|| CoinConfig::random(&mut rand::thread_rng()))
-            .take(100)
-            .collect_vec();
+        let coins = coins(100, 9);
         let mut writer = StateWriter::new(file);
         writer.write_batch(coins.clone()).unwrap();
 
@@ -0,0 +1,255 @@
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter, Write},
+    path::Path,
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::measurements::EncodeMeasurement;
+
+/// Growth in encode time below this is noise, however many percent it is at the smallest sizes.
+pub const MIN_TIME_DELTA: Duration = Duration::from_millis(1);
+
+/// Results of an earlier run kept around to compare the next one against. Stored as json, the
+/// seed of the payloads next to an array of the same `{"codec", "points"}` objects `write_json`
+/// exports for a single codec.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Baseline {
+    /// The payloads the points were measured on, a run compared against the baseline has to
+    /// generate the same ones.
+    seed: u64,
+    codecs: Vec<CodecResults>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CodecResults {
+    codec: String,
+    points: Vec<EncodeMeasurement>,
+}
+
+impl Baseline {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            codecs: vec![],
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Replaces whatever was recorded for `codec` before.
+    pub fn add(&mut self, codec: &str, points: &[EncodeMeasurement]) {
+        self.codecs.retain(|results| results.codec != codec);
+        self.codecs.push(CodecResults {
+            codec: codec.to_string(),
+            points: points.to_vec(),
+        });
+    }
+
+    pub fn points(&self, codec: &str) -> Option<&[EncodeMeasurement]> {
+        self.codecs
+            .iter()
+            .find(|results| results.codec == codec)
+            .map(|results| results.points.as_slice())
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let reader = BufReader::new(File::open(path)?);
+        Ok(serde_json::from_reader(reader)?)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        serde_json::to_writer(&mut writer, self)?;
+        writer.flush()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Metric {
+    EncodeTime,
+    Bytes,
+}
+
+impl Metric {
+    pub fn label(self) -> &'static str {
+        match self {
+            Metric::EncodeTime => "encode time",
+            Metric::Bytes => "bytes",
+        }
+    }
+
+    fn of(self, measurement: &EncodeMeasurement) -> f64 {
+        match self {
+            Metric::EncodeTime => measurement.encode_time.as_secs_f64(),
+            Metric::Bytes => measurement.bytes as f64,
+        }
+    }
+
+    /// Growth that counts whatever the tolerance. The sizes are the same on every run over the
+    /// same payloads, any growth is real.
+    fn min_delta(self) -> f64 {
+        match self {
+            Metric::EncodeTime => MIN_TIME_DELTA.as_secs_f64(),
+            Metric::Bytes => 0f64,
+        }
+    }
+}
+
+/// A metric that grew by more than the tolerance since the baseline.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Regression {
+    pub codec: String,
+    pub num_elements: usize,
+    pub metric: Metric,
+    pub baseline: f64,
+    pub current: f64,
+    /// Growth relative to the baseline, in percent.
+    pub delta_pct: f64,
+}
+
+impl std::fmt::Display for Regression {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} at {} elements: {} up {:.1}% ({} -> {})",
+            self.codec,
+            self.num_elements,
+            self.metric.label(),
+            self.delta_pct,
+            self.baseline,
+            self.current
+        )
+    }
+}
+
+/// Every metric of `current` that is more than `tolerance_pct` percent above the baseline point
+/// with the same codec and number of elements, and more than `Metric::min_delta` above it in
+/// absolute terms. Points missing on either side are skipped, and so are baseline points of
+/// zero, they have no percentage to grow by.
+pub fn compare(current: &Baseline, baseline: &Baseline, tolerance_pct: f64) -> Vec<Regression> {
+    let mut regressions = vec![];
+    for results in &current.codecs {
+        let Some(baseline_points) = baseline.points(&results.codec) else {
+            continue;
+        };
+        for point in &results.points {
+            let Some(baseline_point) = baseline_points
+                .iter()
+                .find(|m| m.num_elements == point.num_elements)
+            else {
+                continue;
+            };
+            for metric in [Metric::EncodeTime, Metric::Bytes] {
+                let (baseline, current) = (metric.of(baseline_point), metric.of(point));
+                if baseline == 0f64 {
+                    continue;
+                }
+                let delta_pct = (current - baseline) / baseline * 100f64;
+                if delta_pct > tolerance_pct && current - baseline > metric.min_delta() {
+                    regressions.push(Regression {
+                        codec: results.codec.clone(),
+                        num_elements: point.num_elements,
+                        metric,
+                        baseline,
+                        current,
+                        delta_pct,
+                    });
+                }
+            }
+        }
+    }
+    regressions
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    fn point(num_elements: usize, bytes: usize, encode_millis: u64) -> EncodeMeasurement {
+        let time = Duration::from_millis(encode_millis);
+        EncodeMeasurement::new(num_elements, bytes, time, time)
+    }
+
+    #[test]
+    fn baseline_round_trips_through_a_file() {
+        // given
+        let mut baseline = Baseline::new(42);
+        baseline.add("bincode", &[point(0, 0, 0), point(10, 100, 5)]);
+        let file = tempfile::NamedTempFile::new().unwrap();
+
+        // when
+        baseline.save(file.path()).unwrap();
+
+        // then
+        let loaded = Baseline::load(file.path()).unwrap();
+        let points = loaded
+            .points("bincode")
+            .unwrap()
+            .iter()
+            .map(|m| (m.num_elements, m.bytes, m.encode_time))
+            .collect::<Vec<_>>();
+        pretty_assertions::assert_eq!(
+            points,
+            vec![(0, 0, Duration::ZERO), (10, 100, Duration::from_millis(5))]
+        );
+        pretty_assertions::assert_eq!(loaded.seed(), 42);
+    }
+
+    #[test]
+    fn only_growth_beyond_the_tolerance_is_reported() {
+        // given
+        let mut baseline = Baseline::new(1);
+        baseline.add("bincode", &[point(0, 0, 0), point(10, 100, 10)]);
+        baseline.add("parquet", &[point(10, 100, 10)]);
+        let mut current = Baseline::new(1);
+        current.add("bincode", &[point(0, 50, 3), point(10, 105, 20)]);
+        current.add("parquet", &[point(10, 80, 5)]);
+        current.add("msgpack", &[point(10, 500, 50)]);
+
+        // when
+        let regressions = compare(&current, &baseline, 10f64);
+
+        // then
+        let regressions = regressions
+            .iter()
+            .map(|r| {
+                (
+                    r.codec.as_str(),
+                    r.num_elements,
+                    r.metric,
+                    r.delta_pct.round(),
+                )
+            })
+            .collect::<Vec<_>>();
+        pretty_assertions::assert_eq!(
+            regressions,
+            vec![("bincode", 10, Metric::EncodeTime, 100f64)]
+        );
+    }
+
+    #[test]
+    fn timings_growing_by_less_than_the_minimum_delta_pass() {
+        // given
+        let timed = |micros| {
+            let time = Duration::from_micros(micros);
+            EncodeMeasurement::new(10, 100, time, time)
+        };
+        let mut baseline = Baseline::new(1);
+        baseline.add("bincode", &[timed(20)]);
+        let mut current = Baseline::new(1);
+        current.add("bincode", &[timed(900)]);
+
+        // when
+        let regressions = compare(&current, &baseline, 10f64);
+
+        // then
+        pretty_assertions::assert_eq!(regressions, vec![]);
+    }
+}
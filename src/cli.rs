@@ -0,0 +1,376 @@
+use std::{ops::RangeInclusive, path::PathBuf};
+
+use clap::{builder::RangedU64ValueParser, ArgAction, Parser, Subcommand, ValueEnum};
+use itertools::Itertools;
+
+use crate::{
+    encoding::{
+        AnyCodec, AvroCodec, BincodeCodec, BsonCodec, CborCodec, CodecError, ColumnarBincodeCodec,
+        CsvCodec, JsonCodec, MessagePackCodec, ParquetCodec, ParquetCompression,
+    },
+    util::PayloadRng,
+};
+
+/// Benchmarks encoding the regenesis state with each codec and plots the results. Every chart
+/// goes into its own subdirectory of `--out-dir`.
+#[derive(Debug, Parser)]
+pub struct Cli {
+    /// Codecs to benchmark, comma separated.
+    #[arg(
+        long,
+        value_enum,
+        value_delimiter = ',',
+        default_values = ["serde_json", "bincode", "parquet", "msgpack"]
+    )]
+    pub codecs: Vec<CodecName>,
+    /// Largest payload size, exclusive.
+    #[arg(long, default_value_t = 200_000)]
+    pub max: usize,
+    /// Distance between two payload sizes.
+    #[arg(
+        long,
+        default_value_t = 10_000,
+        value_parser = RangedU64ValueParser::<usize>::new().range(1..)
+    )]
+    pub step: usize,
+    /// Directory the results and charts are written to, one subdirectory per chart.
+    #[arg(long, default_value = ".")]
+    pub out_dir: PathBuf,
+    /// Skip the compressed variants of the chosen codecs, they're benchmarked by default.
+    #[arg(long = "no-compressed", action = ArgAction::SetFalse)]
+    pub compressed: bool,
+    /// Payload size the fitted curves are extrapolated to.
+    #[arg(long, default_value_t = 1_000_000_000)]
+    pub predict_to: usize,
+    /// Fail before measuring anything if a codec doesn't round trip a payload losslessly.
+    #[arg(long)]
+    pub verify: bool,
+    /// Only encode the chosen codecs across the sweep and skip every other chart, e.g. to profile
+    /// a writer without decoding interleaved, or for a codec whose decode is broken.
+    #[arg(long)]
+    pub encode_only: bool,
+    /// Highest brotli quality parquet is swept up to. The top levels take far longer to encode
+    /// than they save, 0 skips the sweep.
+    #[arg(long, default_value_t = 4, value_parser = clap::value_parser!(u32).range(0..=11))]
+    pub max_brotli_level: u32,
+    /// Seed of the generated payloads, to measure the same payloads as an earlier run that printed
+    /// it. Picked at random if not given. Also taken by `bench-one`.
+    #[arg(long, global = true)]
+    pub seed: Option<u64>,
+    /// Generate the payloads from `StepRng` instead of a seeded `StdRng`, to reproduce results
+    /// from before the payloads were random. They compress far better than real data and don't
+    /// depend on `--seed`. Also taken by `bench-one`. A baseline only records its seed, so it
+    /// can't be combined with `--baseline`.
+    #[arg(long, global = true)]
+    pub step_rng: bool,
+    /// Results of an earlier run. The run measures the payloads of the baseline's seed and fails if
+    /// a codec's encode time or size grew by more than `--tolerance` since, encode times only by
+    /// more than a millisecond. A `--seed` other than the baseline's is an error. Written with
+    /// this run's results if it doesn't exist yet.
+    #[arg(long)]
+    pub baseline: Option<PathBuf>,
+    /// Growth over the baseline, in percent, that still passes.
+    #[arg(long, default_value_t = 10.0)]
+    pub tolerance: f64,
+    /// Sample payload sizes doubling from `--step` up to `--max` instead of one every `--step`,
+    /// for the plain encode/decode sweep only.
+    #[arg(long)]
+    pub geometric: bool,
+    /// Also time every contract of the largest payload encoded on its own and plot how far the
+    /// times spread, for the codecs without a header or footer to pay for on every entry.
+    #[arg(long)]
+    pub per_record: bool,
+    /// Bytes of code of every generated contract, either a single length or a range such as
+    /// `8192..=24576` to draw from. Real bytecode runs into the kilobytes.
+    #[arg(long, default_value = "32", value_parser = parse_code_len)]
+    pub code_len: RangeInclusive<usize>,
+    /// Print every run with its payload sizes and output directory, then exit without generating
+    /// a payload or measuring anything.
+    #[arg(long)]
+    pub dry_run: bool,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Runs a single phase once, for a single codec and payload, and prints how long it took.
+    /// Nothing is swept or plotted, so a profiler sees nothing but the codec.
+    BenchOne {
+        codec: CodecName,
+        /// Payload size, the same as the sizes `--max` and `--step` sweep through.
+        num_elements: usize,
+        #[arg(value_enum, default_value_t = Phase::Both)]
+        phase: Phase,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Phase {
+    Encode,
+    /// Only the decoding is timed, the payload is encoded untimed beforehand.
+    Decode,
+    Both,
+}
+
+fn parse_code_len(arg: &str) -> Result<RangeInclusive<usize>, String> {
+    let parse = |len: &str| {
+        len.trim()
+            .parse::<usize>()
+            .map_err(|err| format!("`{len}` isn't a length: {err}"))
+    };
+    let code_len = match arg.split_once("..=") {
+        Some((min, max)) => parse(min)?..=parse(max)?,
+        None => parse(arg)?..=parse(arg)?,
+    };
+    if code_len.is_empty() {
+        return Err(format!("`{arg}` is an empty range"));
+    }
+    Ok(code_len)
+}
+
+impl Cli {
+    /// The generator `--step-rng` picks for the payloads.
+    pub fn payload_rng(&self) -> PayloadRng {
+        if self.step_rng {
+            PayloadRng::Step
+        } else {
+            PayloadRng::Std
+        }
+    }
+
+    /// Prefix for every result and chart file, e.g. `bincode-parquet_compressed`, so runs over
+    /// other codecs or without compression can share an `--out-dir` without overwriting each
+    /// other's files.
+    pub fn chart_prefix(&self) -> String {
+        let codecs = self.codecs.iter().map(|name| name.label()).join("-");
+        let compression = if self.compressed {
+            "compressed"
+        } else {
+            "uncompressed"
+        };
+        format!("{codecs}_{compression}")
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum CodecName {
+    #[value(name = "serde_json")]
+    Json,
+    /// serde_json writing every subset as one array instead of a line per entry.
+    #[value(name = "serde_json_array")]
+    JsonArray,
+    Bson,
+    Bincode,
+    Parquet,
+    Msgpack,
+    Cbor,
+    Avro,
+    Csv,
+    /// Bincode of the raw values, one column per field like parquet.
+    #[value(name = "bincode_columnar")]
+    BincodeColumnar,
+}
+
+impl CodecName {
+    /// Name used for the series and the result files.
+    pub fn label(self) -> &'static str {
+        match self {
+            CodecName::Json => "serde_json",
+            CodecName::JsonArray => "serde_json_array",
+            CodecName::Bson => "bson",
+            CodecName::Bincode => "bincode",
+            CodecName::Parquet => "parquet",
+            CodecName::Msgpack => "msgpack",
+            CodecName::Cbor => "cbor",
+            CodecName::Avro => "avro",
+            CodecName::Csv => "csv",
+            CodecName::BincodeColumnar => "bincode_columnar",
+        }
+    }
+
+    /// The codec without any compression.
+    pub fn codec(self) -> Result<AnyCodec, CodecError> {
+        let codec = match self {
+            CodecName::Json => AnyCodec::Json(JsonCodec::Lines),
+            CodecName::JsonArray => AnyCodec::Json(JsonCodec::Array),
+            CodecName::Bson => AnyCodec::Bson(BsonCodec),
+            CodecName::Bincode => AnyCodec::Bincode(BincodeCodec::default()),
+            CodecName::Parquet => {
+                AnyCodec::Parquet(ParquetCodec::try_new(50000, ParquetCompression::Gzip(0))?)
+            }
+            CodecName::Msgpack => AnyCodec::MessagePack(MessagePackCodec),
+            CodecName::Cbor => AnyCodec::Cbor(CborCodec),
+            CodecName::Avro => AnyCodec::Avro(AvroCodec),
+            CodecName::Csv => AnyCodec::Csv(CsvCodec),
+            CodecName::BincodeColumnar => AnyCodec::ColumnarBincode(ColumnarBincodeCodec::Columns),
+        };
+        Ok(codec)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::serde_types::DEFAULT_CODE_LEN;
+
+    #[test]
+    fn no_flags_keeps_the_defaults() {
+        // when
+        let cli = Cli::parse_from(["regenesis_encoding"]);
+
+        // then
+        pretty_assertions::assert_eq!(
+            cli.codecs,
+            vec![
+                CodecName::Json,
+                CodecName::Bincode,
+                CodecName::Parquet,
+                CodecName::Msgpack
+            ]
+        );
+        pretty_assertions::assert_eq!((cli.max, cli.step), (200_000, 10_000));
+        pretty_assertions::assert_eq!(cli.out_dir, PathBuf::from("."));
+        assert!(cli.compressed);
+        pretty_assertions::assert_eq!(cli.predict_to, 1_000_000_000);
+        assert!(!cli.verify);
+        assert!(!cli.encode_only);
+        assert!(!cli.per_record);
+        assert!(!cli.dry_run);
+        pretty_assertions::assert_eq!(cli.seed, None);
+        pretty_assertions::assert_eq!(cli.max_brotli_level, 4);
+        pretty_assertions::assert_eq!(cli.baseline, None);
+        pretty_assertions::assert_eq!(cli.tolerance, 10.0);
+        assert!(cli.command.is_none());
+    }
+
+    #[test]
+    fn parses_bench_one() {
+        // when
+        let cli = Cli::parse_from([
+            "regenesis_encoding",
+            "bench-one",
+            "parquet",
+            "200000",
+            "encode",
+        ]);
+
+        // then
+        assert!(matches!(
+            cli.command,
+            Some(Command::BenchOne {
+                codec: CodecName::Parquet,
+                num_elements: 200_000,
+                phase: Phase::Encode
+            })
+        ));
+    }
+
+    #[test]
+    fn bench_one_takes_a_seed() {
+        // when
+        let cli = Cli::parse_from([
+            "regenesis_encoding",
+            "bench-one",
+            "bincode",
+            "10",
+            "--seed",
+            "42",
+        ]);
+
+        // then
+        pretty_assertions::assert_eq!(cli.seed, Some(42));
+    }
+
+    #[test]
+    fn bench_one_takes_the_step_rng() {
+        // when
+        let cli = Cli::parse_from([
+            "regenesis_encoding",
+            "bench-one",
+            "bincode",
+            "10",
+            "--step-rng",
+        ]);
+
+        // then
+        assert!(cli.step_rng);
+    }
+
+    #[test]
+    fn parses_code_lengths_and_ranges() {
+        // when
+        let parse = |arg: &str| {
+            Cli::try_parse_from(["regenesis_encoding", "--code-len", arg]).map(|cli| cli.code_len)
+        };
+
+        // then
+        pretty_assertions::assert_eq!(parse("8192..=24576").unwrap(), 8192..=24576);
+        pretty_assertions::assert_eq!(parse("100").unwrap(), 100..=100);
+        assert!(parse("10..=1").is_err());
+        assert!(parse("1..5").is_err());
+        pretty_assertions::assert_eq!(
+            Cli::parse_from(["regenesis_encoding"]).code_len,
+            DEFAULT_CODE_LEN..=DEFAULT_CODE_LEN
+        );
+    }
+
+    #[test]
+    fn parses_a_codec_list() {
+        // when
+        let cli = Cli::parse_from([
+            "regenesis_encoding",
+            "--codecs",
+            "bincode,bson",
+            "--no-compressed",
+        ]);
+
+        // then
+        pretty_assertions::assert_eq!(cli.codecs, vec![CodecName::Bincode, CodecName::Bson]);
+        assert!(!cli.compressed);
+    }
+
+    #[test]
+    fn brotli_levels_past_11_are_rejected() {
+        // when
+        let result = Cli::try_parse_from(["regenesis_encoding", "--max-brotli-level", "12"]);
+
+        // then
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn chart_prefix_names_the_codecs_and_the_compression() {
+        // given
+        let cli = Cli::parse_from([
+            "regenesis_encoding",
+            "--codecs",
+            "bincode,serde_json_array",
+            "--no-compressed",
+        ]);
+
+        // when
+        let prefix = cli.chart_prefix();
+
+        // then
+        pretty_assertions::assert_eq!(prefix, "bincode-serde_json_array_uncompressed");
+    }
+
+    #[test]
+    fn a_zero_step_is_rejected() {
+        // when
+        let result = Cli::try_parse_from(["regenesis_encoding", "--step", "0"]);
+
+        // then
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn unknown_codecs_are_rejected() {
+        // when
+        let result = Cli::try_parse_from(["regenesis_encoding", "--codecs", "protobuf"]);
+
+        // then
+        assert!(result.is_err());
+    }
+}
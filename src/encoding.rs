@@ -1,58 +1,1170 @@
+mod any_codec;
+mod append_codec;
+#[cfg(feature = "async")]
+mod async_codec;
+mod avro_codec;
 mod bincode_codec;
 mod bson_codec;
+mod cbor_codec;
+mod columnar_bincode_codec;
+mod csv_codec;
+mod entry_codec;
 mod json_codec;
+mod msgpack_codec;
 mod parquet_codec;
 
+pub use any_codec::*;
+pub use append_codec::*;
+#[cfg(feature = "async")]
+pub use async_codec::*;
+pub use avro_codec::*;
 pub use bincode_codec::*;
 pub use bson_codec::*;
+pub use cbor_codec::*;
+pub use columnar_bincode_codec::*;
+pub use csv_codec::*;
+pub use entry_codec::*;
 pub use json_codec::*;
+pub use msgpack_codec::*;
 pub use parquet_codec::*;
 
+use std::{
+    fmt,
+    io::{BufWriter, Write},
+    time::{Duration, Instant},
+};
+
 use crate::{
-    serde_types::{CoinConfig, ContractBalance, ContractConfig, ContractState, MessageConfig},
-    util::{Data, Payload},
+    serde_types::{
+        CoinConfig, ContractBalance, ContractConfig, ContractState, ContractStateRoot,
+        MessageConfig, StateEntry,
+    },
+    util::{CountingSink, Data, Payload},
 };
 
+#[derive(Debug)]
+pub enum CodecError {
+    Io(std::io::Error),
+    /// Whatever the underlying serde format complained about.
+    Serde(Box<dyn std::error::Error + Send + Sync>),
+    Parquet(parquet::errors::ParquetError),
+    /// A parquet row had a missing or wrongly typed field at the given column index.
+    UnexpectedField {
+        column: usize,
+    },
+    /// A parquet projection named a column the schema doesn't have.
+    UnknownColumn {
+        name: String,
+    },
+    /// A fixed length parquet field, i.e. a hash, had the wrong number of bytes.
+    BadFieldLength {
+        field: String,
+        expected: usize,
+        got: usize,
+    },
+    /// A snapshot subset doesn't hash to the checksum stored for it, the file is corrupt.
+    ChecksumMismatch {
+        subset: &'static str,
+    },
+    /// A parquet file wasn't written with the schema of the type it's decoded into. `expected`
+    /// and `found` are the first column that differs, `none` where one side has fewer columns.
+    SchemaMismatch {
+        schema: String,
+        expected: String,
+        found: String,
+    },
+    /// A batch or row group size of zero, there'd be nothing to write per batch.
+    EmptyBatch {
+        setting: &'static str,
+    },
+    /// A decimal scale outside of `0..=precision`.
+    DecimalScale {
+        scale: i32,
+        precision: i32,
+    },
+}
+
+impl fmt::Display for CodecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CodecError::Io(err) => write!(f, "io error: {err}"),
+            CodecError::Serde(err) => write!(f, "serde error: {err}"),
+            CodecError::Parquet(err) => write!(f, "parquet error: {err}"),
+            CodecError::UnexpectedField { column } => {
+                write!(f, "unexpected field type in column {column}")
+            }
+            CodecError::UnknownColumn { name } => write!(f, "no column named {name}"),
+            CodecError::BadFieldLength {
+                field,
+                expected,
+                got,
+            } => write!(
+                f,
+                "field {field} should be {expected} bytes long, got {got}"
+            ),
+            CodecError::ChecksumMismatch { subset } => {
+                write!(f, "checksum mismatch in {subset}, the snapshot is corrupt")
+            }
+            CodecError::SchemaMismatch {
+                schema,
+                expected,
+                found,
+            } => write!(
+                f,
+                "file doesn't have the {schema} schema, expected column {expected}, found {found}"
+            ),
+            CodecError::EmptyBatch { setting } => write!(f, "{setting} must be positive"),
+            CodecError::DecimalScale { scale, precision } => write!(
+                f,
+                "decimal scale {scale} is outside the precision of {precision}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for CodecError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CodecError::Io(err) => Some(err),
+            CodecError::Serde(err) => Some(err.as_ref()),
+            CodecError::Parquet(err) => Some(err),
+            CodecError::UnexpectedField { .. }
+            | CodecError::UnknownColumn { .. }
+            | CodecError::BadFieldLength { .. }
+            | CodecError::ChecksumMismatch { .. }
+            | CodecError::SchemaMismatch { .. }
+            | CodecError::EmptyBatch { .. }
+            | CodecError::DecimalScale { .. } => None,
+        }
+    }
+}
+
+/// Why `PayloadCodec::decode_and_verify` rejected what it decoded.
+#[derive(Debug)]
+pub enum Mismatch {
+    Codec(CodecError),
+    /// A subset decoded to a different number of entries than expected.
+    Len {
+        subset: &'static str,
+        expected: usize,
+        got: usize,
+    },
+    /// The first entry of a subset that differs from the expected one. `field` is the first
+    /// serialized field that differs, empty if only the entries as a whole compare unequal.
+    Entry {
+        subset: &'static str,
+        index: usize,
+        field: String,
+    },
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Mismatch::Codec(err) => write!(f, "{err}"),
+            Mismatch::Len {
+                subset,
+                expected,
+                got,
+            } => write!(f, "expected {expected} {subset}, decoded {got}"),
+            Mismatch::Entry {
+                subset,
+                index,
+                field,
+            } => write!(f, "{subset}[{index}] differs in {field:?}"),
+        }
+    }
+}
+
+impl std::error::Error for Mismatch {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            // displayed as is, so the chain continues below it
+            Mismatch::Codec(err) => err.source(),
+            Mismatch::Len { .. } | Mismatch::Entry { .. } => None,
+        }
+    }
+}
+
+impl From<CodecError> for Mismatch {
+    fn from(err: CodecError) -> Self {
+        Mismatch::Codec(err)
+    }
+}
+
+/// Finds the first entry of `decoded` that isn't the one in `expected`.
+fn verify_subset<T: PartialEq + serde::Serialize>(
+    subset: &'static str,
+    decoded: &[T],
+    expected: &[T],
+) -> Result<(), Mismatch> {
+    if decoded.len() != expected.len() {
+        return Err(Mismatch::Len {
+            subset,
+            expected: expected.len(),
+            got: decoded.len(),
+        });
+    }
+    let Some(index) = std::iter::zip(decoded, expected).position(|(got, want)| got != want) else {
+        return Ok(());
+    };
+    Err(Mismatch::Entry {
+        subset,
+        index,
+        field: differing_field(&decoded[index], &expected[index]).unwrap_or_default(),
+    })
+}
+
+/// Name of the first field that serializes differently, going by the json representation.
+fn differing_field(got: &impl serde::Serialize, want: &impl serde::Serialize) -> Option<String> {
+    let (serde_json::Value::Object(got), serde_json::Value::Object(want)) = (
+        serde_json::to_value(got).ok()?,
+        serde_json::to_value(want).ok()?,
+    ) else {
+        return None;
+    };
+    want.iter()
+        .find(|(name, value)| got.get(name.as_str()) != Some(value))
+        .map(|(name, _)| name.clone())
+}
+
+impl From<std::io::Error> for CodecError {
+    fn from(err: std::io::Error) -> Self {
+        CodecError::Io(err)
+    }
+}
+
+impl From<parquet::errors::ParquetError> for CodecError {
+    fn from(err: parquet::errors::ParquetError) -> Self {
+        CodecError::Parquet(err)
+    }
+}
+
+macro_rules! impl_from_serde_error {
+    ($($err:ty),*) => {
+        $(
+            impl From<$err> for CodecError {
+                fn from(err: $err) -> Self {
+                    CodecError::Serde(Box::new(err))
+                }
+            }
+        )*
+    };
+}
+
+impl_from_serde_error!(
+    bincode::error::EncodeError,
+    bincode::error::DecodeError,
+    serde_json::Error,
+    bson::ser::Error,
+    bson::de::Error,
+    rmp_serde::encode::Error,
+    rmp_serde::decode::Error,
+    csv::Error
+);
+
+/// ciborium reports the reader's and writer's own errors, those stay io errors.
+impl From<ciborium::de::Error<std::io::Error>> for CodecError {
+    fn from(err: ciborium::de::Error<std::io::Error>) -> Self {
+        match err {
+            ciborium::de::Error::Io(err) => CodecError::Io(err),
+            err => CodecError::Serde(Box::new(err)),
+        }
+    }
+}
+
+impl From<ciborium::ser::Error<std::io::Error>> for CodecError {
+    fn from(err: ciborium::ser::Error<std::io::Error>) -> Self {
+        match err {
+            ciborium::ser::Error::Io(err) => CodecError::Io(err),
+            err => CodecError::Serde(Box::new(err)),
+        }
+    }
+}
+
 pub trait PayloadCodec<R, W> {
-    fn encode(&self, payload: Payload, writers: &mut Data<W>);
-    fn decode(&self, readers: Data<R>);
+    fn encode(&self, payload: &Payload, writers: &mut Data<W>) -> Result<(), CodecError>;
+    fn decode(&self, readers: Data<R>) -> Result<Payload, CodecError>;
+    /// Same as `encode` but times every subset separately.
+    fn encode_timed(
+        &self,
+        payload: &Payload,
+        writers: &mut Data<W>,
+    ) -> Result<Data<Duration>, CodecError>;
+    /// Same as `decode` but times every subset separately.
+    fn decode_timed(&self, readers: Data<R>) -> Result<(Payload, Data<DecodeTime>), CodecError>;
+    /// Decodes and compares every subset entry by entry against `expected`, failing on the
+    /// first difference. Meant to catch codecs that silently lose data on a round trip.
+    fn decode_and_verify(&self, readers: Data<R>, expected: &Payload) -> Result<(), Mismatch>;
+    /// Bytes every subset would take, without keeping any of them. Encodes into `CountingSink`s
+    /// so nothing but the codec's own buffers is allocated.
+    fn estimate_size(&self, payload: &Payload) -> Result<Data<usize>, CodecError>;
+    /// Same as `encode` but the entries come from an iterator, in any order, so the whole
+    /// `Payload` never has to be in memory. Every type is handed to its encoder in batches of
+    /// `batch_size`, which must be positive.
+    fn encode_stream(
+        &self,
+        entries: impl IntoIterator<Item = StateEntry>,
+        batch_size: usize,
+        writers: &mut Data<W>,
+    ) -> Result<(), CodecError>;
+    /// Same as `encode` but every subset is encoded on its own rayon task. The subsets have
+    /// their own writers so nothing is shared between the tasks.
+    #[cfg(feature = "parallel")]
+    fn encode_parallel(&self, payload: &Payload, writers: &mut Data<W>) -> Result<(), CodecError>
+    where
+        Self: Sync,
+        W: Send;
+}
+
+/// Hands `writer` to the codec behind a `BufWriter`, the same way `api::StateWriter` does, so
+/// codecs that write entry by entry don't cost a write call per entry on real files. Flushed
+/// before returning.
+fn encode_buffered<C, T, W>(codec: &C, data: &[T], writer: &mut W) -> Result<(), CodecError>
+where
+    C: for<'a> Encode<T, BufWriter<&'a mut W>>,
+    W: Write,
+{
+    let mut writer = BufWriter::new(writer);
+    codec.encode_subset(data, &mut writer)?;
+    writer.flush()?;
+    Ok(())
+}
+
+fn timed<T>(action: impl FnOnce() -> Result<T, CodecError>) -> Result<(Duration, T), CodecError> {
+    let start = Instant::now();
+    let ret = action()?;
+    Ok((start.elapsed(), ret))
+}
+
+/// How long decoding a subset took and how much of that went into opening it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DecodeTime {
+    pub total: Duration,
+    /// Part of `total` spent before the first entry could be read, e.g. parsing parquet's
+    /// footer. Zero for the formats that start right at the first entry.
+    pub open: Duration,
+}
+
+fn decode_opened<T, R>(
+    codec: &impl Decode<T, R>,
+    reader: R,
+) -> Result<(DecodeTime, Vec<T>), CodecError> {
+    let (total, (decoded, open)) = timed(|| codec.decode_subset_opened(reader))?;
+    Ok((DecodeTime { total, open }, decoded))
 }
 
 impl<
         R,
         W,
-        T: Encode<CoinConfig, W>
+        T: for<'a> Encode<CoinConfig, BufWriter<&'a mut W>>
             + Decode<CoinConfig, R>
-            + Encode<ContractConfig, W>
+            + for<'a> Encode<ContractConfig, BufWriter<&'a mut W>>
             + Decode<ContractConfig, R>
-            + Encode<MessageConfig, W>
+            + for<'a> Encode<MessageConfig, BufWriter<&'a mut W>>
             + Decode<MessageConfig, R>
-            + Encode<ContractState, W>
+            + for<'a> Encode<ContractState, BufWriter<&'a mut W>>
             + Decode<ContractState, R>
-            + Encode<ContractBalance, W>
-            + Decode<ContractBalance, R>,
+            + for<'a> Encode<ContractBalance, BufWriter<&'a mut W>>
+            + Decode<ContractBalance, R>
+            + for<'a> Encode<ContractStateRoot, BufWriter<&'a mut W>>
+            + Decode<ContractStateRoot, R>
+            + EncodeStream<CoinConfig, W>
+            + EncodeStream<ContractConfig, W>
+            + EncodeStream<MessageConfig, W>
+            + EncodeStream<ContractState, W>
+            + EncodeStream<ContractBalance, W>
+            + EncodeStream<ContractStateRoot, W>
+            + Encode<CoinConfig, CountingSink>
+            + Encode<ContractConfig, CountingSink>
+            + Encode<MessageConfig, CountingSink>
+            + Encode<ContractState, CountingSink>
+            + Encode<ContractBalance, CountingSink>
+            + Encode<ContractStateRoot, CountingSink>,
     > PayloadCodec<R, W> for T
+where
+    W: Write,
 {
-    fn encode(&self, payload: Payload, writers: &mut Data<W>) {
-        self.encode_subset(payload.coins, &mut writers.coins);
-        self.encode_subset(payload.messages, &mut writers.messages);
-        self.encode_subset(payload.contracts, &mut writers.contracts);
-        self.encode_subset(payload.contract_state, &mut writers.contract_state);
-        self.encode_subset(payload.contract_balance, &mut writers.contract_balance);
+    fn encode(&self, payload: &Payload, writers: &mut Data<W>) -> Result<(), CodecError> {
+        encode_buffered(self, &payload.coins, &mut writers.coins)?;
+        encode_buffered(self, &payload.messages, &mut writers.messages)?;
+        encode_buffered(self, &payload.contracts, &mut writers.contracts)?;
+        encode_buffered(self, &payload.contract_state, &mut writers.contract_state)?;
+        encode_buffered(
+            self,
+            &payload.contract_balance,
+            &mut writers.contract_balance,
+        )?;
+        encode_buffered(
+            self,
+            &payload.contract_state_root,
+            &mut writers.contract_state_root,
+        )?;
+        Ok(())
+    }
+    fn decode(&self, readers: Data<R>) -> Result<Payload, CodecError> {
+        Ok(Payload {
+            coins: self.decode_subset(readers.coins)?,
+            messages: self.decode_subset(readers.messages)?,
+            contracts: self.decode_subset(readers.contracts)?,
+            contract_state: self.decode_subset(readers.contract_state)?,
+            contract_balance: self.decode_subset(readers.contract_balance)?,
+            contract_state_root: self.decode_subset(readers.contract_state_root)?,
+        })
+    }
+    fn decode_and_verify(&self, readers: Data<R>, expected: &Payload) -> Result<(), Mismatch> {
+        let decoded = self.decode(readers)?;
+        let [coins, messages, contracts, contract_state, contract_balance, contract_state_root] =
+            Data::LABELS;
+        verify_subset(coins, &decoded.coins, &expected.coins)?;
+        verify_subset(messages, &decoded.messages, &expected.messages)?;
+        verify_subset(contracts, &decoded.contracts, &expected.contracts)?;
+        verify_subset(
+            contract_state,
+            &decoded.contract_state,
+            &expected.contract_state,
+        )?;
+        verify_subset(
+            contract_balance,
+            &decoded.contract_balance,
+            &expected.contract_balance,
+        )?;
+        verify_subset(
+            contract_state_root,
+            &decoded.contract_state_root,
+            &expected.contract_state_root,
+        )
+    }
+    fn encode_timed(
+        &self,
+        payload: &Payload,
+        writers: &mut Data<W>,
+    ) -> Result<Data<Duration>, CodecError> {
+        Ok(Data {
+            coins: timed(|| encode_buffered(self, &payload.coins, &mut writers.coins))?.0,
+            messages: timed(|| encode_buffered(self, &payload.messages, &mut writers.messages))?.0,
+            contracts: timed(|| encode_buffered(self, &payload.contracts, &mut writers.contracts))?
+                .0,
+            contract_state: timed(|| {
+                encode_buffered(self, &payload.contract_state, &mut writers.contract_state)
+            })?
+            .0,
+            contract_balance: timed(|| {
+                encode_buffered(
+                    self,
+                    &payload.contract_balance,
+                    &mut writers.contract_balance,
+                )
+            })?
+            .0,
+            contract_state_root: timed(|| {
+                encode_buffered(
+                    self,
+                    &payload.contract_state_root,
+                    &mut writers.contract_state_root,
+                )
+            })?
+            .0,
+        })
+    }
+    fn estimate_size(&self, payload: &Payload) -> Result<Data<usize>, CodecError> {
+        let mut sinks = Data::from_array(std::array::from_fn(|_| CountingSink::default()));
+        self.encode_subset(&payload.coins, &mut sinks.coins)?;
+        self.encode_subset(&payload.messages, &mut sinks.messages)?;
+        self.encode_subset(&payload.contracts, &mut sinks.contracts)?;
+        self.encode_subset(&payload.contract_state, &mut sinks.contract_state)?;
+        self.encode_subset(&payload.contract_balance, &mut sinks.contract_balance)?;
+        self.encode_subset(&payload.contract_state_root, &mut sinks.contract_state_root)?;
+        Ok(sinks.map(|sink| sink.written_bytes))
+    }
+    fn encode_stream(
+        &self,
+        entries: impl IntoIterator<Item = StateEntry>,
+        batch_size: usize,
+        writers: &mut Data<W>,
+    ) -> Result<(), CodecError> {
+        if batch_size == 0 {
+            return Err(CodecError::EmptyBatch {
+                setting: "batch size",
+            });
+        }
+        let mut coins = Batched::new(self.stream_encoder(&mut writers.coins)?, batch_size);
+        let mut messages = Batched::new(self.stream_encoder(&mut writers.messages)?, batch_size);
+        let mut contracts = Batched::new(self.stream_encoder(&mut writers.contracts)?, batch_size);
+        let mut contract_state = Batched::new(
+            self.stream_encoder(&mut writers.contract_state)?,
+            batch_size,
+        );
+        let mut contract_balance = Batched::new(
+            self.stream_encoder(&mut writers.contract_balance)?,
+            batch_size,
+        );
+        let mut contract_state_root = Batched::new(
+            self.stream_encoder(&mut writers.contract_state_root)?,
+            batch_size,
+        );
+
+        for entry in entries {
+            match entry {
+                StateEntry::Coin(entry) => coins.push(entry)?,
+                StateEntry::Message(entry) => messages.push(entry)?,
+                StateEntry::Contract(entry) => contracts.push(entry)?,
+                StateEntry::ContractState(entry) => contract_state.push(entry)?,
+                StateEntry::ContractBalance(entry) => contract_balance.push(entry)?,
+                StateEntry::ContractStateRoot(entry) => contract_state_root.push(entry)?,
+            }
+        }
+
+        coins.finish()?;
+        messages.finish()?;
+        contracts.finish()?;
+        contract_state.finish()?;
+        contract_balance.finish()?;
+        contract_state_root.finish()?;
+        Ok(())
+    }
+    #[cfg(feature = "parallel")]
+    fn encode_parallel(&self, payload: &Payload, writers: &mut Data<W>) -> Result<(), CodecError>
+    where
+        Self: Sync,
+        W: Send,
+    {
+        let Data {
+            coins,
+            messages,
+            contracts,
+            contract_state,
+            contract_balance,
+            contract_state_root,
+        } = writers;
+        let mut results: [Result<(), CodecError>; 6] = std::array::from_fn(|_| Ok(()));
+        let [coins_res, messages_res, contracts_res, contract_state_res, contract_balance_res, contract_state_root_res] =
+            &mut results;
+
+        rayon::scope(|s| {
+            s.spawn(|_| *coins_res = encode_buffered(self, &payload.coins, coins));
+            s.spawn(|_| *messages_res = encode_buffered(self, &payload.messages, messages));
+            s.spawn(|_| *contracts_res = encode_buffered(self, &payload.contracts, contracts));
+            s.spawn(|_| {
+                *contract_state_res = encode_buffered(self, &payload.contract_state, contract_state)
+            });
+            s.spawn(|_| {
+                *contract_balance_res =
+                    encode_buffered(self, &payload.contract_balance, contract_balance)
+            });
+            s.spawn(|_| {
+                *contract_state_root_res =
+                    encode_buffered(self, &payload.contract_state_root, contract_state_root)
+            });
+        });
+
+        results.into_iter().collect()
     }
-    fn decode(&self, readers: Data<R>) {
-        Decode::<CoinConfig, _>::decode_subset(self, readers.coins);
-        Decode::<MessageConfig, _>::decode_subset(self, readers.messages);
-        Decode::<ContractConfig, _>::decode_subset(self, readers.contracts);
-        Decode::<ContractState, _>::decode_subset(self, readers.contract_state);
-        Decode::<ContractBalance, _>::decode_subset(self, readers.contract_balance);
+    fn decode_timed(&self, readers: Data<R>) -> Result<(Payload, Data<DecodeTime>), CodecError> {
+        let (coins_time, coins) = decode_opened(self, readers.coins)?;
+        let (messages_time, messages) = decode_opened(self, readers.messages)?;
+        let (contracts_time, contracts) = decode_opened(self, readers.contracts)?;
+        let (contract_state_time, contract_state) = decode_opened(self, readers.contract_state)?;
+        let (contract_balance_time, contract_balance) =
+            decode_opened(self, readers.contract_balance)?;
+        let (contract_state_root_time, contract_state_root) =
+            decode_opened(self, readers.contract_state_root)?;
+
+        let payload = Payload {
+            coins,
+            messages,
+            contracts,
+            contract_state,
+            contract_balance,
+            contract_state_root,
+        };
+        let times = Data {
+            coins: coins_time,
+            messages: messages_time,
+            contracts: contracts_time,
+            contract_state: contract_state_time,
+            contract_balance: contract_balance_time,
+            contract_state_root: contract_state_root_time,
+        };
+        Ok((payload, times))
     }
 }
 
 trait Encode<T, W> {
-    fn encode_subset(&self, data: Vec<T>, writer: &mut W);
+    fn encode_subset(&self, data: &[T], writer: &mut W) -> Result<(), CodecError>;
 }
 
 trait Decode<T, R> {
-    fn decode_subset(&self, reader: R);
+    fn decode_subset(&self, reader: R) -> Result<Vec<T>, CodecError>;
+
+    /// `decode_subset` that also returns how long opening the subset took, see `DecodeTime`.
+    fn decode_subset_opened(&self, reader: R) -> Result<(Vec<T>, Duration), CodecError> {
+        Ok((self.decode_subset(reader)?, Duration::ZERO))
+    }
+
+    /// Hands every entry to `f` instead of collecting them. By default the subset is still
+    /// decoded as a whole first, the codecs that read one record at a time override it to hold
+    /// no more than the record at hand.
+    fn decode_subset_each(&self, reader: R, f: impl FnMut(T)) -> Result<(), CodecError> {
+        self.decode_subset(reader)?.into_iter().for_each(f);
+        Ok(())
+    }
+}
+
+/// A single subset decoded into a callback, e.g. to insert every entry somewhere else and drop
+/// it right away. Whatever `f` was handed before an error stays handed.
+pub trait DecodeEach<T, R> {
+    fn decode_each(&self, reader: R, f: impl FnMut(T)) -> Result<(), CodecError>;
+}
+
+impl<T, R, C: Decode<T, R>> DecodeEach<T, R> for C {
+    fn decode_each(&self, reader: R, f: impl FnMut(T)) -> Result<(), CodecError> {
+        self.decode_subset_each(reader, f)
+    }
+}
+
+/// A single subset encoded into memory, e.g. one entry at a time to time every entry on its own.
+/// Formats with a header or a footer, like parquet, avro and csv, write them for every call.
+pub trait EncodeEntries<T> {
+    fn encode_entries(&self, data: &[T], writer: &mut Vec<u8>) -> Result<(), CodecError>;
+}
+
+impl<T, C: Encode<T, Vec<u8>>> EncodeEntries<T> for C {
+    fn encode_entries(&self, data: &[T], writer: &mut Vec<u8>) -> Result<(), CodecError> {
+        self.encode_subset(data, writer)
+    }
+}
+
+/// Partial counterpart of `Decode` for the codecs whose subsets are a plain sequence of records.
+/// Stops after `max` entries, or at the end of the subset, and leaves `reader` right after the
+/// last entry it decoded, so another call carries on where this one stopped.
+pub trait DecodeLimited<T, R> {
+    fn decode_subset_limited(&self, reader: &mut R, max: usize) -> Result<Vec<T>, CodecError>;
+}
+
+/// Incremental counterpart of `Encode`. Batches are written as they are pushed and `finish`
+/// writes whatever the format needs at the end.
+trait StreamEncoder<T> {
+    fn encode_batch(&mut self, batch: &[T]) -> Result<(), CodecError>;
+    fn finish(self: Box<Self>) -> Result<(), CodecError>;
+}
+
+trait EncodeStream<T, W> {
+    fn stream_encoder<'a>(
+        &'a self,
+        writer: &'a mut W,
+    ) -> Result<Box<dyn StreamEncoder<T> + 'a>, CodecError>
+    where
+        T: 'a;
+}
+
+/// Extends a subset that was encoded before, so it decodes as the old entries followed by `data`.
+trait EncodeAppend<T> {
+    fn append_subset(&self, data: &[T], existing: &mut Vec<u8>) -> Result<(), CodecError>;
+}
+
+/// For formats that are nothing but a sequence of records, encoding batch after batch gives the
+/// same output as encoding everything at once.
+struct Appending<'a, C, W> {
+    codec: &'a C,
+    writer: &'a mut W,
+}
+
+impl<T, C: Encode<T, W>, W> StreamEncoder<T> for Appending<'_, C, W> {
+    fn encode_batch(&mut self, batch: &[T]) -> Result<(), CodecError> {
+        self.codec.encode_subset(batch, self.writer)
+    }
+
+    fn finish(self: Box<Self>) -> Result<(), CodecError> {
+        Ok(())
+    }
+}
+
+macro_rules! impl_appending_stream {
+    ($($codec:ty),*) => {
+        $(
+            impl<T, W> EncodeStream<T, W> for $codec
+            where
+                $codec: Encode<T, W>,
+            {
+                fn stream_encoder<'a>(
+                    &'a self,
+                    writer: &'a mut W,
+                ) -> Result<Box<dyn StreamEncoder<T> + 'a>, CodecError>
+                where
+                    T: 'a,
+                {
+                    Ok(Box::new(Appending {
+                        codec: self,
+                        writer,
+                    }))
+                }
+            }
+
+            impl<T> EncodeAppend<T> for $codec
+            where
+                $codec: Encode<T, Vec<u8>>,
+            {
+                fn append_subset(&self, data: &[T], existing: &mut Vec<u8>) -> Result<(), CodecError> {
+                    self.encode_subset(data, existing)
+                }
+            }
+        )*
+    };
+}
+
+impl_appending_stream!(
+    BsonCodec,
+    BincodeCodec,
+    MessagePackCodec,
+    CborCodec,
+    ColumnarBincodeCodec
+);
+
+/// Collects entries of one type until there's a whole batch for the encoder.
+struct Batched<'a, T> {
+    encoder: Box<dyn StreamEncoder<T> + 'a>,
+    pending: Vec<T>,
+    batch_size: usize,
+}
+
+impl<'a, T> Batched<'a, T> {
+    fn new(encoder: Box<dyn StreamEncoder<T> + 'a>, batch_size: usize) -> Self {
+        Self {
+            encoder,
+            pending: Vec::with_capacity(batch_size),
+            batch_size,
+        }
+    }
+
+    fn push(&mut self, entry: T) -> Result<(), CodecError> {
+        self.pending.push(entry);
+        if self.pending.len() >= self.batch_size {
+            self.encoder.encode_batch(&self.pending)?;
+            self.pending.clear();
+        }
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<(), CodecError> {
+        if !self.pending.is_empty() {
+            self.encoder.encode_batch(&self.pending)?;
+        }
+        self.encoder.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufReader, Cursor};
+
+    use itertools::Itertools;
+
+    use super::*;
+    use crate::{
+        test_support::full_payload,
+        util::{payload_with, PayloadConfig},
+    };
+
+    fn small_payload() -> PayloadConfig {
+        PayloadConfig {
+            contract_state: 300,
+            contract_balance: 300,
+            ..PayloadConfig::new(900).with_seed(7)
+        }
+    }
+
+    fn assert_round_trip(
+        codec: &impl PayloadCodec<Cursor<Vec<u8>>, Vec<u8>>,
+        config: PayloadConfig,
+    ) {
+        // given
+        let payload = payload_with(&config);
+        let mut data = Data::with_capacity(0);
+
+        // when
+        codec.encode(&payload, &mut data).unwrap();
+        let decoded = codec.decode(data.wrap_in_cursor()).unwrap();
+
+        // then
+        pretty_assertions::assert_eq!(decoded.coins, payload.coins);
+        pretty_assertions::assert_eq!(decoded.messages, payload.messages);
+        pretty_assertions::assert_eq!(decoded.contracts, payload.contracts);
+        pretty_assertions::assert_eq!(decoded.contract_state, payload.contract_state);
+        pretty_assertions::assert_eq!(decoded.contract_balance, payload.contract_balance);
+        pretty_assertions::assert_eq!(decoded.contract_state_root, payload.contract_state_root);
+    }
+
+    fn assert_stream_round_trip(
+        codec: &impl PayloadCodec<Cursor<Vec<u8>>, Vec<u8>>,
+        config: PayloadConfig,
+    ) {
+        // given
+        let payload = payload_with(&config);
+        // mix the types so every encoder is open at the same time
+        let entries = payload
+            .coins
+            .iter()
+            .cloned()
+            .map(StateEntry::Coin)
+            .interleave(payload.messages.iter().cloned().map(StateEntry::Message))
+            .interleave(payload.contracts.iter().cloned().map(StateEntry::Contract))
+            .interleave(
+                payload
+                    .contract_state
+                    .iter()
+                    .cloned()
+                    .map(StateEntry::ContractState),
+            )
+            .interleave(
+                payload
+                    .contract_balance
+                    .iter()
+                    .cloned()
+                    .map(StateEntry::ContractBalance),
+            )
+            .interleave(
+                payload
+                    .contract_state_root
+                    .iter()
+                    .cloned()
+                    .map(StateEntry::ContractStateRoot),
+            );
+        let mut data = Data::with_capacity(0);
+
+        // when
+        codec.encode_stream(entries, 64, &mut data).unwrap();
+        let decoded = codec.decode(data.wrap_in_cursor()).unwrap();
+
+        // then
+        pretty_assertions::assert_eq!(decoded, payload);
+    }
+
+    #[test]
+    fn bincode_round_trips() {
+        assert_round_trip(&BincodeCodec::default(), small_payload());
+    }
+
+    #[test]
+    fn json_round_trips() {
+        assert_round_trip(&JsonCodec::Lines, small_payload());
+        assert_round_trip(&JsonCodec::Array, small_payload());
+    }
+
+    #[test]
+    fn bson_round_trips() {
+        assert_round_trip(&BsonCodec, small_payload());
+    }
+
+    #[test]
+    fn parquet_round_trips() {
+        assert_round_trip(
+            &ParquetCodec::new(100, ParquetCompression::Gzip(1)),
+            small_payload(),
+        );
+    }
+
+    #[test]
+    fn msgpack_round_trips() {
+        assert_round_trip(&MessagePackCodec, small_payload());
+    }
+
+    #[test]
+    fn cbor_round_trips() {
+        assert_round_trip(&CborCodec, small_payload());
+    }
+
+    #[test]
+    fn avro_round_trips() {
+        assert_round_trip(&AvroCodec, small_payload());
+    }
+
+    #[test]
+    fn csv_round_trips() {
+        assert_round_trip(&CsvCodec, small_payload());
+    }
+
+    #[test]
+    fn any_codec_round_trips() {
+        assert_round_trip(&AnyCodec::Bincode(BincodeCodec::default()), small_payload());
+    }
+
+    #[test]
+    fn appending_codecs_stream() {
+        assert_stream_round_trip(&BincodeCodec::default(), small_payload());
+        assert_stream_round_trip(&JsonCodec::Lines, small_payload());
+        assert_stream_round_trip(&JsonCodec::Array, small_payload());
+        assert_stream_round_trip(&BsonCodec, small_payload());
+        assert_stream_round_trip(&MessagePackCodec, small_payload());
+        assert_stream_round_trip(&CborCodec, small_payload());
+    }
+
+    #[test]
+    fn parquet_streams_across_row_groups() {
+        let codec = ParquetCodec::new(50, ParquetCompression::Gzip(1))
+            .with_row_group_size(200)
+            .unwrap();
+        assert_stream_round_trip(&codec, small_payload());
+    }
+
+    #[test]
+    fn avro_streams() {
+        assert_stream_round_trip(&AvroCodec, small_payload());
+    }
+
+    #[test]
+    fn csv_streams() {
+        assert_stream_round_trip(&CsvCodec, small_payload());
+    }
+
+    #[test]
+    fn any_codec_streams() {
+        assert_stream_round_trip(
+            &AnyCodec::Parquet(ParquetCodec::new(100, ParquetCompression::Gzip(1))),
+            small_payload(),
+        );
+    }
+
+    #[test]
+    fn streaming_in_empty_batches_is_an_error() {
+        // given
+        let entries = payload_with(&small_payload()).into_entries();
+        let mut data = Data::with_capacity(0);
+
+        // when
+        let result = PayloadCodec::<Cursor<Vec<u8>>, _>::encode_stream(
+            &BincodeCodec::default(),
+            entries,
+            0,
+            &mut data,
+        );
+
+        // then
+        assert!(matches!(
+            result,
+            Err(CodecError::EmptyBatch {
+                setting: "batch size"
+            })
+        ));
+    }
+
+    fn assert_reads_the_first_coins<C>(codec: &C)
+    where
+        C: Encode<CoinConfig, Vec<u8>>
+            + DecodeLimited<CoinConfig, BufReader<Cursor<Vec<u8>>>>
+            + for<'a> Decode<CoinConfig, &'a mut BufReader<Cursor<Vec<u8>>>>,
+    {
+        // given
+        let coins = payload_with(&PayloadConfig {
+            coins: 100,
+            contract_state: 0,
+            contract_balance: 0,
+            ..PayloadConfig::new(0)
+        })
+        .coins;
+        let mut buffer = vec![];
+        codec.encode_subset(&coins, &mut buffer).unwrap();
+        // a single byte buffer, whatever a codec reads past the last record is lost for good
+        let mut reader = BufReader::with_capacity(1, Cursor::new(buffer));
+
+        // when
+        let first = codec.decode_subset_limited(&mut reader, 10).unwrap();
+
+        // then
+        pretty_assertions::assert_eq!(first, coins[..10]);
+        let rest = codec.decode_subset(&mut reader).unwrap();
+        pretty_assertions::assert_eq!(rest, coins[10..]);
+    }
+
+    #[test]
+    fn limited_decoding_stops_right_after_the_last_record() {
+        assert_reads_the_first_coins(&BincodeCodec::default());
+        assert_reads_the_first_coins(&JsonCodec::Lines);
+        assert_reads_the_first_coins(&BsonCodec);
+        assert_reads_the_first_coins(&MessagePackCodec);
+        assert_reads_the_first_coins(&CborCodec);
+    }
+
+    fn assert_hands_over_the_coins_before_a_broken_one<C>(codec: &C)
+    where
+        C: Encode<CoinConfig, Vec<u8>> + DecodeEach<CoinConfig, Cursor<Vec<u8>>>,
+    {
+        // given
+        let coins = payload_with(&PayloadConfig {
+            coins: 100,
+            contract_state: 0,
+            contract_balance: 0,
+            ..PayloadConfig::new(0)
+        })
+        .coins;
+        let mut buffer = vec![];
+        codec.encode_subset(&coins, &mut buffer).unwrap();
+        // cuts into the last record, or the closing bracket and the last record of an array
+        buffer.truncate(buffer.len() - 10);
+        let mut handed = vec![];
+
+        // when
+        let result = codec.decode_each(Cursor::new(buffer), |coin| handed.push(coin));
+
+        // then
+        assert!(result.is_err());
+        pretty_assertions::assert_eq!(handed, coins[..99]);
+    }
+
+    #[test]
+    fn stream_codecs_hand_over_every_record_as_it_is_decoded() {
+        assert_hands_over_the_coins_before_a_broken_one(&BincodeCodec::default());
+        assert_hands_over_the_coins_before_a_broken_one(&JsonCodec::Lines);
+        assert_hands_over_the_coins_before_a_broken_one(&JsonCodec::Array);
+        assert_hands_over_the_coins_before_a_broken_one(&BsonCodec);
+        assert_hands_over_the_coins_before_a_broken_one(&MessagePackCodec);
+        assert_hands_over_the_coins_before_a_broken_one(&CborCodec);
+    }
+
+    #[test]
+    fn parquet_hands_over_every_row() {
+        // given
+        let coins = payload_with(&small_payload()).coins;
+        let codec = AnyCodec::Parquet(ParquetCodec::new(100, ParquetCompression::Gzip(1)));
+        let mut buffer = vec![];
+        codec.encode_subset(&coins, &mut buffer).unwrap();
+        let mut handed = vec![];
+
+        // when
+        codec
+            .decode_each(Cursor::new(buffer), |coin: CoinConfig| handed.push(coin))
+            .unwrap();
+
+        // then
+        pretty_assertions::assert_eq!(handed, coins);
+    }
+
+    #[test]
+    fn parquet_size_estimate_matches_every_subset() {
+        // given
+        let codec = ParquetCodec::new(100, ParquetCompression::Gzip(1));
+        let payload = payload_with(&small_payload());
+        let mut data = Data::with_capacity(0);
+        PayloadCodec::<Cursor<Vec<u8>>, _>::encode(&codec, &payload, &mut data).unwrap();
+
+        // when
+        let estimate =
+            PayloadCodec::<Cursor<Vec<u8>>, Vec<u8>>::estimate_size(&codec, &payload).unwrap();
+
+        // then
+        pretty_assertions::assert_eq!(
+            estimate.into_array(),
+            data.each_ref().map(Vec::len).into_array()
+        );
+    }
+
+    #[test]
+    fn streaming_parquet_matches_encode() {
+        // given
+        let codec = ParquetCodec::new(50, ParquetCompression::Gzip(1))
+            .with_row_group_size(200)
+            .unwrap();
+        let payload = payload_with(&small_payload());
+        let mut streamed = Data::with_capacity(0);
+        let mut encoded = Data::with_capacity(0);
+
+        // when
+        PayloadCodec::<Cursor<Vec<u8>>, _>::encode_stream(
+            &codec,
+            payload.clone().into_entries(),
+            30,
+            &mut streamed,
+        )
+        .unwrap();
+        PayloadCodec::<Cursor<Vec<u8>>, _>::encode(&codec, &payload, &mut encoded).unwrap();
+
+        // then
+        pretty_assertions::assert_eq!(streamed.coins, encoded.coins);
+        pretty_assertions::assert_eq!(streamed.contract_state, encoded.contract_state);
+    }
+
+    #[test]
+    fn verify_accepts_a_lossless_round_trip() {
+        // given
+        let payload = payload_with(&small_payload());
+        let mut data = Data::with_capacity(0);
+        PayloadCodec::<Cursor<Vec<u8>>, _>::encode(&BincodeCodec::default(), &payload, &mut data)
+            .unwrap();
+
+        // when
+        let result = PayloadCodec::<_, Vec<u8>>::decode_and_verify(
+            &BincodeCodec::default(),
+            data.wrap_in_cursor(),
+            &payload,
+        );
+
+        // then
+        assert!(result.is_ok(), "{result:?}");
+    }
+
+    #[test]
+    fn verify_points_at_the_first_differing_field() {
+        // given
+        let payload = payload_with(&small_payload());
+        let mut data = Data::with_capacity(0);
+        PayloadCodec::<Cursor<Vec<u8>>, _>::encode(&BincodeCodec::default(), &payload, &mut data)
+            .unwrap();
+        let mut expected = payload;
+        expected.coins[1].amount += 1;
+
+        // when
+        let result = PayloadCodec::<_, Vec<u8>>::decode_and_verify(
+            &BincodeCodec::default(),
+            data.wrap_in_cursor(),
+            &expected,
+        );
+
+        // then
+        let err = result.unwrap_err();
+        assert!(
+            matches!(
+                &err,
+                Mismatch::Entry { subset: "coins", index: 1, field } if field == "amount"
+            ),
+            "{err}"
+        );
+    }
+
+    #[derive(Default)]
+    struct WriteCalls {
+        calls: usize,
+    }
+
+    impl Write for WriteCalls {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.calls += 1;
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn entries_are_not_written_one_by_one() {
+        // given
+        let payload = full_payload(Data::from_array([1_000, 0, 0, 0, 0, 0]), 1);
+        let mut writers = Data::from_array(std::array::from_fn(|_| WriteCalls::default()));
+
+        // when
+        PayloadCodec::<Cursor<Vec<u8>>, _>::encode(
+            &BincodeCodec::default(),
+            &payload,
+            &mut writers,
+        )
+        .unwrap();
+
+        // then
+        assert!(
+            writers.coins.calls < 100,
+            "{} writes for 1000 coins",
+            writers.coins.calls
+        );
+    }
 }
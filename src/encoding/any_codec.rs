@@ -0,0 +1,157 @@
+use std::time::Duration;
+
+use super::{
+    AvroCodec, BincodeCodec, BsonCodec, CborCodec, CodecError, ColumnarBincodeCodec, CsvCodec,
+    Decode, Encode, EncodeStream, JsonCodec, MessagePackCodec, ParquetCodec, StreamEncoder,
+};
+
+/// One of the codecs, picked at runtime. `PayloadCodec` is generic over the readers and writers so
+/// it can't be turned into a trait object, this dispatches by hand instead. It only supports the
+/// readers and writers every codec supports.
+pub enum AnyCodec {
+    Json(JsonCodec),
+    Bson(BsonCodec),
+    Bincode(BincodeCodec),
+    Parquet(ParquetCodec),
+    MessagePack(MessagePackCodec),
+    Cbor(CborCodec),
+    Avro(AvroCodec),
+    Csv(CsvCodec),
+    ColumnarBincode(ColumnarBincodeCodec),
+}
+
+impl AnyCodec {
+    /// Tells the output formats apart, e.g. in a snapshot header. Settings that don't change how
+    /// the output is read back, like parquet's compression, share an id.
+    pub fn id(&self) -> u8 {
+        match self {
+            AnyCodec::Json(JsonCodec::Lines) => 0,
+            AnyCodec::Bson(_) => 1,
+            AnyCodec::Bincode(_) => 2,
+            AnyCodec::Parquet(_) => 3,
+            AnyCodec::MessagePack(_) => 4,
+            AnyCodec::Avro(_) => 5,
+            AnyCodec::Csv(_) => 6,
+            AnyCodec::Json(JsonCodec::Array) => 7,
+            // both layouts carry the same values, they still can't be read back as one another
+            AnyCodec::ColumnarBincode(ColumnarBincodeCodec::Columns) => 8,
+            AnyCodec::ColumnarBincode(ColumnarBincodeCodec::Rows) => 9,
+            AnyCodec::Cbor(_) => 10,
+        }
+    }
+}
+
+impl<T, W> Encode<T, W> for AnyCodec
+where
+    JsonCodec: Encode<T, W>,
+    BsonCodec: Encode<T, W>,
+    BincodeCodec: Encode<T, W>,
+    ParquetCodec: Encode<T, W>,
+    MessagePackCodec: Encode<T, W>,
+    CborCodec: Encode<T, W>,
+    AvroCodec: Encode<T, W>,
+    CsvCodec: Encode<T, W>,
+    ColumnarBincodeCodec: Encode<T, W>,
+{
+    fn encode_subset(&self, data: &[T], writer: &mut W) -> Result<(), CodecError> {
+        match self {
+            AnyCodec::Json(codec) => codec.encode_subset(data, writer),
+            AnyCodec::Bson(codec) => codec.encode_subset(data, writer),
+            AnyCodec::Bincode(codec) => codec.encode_subset(data, writer),
+            AnyCodec::Parquet(codec) => codec.encode_subset(data, writer),
+            AnyCodec::MessagePack(codec) => codec.encode_subset(data, writer),
+            AnyCodec::Cbor(codec) => codec.encode_subset(data, writer),
+            AnyCodec::Avro(codec) => codec.encode_subset(data, writer),
+            AnyCodec::Csv(codec) => codec.encode_subset(data, writer),
+            AnyCodec::ColumnarBincode(codec) => codec.encode_subset(data, writer),
+        }
+    }
+}
+
+impl<T, R> Decode<T, R> for AnyCodec
+where
+    JsonCodec: Decode<T, R>,
+    BsonCodec: Decode<T, R>,
+    BincodeCodec: Decode<T, R>,
+    ParquetCodec: Decode<T, R>,
+    MessagePackCodec: Decode<T, R>,
+    CborCodec: Decode<T, R>,
+    AvroCodec: Decode<T, R>,
+    CsvCodec: Decode<T, R>,
+    ColumnarBincodeCodec: Decode<T, R>,
+{
+    fn decode_subset(&self, reader: R) -> Result<Vec<T>, CodecError> {
+        match self {
+            AnyCodec::Json(codec) => codec.decode_subset(reader),
+            AnyCodec::Bson(codec) => codec.decode_subset(reader),
+            AnyCodec::Bincode(codec) => codec.decode_subset(reader),
+            AnyCodec::Parquet(codec) => codec.decode_subset(reader),
+            AnyCodec::MessagePack(codec) => codec.decode_subset(reader),
+            AnyCodec::Cbor(codec) => codec.decode_subset(reader),
+            AnyCodec::Avro(codec) => codec.decode_subset(reader),
+            AnyCodec::Csv(codec) => codec.decode_subset(reader),
+            AnyCodec::ColumnarBincode(codec) => codec.decode_subset(reader),
+        }
+    }
+
+    fn decode_subset_opened(&self, reader: R) -> Result<(Vec<T>, Duration), CodecError> {
+        match self {
+            AnyCodec::Json(codec) => codec.decode_subset_opened(reader),
+            AnyCodec::Bson(codec) => codec.decode_subset_opened(reader),
+            AnyCodec::Bincode(codec) => codec.decode_subset_opened(reader),
+            AnyCodec::Parquet(codec) => codec.decode_subset_opened(reader),
+            AnyCodec::MessagePack(codec) => codec.decode_subset_opened(reader),
+            AnyCodec::Cbor(codec) => codec.decode_subset_opened(reader),
+            AnyCodec::Avro(codec) => codec.decode_subset_opened(reader),
+            AnyCodec::Csv(codec) => codec.decode_subset_opened(reader),
+            AnyCodec::ColumnarBincode(codec) => codec.decode_subset_opened(reader),
+        }
+    }
+
+    fn decode_subset_each(&self, reader: R, f: impl FnMut(T)) -> Result<(), CodecError> {
+        match self {
+            AnyCodec::Json(codec) => codec.decode_subset_each(reader, f),
+            AnyCodec::Bson(codec) => codec.decode_subset_each(reader, f),
+            AnyCodec::Bincode(codec) => codec.decode_subset_each(reader, f),
+            AnyCodec::Parquet(codec) => codec.decode_subset_each(reader, f),
+            AnyCodec::MessagePack(codec) => codec.decode_subset_each(reader, f),
+            AnyCodec::Cbor(codec) => codec.decode_subset_each(reader, f),
+            AnyCodec::Avro(codec) => codec.decode_subset_each(reader, f),
+            AnyCodec::Csv(codec) => codec.decode_subset_each(reader, f),
+            AnyCodec::ColumnarBincode(codec) => codec.decode_subset_each(reader, f),
+        }
+    }
+}
+
+impl<T, W> EncodeStream<T, W> for AnyCodec
+where
+    JsonCodec: EncodeStream<T, W>,
+    BsonCodec: EncodeStream<T, W>,
+    BincodeCodec: EncodeStream<T, W>,
+    ParquetCodec: EncodeStream<T, W>,
+    MessagePackCodec: EncodeStream<T, W>,
+    CborCodec: EncodeStream<T, W>,
+    AvroCodec: EncodeStream<T, W>,
+    CsvCodec: EncodeStream<T, W>,
+    ColumnarBincodeCodec: EncodeStream<T, W>,
+{
+    fn stream_encoder<'a>(
+        &'a self,
+        writer: &'a mut W,
+    ) -> Result<Box<dyn StreamEncoder<T> + 'a>, CodecError>
+    where
+        T: 'a,
+    {
+        match self {
+            AnyCodec::Json(codec) => codec.stream_encoder(writer),
+            AnyCodec::Bson(codec) => codec.stream_encoder(writer),
+            AnyCodec::Bincode(codec) => codec.stream_encoder(writer),
+            AnyCodec::Parquet(codec) => codec.stream_encoder(writer),
+            AnyCodec::MessagePack(codec) => codec.stream_encoder(writer),
+            AnyCodec::Cbor(codec) => codec.stream_encoder(writer),
+            AnyCodec::Avro(codec) => codec.stream_encoder(writer),
+            AnyCodec::Csv(codec) => codec.stream_encoder(writer),
+            AnyCodec::ColumnarBincode(codec) => codec.stream_encoder(writer),
+        }
+    }
+}
@@ -0,0 +1,86 @@
+use super::{CodecError, EncodeAppend};
+use crate::{
+    serde_types::{
+        CoinConfig, ContractBalance, ContractConfig, ContractState, ContractStateRoot,
+        MessageConfig,
+    },
+    util::{Data, Payload},
+};
+
+/// Extends an encoded payload with more entries instead of encoding everything again. Every
+/// subset of `existing` has to be the complete output of an earlier `encode` or `append`, it
+/// then decodes as the old entries followed by the ones of `payload`.
+///
+/// Implemented by the codecs that can be extended: the sequences of records just keep writing,
+/// json arrays are reopened and parquet copies its existing row groups into a new file. Avro and
+/// csv are left out.
+pub trait AppendCodec {
+    fn append(&self, payload: &Payload, existing: &mut Data<Vec<u8>>) -> Result<(), CodecError>;
+}
+
+impl<C> AppendCodec for C
+where
+    C: EncodeAppend<CoinConfig>
+        + EncodeAppend<MessageConfig>
+        + EncodeAppend<ContractConfig>
+        + EncodeAppend<ContractState>
+        + EncodeAppend<ContractBalance>
+        + EncodeAppend<ContractStateRoot>,
+{
+    fn append(&self, payload: &Payload, existing: &mut Data<Vec<u8>>) -> Result<(), CodecError> {
+        self.append_subset(&payload.coins, &mut existing.coins)?;
+        self.append_subset(&payload.messages, &mut existing.messages)?;
+        self.append_subset(&payload.contracts, &mut existing.contracts)?;
+        self.append_subset(&payload.contract_state, &mut existing.contract_state)?;
+        self.append_subset(&payload.contract_balance, &mut existing.contract_balance)?;
+        self.append_subset(
+            &payload.contract_state_root,
+            &mut existing.contract_state_root,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::{
+        encoding::{
+            BincodeCodec, ColumnarBincodeCodec, JsonCodec, ParquetCodec, ParquetCompression,
+            PayloadCodec,
+        },
+        test_support::full_payload,
+    };
+
+    fn assert_appends<C>(codec: &C)
+    where
+        C: AppendCodec + PayloadCodec<Cursor<Vec<u8>>, Vec<u8>>,
+    {
+        // given
+        let corpus = full_payload(Data::from_array([30, 20, 10, 40, 20, 10]), 1);
+        let increment = full_payload(Data::from_array([7, 0, 3, 5, 2, 1]), 2);
+        let mut data = Data::with_capacity(0);
+        codec.encode(&corpus, &mut data).unwrap();
+
+        // when
+        codec.append(&increment, &mut data).unwrap();
+
+        // then
+        let decoded = codec.decode(data.wrap_in_cursor()).unwrap();
+        pretty_assertions::assert_eq!(decoded, corpus.merge(increment));
+    }
+
+    #[test]
+    fn appended_entries_follow_the_existing_ones() {
+        assert_appends(&BincodeCodec::default());
+        assert_appends(&ColumnarBincodeCodec::Columns);
+        assert_appends(&JsonCodec::Lines);
+        assert_appends(&JsonCodec::Array);
+        assert_appends(
+            &ParquetCodec::new(8, ParquetCompression::Zstd(1))
+                .with_row_group_size(16)
+                .unwrap(),
+        );
+    }
+}
@@ -0,0 +1,172 @@
+use std::future::Future;
+
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+
+use super::{BincodeCodec, BsonCodec, CborCodec, CodecError, Encode, JsonCodec, MessagePackCodec};
+use crate::{
+    serde_types::{
+        CoinConfig, ContractBalance, ContractConfig, ContractState, ContractStateRoot,
+        MessageConfig,
+    },
+    util::{Data, Payload},
+};
+
+/// Entries serialized per blocking task. Each chunk is written out before the next one is
+/// encoded, so at most one chunk of output is held at a time.
+const CHUNK_SIZE: usize = 1000;
+
+/// Codecs whose output is a plain sequence of records, so a subset can be encoded chunk by chunk
+/// and the chunks concatenated.
+trait Appendable {
+    /// Entries per chunk, `usize::MAX` if this instance's output can't be split after all.
+    fn chunk_size(&self) -> usize {
+        CHUNK_SIZE
+    }
+}
+
+impl Appendable for JsonCodec {
+    fn chunk_size(&self) -> usize {
+        match self {
+            JsonCodec::Lines => CHUNK_SIZE,
+            // one array per subset, it's encoded in a single go
+            JsonCodec::Array => usize::MAX,
+        }
+    }
+}
+impl Appendable for BsonCodec {}
+impl Appendable for BincodeCodec {}
+impl Appendable for MessagePackCodec {}
+impl Appendable for CborCodec {}
+
+pub trait AsyncPayloadCodec {
+    /// Same as `PayloadCodec::encode` but into tokio writers. The serde work runs on the
+    /// blocking pool so the runtime isn't stalled by it.
+    fn encode_async<W: AsyncWrite + Unpin + Send>(
+        &self,
+        payload: Payload,
+        writers: &mut Data<W>,
+    ) -> impl Future<Output = Result<(), CodecError>> + Send;
+}
+
+impl<C> AsyncPayloadCodec for C
+where
+    C: Appendable
+        + Clone
+        + Send
+        + Sync
+        + 'static
+        + Encode<CoinConfig, Vec<u8>>
+        + Encode<MessageConfig, Vec<u8>>
+        + Encode<ContractConfig, Vec<u8>>
+        + Encode<ContractState, Vec<u8>>
+        + Encode<ContractBalance, Vec<u8>>
+        + Encode<ContractStateRoot, Vec<u8>>,
+{
+    async fn encode_async<W: AsyncWrite + Unpin + Send>(
+        &self,
+        payload: Payload,
+        writers: &mut Data<W>,
+    ) -> Result<(), CodecError> {
+        encode_subset_async(self, payload.coins, &mut writers.coins).await?;
+        encode_subset_async(self, payload.messages, &mut writers.messages).await?;
+        encode_subset_async(self, payload.contracts, &mut writers.contracts).await?;
+        encode_subset_async(self, payload.contract_state, &mut writers.contract_state).await?;
+        encode_subset_async(
+            self,
+            payload.contract_balance,
+            &mut writers.contract_balance,
+        )
+        .await?;
+        encode_subset_async(
+            self,
+            payload.contract_state_root,
+            &mut writers.contract_state_root,
+        )
+        .await?;
+        Ok(())
+    }
+}
+
+async fn encode_subset_async<C, T, W>(
+    codec: &C,
+    data: Vec<T>,
+    writer: &mut W,
+) -> Result<(), CodecError>
+where
+    C: Appendable + Encode<T, Vec<u8>> + Clone + Send + 'static,
+    T: Send + 'static,
+    W: AsyncWrite + Unpin,
+{
+    let chunk_size = codec.chunk_size();
+    let mut data = data.into_iter().peekable();
+    // at least one chunk, an empty subset still has to be framed by the formats that do so
+    loop {
+        let chunk: Vec<T> = data.by_ref().take(chunk_size).collect();
+        let codec = codec.clone();
+        let encoded = tokio::task::spawn_blocking(move || {
+            let mut buffer = vec![];
+            codec.encode_subset(&chunk, &mut buffer).map(|_| buffer)
+        })
+        .await
+        .map_err(std::io::Error::other)??;
+        writer.write_all(&encoded).await?;
+        if data.peek().is_none() {
+            break;
+        }
+    }
+    writer.flush().await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        encoding::PayloadCodec,
+        util::{payload_with, PayloadConfig},
+    };
+
+    #[tokio::test]
+    async fn coins_round_trip_through_async_writers() {
+        // given
+        let payload = payload_with(&PayloadConfig {
+            coins: 2 * CHUNK_SIZE + 1,
+            ..PayloadConfig::new(0).with_seed(3)
+        });
+        let mut data = Data::with_capacity(0);
+
+        // when
+        BincodeCodec::default()
+            .encode_async(payload.clone(), &mut data)
+            .await
+            .unwrap();
+
+        // then
+        let decoded =
+            PayloadCodec::<_, Vec<u8>>::decode(&BincodeCodec::default(), data.wrap_in_cursor())
+                .unwrap();
+        pretty_assertions::assert_eq!(decoded.coins.len(), 2 * CHUNK_SIZE + 1);
+        pretty_assertions::assert_eq!(decoded, payload);
+    }
+
+    #[tokio::test]
+    async fn json_arrays_are_not_split_into_chunks() {
+        // given
+        let payload = payload_with(&PayloadConfig {
+            coins: 2 * CHUNK_SIZE + 1,
+            ..PayloadConfig::new(0).with_seed(3)
+        });
+        let mut data = Data::with_capacity(0);
+
+        // when
+        JsonCodec::Array
+            .encode_async(payload.clone(), &mut data)
+            .await
+            .unwrap();
+
+        // then
+        let decoded =
+            PayloadCodec::<_, Vec<u8>>::decode(&JsonCodec::Array, data.wrap_in_cursor()).unwrap();
+        pretty_assertions::assert_eq!(decoded, payload);
+    }
+}
@@ -0,0 +1,552 @@
+use std::{
+    collections::HashMap,
+    io::{BufRead, ErrorKind, Read, Write},
+};
+
+use fuel_core_types::blockchain::primitives::DaBlockHeight;
+use fuel_types::{Address, AssetId, BlockHeight, Bytes32, ContractId, Nonce, Salt};
+use serde_json::{json, Value};
+
+use super::{CodecError, Decode, Encode, EncodeStream, StreamEncoder};
+
+const MAGIC: &[u8; 4] = b"Obj\x01";
+/// Records per data block. Each block is followed by the sync marker.
+const BLOCK_SIZE: usize = 1000;
+/// The fewest bytes holding every u64 as a big endian two's complement.
+const U64_LEN: usize = 9;
+/// Digits of `u64::MAX`.
+const U64_PRECISION: usize = 20;
+
+/// Writes every subset as an uncompressed Avro object container file, the record schema is
+/// stored in the file header.
+#[derive(Clone)]
+pub struct AvroCodec;
+
+/// A config type stored as an Avro record, derived along with `ParquetSchema`.
+pub trait AvroRecord: Sized {
+    fn schema() -> Value;
+    fn write(&self, out: &mut Vec<u8>);
+    fn read(input: &mut impl Read) -> std::io::Result<Self>;
+}
+
+/// A field of an `AvroRecord`. `name` is only used by the named types, i.e. `fixed`.
+pub trait AvroValue: Sized {
+    fn schema(name: &str) -> Value;
+    fn write(&self, out: &mut Vec<u8>);
+    fn read(input: &mut impl Read) -> std::io::Result<Self>;
+}
+
+impl<T: AvroRecord, W: Write> Encode<T, W> for AvroCodec {
+    fn encode_subset(&self, data: &[T], writer: &mut W) -> Result<(), CodecError> {
+        let mut encoder = AvroStreamEncoder::new::<T>(writer)?;
+        encoder.encode_batch(data)
+    }
+}
+
+impl<T: AvroRecord, W: Write> EncodeStream<T, W> for AvroCodec {
+    fn stream_encoder<'a>(
+        &'a self,
+        writer: &'a mut W,
+    ) -> Result<Box<dyn StreamEncoder<T> + 'a>, CodecError>
+    where
+        T: 'a,
+    {
+        Ok(Box::new(AvroStreamEncoder::new::<T>(writer)?))
+    }
+}
+
+/// Writes the header up front, every batch then becomes one or more blocks. A short batch gives
+/// a short block, the blocks carry their own record count.
+struct AvroStreamEncoder<'a, W> {
+    writer: &'a mut W,
+    sync: [u8; 16],
+    block: Vec<u8>,
+    framing: Vec<u8>,
+}
+
+impl<'a, W: Write> AvroStreamEncoder<'a, W> {
+    fn new<T: AvroRecord>(writer: &'a mut W) -> Result<Self, CodecError> {
+        let sync: [u8; 16] = rand::random();
+
+        let mut header = MAGIC.to_vec();
+        let schema = T::schema().to_string();
+        write_long(&mut header, 2);
+        write_bytes(&mut header, b"avro.schema");
+        write_bytes(&mut header, schema.as_bytes());
+        write_bytes(&mut header, b"avro.codec");
+        write_bytes(&mut header, b"null");
+        write_long(&mut header, 0);
+        header.extend_from_slice(&sync);
+        writer.write_all(&header)?;
+
+        Ok(Self {
+            writer,
+            sync,
+            block: vec![],
+            framing: vec![],
+        })
+    }
+}
+
+impl<T: AvroRecord, W: Write> StreamEncoder<T> for AvroStreamEncoder<'_, W> {
+    fn encode_batch(&mut self, batch: &[T]) -> Result<(), CodecError> {
+        for chunk in batch.chunks(BLOCK_SIZE) {
+            self.block.clear();
+            for entry in chunk {
+                entry.write(&mut self.block);
+            }
+            self.framing.clear();
+            write_long(&mut self.framing, chunk.len() as i64);
+            write_long(&mut self.framing, self.block.len() as i64);
+            self.writer.write_all(&self.framing)?;
+            self.writer.write_all(&self.block)?;
+            self.writer.write_all(&self.sync)?;
+        }
+        Ok(())
+    }
+
+    fn finish(self: Box<Self>) -> Result<(), CodecError> {
+        Ok(())
+    }
+}
+
+impl<T: AvroRecord, R: BufRead> Decode<T, R> for AvroCodec {
+    fn decode_subset(&self, mut data: R) -> Result<Vec<T>, CodecError> {
+        let mut magic = [0; 4];
+        data.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid_data("not an avro object container file").into());
+        }
+
+        let metadata = read_metadata(&mut data)?;
+        if let Some(codec) = metadata.get("avro.codec") {
+            if codec != b"null" {
+                let codec = String::from_utf8_lossy(codec);
+                return Err(invalid_data(format!("unsupported avro codec {codec}")).into());
+            }
+        }
+        let schema = metadata
+            .get("avro.schema")
+            .ok_or_else(|| invalid_data("avro file has no schema"))?;
+        if flatten_schema(serde_json::from_slice(schema)?) != flatten_schema(T::schema()) {
+            return Err(invalid_data("avro schema doesn't match the decoded type").into());
+        }
+
+        let mut sync = [0; 16];
+        data.read_exact(&mut sync)?;
+
+        let mut decoded = vec![];
+        let mut marker = [0; 16];
+        while !data.fill_buf()?.is_empty() {
+            let count = read_long(&mut data)?;
+            let _block_len = read_long(&mut data)?;
+            for _ in 0..count {
+                decoded.push(T::read(&mut data)?);
+            }
+            data.read_exact(&mut marker)?;
+            if marker != sync {
+                return Err(invalid_data("avro block is not followed by the sync marker").into());
+            }
+        }
+        Ok(decoded)
+    }
+}
+
+/// Other writers, `apache-avro` among them, wrap a logical type around its underlying type,
+/// `{"type": {"type": "fixed", ..}, "logicalType": ..}`, rather than adding it as attributes of
+/// the `fixed`. Both spell the same schema, this turns the first into the second.
+fn flatten_schema(schema: Value) -> Value {
+    match schema {
+        Value::Array(branches) => branches.into_iter().map(flatten_schema).collect(),
+        Value::Object(mut attributes) => {
+            match attributes.remove("type") {
+                Some(Value::Object(underlying)) => {
+                    attributes.extend(underlying);
+                    return flatten_schema(Value::Object(attributes));
+                }
+                Some(ty) => {
+                    attributes.insert("type".to_string(), ty);
+                }
+                None => {}
+            }
+            if let Some(Value::Array(fields)) = attributes.get_mut("fields") {
+                for field in fields.iter_mut().filter_map(Value::as_object_mut) {
+                    if let Some(ty) = field.remove("type") {
+                        field.insert("type".to_string(), flatten_schema(ty));
+                    }
+                }
+            }
+            Value::Object(attributes)
+        }
+        other => other,
+    }
+}
+
+fn invalid_data(msg: impl Into<String>) -> std::io::Error {
+    std::io::Error::new(ErrorKind::InvalidData, msg.into())
+}
+
+/// Zigzag encoded variable length integer, used for both `int` and `long`.
+fn write_long(out: &mut Vec<u8>, value: i64) {
+    let mut value = ((value << 1) ^ (value >> 63)) as u64;
+    while value >= 0x80 {
+        out.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn read_long(input: &mut impl Read) -> std::io::Result<i64> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let mut byte = [0];
+        input.read_exact(&mut byte)?;
+        value |= u64::from(byte[0] & 0x7f) << shift;
+        if byte[0] & 0x80 == 0 {
+            return Ok((value >> 1) as i64 ^ -((value & 1) as i64));
+        }
+    }
+    Err(invalid_data("avro varint is longer than 10 bytes"))
+}
+
+fn write_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+    write_long(out, bytes.len() as i64);
+    out.extend_from_slice(bytes);
+}
+
+fn read_bytes(input: &mut impl Read) -> std::io::Result<Vec<u8>> {
+    let len = usize::try_from(read_long(input)?)
+        .map_err(|_| invalid_data("negative avro bytes length"))?;
+    let mut bytes = vec![];
+    input.take(len as u64).read_to_end(&mut bytes)?;
+    if bytes.len() != len {
+        return Err(ErrorKind::UnexpectedEof.into());
+    }
+    Ok(bytes)
+}
+
+fn read_metadata(input: &mut impl Read) -> std::io::Result<HashMap<String, Vec<u8>>> {
+    let mut metadata = HashMap::new();
+    loop {
+        let count = match read_long(input)? {
+            0 => return Ok(metadata),
+            // a negative count is followed by the size of the block in bytes
+            count if count < 0 => {
+                read_long(input)?;
+                -count
+            }
+            count => count,
+        };
+        for _ in 0..count {
+            let key = String::from_utf8(read_bytes(input)?)
+                .map_err(|_| invalid_data("avro metadata key is not utf8"))?;
+            metadata.insert(key, read_bytes(input)?);
+        }
+    }
+}
+
+macro_rules! impl_avro_value_for_hash {
+    ($($hash:ty),*) => {
+        $(
+            impl AvroValue for $hash {
+                fn schema(name: &str) -> Value {
+                    json!({"type": "fixed", "name": name, "size": 32})
+                }
+
+                fn write(&self, out: &mut Vec<u8>) {
+                    out.extend_from_slice(self.as_ref());
+                }
+
+                fn read(input: &mut impl Read) -> std::io::Result<Self> {
+                    let mut bytes = [0; 32];
+                    input.read_exact(&mut bytes)?;
+                    Ok(<$hash>::new(bytes))
+                }
+            }
+        )*
+    };
+}
+
+impl_avro_value_for_hash!(Bytes32, Address, AssetId, ContractId, Salt, Nonce);
+
+impl AvroValue for Vec<u8> {
+    fn schema(_: &str) -> Value {
+        json!("bytes")
+    }
+
+    fn write(&self, out: &mut Vec<u8>) {
+        write_bytes(out, self);
+    }
+
+    fn read(input: &mut impl Read) -> std::io::Result<Self> {
+        read_bytes(input)
+    }
+}
+
+macro_rules! impl_avro_value_for_int {
+    ($($int:ty => $avro:literal),*) => {
+        $(
+            impl AvroValue for $int {
+                fn schema(_: &str) -> Value {
+                    json!($avro)
+                }
+
+                fn write(&self, out: &mut Vec<u8>) {
+                    write_long(out, i64::from(*self));
+                }
+
+                fn read(input: &mut impl Read) -> std::io::Result<Self> {
+                    <$int>::try_from(read_long(input)?).map_err(|_| {
+                        invalid_data(concat!("avro value out of range for ", stringify!($int)))
+                    })
+                }
+            }
+        )*
+    };
+}
+
+// avro's `int` is a signed 32 bit integer, so u32 goes into a `long`
+impl_avro_value_for_int!(u8 => "int", u16 => "int", u32 => "long");
+
+/// Avro has no unsigned types and a `long` can't hold the larger amounts, so a u64 is a decimal
+/// without a scale. Readers see the actual number rather than a negative one.
+impl AvroValue for u64 {
+    fn schema(name: &str) -> Value {
+        json!({
+            "type": "fixed",
+            "name": name,
+            "size": U64_LEN,
+            "logicalType": "decimal",
+            "precision": U64_PRECISION,
+            "scale": 0,
+        })
+    }
+
+    fn write(&self, out: &mut Vec<u8>) {
+        out.push(0);
+        out.extend_from_slice(&self.to_be_bytes());
+    }
+
+    fn read(input: &mut impl Read) -> std::io::Result<Self> {
+        let mut bytes = [0; U64_LEN];
+        input.read_exact(&mut bytes)?;
+        let [0, unsigned @ ..] = bytes else {
+            return Err(invalid_data("avro decimal out of range for u64"));
+        };
+        Ok(u64::from_be_bytes(unsigned))
+    }
+}
+
+impl AvroValue for BlockHeight {
+    fn schema(name: &str) -> Value {
+        u32::schema(name)
+    }
+
+    fn write(&self, out: &mut Vec<u8>) {
+        (**self).write(out);
+    }
+
+    fn read(input: &mut impl Read) -> std::io::Result<Self> {
+        u32::read(input).map(BlockHeight::new)
+    }
+}
+
+impl AvroValue for DaBlockHeight {
+    fn schema(name: &str) -> Value {
+        u64::schema(name)
+    }
+
+    fn write(&self, out: &mut Vec<u8>) {
+        self.0.write(out);
+    }
+
+    fn read(input: &mut impl Read) -> std::io::Result<Self> {
+        u64::read(input).map(DaBlockHeight)
+    }
+}
+
+impl<T: AvroValue> AvroValue for Option<T> {
+    fn schema(name: &str) -> Value {
+        json!(["null", T::schema(name)])
+    }
+
+    fn write(&self, out: &mut Vec<u8>) {
+        match self {
+            None => write_long(out, 0),
+            Some(value) => {
+                write_long(out, 1);
+                value.write(out);
+            }
+        }
+    }
+
+    fn read(input: &mut impl Read) -> std::io::Result<Self> {
+        match read_long(input)? {
+            0 => Ok(None),
+            1 => T::read(input).map(Some),
+            index => Err(invalid_data(format!("union has no branch {index}"))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use apache_avro::{types::Value as ApacheValue, Decimal, Reader, Schema, Writer};
+    use itertools::Itertools;
+
+    use super::*;
+    use crate::{
+        serde_types::{CoinConfig, ContractConfig},
+        test_support::seeded_rng,
+    };
+
+    /// `coin` as `apache-avro` reads it back.
+    fn apache_record(coin: &CoinConfig) -> ApacheValue {
+        let fixed = |hash: &[u8]| ApacheValue::Fixed(32, hash.to_vec());
+        let some = |value| ApacheValue::Union(1, Box::new(value));
+        let none = ApacheValue::Union(0, Box::new(ApacheValue::Null));
+        let amount = [&[0][..], &coin.amount.to_be_bytes()].concat();
+        let fields = [
+            (
+                "tx_id",
+                coin.tx_id.map_or(none.clone(), |id| some(fixed(&*id))),
+            ),
+            (
+                "output_index",
+                coin.output_index
+                    .map_or(none.clone(), |idx| some(ApacheValue::Int(idx.into()))),
+            ),
+            (
+                "tx_pointer_block_height",
+                coin.tx_pointer_block_height.map_or(none.clone(), |height| {
+                    some(ApacheValue::Long((*height).into()))
+                }),
+            ),
+            (
+                "tx_pointer_tx_idx",
+                coin.tx_pointer_tx_idx
+                    .map_or(none.clone(), |idx| some(ApacheValue::Int(idx.into()))),
+            ),
+            (
+                "maturity",
+                coin.maturity.map_or(none.clone(), |height| {
+                    some(ApacheValue::Long((*height).into()))
+                }),
+            ),
+            ("owner", fixed(&*coin.owner)),
+            ("amount", ApacheValue::Decimal(Decimal::from(amount))),
+            ("asset_id", fixed(&*coin.asset_id)),
+        ];
+        ApacheValue::Record(
+            fields
+                .into_iter()
+                .map(|(name, value)| (name.to_string(), value))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn apache_avro_reads_the_encoded_file() {
+        // given
+        let mut rng = seeded_rng(1);
+        let coins = vec![
+            CoinConfig {
+                amount: u64::MAX,
+                ..CoinConfig::random(&mut rng)
+            },
+            CoinConfig {
+                tx_id: None,
+                maturity: None,
+                ..CoinConfig::random(&mut rng)
+            },
+        ];
+        let mut buffer = vec![];
+        AvroCodec.encode_subset(&coins, &mut buffer).unwrap();
+
+        // when
+        let records = Reader::new(buffer.as_slice())
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+
+        // then
+        pretty_assertions::assert_eq!(records, coins.iter().map(apache_record).collect_vec());
+    }
+
+    #[test]
+    fn reads_files_written_by_apache_avro() {
+        // given
+        let mut rng = seeded_rng(1);
+        let coins = [CoinConfig::random(&mut rng), CoinConfig::random(&mut rng)];
+        let schema = Schema::parse(&CoinConfig::schema()).unwrap();
+        let mut writer = Writer::new(&schema, vec![]);
+        for coin in &coins {
+            writer.append(apache_record(coin)).unwrap();
+        }
+        let buffer = writer.into_inner().unwrap();
+
+        // when
+        let decoded: Vec<CoinConfig> = AvroCodec.decode_subset(Cursor::new(buffer)).unwrap();
+
+        // then
+        pretty_assertions::assert_eq!(decoded, coins);
+    }
+
+    #[test]
+    fn round_trips_contracts_with_and_without_optional_fields() {
+        // given
+        let mut rng = seeded_rng(1);
+        let with_optionals = ContractConfig::random(&mut rng);
+        let without_optionals = ContractConfig {
+            tx_id: None,
+            output_index: None,
+            tx_pointer_block_height: None,
+            tx_pointer_tx_idx: None,
+            ..ContractConfig::random(&mut rng)
+        };
+        let contracts = vec![with_optionals, without_optionals];
+        let mut buffer = vec![];
+
+        // when
+        AvroCodec.encode_subset(&contracts, &mut buffer).unwrap();
+        let decoded: Vec<ContractConfig> = AvroCodec.decode_subset(Cursor::new(buffer)).unwrap();
+
+        // then
+        pretty_assertions::assert_eq!(decoded, contracts);
+    }
+
+    #[test]
+    fn schema_maps_hashes_to_fixed_and_options_to_unions() {
+        // when
+        let schema = ContractConfig::schema();
+
+        // then
+        pretty_assertions::assert_eq!(
+            schema["fields"][0],
+            json!({"name": "contract_id", "type": {"type": "fixed", "name": "contract_id", "size": 32}})
+        );
+        pretty_assertions::assert_eq!(
+            schema["fields"][4],
+            json!({"name": "output_index", "type": ["null", "int"]})
+        );
+    }
+
+    #[test]
+    fn longs_survive_the_zigzag_encoding() {
+        // given
+        let values = [0, 1, -1, 63, -64, 64, i64::MAX, i64::MIN];
+        let mut buffer = vec![];
+
+        // when
+        for value in values {
+            write_long(&mut buffer, value);
+        }
+
+        // then
+        let mut input = Cursor::new(buffer);
+        let decoded = values.map(|_| read_long(&mut input).unwrap());
+        pretty_assertions::assert_eq!(decoded, values);
+    }
+}
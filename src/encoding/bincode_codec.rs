@@ -1,31 +1,176 @@
-use bincode::config::{Configuration, LittleEndian, NoLimit, Varint};
+use bincode::config::{self, Configuration};
 use serde::{de::DeserializeOwned, Serialize};
 
-use super::{Decode, Encode};
-#[derive(Clone)]
-pub struct BincodeCodec;
+use super::{CodecError, Decode, DecodeLimited, Encode};
+
+/// The bincode `Configuration` entries are written and read back with. Bincode stores neither,
+/// a subset only decodes with the config it was encoded with.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum BincodeConfig {
+    /// Little endian varints, bincode's `standard` config.
+    #[default]
+    LittleEndian,
+    /// Big endian varints.
+    BigEndian,
+    /// Little endian integers written at their full width.
+    Fixint,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct BincodeCodec {
+    config: BincodeConfig,
+}
+
+impl BincodeCodec {
+    /// The default, little endian varints.
+    pub fn little_endian() -> Self {
+        Self {
+            config: BincodeConfig::LittleEndian,
+        }
+    }
+
+    /// Varints too, but the bytes of every integer wider than a varint's single byte are big
+    /// endian.
+    pub fn big_endian() -> Self {
+        Self {
+            config: BincodeConfig::BigEndian,
+        }
+    }
+
+    /// Every integer at its full width, little endian. Larger but without the varint branches.
+    pub fn fixint() -> Self {
+        Self {
+            config: BincodeConfig::Fixint,
+        }
+    }
+
+    fn encode_entry<T: Serialize>(
+        &self,
+        entry: &T,
+        writer: &mut impl std::io::Write,
+    ) -> Result<(), CodecError> {
+        let standard = config::standard();
+        match self.config {
+            BincodeConfig::LittleEndian => {
+                bincode::serde::encode_into_std_write(entry, writer, standard)?
+            }
+            BincodeConfig::BigEndian => {
+                bincode::serde::encode_into_std_write(entry, writer, standard.with_big_endian())?
+            }
+            BincodeConfig::Fixint => bincode::serde::encode_into_std_write(
+                entry,
+                writer,
+                standard.with_fixed_int_encoding(),
+            )?,
+        };
+        Ok(())
+    }
+
+    fn decode_entry<T: DeserializeOwned>(
+        &self,
+        reader: &mut impl std::io::Read,
+    ) -> Result<T, CodecError> {
+        let standard: Configuration = config::standard();
+        let entry = match self.config {
+            BincodeConfig::LittleEndian => bincode::serde::decode_from_std_read(reader, standard)?,
+            BincodeConfig::BigEndian => {
+                bincode::serde::decode_from_std_read(reader, standard.with_big_endian())?
+            }
+            BincodeConfig::Fixint => {
+                bincode::serde::decode_from_std_read(reader, standard.with_fixed_int_encoding())?
+            }
+        };
+        Ok(entry)
+    }
+
+    /// Hands up to `max` entries to `f` one by one, see `DecodeLimited`.
+    fn decode_each_limited<T: DeserializeOwned>(
+        &self,
+        data: &mut impl std::io::BufRead,
+        max: usize,
+        mut f: impl FnMut(T),
+    ) -> Result<(), CodecError> {
+        let mut decoded = 0;
+        while decoded < max && !data.fill_buf()?.is_empty() {
+            f(self.decode_entry(data)?);
+            decoded += 1;
+        }
+        Ok(())
+    }
+}
+
 impl<T: Serialize, W: std::io::Write> Encode<T, W> for BincodeCodec {
-    fn encode_subset(&self, data: Vec<T>, mut writer: &mut W) {
+    fn encode_subset(&self, data: &[T], writer: &mut W) -> Result<(), CodecError> {
         for entry in data {
-            bincode::serde::encode_into_std_write::<
-                _,
-                Configuration<LittleEndian, Varint, NoLimit>,
-                _,
-            >(entry, &mut writer, Configuration::default())
-            .unwrap();
+            self.encode_entry(entry, writer)?;
         }
+        Ok(())
     }
 }
 
 impl<T: DeserializeOwned, R: std::io::BufRead> Decode<T, R> for BincodeCodec {
-    fn decode_subset(&self, mut data: R) {
-        while !data.fill_buf().unwrap().is_empty() {
-            bincode::serde::decode_from_std_read::<
-                T,
-                Configuration<LittleEndian, Varint, NoLimit>,
-                _,
-            >(&mut data, Configuration::default())
-            .unwrap();
+    fn decode_subset(&self, mut data: R) -> Result<Vec<T>, CodecError> {
+        self.decode_subset_limited(&mut data, usize::MAX)
+    }
+
+    fn decode_subset_each(&self, mut data: R, f: impl FnMut(T)) -> Result<(), CodecError> {
+        self.decode_each_limited(&mut data, usize::MAX, f)
+    }
+}
+
+impl<T: DeserializeOwned, R: std::io::BufRead> DecodeLimited<T, R> for BincodeCodec {
+    fn decode_subset_limited(&self, data: &mut R, max: usize) -> Result<Vec<T>, CodecError> {
+        let mut decoded = vec![];
+        self.decode_each_limited(data, max, |entry| decoded.push(entry))?;
+        Ok(decoded)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::{serde_types::CoinConfig, test_support::coins};
+
+    #[test]
+    fn every_config_round_trips() {
+        // given
+        let coins = coins(20, 3);
+
+        for codec in [
+            BincodeCodec::little_endian(),
+            BincodeCodec::big_endian(),
+            BincodeCodec::fixint(),
+        ] {
+            let mut buffer = vec![];
+
+            // when
+            codec.encode_subset(&coins, &mut buffer).unwrap();
+
+            // then
+            let decoded: Vec<CoinConfig> = codec.decode_subset(Cursor::new(buffer)).unwrap();
+            pretty_assertions::assert_eq!(decoded, coins);
         }
     }
+
+    #[test]
+    fn configs_lay_out_integers_differently() {
+        // given
+        let encode = |codec: BincodeCodec| {
+            let mut buffer = vec![];
+            codec.encode_subset(&[0x0102u64], &mut buffer).unwrap();
+            buffer
+        };
+
+        // when
+        let little = encode(BincodeCodec::little_endian());
+        let big = encode(BincodeCodec::big_endian());
+        let fixint = encode(BincodeCodec::fixint());
+
+        // then
+        pretty_assertions::assert_eq!(little, [251, 0x02, 0x01]);
+        pretty_assertions::assert_eq!(big, [251, 0x01, 0x02]);
+        pretty_assertions::assert_eq!(fixint, [0x02, 0x01, 0, 0, 0, 0, 0, 0]);
+    }
 }
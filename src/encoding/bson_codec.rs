@@ -1,20 +1,175 @@
+use std::io::ErrorKind;
+
 use serde::{de::DeserializeOwned, Serialize};
 
-use super::{Decode, Encode};
+use super::{CodecError, Decode, DecodeLimited, Encode};
+
+/// Every bson document starts with its total length, prefix included, as a little endian i32.
+const LENGTH_PREFIX: usize = 4;
+/// The length prefix plus the trailing null byte of an empty document.
+const MIN_DOCUMENT_LEN: usize = LENGTH_PREFIX + 1;
+
+/// Writes every entry as its own bson document. bson has no unsigned 64 bit integers, the amounts
+/// are stored as the `i64` of the same bits, see `serde_types::i64_bits`.
 #[derive(Clone)]
 pub struct BsonCodec;
 impl<T: Serialize, W: std::io::Write> Encode<T, W> for BsonCodec {
-    fn encode_subset(&self, data: Vec<T>, writer: &mut W) {
+    fn encode_subset(&self, data: &[T], writer: &mut W) -> Result<(), CodecError> {
         for entry in data {
-            let bytes = bson::to_vec(&entry).unwrap();
-            writer.write_all(&bytes).unwrap();
+            // the document's own length prefix is what frames it in the stream
+            let bytes = bson::to_vec(entry)?;
+            debug_assert_eq!(document_len(&bytes), bytes.len());
+            writer.write_all(&bytes)?;
         }
+        Ok(())
     }
 }
-impl<T: DeserializeOwned, R: std::io::BufRead> Decode<T, R> for BsonCodec {
-    fn decode_subset(&self, mut data: R) {
-        while !data.fill_buf().unwrap().is_empty() {
-            bson::from_reader::<_, T>(&mut data).unwrap();
+impl BsonCodec {
+    /// Hands up to `max` entries to `f` one by one, see `DecodeLimited`.
+    fn decode_each_limited<T: DeserializeOwned>(
+        &self,
+        data: &mut impl std::io::BufRead,
+        max: usize,
+        mut f: impl FnMut(T),
+    ) -> Result<(), CodecError> {
+        let mut decoded = 0;
+        let mut document = vec![];
+        while decoded < max && !data.fill_buf()?.is_empty() {
+            // read the exact document so a short read can't shift the next one
+            document.resize(LENGTH_PREFIX, 0);
+            data.read_exact(&mut document)?;
+            let len = document_len(&document);
+            if len < MIN_DOCUMENT_LEN {
+                return Err(std::io::Error::new(
+                    ErrorKind::InvalidData,
+                    format!("bson document claims to be {len} bytes long"),
+                )
+                .into());
+            }
+            document.resize(len, 0);
+            data.read_exact(&mut document[LENGTH_PREFIX..])?;
+            f(bson::from_slice::<T>(&document)?);
+            decoded += 1;
         }
+        Ok(())
+    }
+}
+
+impl<T: DeserializeOwned, R: std::io::BufRead> Decode<T, R> for BsonCodec {
+    fn decode_subset(&self, mut data: R) -> Result<Vec<T>, CodecError> {
+        self.decode_subset_limited(&mut data, usize::MAX)
+    }
+
+    fn decode_subset_each(&self, mut data: R, f: impl FnMut(T)) -> Result<(), CodecError> {
+        self.decode_each_limited(&mut data, usize::MAX, f)
+    }
+}
+
+impl<T: DeserializeOwned, R: std::io::BufRead> DecodeLimited<T, R> for BsonCodec {
+    fn decode_subset_limited(&self, data: &mut R, max: usize) -> Result<Vec<T>, CodecError> {
+        let mut decoded = vec![];
+        self.decode_each_limited(data, max, |entry| decoded.push(entry))?;
+        Ok(decoded)
+    }
+}
+
+fn document_len(document: &[u8]) -> usize {
+    let prefix = document[..LENGTH_PREFIX]
+        .try_into()
+        .expect("prefix is 4 bytes");
+    i32::from_le_bytes(prefix).max(0) as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufReader, Cursor};
+
+    use itertools::Itertools;
+
+    use super::*;
+    use crate::{serde_types::CoinConfig, test_support::seeded_rng};
+
+    fn coins(count: usize) -> Vec<CoinConfig> {
+        let mut rng = seeded_rng(1);
+        (0..count)
+            .map(|_| CoinConfig::random(&mut rng))
+            .collect_vec()
+    }
+
+    #[test]
+    fn documents_are_prefixed_with_their_length() {
+        // given
+        let coin = coins(1);
+        let mut buffer = vec![];
+
+        // when
+        BsonCodec.encode_subset(&coin, &mut buffer).unwrap();
+
+        // then
+        pretty_assertions::assert_eq!(document_len(&buffer), buffer.len());
+    }
+
+    #[test]
+    fn reads_back_many_documents() {
+        // given
+        let coins = coins(1000);
+        let mut buffer = vec![];
+        BsonCodec.encode_subset(&coins, &mut buffer).unwrap();
+
+        // when
+        let decoded: Vec<CoinConfig> = BsonCodec.decode_subset(Cursor::new(buffer)).unwrap();
+
+        // then
+        pretty_assertions::assert_eq!(decoded, coins);
+    }
+
+    #[test]
+    fn reads_back_documents_one_byte_at_a_time() {
+        // given
+        let coins = coins(1000);
+        let mut buffer = vec![];
+        BsonCodec.encode_subset(&coins, &mut buffer).unwrap();
+        let reader = BufReader::with_capacity(1, Cursor::new(buffer));
+
+        // when
+        let decoded: Vec<CoinConfig> = BsonCodec.decode_subset(reader).unwrap();
+
+        // then
+        pretty_assertions::assert_eq!(decoded, coins);
+    }
+
+    #[test]
+    fn amounts_above_i64_max_are_kept_as_their_bits() {
+        // given
+        let coin = CoinConfig {
+            amount: u64::MAX - 1,
+            ..coins(1).remove(0)
+        };
+        let mut buffer = vec![];
+
+        // when
+        BsonCodec
+            .encode_subset(std::slice::from_ref(&coin), &mut buffer)
+            .unwrap();
+
+        // then
+        let document = bson::Document::from_reader(Cursor::new(&buffer)).unwrap();
+        pretty_assertions::assert_eq!(document.get_i64("amount").unwrap(), -2);
+        let decoded: Vec<CoinConfig> = BsonCodec.decode_subset(Cursor::new(buffer)).unwrap();
+        pretty_assertions::assert_eq!(decoded, vec![coin]);
+    }
+
+    #[test]
+    fn truncated_document_is_an_error() {
+        // given
+        let mut buffer = vec![];
+        BsonCodec.encode_subset(&coins(1), &mut buffer).unwrap();
+        buffer.pop();
+
+        // when
+        let result = Decode::<CoinConfig, _>::decode_subset(&BsonCodec, Cursor::new(buffer));
+
+        // then
+        assert!(matches!(result, Err(CodecError::Io(_))));
     }
 }
@@ -0,0 +1,91 @@
+use serde::{de::DeserializeOwned, Serialize};
+
+use super::{CodecError, Decode, DecodeLimited, Encode};
+
+/// Writes every entry as its own cbor item, one after the other.
+#[derive(Clone)]
+pub struct CborCodec;
+impl<T: Serialize, W: std::io::Write> Encode<T, W> for CborCodec {
+    fn encode_subset(&self, data: &[T], writer: &mut W) -> Result<(), CodecError> {
+        for entry in data {
+            ciborium::into_writer(entry, &mut *writer)?;
+        }
+        Ok(())
+    }
+}
+
+impl CborCodec {
+    /// Hands up to `max` entries to `f` one by one, see `DecodeLimited`.
+    fn decode_each_limited<T: DeserializeOwned>(
+        &self,
+        data: &mut impl std::io::BufRead,
+        max: usize,
+        mut f: impl FnMut(T),
+    ) -> Result<(), CodecError> {
+        let mut decoded = 0;
+        while decoded < max && !data.fill_buf()?.is_empty() {
+            f(ciborium::from_reader::<T, _>(&mut *data)?);
+            decoded += 1;
+        }
+        Ok(())
+    }
+}
+
+impl<T: DeserializeOwned, R: std::io::BufRead> Decode<T, R> for CborCodec {
+    fn decode_subset(&self, mut data: R) -> Result<Vec<T>, CodecError> {
+        self.decode_subset_limited(&mut data, usize::MAX)
+    }
+
+    fn decode_subset_each(&self, mut data: R, f: impl FnMut(T)) -> Result<(), CodecError> {
+        self.decode_each_limited(&mut data, usize::MAX, f)
+    }
+}
+
+impl<T: DeserializeOwned, R: std::io::BufRead> DecodeLimited<T, R> for CborCodec {
+    fn decode_subset_limited(&self, data: &mut R, max: usize) -> Result<Vec<T>, CodecError> {
+        let mut decoded = vec![];
+        self.decode_each_limited(data, max, |entry| decoded.push(entry))?;
+        Ok(decoded)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::{serde_types::ContractConfig, test_support::seeded_rng};
+
+    #[test]
+    fn reads_back_code_longer_than_the_scratch_buffer() {
+        // given
+        let contract = ContractConfig::random_with(&mut seeded_rng(1), 10_000..=10_000);
+        let mut buffer = vec![];
+
+        // when
+        CborCodec
+            .encode_subset(std::slice::from_ref(&contract), &mut buffer)
+            .unwrap();
+
+        // then
+        let decoded: Vec<ContractConfig> = CborCodec.decode_subset(Cursor::new(buffer)).unwrap();
+        pretty_assertions::assert_eq!(decoded, vec![contract]);
+    }
+
+    #[test]
+    fn truncated_entry_is_an_io_error() {
+        // given
+        let contract = ContractConfig::random(&mut seeded_rng(1));
+        let mut buffer = vec![];
+        CborCodec
+            .encode_subset(std::slice::from_ref(&contract), &mut buffer)
+            .unwrap();
+        buffer.pop();
+
+        // when
+        let result = Decode::<ContractConfig, _>::decode_subset(&CborCodec, Cursor::new(buffer));
+
+        // then
+        assert!(matches!(result, Err(CodecError::Io(_))));
+    }
+}
@@ -0,0 +1,217 @@
+use std::io::{BufRead, Read, Write};
+
+use fuel_core_types::blockchain::primitives::DaBlockHeight;
+use fuel_types::{Address, AssetId, BlockHeight, Bytes32, ContractId, Nonce, Salt};
+
+use super::{CodecError, Decode, Encode};
+
+/// Bincode without serde: hashes are raw bytes, numbers varints and a missing optional a single
+/// byte. Every `encode_subset` writes a block of the entry count followed by the entries, blocks
+/// can be appended one after the other.
+///
+/// `Columns` transposes the entries like parquet does, all `owner`s, then all `amount`s and so on.
+/// `Rows` writes the very same values entry by entry, the baseline that isolates what the columnar
+/// layout alone is worth from parquet's container and from bincode's serde representation.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ColumnarBincodeCodec {
+    #[default]
+    Columns,
+    Rows,
+}
+
+/// A config type stored field by field, derived along with `ParquetSchema`.
+pub trait ColumnarRecord: Sized {
+    fn encode_columns(rows: &[Self], writer: &mut impl Write) -> Result<(), CodecError>;
+    fn encode_rows(rows: &[Self], writer: &mut impl Write) -> Result<(), CodecError>;
+    fn decode_columns(len: usize, reader: &mut impl Read) -> Result<Vec<Self>, CodecError>;
+    fn decode_rows(len: usize, reader: &mut impl Read) -> Result<Vec<Self>, CodecError>;
+}
+
+/// A single field of a `ColumnarRecord`.
+pub trait BinaryValue: Sized {
+    fn encode_value(&self, writer: &mut impl Write) -> Result<(), CodecError>;
+    fn decode_value(reader: &mut impl Read) -> Result<Self, CodecError>;
+}
+
+impl<T: ColumnarRecord, W: Write> Encode<T, W> for ColumnarBincodeCodec {
+    fn encode_subset(&self, data: &[T], writer: &mut W) -> Result<(), CodecError> {
+        encode(data.len() as u64, writer)?;
+        match self {
+            ColumnarBincodeCodec::Columns => T::encode_columns(data, writer),
+            ColumnarBincodeCodec::Rows => T::encode_rows(data, writer),
+        }
+    }
+}
+
+impl<T: ColumnarRecord, R: BufRead> Decode<T, R> for ColumnarBincodeCodec {
+    fn decode_subset(&self, mut data: R) -> Result<Vec<T>, CodecError> {
+        let mut decoded = vec![];
+        while !data.fill_buf()?.is_empty() {
+            let len = decode::<u64>(&mut data)? as usize;
+            let block = match self {
+                ColumnarBincodeCodec::Columns => T::decode_columns(len, &mut data)?,
+                ColumnarBincodeCodec::Rows => T::decode_rows(len, &mut data)?,
+            };
+            decoded.extend(block);
+        }
+        Ok(decoded)
+    }
+}
+
+fn encode(value: impl bincode::Encode, writer: &mut impl Write) -> Result<(), CodecError> {
+    bincode::encode_into_std_write(value, writer, bincode::config::standard())?;
+    Ok(())
+}
+
+fn decode<T: bincode::Decode>(reader: &mut impl Read) -> Result<T, CodecError> {
+    Ok(bincode::decode_from_std_read(
+        reader,
+        bincode::config::standard(),
+    )?)
+}
+
+macro_rules! impl_binary_value_for_hash {
+    ($($hash:ty),*) => {
+        $(
+            impl BinaryValue for $hash {
+                fn encode_value(&self, writer: &mut impl Write) -> Result<(), CodecError> {
+                    encode(**self, writer)
+                }
+
+                fn decode_value(reader: &mut impl Read) -> Result<Self, CodecError> {
+                    decode::<[u8; 32]>(reader).map(<$hash>::new)
+                }
+            }
+        )*
+    };
+}
+
+impl_binary_value_for_hash!(Bytes32, Address, AssetId, ContractId, Salt, Nonce);
+
+macro_rules! impl_binary_value_for_native {
+    ($($native:ty),*) => {
+        $(
+            impl BinaryValue for $native {
+                fn encode_value(&self, writer: &mut impl Write) -> Result<(), CodecError> {
+                    encode(self, writer)
+                }
+
+                fn decode_value(reader: &mut impl Read) -> Result<Self, CodecError> {
+                    decode(reader)
+                }
+            }
+        )*
+    };
+}
+
+impl_binary_value_for_native!(u8, u16, u32, u64, Vec<u8>);
+
+impl BinaryValue for BlockHeight {
+    fn encode_value(&self, writer: &mut impl Write) -> Result<(), CodecError> {
+        (**self).encode_value(writer)
+    }
+
+    fn decode_value(reader: &mut impl Read) -> Result<Self, CodecError> {
+        u32::decode_value(reader).map(BlockHeight::new)
+    }
+}
+
+impl BinaryValue for DaBlockHeight {
+    fn encode_value(&self, writer: &mut impl Write) -> Result<(), CodecError> {
+        self.0.encode_value(writer)
+    }
+
+    fn decode_value(reader: &mut impl Read) -> Result<Self, CodecError> {
+        u64::decode_value(reader).map(DaBlockHeight)
+    }
+}
+
+impl<T: BinaryValue> BinaryValue for Option<T> {
+    fn encode_value(&self, writer: &mut impl Write) -> Result<(), CodecError> {
+        encode(self.is_some(), writer)?;
+        match self {
+            Some(value) => value.encode_value(writer),
+            None => Ok(()),
+        }
+    }
+
+    fn decode_value(reader: &mut impl Read) -> Result<Self, CodecError> {
+        if decode::<bool>(reader)? {
+            T::decode_value(reader).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::{
+        encoding::PayloadCodec,
+        serde_types::{CoinConfig, ContractBalance},
+        test_support::{coins, full_payload},
+        util::Data,
+    };
+
+    #[test]
+    fn both_layouts_round_trip_a_payload() {
+        // given
+        let payload = full_payload(Data::from_array([30, 20, 10, 40, 20, 10]), 1);
+
+        for codec in [ColumnarBincodeCodec::Columns, ColumnarBincodeCodec::Rows] {
+            let mut data = Data::with_capacity(0);
+
+            // when
+            PayloadCodec::<Cursor<Vec<u8>>, _>::encode(&codec, &payload, &mut data).unwrap();
+
+            // then
+            let decoded =
+                PayloadCodec::<_, Vec<u8>>::decode(&codec, data.wrap_in_cursor()).unwrap();
+            pretty_assertions::assert_eq!(decoded, payload);
+        }
+    }
+
+    #[test]
+    fn columns_hold_the_values_of_one_field_back_to_back() {
+        // given
+        let balances = [1, 2].map(|amount| ContractBalance {
+            asset_id: AssetId::new([amount as u8; 32]),
+            amount,
+        });
+        let mut columns = vec![];
+        let mut rows = vec![];
+
+        // when
+        ColumnarBincodeCodec::Columns
+            .encode_subset(&balances, &mut columns)
+            .unwrap();
+        ColumnarBincodeCodec::Rows
+            .encode_subset(&balances, &mut rows)
+            .unwrap();
+
+        // then
+        let expected_columns = [vec![2], vec![1; 32], vec![2; 32], vec![1, 2]].concat();
+        let expected_rows = [vec![2], vec![1; 32], vec![1], vec![2; 32], vec![2]].concat();
+        pretty_assertions::assert_eq!(columns, expected_columns);
+        pretty_assertions::assert_eq!(rows, expected_rows);
+    }
+
+    #[test]
+    fn appended_blocks_decode_as_one_subset() {
+        // given
+        let coins = coins(5, 2);
+        let mut buffer = vec![];
+        let codec = ColumnarBincodeCodec::Columns;
+        codec.encode_subset(&coins[..2], &mut buffer).unwrap();
+        codec.encode_subset(&coins[2..], &mut buffer).unwrap();
+
+        // when
+        let decoded: Vec<CoinConfig> = codec.decode_subset(Cursor::new(buffer)).unwrap();
+
+        // then
+        pretty_assertions::assert_eq!(decoded, coins);
+    }
+}
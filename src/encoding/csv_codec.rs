@@ -0,0 +1,278 @@
+use std::io::ErrorKind;
+
+use csv::StringRecord;
+use fuel_core_types::blockchain::primitives::DaBlockHeight;
+use fuel_types::{Address, AssetId, BlockHeight, Bytes32, ContractId, Nonce, Salt};
+
+use super::{CodecError, Decode, Encode, EncodeStream, StreamEncoder};
+use crate::serde_types::serde_hex;
+
+/// One header row followed by a row per entry. Hashes and byte blobs are `0x` prefixed hex,
+/// numbers are decimal and a missing optional value is an empty cell. Meant as a human readable
+/// baseline rather than a contender.
+#[derive(Clone)]
+pub struct CsvCodec;
+
+/// A config type stored as a csv row, one cell per field, derived along with `ParquetSchema`.
+pub trait CsvRecord: Sized {
+    fn headers() -> Vec<&'static str>;
+    fn to_record(&self) -> Vec<String>;
+    fn from_record(record: &StringRecord) -> Result<Self, CodecError>;
+}
+
+/// A single cell of a `CsvRecord`.
+pub trait CsvField: Sized {
+    fn to_field(&self) -> String;
+    fn from_field(field: &str) -> Result<Self, String>;
+}
+
+impl<T: CsvRecord, W: std::io::Write> Encode<T, W> for CsvCodec {
+    fn encode_subset(&self, data: &[T], writer: &mut W) -> Result<(), CodecError> {
+        let mut encoder = CsvStreamEncoder::new::<T>(writer)?;
+        encoder.encode_batch(data)?;
+        StreamEncoder::<T>::finish(Box::new(encoder))
+    }
+}
+
+impl<T: CsvRecord, W: std::io::Write> EncodeStream<T, W> for CsvCodec {
+    fn stream_encoder<'a>(
+        &'a self,
+        writer: &'a mut W,
+    ) -> Result<Box<dyn StreamEncoder<T> + 'a>, CodecError>
+    where
+        T: 'a,
+    {
+        Ok(Box::new(CsvStreamEncoder::new::<T>(writer)?))
+    }
+}
+
+/// Writes the header once, the batches only add rows.
+struct CsvStreamEncoder<'a, W: std::io::Write> {
+    writer: csv::Writer<&'a mut W>,
+}
+
+impl<'a, W: std::io::Write> CsvStreamEncoder<'a, W> {
+    fn new<T: CsvRecord>(writer: &'a mut W) -> Result<Self, CodecError> {
+        let mut writer = csv::Writer::from_writer(writer);
+        writer.write_record(T::headers())?;
+        Ok(Self { writer })
+    }
+}
+
+impl<T: CsvRecord, W: std::io::Write> StreamEncoder<T> for CsvStreamEncoder<'_, W> {
+    fn encode_batch(&mut self, batch: &[T]) -> Result<(), CodecError> {
+        for entry in batch {
+            self.writer.write_record(entry.to_record())?;
+        }
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> Result<(), CodecError> {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+impl<T: CsvRecord, R: std::io::BufRead> Decode<T, R> for CsvCodec {
+    fn decode_subset(&self, data: R) -> Result<Vec<T>, CodecError> {
+        let mut reader = csv::Reader::from_reader(data);
+        if reader.headers()? != T::headers().as_slice() {
+            return Err(invalid_data(format!(
+                "csv headers {:?} don't match {:?}",
+                reader.headers()?,
+                T::headers()
+            )));
+        }
+        reader
+            .records()
+            .map(|record| T::from_record(&record?))
+            .collect()
+    }
+}
+
+/// The cells of a row read in the order of the fields.
+pub struct CsvCells<'a> {
+    cells: csv::StringRecordIter<'a>,
+}
+
+impl<'a> CsvCells<'a> {
+    pub fn new(record: &'a StringRecord) -> Self {
+        Self {
+            cells: record.iter(),
+        }
+    }
+
+    /// Parses the next cell as the field `name`.
+    pub fn read<T: CsvField>(&mut self, name: &str) -> Result<T, CodecError> {
+        let cell = self
+            .cells
+            .next()
+            .ok_or_else(|| invalid_data(format!("row is missing {name}")))?;
+        T::from_field(cell).map_err(|e| invalid_data(format!("bad {name}: {e}")))
+    }
+}
+
+fn invalid_data(msg: String) -> CodecError {
+    std::io::Error::new(ErrorKind::InvalidData, msg).into()
+}
+
+macro_rules! impl_csv_field_for_hash {
+    ($($hash:ty),*) => {
+        $(
+            impl CsvField for $hash {
+                fn to_field(&self) -> String {
+                    serde_hex::encode(self, true)
+                }
+
+                fn from_field(field: &str) -> Result<Self, String> {
+                    let bytes = serde_hex::decode(field, true).map_err(|e| e.to_string())?;
+                    <$hash>::try_from(bytes.as_slice()).map_err(|e| e.to_string())
+                }
+            }
+        )*
+    };
+}
+
+impl_csv_field_for_hash!(Bytes32, Address, AssetId, ContractId, Salt, Nonce);
+
+impl CsvField for Vec<u8> {
+    fn to_field(&self) -> String {
+        serde_hex::encode(self, true)
+    }
+
+    fn from_field(field: &str) -> Result<Self, String> {
+        serde_hex::decode(field, true).map_err(|e| e.to_string())
+    }
+}
+
+macro_rules! impl_csv_field_for_int {
+    ($($int:ty),*) => {
+        $(
+            impl CsvField for $int {
+                fn to_field(&self) -> String {
+                    self.to_string()
+                }
+
+                fn from_field(field: &str) -> Result<Self, String> {
+                    field.parse().map_err(|e: std::num::ParseIntError| e.to_string())
+                }
+            }
+        )*
+    };
+}
+
+impl_csv_field_for_int!(u8, u16, u32, u64);
+
+impl CsvField for BlockHeight {
+    fn to_field(&self) -> String {
+        (**self).to_field()
+    }
+
+    fn from_field(field: &str) -> Result<Self, String> {
+        u32::from_field(field).map(BlockHeight::new)
+    }
+}
+
+impl CsvField for DaBlockHeight {
+    fn to_field(&self) -> String {
+        self.0.to_field()
+    }
+
+    fn from_field(field: &str) -> Result<Self, String> {
+        u64::from_field(field).map(DaBlockHeight)
+    }
+}
+
+impl<T: CsvField> CsvField for Option<T> {
+    fn to_field(&self) -> String {
+        self.as_ref().map(T::to_field).unwrap_or_default()
+    }
+
+    fn from_field(field: &str) -> Result<Self, String> {
+        if field.is_empty() {
+            Ok(None)
+        } else {
+            T::from_field(field).map(Some)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::{
+        serde_types::{CoinConfig, ContractBalance, ContractState, MessageConfig},
+        test_support::seeded_rng,
+    };
+
+    #[test]
+    fn writes_a_header_and_readable_cells() {
+        // given
+        let balance = ContractBalance {
+            asset_id: AssetId::new([0xab; 32]),
+            amount: 42,
+        };
+        let mut buffer = vec![];
+
+        // when
+        CsvCodec.encode_subset(&[balance], &mut buffer).unwrap();
+
+        // then
+        let expected = format!("asset_id,amount\n0x{},42\n", "ab".repeat(32));
+        pretty_assertions::assert_eq!(String::from_utf8(buffer).unwrap(), expected);
+    }
+
+    #[test]
+    fn round_trips_messages_and_missing_optionals() {
+        // given
+        let mut rng = seeded_rng(1);
+        let messages = vec![
+            MessageConfig::random(&mut rng),
+            MessageConfig::random(&mut rng),
+        ];
+        let coins = vec![CoinConfig {
+            tx_id: None,
+            maturity: None,
+            ..CoinConfig::random(&mut rng)
+        }];
+        let mut messages_buf = vec![];
+        let mut coins_buf = vec![];
+
+        // when
+        CsvCodec
+            .encode_subset(&messages, &mut messages_buf)
+            .unwrap();
+        CsvCodec.encode_subset(&coins, &mut coins_buf).unwrap();
+
+        // then
+        let decoded_messages: Vec<MessageConfig> =
+            CsvCodec.decode_subset(Cursor::new(messages_buf)).unwrap();
+        let decoded_coins: Vec<CoinConfig> =
+            CsvCodec.decode_subset(Cursor::new(coins_buf)).unwrap();
+        pretty_assertions::assert_eq!(decoded_messages, messages);
+        pretty_assertions::assert_eq!(decoded_coins, coins);
+    }
+
+    #[test]
+    fn rejects_headers_of_another_type() {
+        // given
+        let mut buffer = vec![];
+        CsvCodec
+            .encode_subset(
+                &[ContractState {
+                    key: Bytes32::zeroed(),
+                    value: Bytes32::zeroed(),
+                }],
+                &mut buffer,
+            )
+            .unwrap();
+
+        // when
+        let result = Decode::<ContractBalance, _>::decode_subset(&CsvCodec, Cursor::new(buffer));
+
+        // then
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,112 @@
+use itertools::Itertools;
+
+use super::{CodecError, Decode, EncodeStream};
+use crate::serde_types::StateEntry;
+
+/// Entries handed to the stream encoder at once.
+const BATCH_SIZE: usize = 1000;
+
+/// Writes all state types into a single stream of `StateEntry`s instead of a stream per type.
+/// Every entry carries its variant tag, so mixed streams pay for it on every entry where the per
+/// type streams don't.
+pub trait EntryCodec<R, W> {
+    fn encode_entries(
+        &self,
+        entries: impl IntoIterator<Item = StateEntry>,
+        writer: &mut W,
+    ) -> Result<(), CodecError>;
+    fn decode_entries(&self, reader: R) -> Result<Vec<StateEntry>, CodecError>;
+}
+
+impl<C, R, W> EntryCodec<R, W> for C
+where
+    C: EncodeStream<StateEntry, W> + Decode<StateEntry, R>,
+{
+    fn encode_entries(
+        &self,
+        entries: impl IntoIterator<Item = StateEntry>,
+        writer: &mut W,
+    ) -> Result<(), CodecError> {
+        let mut encoder = self.stream_encoder(writer)?;
+        for batch in &entries.into_iter().chunks(BATCH_SIZE) {
+            encoder.encode_batch(&batch.collect_vec())?;
+        }
+        encoder.finish()
+    }
+
+    fn decode_entries(&self, reader: R) -> Result<Vec<StateEntry>, CodecError> {
+        self.decode_subset(reader)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use rand::seq::SliceRandom;
+
+    use super::*;
+    use crate::{
+        encoding::{BincodeCodec, BsonCodec, CborCodec, JsonCodec, MessagePackCodec, PayloadCodec},
+        test_support::{full_payload, seeded_rng},
+        util::Data,
+    };
+
+    fn round_trip(
+        codec: &impl EntryCodec<Cursor<Vec<u8>>, Vec<u8>>,
+        entries: &[StateEntry],
+    ) -> Vec<StateEntry> {
+        let mut buffer = vec![];
+        codec.encode_entries(entries.to_vec(), &mut buffer).unwrap();
+        codec.decode_entries(Cursor::new(buffer)).unwrap()
+    }
+
+    #[test]
+    fn shuffled_entries_round_trip() {
+        // given
+        let payload = full_payload(Data::from_array([30, 20, 10, 40, 20, 10]), 1);
+        let mut entries = payload.into_entries().collect_vec();
+        entries.shuffle(&mut seeded_rng(1));
+
+        // when
+        let lines = round_trip(&JsonCodec::Lines, &entries);
+        let array = round_trip(&JsonCodec::Array, &entries);
+        let bincode = round_trip(&BincodeCodec::default(), &entries);
+        let msgpack = round_trip(&MessagePackCodec, &entries);
+        let bson = round_trip(&BsonCodec, &entries);
+        let cbor = round_trip(&CborCodec, &entries);
+
+        // then
+        pretty_assertions::assert_eq!(lines, entries);
+        pretty_assertions::assert_eq!(array, entries);
+        pretty_assertions::assert_eq!(bincode, entries);
+        pretty_assertions::assert_eq!(msgpack, entries);
+        pretty_assertions::assert_eq!(bson, entries);
+        pretty_assertions::assert_eq!(cbor, entries);
+    }
+
+    #[test]
+    fn bincode_pays_a_tag_byte_per_entry() {
+        // given
+        let payload = full_payload(Data::from_array([30, 20, 10, 40, 20, 10]), 2);
+        let mut per_type = Data::with_capacity(0);
+        PayloadCodec::<Cursor<Vec<u8>>, _>::encode(
+            &BincodeCodec::default(),
+            &payload,
+            &mut per_type,
+        )
+        .unwrap();
+        let mut single = vec![];
+
+        // when
+        EntryCodec::<Cursor<Vec<u8>>, _>::encode_entries(
+            &BincodeCodec::default(),
+            payload.clone().into_entries(),
+            &mut single,
+        )
+        .unwrap();
+
+        // then
+        pretty_assertions::assert_eq!(single.len(), per_type.len() + payload.num_entries());
+    }
+}
@@ -1,22 +1,268 @@
-use serde::{de::DeserializeOwned, Serialize};
+use std::marker::PhantomData;
+
+use serde::{
+    de::{DeserializeOwned, SeqAccess, Visitor},
+    Deserializer, Serialize,
+};
+
+use super::{
+    Appending, CodecError, Decode, DecodeLimited, Encode, EncodeAppend, EncodeStream, StreamEncoder,
+};
+
+/// How the records of a subset are laid out.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum JsonCodec {
+    /// One object per line, i.e. JSON Lines.
+    #[default]
+    Lines,
+    /// A single array of objects, without any whitespace.
+    Array,
+}
 
-use super::{Decode, Encode};
-#[derive(Clone)]
-pub struct JsonCodec;
 impl<T: Serialize, W: std::io::Write> Encode<T, W> for JsonCodec {
-    fn encode_subset(&self, data: Vec<T>, mut writer: &mut W) {
-        for entry in data {
-            serde_json::to_writer(&mut writer, &entry).unwrap();
-            writer.write_all("\n".as_bytes()).unwrap();
+    fn encode_subset(&self, data: &[T], mut writer: &mut W) -> Result<(), CodecError> {
+        match self {
+            JsonCodec::Lines => {
+                for entry in data {
+                    serde_json::to_writer(&mut writer, entry)?;
+                    writer.write_all("\n".as_bytes())?;
+                }
+                Ok(())
+            }
+            JsonCodec::Array => {
+                let mut encoder = JsonArrayEncoder::new(writer)?;
+                encoder.encode_batch(data)?;
+                StreamEncoder::<T>::finish(Box::new(encoder))
+            }
         }
     }
 }
-impl<T: DeserializeOwned, R: std::io::BufRead> Decode<T, R> for JsonCodec {
-    fn decode_subset(&self, mut data: R) {
+
+impl<T: Serialize, W: std::io::Write> EncodeStream<T, W> for JsonCodec {
+    fn stream_encoder<'a>(
+        &'a self,
+        writer: &'a mut W,
+    ) -> Result<Box<dyn StreamEncoder<T> + 'a>, CodecError>
+    where
+        T: 'a,
+    {
+        match self {
+            JsonCodec::Lines => Ok(Box::new(Appending {
+                codec: self,
+                writer,
+            })),
+            JsonCodec::Array => Ok(Box::new(JsonArrayEncoder::new(writer)?)),
+        }
+    }
+}
+
+/// Lines are simply written after the existing ones. The closing bracket of an array is taken off
+/// and written again after the new elements.
+impl<T: Serialize> EncodeAppend<T> for JsonCodec {
+    fn append_subset(&self, data: &[T], existing: &mut Vec<u8>) -> Result<(), CodecError> {
+        if let JsonCodec::Lines = self {
+            return self.encode_subset(data, existing);
+        }
+        if existing.pop() != Some(b']') {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "can only append to a complete json array",
+            )
+            .into());
+        }
+        let empty = existing.last() == Some(&b'[');
+        let mut encoder = JsonArrayEncoder {
+            writer: existing,
+            empty,
+        };
+        encoder.encode_batch(data)?;
+        StreamEncoder::<T>::finish(Box::new(encoder))
+    }
+}
+
+/// Opens the array up front and closes it in `finish`, the batches only add elements.
+struct JsonArrayEncoder<'a, W> {
+    writer: &'a mut W,
+    empty: bool,
+}
+
+impl<'a, W: std::io::Write> JsonArrayEncoder<'a, W> {
+    fn new(writer: &'a mut W) -> Result<Self, CodecError> {
+        writer.write_all(b"[")?;
+        Ok(Self {
+            writer,
+            empty: true,
+        })
+    }
+}
+
+impl<T: Serialize, W: std::io::Write> StreamEncoder<T> for JsonArrayEncoder<'_, W> {
+    fn encode_batch(&mut self, batch: &[T]) -> Result<(), CodecError> {
+        for entry in batch {
+            if !self.empty {
+                self.writer.write_all(b",")?;
+            }
+            serde_json::to_writer(&mut *self.writer, entry)?;
+            self.empty = false;
+        }
+        Ok(())
+    }
+
+    fn finish(self: Box<Self>) -> Result<(), CodecError> {
+        self.writer.write_all(b"]")?;
+        Ok(())
+    }
+}
+
+impl JsonCodec {
+    /// Hands up to `max` lines to `f` one by one, see `DecodeLimited`.
+    fn decode_lines_limited<T: DeserializeOwned>(
+        data: &mut impl std::io::BufRead,
+        max: usize,
+        mut f: impl FnMut(T),
+    ) -> Result<(), CodecError> {
+        let mut decoded = 0;
         let mut line = String::new();
-        while data.read_line(&mut line).is_ok() && !line.is_empty() {
-            serde_json::from_str::<T>(&line).unwrap();
+        // `Ok(0)` is the only real EOF, a last line without the trailing newline still holds a
+        // whole record
+        while decoded < max && data.read_line(&mut line)? != 0 {
+            f(serde_json::from_str::<T>(&line)?);
             line.clear();
+            decoded += 1;
         }
+        Ok(())
+    }
+}
+
+/// Hands the elements of a json array to a callback as they are deserialized.
+struct EachElement<T, F>(F, PhantomData<T>);
+
+impl<'de, T: DeserializeOwned, F: FnMut(T)> Visitor<'de> for EachElement<T, F> {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("an array of records")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(mut self, mut seq: A) -> Result<(), A::Error> {
+        while let Some(entry) = seq.next_element()? {
+            (self.0)(entry);
+        }
+        Ok(())
+    }
+}
+
+impl<T: DeserializeOwned, R: std::io::BufRead> Decode<T, R> for JsonCodec {
+    fn decode_subset(&self, mut data: R) -> Result<Vec<T>, CodecError> {
+        if let JsonCodec::Array = self {
+            // reads the elements straight off the reader, the input is never held as a whole
+            return Ok(serde_json::from_reader(data)?);
+        }
+        self.decode_subset_limited(&mut data, usize::MAX)
+    }
+
+    fn decode_subset_each(&self, mut data: R, f: impl FnMut(T)) -> Result<(), CodecError> {
+        match self {
+            JsonCodec::Lines => Self::decode_lines_limited(&mut data, usize::MAX, f),
+            JsonCodec::Array => {
+                let mut deserializer = serde_json::Deserializer::from_reader(data);
+                (&mut deserializer).deserialize_seq(EachElement(f, PhantomData))?;
+                deserializer.end()?;
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Lines only, an array has to be read up to its closing bracket to be valid json.
+impl<T: DeserializeOwned, R: std::io::BufRead> DecodeLimited<T, R> for JsonCodec {
+    fn decode_subset_limited(&self, data: &mut R, max: usize) -> Result<Vec<T>, CodecError> {
+        if let JsonCodec::Array = self {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "a json array can only be decoded as a whole",
+            )
+            .into());
+        }
+        let mut decoded = vec![];
+        Self::decode_lines_limited(data, max, |entry| decoded.push(entry))?;
+        Ok(decoded)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufReader, Cursor, Read};
+
+    use super::*;
+    use crate::{serde_types::CoinConfig, test_support::coins};
+
+    struct BrokenReader;
+
+    impl Read for BrokenReader {
+        fn read(&mut self, _: &mut [u8]) -> std::io::Result<usize> {
+            Err(std::io::Error::other("connection reset"))
+        }
+    }
+
+    #[test]
+    fn decodes_last_record_without_trailing_newline() {
+        // given
+        let coins = coins(2, 1);
+        let mut buffer = vec![];
+        JsonCodec::Lines.encode_subset(&coins, &mut buffer).unwrap();
+        assert_eq!(buffer.pop(), Some(b'\n'));
+
+        // when
+        let decoded: Vec<CoinConfig> = JsonCodec::Lines.decode_subset(Cursor::new(buffer)).unwrap();
+
+        // then
+        pretty_assertions::assert_eq!(decoded, coins);
+    }
+
+    #[test]
+    fn read_errors_are_propagated() {
+        // given
+        let mut buffer = vec![];
+        JsonCodec::Lines
+            .encode_subset(&coins(1, 2), &mut buffer)
+            .unwrap();
+        let reader = BufReader::new(Cursor::new(buffer).chain(BrokenReader));
+
+        // when
+        let result = Decode::<CoinConfig, _>::decode_subset(&JsonCodec::Lines, reader);
+
+        // then
+        assert!(matches!(result, Err(CodecError::Io(_))));
+    }
+
+    #[test]
+    fn array_mode_writes_a_single_array() {
+        // given
+        let coins = coins(2, 3);
+        let mut buffer = vec![];
+
+        // when
+        JsonCodec::Array.encode_subset(&coins, &mut buffer).unwrap();
+
+        // then
+        let parsed: serde_json::Value = serde_json::from_slice(&buffer).unwrap();
+        pretty_assertions::assert_eq!(parsed.as_array().map(Vec::len), Some(2));
+        let decoded: Vec<CoinConfig> = JsonCodec::Array.decode_subset(Cursor::new(buffer)).unwrap();
+        pretty_assertions::assert_eq!(decoded, coins);
+    }
+
+    #[test]
+    fn empty_array_round_trips() {
+        // given
+        let mut buffer = vec![];
+
+        // when
+        Encode::<CoinConfig, _>::encode_subset(&JsonCodec::Array, &[], &mut buffer).unwrap();
+
+        // then
+        pretty_assertions::assert_eq!(buffer, b"[]");
+        let decoded: Vec<CoinConfig> = JsonCodec::Array.decode_subset(Cursor::new(buffer)).unwrap();
+        assert!(decoded.is_empty());
     }
 }
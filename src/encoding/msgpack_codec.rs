@@ -0,0 +1,100 @@
+use serde::{de::DeserializeOwned, Serialize};
+
+use super::{CodecError, Decode, DecodeLimited, Encode};
+#[derive(Clone)]
+pub struct MessagePackCodec;
+impl<T: Serialize, W: std::io::Write> Encode<T, W> for MessagePackCodec {
+    fn encode_subset(&self, data: &[T], writer: &mut W) -> Result<(), CodecError> {
+        for entry in data {
+            rmp_serde::encode::write(writer, entry)?;
+        }
+        Ok(())
+    }
+}
+
+impl MessagePackCodec {
+    /// Hands up to `max` entries to `f` one by one, see `DecodeLimited`.
+    fn decode_each_limited<T: DeserializeOwned>(
+        &self,
+        data: &mut impl std::io::BufRead,
+        max: usize,
+        mut f: impl FnMut(T),
+    ) -> Result<(), CodecError> {
+        let mut decoded = 0;
+        while decoded < max && !data.fill_buf()?.is_empty() {
+            f(rmp_serde::decode::from_read::<_, T>(&mut *data)?);
+            decoded += 1;
+        }
+        Ok(())
+    }
+}
+
+impl<T: DeserializeOwned, R: std::io::BufRead> Decode<T, R> for MessagePackCodec {
+    fn decode_subset(&self, mut data: R) -> Result<Vec<T>, CodecError> {
+        self.decode_subset_limited(&mut data, usize::MAX)
+    }
+
+    fn decode_subset_each(&self, mut data: R, f: impl FnMut(T)) -> Result<(), CodecError> {
+        self.decode_each_limited(&mut data, usize::MAX, f)
+    }
+}
+
+impl<T: DeserializeOwned, R: std::io::BufRead> DecodeLimited<T, R> for MessagePackCodec {
+    fn decode_subset_limited(&self, data: &mut R, max: usize) -> Result<Vec<T>, CodecError> {
+        let mut decoded = vec![];
+        self.decode_each_limited(data, max, |entry| decoded.push(entry))?;
+        Ok(decoded)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::{
+        serde_types::{CoinConfig, ContractConfig},
+        test_support::seeded_rng,
+    };
+
+    #[test]
+    fn handles_missing_optional_fields() {
+        // given
+        let mut rng = seeded_rng(1);
+        let coin = CoinConfig {
+            tx_id: None,
+            output_index: None,
+            tx_pointer_block_height: None,
+            tx_pointer_tx_idx: None,
+            maturity: None,
+            ..CoinConfig::random(&mut rng)
+        };
+        let contract = ContractConfig {
+            tx_id: None,
+            output_index: None,
+            tx_pointer_block_height: None,
+            tx_pointer_tx_idx: None,
+            ..ContractConfig::random(&mut rng)
+        };
+        let mut coins_buf = vec![];
+        let mut contracts_buf = vec![];
+
+        // when
+        MessagePackCodec
+            .encode_subset(std::slice::from_ref(&coin), &mut coins_buf)
+            .unwrap();
+        MessagePackCodec
+            .encode_subset(std::slice::from_ref(&contract), &mut contracts_buf)
+            .unwrap();
+
+        // then
+        let decoded_coins: Vec<CoinConfig> = MessagePackCodec
+            .decode_subset(Cursor::new(coins_buf))
+            .unwrap();
+        let decoded_contracts: Vec<ContractConfig> = MessagePackCodec
+            .decode_subset(Cursor::new(contracts_buf))
+            .unwrap();
+        pretty_assertions::assert_eq!(vec![coin], decoded_coins);
+        pretty_assertions::assert_eq!(vec![contract], decoded_contracts);
+    }
+}
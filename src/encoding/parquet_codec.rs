@@ -1,846 +1,1880 @@
-use std::{io::Cursor, sync::Arc};
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufReader, Cursor},
+    path::Path,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use bytes::Bytes;
 use fuel_core_types::blockchain::primitives::DaBlockHeight;
 use fuel_types::{Address, AssetId, BlockHeight, Bytes32, ContractId, Nonce, Salt};
 use itertools::Itertools;
 use parquet::{
-    basic::{Compression, GzipLevel, Repetition},
-    data_type::{ByteArrayType, FixedLenByteArrayType, Int32Type, Int64Type},
+    basic::{
+        BrotliLevel, Compression, ConvertedType, Encoding, GzipLevel, LogicalType, Repetition,
+        Type as PhysicalType, ZstdLevel,
+    },
+    column::writer::{ColumnCloseResult, ColumnWriter},
+    data_type::{
+        ByteArray, ByteArrayType, DataType, FixedLenByteArray, FixedLenByteArrayType, Int32Type,
+        Int64Type,
+    },
     file::{
-        properties::WriterProperties,
-        reader::FileReader,
-        serialized_reader::SerializedFileReader,
+        properties::{EnabledStatistics, ReaderProperties, WriterProperties},
+        reader::{ChunkReader, FileReader},
+        serialized_reader::{ReadOptionsBuilder, SerializedFileReader},
+        statistics::Statistics,
         writer::{SerializedColumnWriter, SerializedFileWriter},
     },
-    record::{Field, Row},
-    schema::types::Type,
+    record::{Field, Row, RowColumnIter},
+    schema::types::{ColumnPath, PrimitiveTypeBuilder, Type},
 };
 
-use super::{Decode, Encode};
-use crate::serde_types::{
-    CoinConfig, ContractBalance, ContractConfig, ContractState, MessageConfig,
-};
+use super::{CodecError, Decode, Encode, EncodeAppend, EncodeStream, StreamEncoder};
+use crate::serde_types::CoinConfig;
 
-trait ParquetSchema {
+/// The parquet schema a type is written with, one column per field.
+pub trait ParquetSchema {
     fn schema() -> Type;
     fn num_of_columns() -> usize {
         Self::schema().get_fields().len()
     }
 }
 
-trait ColumnEncoder {
+/// Writes one column of a row group at a time. Derived together with `ParquetSchema`.
+pub trait ColumnEncoder {
     type ElementT: ParquetSchema;
-    fn encode_columns<W: std::io::Write + Send>(&self, writer: &mut SerializedFileWriter<W>) {
-        let mut group = writer.next_row_group().unwrap();
+    /// Writes all of `batches` into a single row group, each batch is a separate `write_batch`
+    /// call on every column.
+    fn encode_row_group<W: std::io::Write + Send>(
+        batches: &[&Self],
+        writer: &mut SerializedFileWriter<W>,
+    ) -> Result<(), CodecError> {
+        let mut group = writer.next_row_group()?;
 
         for index in 0..<Self::ElementT>::num_of_columns() {
-            let mut column = group.next_column().unwrap().unwrap();
-            self.encode_column(index, &mut column);
-            column.close().unwrap();
+            let mut column = group
+                .next_column()?
+                .expect("schema and row group have the same number of columns");
+            for batch in batches {
+                batch.encode_column(index, &mut column)?;
+            }
+            column.close()?;
         }
 
-        group.close().unwrap();
+        group.close()?;
+        Ok(())
     }
-    fn encode_column(&self, index: usize, column: &mut SerializedColumnWriter<'_>);
+    fn encode_column(
+        &self,
+        index: usize,
+        column: &mut SerializedColumnWriter<'_>,
+    ) -> Result<(), CodecError>;
 }
 
-impl ColumnEncoder for Vec<ContractConfig> {
-    type ElementT = ContractConfig;
+/// How a field type maps onto a parquet column. Implemented for the leaf types through
+/// `ParquetValue`, with `Option` turning any of them into an optional column.
+pub trait ParquetColumn: Sized {
+    fn column_type(name: &str) -> Type;
+    fn write_column<'a>(
+        values: impl Iterator<Item = &'a Self>,
+        column: &mut SerializedColumnWriter<'_>,
+    ) -> Result<(), CodecError>
+    where
+        Self: 'a;
+    fn read_field(field: &Field) -> Result<Self, FieldError>;
+}
 
-    fn encode_column(&self, index: usize, column: &mut SerializedColumnWriter<'_>) {
-        match index {
-            0 => {
-                let data = self
-                    .iter()
-                    .map(|el| el.contract_id.to_vec().into())
-                    .collect_vec();
-                column
-                    .typed::<FixedLenByteArrayType>()
-                    .write_batch(&data, None, None)
-                    .unwrap();
-            }
-            1 => {
-                let data = self.iter().map(|el| el.code.clone().into()).collect_vec();
-                column
-                    .typed::<ByteArrayType>()
-                    .write_batch(&data, None, None)
-                    .unwrap();
-            }
-            2 => {
-                let data = self.iter().map(|el| el.salt.to_vec().into()).collect_vec();
-                column
-                    .typed::<FixedLenByteArrayType>()
-                    .write_batch(&data, None, None)
-                    .unwrap();
-            }
-            3 => {
-                let def_levels = self
-                    .iter()
-                    .map(|el| el.tx_id.is_some() as i16)
-                    .collect_vec();
-                let data = self
-                    .iter()
-                    .filter_map(|el| el.tx_id)
-                    .map(|el| el.to_vec().into())
-                    .collect_vec();
-                column
-                    .typed::<FixedLenByteArrayType>()
-                    .write_batch(&data, Some(&def_levels), None)
-                    .unwrap();
-            }
-            4 => {
-                let def_levels = self
-                    .iter()
-                    .map(|el| el.output_index.is_some() as i16)
-                    .collect_vec();
-                let data = self
-                    .iter()
-                    .filter_map(|el| el.output_index)
-                    .map(|el| el as i32)
-                    .collect_vec();
-                column
-                    .typed::<Int32Type>()
-                    .write_batch(&data, Some(&def_levels), None)
-                    .unwrap();
-            }
-            5 => {
-                let def_levels = self
-                    .iter()
-                    .map(|el| el.tx_pointer_block_height.is_some() as i16)
-                    .collect_vec();
-                let data = self
-                    .iter()
-                    .filter_map(|el| el.tx_pointer_block_height)
-                    .map(|el| *el as i32)
-                    .collect_vec();
-                column
-                    .typed::<Int32Type>()
-                    .write_batch(&data, Some(&def_levels), None)
-                    .unwrap();
-            }
-            6 => {
-                let def_levels = self
-                    .iter()
-                    .map(|el| el.tx_pointer_tx_idx.is_some() as i16)
-                    .collect_vec();
-                let data = self
-                    .iter()
-                    .filter_map(|el| el.tx_pointer_tx_idx)
-                    .map(|el| el as i32)
-                    .collect_vec();
-                column
-                    .typed::<Int32Type>()
-                    .write_batch(&data, Some(&def_levels), None)
-                    .unwrap();
-            }
-            unknown_column => {
-                panic!(
-                    "Unknown column {unknown_column}, doesn't index schema: {:?}",
-                    <Self::ElementT>::schema()
-                )
-            }
-        }
-    }
+/// Why a parquet field couldn't be read back as the expected type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldError {
+    WrongType,
+    BadLength { expected: usize, got: usize },
 }
-impl ColumnEncoder for Vec<CoinConfig> {
-    type ElementT = CoinConfig;
 
-    fn encode_column(&self, index: usize, column: &mut SerializedColumnWriter<'_>) {
-        match index {
-            0 => {
-                let def_levels = self
-                    .iter()
-                    .map(|el| el.tx_id.is_some() as i16)
-                    .collect_vec();
-                let data = self
-                    .iter()
-                    .filter_map(|el| el.tx_id)
-                    .map(|el| el.to_vec().into())
-                    .collect_vec();
-                column
-                    .typed::<FixedLenByteArrayType>()
-                    .write_batch(&data, Some(&def_levels), None)
-                    .unwrap();
-            }
-            1 => {
-                let def_levels = self
-                    .iter()
-                    .map(|el| el.output_index.is_some() as i16)
-                    .collect_vec();
-                let data = self
-                    .iter()
-                    .filter_map(|el| el.output_index)
-                    .map(|el| el as i32)
-                    .collect_vec();
-                column
-                    .typed::<Int32Type>()
-                    .write_batch(&data, Some(&def_levels), None)
-                    .unwrap();
-            }
-            2 => {
-                let def_levels = self
-                    .iter()
-                    .map(|el| el.tx_pointer_block_height.is_some() as i16)
-                    .collect_vec();
-                let data = self
-                    .iter()
-                    .filter_map(|el| el.tx_pointer_block_height)
-                    .map(|el| *el as i32)
-                    .collect_vec();
-                column
-                    .typed::<Int32Type>()
-                    .write_batch(&data, Some(&def_levels), None)
-                    .unwrap();
-            }
-            3 => {
-                let def_levels = self
-                    .iter()
-                    .map(|el| el.tx_pointer_tx_idx.is_some() as i16)
-                    .collect_vec();
-                let data = self
-                    .iter()
-                    .filter_map(|el| el.tx_pointer_tx_idx)
-                    .map(|el| el as i32)
-                    .collect_vec();
-                column
-                    .typed::<Int32Type>()
-                    .write_batch(&data, Some(&def_levels), None)
-                    .unwrap();
-            }
-            4 => {
-                let def_levels = self
-                    .iter()
-                    .map(|el| el.maturity.is_some() as i16)
-                    .collect_vec();
-                let data = self
-                    .iter()
-                    .filter_map(|el| el.maturity)
-                    .map(|el| *el as i32)
-                    .collect_vec();
-                column
-                    .typed::<Int32Type>()
-                    .write_batch(&data, Some(&def_levels), None)
-                    .unwrap();
-            }
-            5 => {
-                let data = self.iter().map(|el| el.owner.to_vec().into()).collect_vec();
-                column
-                    .typed::<FixedLenByteArrayType>()
-                    .write_batch(&data, None, None)
-                    .unwrap();
-            }
-            6 => {
-                let data = self.iter().map(|el| el.amount as i64).collect_vec();
-                column
-                    .typed::<Int64Type>()
-                    .write_batch(&data, None, None)
-                    .unwrap();
-            }
-            7 => {
-                let data = self
-                    .iter()
-                    .map(|el| el.asset_id.to_vec().into())
-                    .collect_vec();
-                column
-                    .typed::<FixedLenByteArrayType>()
-                    .write_batch(&data, None, None)
-                    .unwrap();
-            }
-            unknown_column => {
-                panic!(
-                    "Unknown column {unknown_column}, doesn't index schema: {:?}",
-                    <Self::ElementT>::schema()
-                )
-            }
-        }
+/// A type stored as a single, non null, parquet value.
+pub trait ParquetValue: Sized {
+    type Physical: DataType;
+    /// The primitive type, converted type and length included, without the repetition.
+    fn type_builder(name: &str) -> PrimitiveTypeBuilder<'_>;
+    fn to_physical(&self) -> <Self::Physical as DataType>::T;
+    fn from_field(field: &Field) -> Result<Self, FieldError>;
+
+    /// Writes the values of a column, `def_levels` only for an optional one. Types whose column
+    /// can be stored as something other than `Physical` look at the column writer they're given.
+    fn write_values<'a>(
+        values: impl Iterator<Item = &'a Self>,
+        def_levels: Option<&[i16]>,
+        column: &mut SerializedColumnWriter<'_>,
+    ) -> Result<(), CodecError>
+    where
+        Self: 'a,
+    {
+        let data = values.map(Self::to_physical).collect_vec();
+        column
+            .typed::<Self::Physical>()
+            .write_batch(&data, def_levels, None)?;
+        Ok(())
     }
 }
-impl ColumnEncoder for Vec<MessageConfig> {
-    type ElementT = MessageConfig;
 
-    fn encode_column(&self, index: usize, column: &mut SerializedColumnWriter<'_>) {
-        match index {
-            0 => {
-                let data = self
-                    .iter()
-                    .map(|el| el.sender.to_vec().into())
-                    .collect_vec();
-                column
-                    .typed::<FixedLenByteArrayType>()
-                    .write_batch(&data, None, None)
-                    .unwrap();
-            }
-            1 => {
-                let data = self
-                    .iter()
-                    .map(|el| el.recipient.to_vec().into())
-                    .collect_vec();
-                column
-                    .typed::<FixedLenByteArrayType>()
-                    .write_batch(&data, None, None)
-                    .unwrap();
-            }
-            2 => {
-                let data = self.iter().map(|el| el.nonce.to_vec().into()).collect_vec();
-                column
-                    .typed::<FixedLenByteArrayType>()
-                    .write_batch(&data, None, None)
-                    .unwrap();
-            }
-            3 => {
-                let data = self.iter().map(|el| el.amount as i64).collect_vec();
-                column
-                    .typed::<Int64Type>()
-                    .write_batch(&data, None, None)
-                    .unwrap();
-            }
-            4 => {
-                let data = self.iter().map(|el| el.data.to_vec().into()).collect_vec();
-                column
-                    .typed::<ByteArrayType>()
-                    .write_batch(&data, None, None)
-                    .unwrap();
-            }
-            5 => {
-                let data = self.iter().map(|el| el.da_height.0 as i64).collect_vec();
-                column
-                    .typed::<Int64Type>()
-                    .write_batch(&data, None, None)
-                    .unwrap();
-            }
-            unknown_column => {
-                panic!(
-                    "Unknown column {unknown_column}, doesn't index schema: {:?}",
-                    <Self::ElementT>::schema()
-                )
-            }
-        }
+impl<T: ParquetValue> ParquetColumn for T {
+    fn column_type(name: &str) -> Type {
+        T::type_builder(name)
+            .with_repetition(Repetition::REQUIRED)
+            .build()
+            .expect("leaf types build valid primitive types")
     }
-}
-impl ColumnEncoder for Vec<ContractState> {
-    type ElementT = ContractState;
 
-    fn encode_column(&self, index: usize, column: &mut SerializedColumnWriter<'_>) {
-        match index {
-            0 => {
-                let data = self.iter().map(|el| el.key.to_vec().into()).collect_vec();
-                column
-                    .typed::<FixedLenByteArrayType>()
-                    .write_batch(&data, None, None)
-                    .unwrap();
-            }
-            1 => {
-                let data = self.iter().map(|el| el.value.to_vec().into()).collect_vec();
-                column
-                    .typed::<FixedLenByteArrayType>()
-                    .write_batch(&data, None, None)
-                    .unwrap();
-            }
-            unknown_column => {
-                panic!(
-                    "Unknown column {unknown_column}, doesn't index schema: {:?}",
-                    <Self::ElementT>::schema()
-                )
-            }
-        }
+    fn write_column<'a>(
+        values: impl Iterator<Item = &'a Self>,
+        column: &mut SerializedColumnWriter<'_>,
+    ) -> Result<(), CodecError>
+    where
+        Self: 'a,
+    {
+        T::write_values(values, None, column)
+    }
+
+    fn read_field(field: &Field) -> Result<Self, FieldError> {
+        T::from_field(field)
     }
 }
-impl ColumnEncoder for Vec<ContractBalance> {
-    type ElementT = ContractBalance;
 
-    fn encode_column(&self, index: usize, column: &mut SerializedColumnWriter<'_>) {
-        match index {
-            0 => {
-                let data = self
-                    .iter()
-                    .map(|el| el.asset_id.to_vec().into())
-                    .collect_vec();
-                column
-                    .typed::<FixedLenByteArrayType>()
-                    .write_batch(&data, None, None)
-                    .unwrap();
-            }
-            1 => {
-                let data = self.iter().map(|el| el.amount as i64).collect_vec();
-                column
-                    .typed::<Int64Type>()
-                    .write_batch(&data, None, None)
-                    .unwrap();
-            }
-            unknown_column => {
-                panic!(
-                    "Unknown column {unknown_column}, doesn't index schema: {:?}",
-                    <Self::ElementT>::schema()
-                )
-            }
+impl<T: ParquetValue> ParquetColumn for Option<T> {
+    fn column_type(name: &str) -> Type {
+        T::type_builder(name)
+            .with_repetition(Repetition::OPTIONAL)
+            .build()
+            .expect("leaf types build valid primitive types")
+    }
+
+    fn write_column<'a>(
+        values: impl Iterator<Item = &'a Self>,
+        column: &mut SerializedColumnWriter<'_>,
+    ) -> Result<(), CodecError>
+    where
+        Self: 'a,
+    {
+        let values = values.collect_vec();
+        let def_levels = values.iter().map(|el| el.is_some() as i16).collect_vec();
+        T::write_values(
+            values.iter().filter_map(|el| el.as_ref()),
+            Some(&def_levels),
+            column,
+        )
+    }
+
+    fn read_field(field: &Field) -> Result<Self, FieldError> {
+        match field {
+            Field::Null => Ok(None),
+            field => T::from_field(field).map(Some),
         }
     }
 }
 
-pub struct ParquetCodec {
-    pub batch_size: usize,
-    pub compression_level: u32,
+macro_rules! impl_parquet_value_for_hash {
+    ($($hash:ty),*) => {
+        $(
+            impl ParquetValue for $hash {
+                type Physical = FixedLenByteArrayType;
+
+                fn type_builder(name: &str) -> PrimitiveTypeBuilder<'_> {
+                    Type::primitive_type_builder(name, PhysicalType::FIXED_LEN_BYTE_ARRAY)
+                        .with_length(32)
+                }
+
+                fn to_physical(&self) -> FixedLenByteArray {
+                    self.to_vec().into()
+                }
+
+                fn from_field(field: &Field) -> Result<Self, FieldError> {
+                    match field {
+                        Field::Bytes(bytes) => bytes
+                            .data()
+                            .try_into()
+                            .map(<$hash>::new)
+                            .map_err(|_| FieldError::BadLength {
+                                expected: 32,
+                                got: bytes.len(),
+                            }),
+                        _ => Err(FieldError::WrongType),
+                    }
+                }
+            }
+        )*
+    };
 }
 
-impl ParquetCodec {
-    pub fn new(batch_size: usize, compression_level: u32) -> Self {
-        Self {
-            batch_size,
-            compression_level,
-        }
+impl_parquet_value_for_hash!(Bytes32, Address, AssetId, ContractId, Salt, Nonce);
+
+impl ParquetValue for Vec<u8> {
+    type Physical = ByteArrayType;
+
+    fn type_builder(name: &str) -> PrimitiveTypeBuilder<'_> {
+        Type::primitive_type_builder(name, PhysicalType::BYTE_ARRAY)
     }
-}
 
-impl<T, W> Encode<T, W> for ParquetCodec
-where
-    Vec<T>: ColumnEncoder<ElementT = T>,
-    T: ParquetSchema,
-    W: std::io::Write + Send,
-{
-    fn encode_subset(&self, data: Vec<T>, writer: &mut W) {
-        let mut writer = SerializedFileWriter::new(
-            writer,
-            Arc::new(T::schema()),
-            Arc::new(
-                WriterProperties::builder()
-                    .set_compression(Compression::GZIP(
-                        GzipLevel::try_new(self.compression_level).unwrap(),
-                    ))
-                    .build(),
-            ),
-        )
-        .unwrap();
-        for chunk in data.into_iter().chunks(self.batch_size).into_iter() {
-            chunk.collect_vec().encode_columns(&mut writer);
+    fn to_physical(&self) -> ByteArray {
+        self.clone().into()
+    }
+
+    fn from_field(field: &Field) -> Result<Self, FieldError> {
+        match field {
+            Field::Bytes(bytes) => Ok(bytes.data().to_vec()),
+            _ => Err(FieldError::WrongType),
         }
-        writer.close().unwrap();
     }
 }
 
-impl From<Row> for CoinConfig {
-    fn from(row: Row) -> Self {
-        let mut iter = row.get_column_iter();
+impl ParquetValue for u8 {
+    type Physical = Int32Type;
 
-        let tx_id = match iter.next().unwrap().1 {
-            Field::Null => None,
-            Field::Bytes(tx_id) => Some(tx_id),
-            _ => panic!("Unexpected type!"),
-        };
-        let tx_id = tx_id.map(|bytes| Bytes32::new(bytes.data().try_into().unwrap()));
-
-        let output_index = match iter.next().unwrap().1 {
-            Field::UByte(output_index) => Some(*output_index),
-            Field::Null => None,
-            _ => panic!("Should not happen"),
-        };
+    fn type_builder(name: &str) -> PrimitiveTypeBuilder<'_> {
+        Type::primitive_type_builder(name, PhysicalType::INT32)
+            .with_converted_type(ConvertedType::UINT_8)
+    }
 
-        let tx_pointer_block_height = match iter.next().unwrap().1 {
-            Field::UInt(tx_pointer_block_height) => Some(*tx_pointer_block_height),
-            Field::Null => None,
-            _ => panic!("Should not happen"),
-        };
-        let tx_pointer_block_height = tx_pointer_block_height.map(BlockHeight::new);
+    fn to_physical(&self) -> i32 {
+        *self as i32
+    }
 
-        let tx_pointer_tx_idx = match iter.next().unwrap().1 {
-            Field::UShort(tx_pointer_tx_idx) => Some(*tx_pointer_tx_idx),
-            Field::Null => None,
-            _ => panic!("Should not happen"),
-        };
-        let maturity = match iter.next().unwrap().1 {
-            Field::UInt(maturity) => Some(*maturity),
-            Field::Null => None,
-            _ => panic!("Should not happen"),
-        };
-        let maturity = maturity.map(BlockHeight::new);
+    fn from_field(field: &Field) -> Result<Self, FieldError> {
+        match field {
+            Field::UByte(value) => Ok(*value),
+            _ => Err(FieldError::WrongType),
+        }
+    }
+}
 
-        let Field::Bytes(owner) = iter.next().unwrap().1 else {
-            panic!("Unexpected type!");
-        };
-        let owner = Address::new(owner.data().try_into().unwrap());
+impl ParquetValue for u16 {
+    type Physical = Int32Type;
 
-        let Field::ULong(amount) = iter.next().unwrap().1 else {
-            panic!("Unexpected type!");
-        };
-        let amount = *amount;
+    fn type_builder(name: &str) -> PrimitiveTypeBuilder<'_> {
+        Type::primitive_type_builder(name, PhysicalType::INT32)
+            .with_converted_type(ConvertedType::UINT_16)
+    }
 
-        let Field::Bytes(asset_id) = iter.next().unwrap().1 else {
-            panic!("Unexpected type!");
-        };
-        let asset_id = AssetId::new(asset_id.data().try_into().unwrap());
+    fn to_physical(&self) -> i32 {
+        *self as i32
+    }
 
-        Self {
-            tx_id,
-            output_index,
-            tx_pointer_block_height,
-            tx_pointer_tx_idx,
-            maturity,
-            owner,
-            amount,
-            asset_id,
+    fn from_field(field: &Field) -> Result<Self, FieldError> {
+        match field {
+            Field::UShort(value) => Ok(*value),
+            _ => Err(FieldError::WrongType),
         }
     }
 }
-impl From<Row> for MessageConfig {
-    fn from(row: Row) -> Self {
-        let mut iter = row.get_column_iter();
 
-        let Field::Bytes(sender) = iter.next().unwrap().1 else {
-            panic!("Unexpected type!");
-        };
-        let sender = Address::new(sender.data().try_into().unwrap());
+impl ParquetValue for BlockHeight {
+    type Physical = Int32Type;
 
-        let Field::Bytes(recipient) = iter.next().unwrap().1 else {
-            panic!("Unexpected type!");
-        };
-        let recipient = Address::new(recipient.data().try_into().unwrap());
+    fn type_builder(name: &str) -> PrimitiveTypeBuilder<'_> {
+        Type::primitive_type_builder(name, PhysicalType::INT32)
+            .with_converted_type(ConvertedType::UINT_32)
+    }
 
-        let Field::Bytes(nonce) = iter.next().unwrap().1 else {
-            panic!("Unexpected type!");
-        };
-        let nonce = Nonce::new(nonce.data().try_into().unwrap());
+    fn to_physical(&self) -> i32 {
+        **self as i32
+    }
 
-        let Field::ULong(amount) = iter.next().unwrap().1 else {
-            panic!("Unexpected type!");
-        };
-        let amount = *amount;
+    fn from_field(field: &Field) -> Result<Self, FieldError> {
+        match field {
+            Field::UInt(value) => Ok(BlockHeight::new(*value)),
+            _ => Err(FieldError::WrongType),
+        }
+    }
+}
 
-        let Field::Bytes(data) = iter.next().unwrap().1 else {
-            panic!("Unexpected type!");
-        };
-        let data = data.data().to_vec();
+impl ParquetValue for u64 {
+    type Physical = Int64Type;
 
-        let Field::ULong(da_height) = iter.next().unwrap().1 else {
-            panic!("Unexpected type!");
-        };
-        let da_height = DaBlockHeight(*da_height);
+    fn type_builder(name: &str) -> PrimitiveTypeBuilder<'_> {
+        Type::primitive_type_builder(name, PhysicalType::INT64)
+            .with_converted_type(ConvertedType::UINT_64)
+    }
 
-        Self {
-            sender,
-            recipient,
-            nonce,
-            amount,
-            data,
-            da_height,
+    fn to_physical(&self) -> i64 {
+        // same bits, the UINT_64 annotation has readers take them as unsigned again
+        *self as i64
+    }
+
+    fn from_field(field: &Field) -> Result<Self, FieldError> {
+        match field {
+            Field::ULong(value) => Ok(*value),
+            Field::Decimal(decimal) => {
+                u64_from_decimal(decimal.data()).ok_or(FieldError::WrongType)
+            }
+            _ => Err(FieldError::WrongType),
+        }
+    }
+
+    /// A column `ParquetCodec::with_decimal_amounts` turned into a decimal is written as one.
+    fn write_values<'a>(
+        values: impl Iterator<Item = &'a Self>,
+        def_levels: Option<&[i16]>,
+        column: &mut SerializedColumnWriter<'_>,
+    ) -> Result<(), CodecError> {
+        match column.untyped() {
+            ColumnWriter::FixedLenByteArrayColumnWriter(column) => {
+                let data = values.map(|value| decimal_bytes(*value)).collect_vec();
+                column.write_batch(&data, def_levels, None)?;
+            }
+            _ => {
+                let data = values.map(Self::to_physical).collect_vec();
+                column
+                    .typed::<Int64Type>()
+                    .write_batch(&data, def_levels, None)?;
+            }
         }
+        Ok(())
     }
 }
-impl From<Row> for ContractState {
-    fn from(row: Row) -> Self {
-        let mut iter = row.get_column_iter();
 
-        let Field::Bytes(key) = iter.next().unwrap().1 else {
-            panic!("Unexpected type!");
-        };
-        let key = Bytes32::new(key.data().try_into().unwrap());
-        let Field::Bytes(value) = iter.next().unwrap().1 else {
-            panic!("Unexpected type!");
-        };
-        let value = Bytes32::new(value.data().try_into().unwrap());
+/// Bytes of the DECIMAL(38, _) amounts, the most parquet fits into 16 bytes.
+const DECIMAL_LEN: i32 = 16;
+const DECIMAL_PRECISION: i32 = 38;
 
-        Self { key, value }
+/// The unscaled value of a decimal as a big endian two's complement of `DECIMAL_LEN` bytes. An
+/// amount is never negative, so the padding is all zeros.
+fn decimal_bytes(value: u64) -> FixedLenByteArray {
+    let mut bytes = vec![0; DECIMAL_LEN as usize - 8];
+    bytes.extend(value.to_be_bytes());
+    bytes.into()
+}
+
+/// The unscaled value of a decimal of any length, `check_schema` doesn't compare the widths of
+/// fixed length columns. `None` if it's negative or doesn't fit.
+fn u64_from_decimal(bytes: &[u8]) -> Option<u64> {
+    if bytes.first().is_some_and(|byte| byte & 0x80 != 0) {
+        return None;
     }
+    let (high, low) = bytes.split_at(bytes.len().saturating_sub(8));
+    if high.iter().any(|&byte| byte != 0) {
+        return None;
+    }
+    let mut value = [0; 8];
+    value[8 - low.len()..].copy_from_slice(low);
+    Some(u64::from_be_bytes(value))
 }
-impl From<Row> for ContractConfig {
-    fn from(row: Row) -> Self {
-        let mut iter = row.get_column_iter();
 
-        let (_, Field::Bytes(contract_id)) = iter.next().unwrap() else {
-            panic!("Unexpected type!");
-        };
-        let contract_id = ContractId::new(contract_id.data().try_into().unwrap());
+impl ParquetValue for DaBlockHeight {
+    type Physical = Int64Type;
 
-        let Field::Bytes(code) = iter.next().unwrap().1 else {
-            panic!("Unexpected type!");
-        };
-        let code = Vec::from(code.data());
+    fn type_builder(name: &str) -> PrimitiveTypeBuilder<'_> {
+        u64::type_builder(name)
+    }
 
-        let Field::Bytes(salt) = iter.next().unwrap().1 else {
-            panic!("Unexpected type!");
-        };
-        let salt = Salt::new(salt.data().try_into().unwrap());
+    fn to_physical(&self) -> i64 {
+        self.0.to_physical()
+    }
 
-        let tx_id = match iter.next().unwrap().1 {
-            Field::Bytes(tx_id) => Some(tx_id),
-            Field::Null => None,
-            _ => panic!("Should not happen"),
-        };
-        let tx_id = tx_id.map(|data| Bytes32::new(data.data().try_into().unwrap()));
+    fn from_field(field: &Field) -> Result<Self, FieldError> {
+        u64::from_field(field).map(DaBlockHeight)
+    }
+}
 
-        let output_index = match iter.next().unwrap().1 {
-            Field::UByte(output_index) => Some(*output_index),
-            Field::Null => None,
-            _ => panic!("Should not happen"),
-        };
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParquetCompression {
+    /// Levels 0 to 10. Level 0 still writes gzip, just with stored blocks, so every page pays
+    /// for the gzip framing. Use `Uncompressed` to leave the pages as they are.
+    Gzip(u32),
+    /// Levels 1 to 22.
+    Zstd(i32),
+    /// Quality 0 to 11. The window is parquet's fixed 4MiB one, it isn't configurable through
+    /// the writer properties. Encoding slows down steeply past the first few levels.
+    Brotli(u32),
+    Snappy,
+    Uncompressed,
+}
 
-        let tx_pointer_block_height = match iter.next().unwrap().1 {
-            Field::UInt(tx_pointer_block_height) => Some(*tx_pointer_block_height),
-            Field::Null => None,
-            _ => panic!("Should not happen"),
-        };
-        let tx_pointer_block_height = tx_pointer_block_height.map(BlockHeight::new);
+impl ParquetCompression {
+    /// Fails if the level is out of the compression's range.
+    pub fn validate(&self) -> Result<(), CodecError> {
+        self.as_parquet().map(|_| ())
+    }
 
-        let tx_pointer_tx_idx = match iter.next().unwrap().1 {
-            Field::UShort(tx_pointer_tx_idx) => Some(*tx_pointer_tx_idx),
-            Field::Null => None,
-            _ => panic!("Should not happen"),
+    fn as_parquet(&self) -> Result<Compression, CodecError> {
+        let compression = match *self {
+            ParquetCompression::Gzip(level) => Compression::GZIP(GzipLevel::try_new(level)?),
+            ParquetCompression::Zstd(level) => Compression::ZSTD(ZstdLevel::try_new(level)?),
+            ParquetCompression::Brotli(level) => Compression::BROTLI(BrotliLevel::try_new(level)?),
+            ParquetCompression::Snappy => Compression::SNAPPY,
+            ParquetCompression::Uncompressed => Compression::UNCOMPRESSED,
         };
-        Self {
-            contract_id,
-            code,
-            salt,
-            tx_id,
-            output_index,
-            tx_pointer_block_height,
-            tx_pointer_tx_idx,
-        }
+        Ok(compression)
     }
 }
 
-impl From<Row> for ContractBalance {
-    fn from(row: Row) -> Self {
-        let mut iter = row.get_column_iter();
-
-        let Field::Bytes(asset_id) = iter.next().unwrap().1 else {
-            panic!("Unexpected type!");
-        };
-        let asset_id = AssetId::new(asset_id.data().try_into().unwrap());
+/// Overrides the codec wide settings for a single column.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ColumnProperties {
+    pub compression: Option<ParquetCompression>,
+    /// Used for the values that don't go through the dictionary. Must not be one of the dictionary
+    /// encodings.
+    pub encoding: Option<Encoding>,
+    /// Writes a bloom filter per row group so lookups can skip groups without the value.
+    pub bloom_filter: bool,
+}
 
-        let Field::ULong(amount) = iter.next().unwrap().1 else {
-            panic!("Unexpected type!");
-        };
-        let amount = *amount;
+pub struct ParquetCodec {
+    /// Rows handed to a column writer at once.
+    pub batch_size: usize,
+    /// Rows per row group, the unit parquet compresses and skips over.
+    pub row_group_size: usize,
+    pub compression: ParquetCompression,
+    pub dictionary_enabled: bool,
+    /// Min/max statistics per page and row group, `find_by_owner` prunes row groups with them.
+    pub statistics_enabled: bool,
+    /// Keyed by column name. A name shared by several types, like `amount`, applies to all of
+    /// them.
+    pub column_properties: HashMap<String, ColumnProperties>,
+    /// Scale of the `amount` columns when they are stored as DECIMAL(38, scale) for readers that
+    /// expect token amounts as decimals. `None` keeps them unsigned INT64s.
+    pub decimal_amounts: Option<i32>,
+}
 
-        Self { asset_id, amount }
+impl ParquetCodec {
+    /// One row group per `batch_size` rows. Dictionary encoding stays on, as is the parquet
+    /// default.
+    pub fn new(batch_size: usize, compression: ParquetCompression) -> Self {
+        Self {
+            batch_size,
+            row_group_size: batch_size,
+            compression,
+            dictionary_enabled: true,
+            statistics_enabled: true,
+            column_properties: HashMap::new(),
+            decimal_amounts: None,
+        }
     }
-}
 
-impl<T> Decode<T, Cursor<Vec<u8>>> for ParquetCodec
-where
-    T: ParquetSchema + From<Row>,
-{
-    fn decode_subset(&self, reader: Cursor<Vec<u8>>) {
-        let reader = SerializedFileReader::new(Bytes::from(reader.into_inner())).unwrap();
-        for row in reader.get_row_iter(Some(T::schema())).unwrap() {
-            let _ = T::from(row.unwrap());
+    /// Same as `new` but rejects an invalid compression level right away instead of on the
+    /// first encode, and an empty batch instead of panicking on it.
+    pub fn try_new(batch_size: usize, compression: ParquetCompression) -> Result<Self, CodecError> {
+        compression.validate()?;
+        if batch_size == 0 {
+            return Err(CodecError::EmptyBatch {
+                setting: "batch size",
+            });
         }
+        Ok(Self::new(batch_size, compression))
     }
-}
 
-impl ParquetSchema for ContractConfig {
-    fn schema() -> Type {
-        use parquet::basic::Type as PhysicalType;
-        let contract_id =
-            Type::primitive_type_builder("contract_id", PhysicalType::FIXED_LEN_BYTE_ARRAY)
-                .with_length(32)
-                .with_repetition(Repetition::REQUIRED)
-                .build()
-                .unwrap();
-        let code = Type::primitive_type_builder("code", PhysicalType::BYTE_ARRAY)
-            .with_repetition(Repetition::REQUIRED)
-            .build()
-            .unwrap();
+    pub fn with_column_compression(
+        mut self,
+        column: impl Into<String>,
+        compression: ParquetCompression,
+    ) -> Self {
+        self.column_properties
+            .entry(column.into())
+            .or_default()
+            .compression = Some(compression);
+        self
+    }
 
-        let salt = Type::primitive_type_builder("salt", PhysicalType::FIXED_LEN_BYTE_ARRAY)
-            .with_length(32)
-            .with_repetition(Repetition::REQUIRED)
-            .build()
-            .unwrap();
+    pub fn with_statistics(mut self, enabled: bool) -> Self {
+        self.statistics_enabled = enabled;
+        self
+    }
 
-        let tx_id = Type::primitive_type_builder("tx_id", PhysicalType::FIXED_LEN_BYTE_ARRAY)
-            .with_length(32)
-            .with_repetition(Repetition::OPTIONAL)
-            .build()
-            .unwrap();
+    pub fn with_bloom_filter(mut self, column: impl Into<String>) -> Self {
+        self.column_properties
+            .entry(column.into())
+            .or_default()
+            .bloom_filter = true;
+        self
+    }
 
-        let output_index = Type::primitive_type_builder("output_index", PhysicalType::INT32)
-            .with_converted_type(parquet::basic::ConvertedType::UINT_8)
-            .with_repetition(Repetition::OPTIONAL)
-            .build()
-            .unwrap();
+    pub fn with_column_encoding(mut self, column: impl Into<String>, encoding: Encoding) -> Self {
+        self.column_properties
+            .entry(column.into())
+            .or_default()
+            .encoding = Some(encoding);
+        self
+    }
 
-        let tx_pointer_block_height =
-            Type::primitive_type_builder("tx_pointer_block_height", PhysicalType::INT32)
-                .with_converted_type(parquet::basic::ConvertedType::UINT_32)
-                .with_repetition(Repetition::OPTIONAL)
-                .build()
-                .unwrap();
+    fn writer_properties(&self) -> Result<WriterProperties, CodecError> {
+        let mut builder = WriterProperties::builder()
+            .set_compression(self.compression.as_parquet()?)
+            .set_dictionary_enabled(self.dictionary_enabled)
+            .set_statistics_enabled(if self.statistics_enabled {
+                EnabledStatistics::Page
+            } else {
+                EnabledStatistics::None
+            });
+        for (column, properties) in &self.column_properties {
+            let path = ColumnPath::from(column.as_str());
+            if let Some(compression) = properties.compression {
+                builder = builder.set_column_compression(path.clone(), compression.as_parquet()?);
+            }
+            if properties.bloom_filter {
+                builder = builder.set_column_bloom_filter_enabled(path.clone(), true);
+            }
+            if let Some(encoding) = properties.encoding {
+                builder = builder.set_column_encoding(path, encoding);
+            }
+        }
+        Ok(builder.build())
+    }
 
-        let tx_pointer_tx_idx =
-            Type::primitive_type_builder("tx_pointer_tx_idx", PhysicalType::INT32)
-                .with_converted_type(parquet::basic::ConvertedType::UINT_16)
-                .with_repetition(Repetition::OPTIONAL)
-                .build()
-                .unwrap();
+    /// The unscaled value is the amount as it is, `scale` only tells readers where to put the
+    /// decimal point, e.g. the decimals of the token.
+    pub fn with_decimal_amounts(mut self, scale: i32) -> Result<Self, CodecError> {
+        if !(0..=DECIMAL_PRECISION).contains(&scale) {
+            return Err(CodecError::DecimalScale {
+                scale,
+                precision: DECIMAL_PRECISION,
+            });
+        }
+        self.decimal_amounts = Some(scale);
+        Ok(self)
+    }
 
-        parquet::schema::types::Type::group_type_builder("ContractConfig")
-            .with_fields(
-                [
-                    contract_id,
-                    code,
-                    salt,
-                    tx_id,
-                    output_index,
-                    tx_pointer_block_height,
-                    tx_pointer_tx_idx,
-                ]
-                .map(Arc::new)
-                .to_vec(),
-            )
+    /// `T::schema()` with the `amount` columns swapped for decimals if the codec stores them so.
+    fn schema<T: ParquetSchema>(&self) -> Type {
+        let schema = T::schema();
+        let Some(scale) = self.decimal_amounts else {
+            return schema;
+        };
+        let fields = schema
+            .get_fields()
+            .iter()
+            .map(|field| {
+                if field.name() != "amount" {
+                    return field.clone();
+                }
+                let decimal =
+                    Type::primitive_type_builder("amount", PhysicalType::FIXED_LEN_BYTE_ARRAY)
+                        .with_repetition(field.get_basic_info().repetition())
+                        .with_length(DECIMAL_LEN)
+                        .with_logical_type(Some(LogicalType::Decimal {
+                            scale,
+                            precision: DECIMAL_PRECISION,
+                        }))
+                        .with_precision(DECIMAL_PRECISION)
+                        .with_scale(scale)
+                        .build()
+                        .expect("the precision fits the length and the scale the precision");
+                Arc::new(decimal)
+            })
+            .collect_vec();
+        Type::group_type_builder(schema.name())
+            .with_fields(fields)
             .build()
-            .unwrap()
+            .expect("only leaf types were swapped")
     }
-}
 
-impl ParquetSchema for ContractState {
-    fn schema() -> Type {
-        use parquet::basic::Type as PhysicalType;
-        let key = Type::primitive_type_builder("key", PhysicalType::FIXED_LEN_BYTE_ARRAY)
-            .with_length(32)
-            .with_repetition(Repetition::REQUIRED)
-            .build()
-            .unwrap();
-        let value = Type::primitive_type_builder("value", PhysicalType::FIXED_LEN_BYTE_ARRAY)
-            .with_length(32)
-            .with_repetition(Repetition::REQUIRED)
-            .build()
-            .unwrap();
+    pub fn with_row_group_size(mut self, row_group_size: usize) -> Result<Self, CodecError> {
+        if row_group_size == 0 {
+            return Err(CodecError::EmptyBatch {
+                setting: "row group size",
+            });
+        }
+        self.row_group_size = row_group_size;
+        Ok(self)
+    }
 
-        parquet::schema::types::Type::group_type_builder("ContractState")
-            .with_fields([key, value].map(Arc::new).to_vec())
-            .build()
-            .unwrap()
+    pub fn with_dictionary(mut self, enabled: bool) -> Self {
+        self.dictionary_enabled = enabled;
+        self
     }
 }
 
-impl ParquetSchema for ContractBalance {
-    fn schema() -> Type {
-        use parquet::basic::Type as PhysicalType;
-        let asset_id = Type::primitive_type_builder("asset_id", PhysicalType::FIXED_LEN_BYTE_ARRAY)
-            .with_length(32)
-            .with_repetition(Repetition::REQUIRED)
-            .build()
-            .unwrap();
-        let amount = Type::primitive_type_builder("amount", PhysicalType::INT64)
-            .with_converted_type(parquet::basic::ConvertedType::UINT_64)
-            .with_repetition(Repetition::REQUIRED)
-            .build()
-            .unwrap();
+impl<T, W> Encode<T, W> for ParquetCodec
+where
+    [T]: ColumnEncoder<ElementT = T>,
+    T: ParquetSchema + Clone,
+    W: std::io::Write + Send,
+{
+    fn encode_subset(&self, data: &[T], writer: &mut W) -> Result<(), CodecError> {
+        // the whole subset is at hand, so even the last, short, row group is written straight
+        // from it instead of going through `pending`
+        let mut encoder = ParquetStreamEncoder::new(self, writer)?;
+        for row_group in data.chunks(self.row_group_size) {
+            encoder.write_row_group(row_group)?;
+        }
+        Box::new(encoder).finish()
+    }
+}
 
-        parquet::schema::types::Type::group_type_builder("ContractBalance")
-            .with_fields([asset_id, amount].map(Arc::new).to_vec())
-            .build()
-            .unwrap()
+impl<T, W> EncodeStream<T, W> for ParquetCodec
+where
+    [T]: ColumnEncoder<ElementT = T>,
+    T: ParquetSchema + Clone,
+    W: std::io::Write + Send,
+{
+    fn stream_encoder<'a>(
+        &'a self,
+        writer: &'a mut W,
+    ) -> Result<Box<dyn StreamEncoder<T> + 'a>, CodecError>
+    where
+        T: 'a,
+    {
+        Ok(Box::new(ParquetStreamEncoder::new(self, writer)?))
     }
 }
 
-impl ParquetSchema for CoinConfig {
-    fn schema() -> Type {
-        use parquet::basic::Type as PhysicalType;
-        let tx_id = Type::primitive_type_builder("tx_id", PhysicalType::FIXED_LEN_BYTE_ARRAY)
-            .with_length(32)
-            .with_repetition(Repetition::OPTIONAL)
-            .build()
-            .unwrap();
-        let output_index = Type::primitive_type_builder("output_index", PhysicalType::INT32)
-            .with_converted_type(parquet::basic::ConvertedType::UINT_8)
-            .with_repetition(Repetition::OPTIONAL)
-            .build()
-            .unwrap();
-        let tx_pointer_block_height =
-            Type::primitive_type_builder("tx_pointer_block_height", PhysicalType::INT32)
-                .with_converted_type(parquet::basic::ConvertedType::UINT_32)
-                .with_repetition(Repetition::OPTIONAL)
-                .build()
-                .unwrap();
-        let tx_pointer_tx_idx =
-            Type::primitive_type_builder("tx_pointer_tx_idx", PhysicalType::INT32)
-                .with_converted_type(parquet::basic::ConvertedType::UINT_16)
-                .with_repetition(Repetition::OPTIONAL)
-                .build()
+/// A parquet file ends in a footer listing every row group, there is no writing after it. So the
+/// file is written anew: the existing column chunks are copied over as they are, without decoding
+/// them, and the new row groups follow. Bloom filters are carried over, the page indexes of the
+/// copied row groups are dropped.
+impl<T> EncodeAppend<T> for ParquetCodec
+where
+    [T]: ColumnEncoder<ElementT = T>,
+    T: ParquetSchema + Clone,
+{
+    fn append_subset(&self, data: &[T], existing: &mut Vec<u8>) -> Result<(), CodecError> {
+        let old = Bytes::from(std::mem::take(existing));
+        let options = ReadOptionsBuilder::new()
+            .with_reader_properties(
+                ReaderProperties::builder()
+                    .set_read_bloom_filter(true)
+                    .build(),
+            )
+            .build();
+        let reader = SerializedFileReader::new_with_options(old.clone(), options)?;
+        check_schema(&self.schema::<T>(), &reader)?;
+
+        let mut encoder = ParquetStreamEncoder::<T, _>::new(self, existing)?;
+        for (idx, group) in reader.metadata().row_groups().iter().enumerate() {
+            let group_reader = reader.get_row_group(idx)?;
+            let mut row_group = encoder.writer.next_row_group()?;
+            for (column_idx, column) in group.columns().iter().enumerate() {
+                let copied = ColumnCloseResult {
+                    bytes_written: column.compressed_size() as u64,
+                    rows_written: group.num_rows() as u64,
+                    metadata: column.clone(),
+                    bloom_filter: group_reader.get_column_bloom_filter(column_idx).cloned(),
+                    column_index: None,
+                    offset_index: None,
+                };
+                row_group.append_column(&old, copied)?;
+            }
+            row_group.close()?;
+        }
+        for row_group in data.chunks(self.row_group_size) {
+            encoder.write_row_group(row_group)?;
+        }
+        Box::new(encoder).finish()
+    }
+}
+
+/// Keeps the file open between batches, holding back entries until there's a whole row group.
+struct ParquetStreamEncoder<'a, T, W: std::io::Write + Send> {
+    codec: &'a ParquetCodec,
+    writer: SerializedFileWriter<&'a mut W>,
+    pending: Vec<T>,
+}
+
+impl<'a, T, W> ParquetStreamEncoder<'a, T, W>
+where
+    [T]: ColumnEncoder<ElementT = T>,
+    T: ParquetSchema + Clone,
+    W: std::io::Write + Send,
+{
+    fn new(codec: &'a ParquetCodec, writer: &'a mut W) -> Result<Self, CodecError> {
+        let writer = SerializedFileWriter::new(
+            writer,
+            Arc::new(codec.schema::<T>()),
+            Arc::new(codec.writer_properties()?),
+        )?;
+        Ok(Self {
+            codec,
+            writer,
+            pending: vec![],
+        })
+    }
+
+    fn write_row_group(&mut self, rows: &[T]) -> Result<(), CodecError> {
+        let batches = rows.chunks(self.codec.batch_size).collect_vec();
+        <[T]>::encode_row_group(&batches, &mut self.writer)
+    }
+}
+
+impl<T, W> StreamEncoder<T> for ParquetStreamEncoder<'_, T, W>
+where
+    [T]: ColumnEncoder<ElementT = T>,
+    T: ParquetSchema + Clone,
+    W: std::io::Write + Send,
+{
+    fn encode_batch(&mut self, mut batch: &[T]) -> Result<(), CodecError> {
+        let row_group_size = self.codec.row_group_size;
+        if !self.pending.is_empty() {
+            let missing = (row_group_size - self.pending.len()).min(batch.len());
+            let (head, rest) = batch.split_at(missing);
+            self.pending.extend_from_slice(head);
+            batch = rest;
+            if self.pending.len() < row_group_size {
+                return Ok(());
+            }
+            let row_group = std::mem::take(&mut self.pending);
+            self.write_row_group(&row_group)?;
+        }
+        for row_group in batch.chunks(row_group_size) {
+            if row_group.len() < row_group_size {
+                self.pending = row_group.to_vec();
+            } else {
+                self.write_row_group(row_group)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> Result<(), CodecError> {
+        if !self.pending.is_empty() {
+            let row_group = std::mem::take(&mut self.pending);
+            self.write_row_group(&row_group)?;
+        }
+        self.writer.close()?;
+        Ok(())
+    }
+}
+
+/// Walks the fields of a `Row` while keeping track of the column index, so that a decoding
+/// failure can point at the offending column.
+pub struct RowFields<'a> {
+    iter: RowColumnIter<'a>,
+    column: usize,
+}
+
+impl<'a> RowFields<'a> {
+    pub fn new(row: &'a Row) -> Self {
+        Self {
+            iter: row.get_column_iter(),
+            column: 0,
+        }
+    }
+
+    fn next_field(&mut self) -> Result<(&'a String, &'a Field), CodecError> {
+        let next = self.iter.next().ok_or(CodecError::UnexpectedField {
+            column: self.column,
+        })?;
+        self.column += 1;
+        Ok(next)
+    }
+
+    /// Reads the next field as a `C`.
+    pub fn read<C: ParquetColumn>(&mut self) -> Result<C, CodecError> {
+        let (name, field) = self.next_field()?;
+        C::read_field(field).map_err(|err| match err {
+            FieldError::WrongType => CodecError::UnexpectedField {
+                column: self.column - 1,
+            },
+            FieldError::BadLength { expected, got } => CodecError::BadFieldLength {
+                field: name.clone(),
+                expected,
+                got,
+            },
+        })
+    }
+}
+
+/// Readers parquet can be decoded from. Parquet jumps around the file, footer first, so it needs
+/// a `ChunkReader` rather than the `BufRead` the stream codecs take. Implemented for the readers
+/// those codecs are usually handed as well, so a `PayloadCodec` over either family can decode
+/// from the same reader type, in memory or from a file.
+pub trait ParquetSource {
+    type Reader: ChunkReader + 'static;
+    fn into_chunk_reader(self) -> Self::Reader;
+}
+
+impl ParquetSource for Cursor<Vec<u8>> {
+    type Reader = Bytes;
+    fn into_chunk_reader(self) -> Bytes {
+        Bytes::from(self.into_inner())
+    }
+}
+
+impl ParquetSource for Bytes {
+    type Reader = Bytes;
+    fn into_chunk_reader(self) -> Bytes {
+        self
+    }
+}
+
+impl ParquetSource for File {
+    type Reader = File;
+    fn into_chunk_reader(self) -> File {
+        self
+    }
+}
+
+/// Parquet only reads from sources it can hold on to, so the bytes are copied once. Hand over
+/// `Bytes` instead to share them without copying. The stream codecs read the slice as it is.
+impl ParquetSource for &[u8] {
+    type Reader = Bytes;
+    fn into_chunk_reader(self) -> Bytes {
+        Bytes::copy_from_slice(self)
+    }
+}
+
+/// Whatever the `BufReader` buffered is dropped, parquet reads at absolute offsets anyway.
+impl ParquetSource for BufReader<File> {
+    type Reader = File;
+    fn into_chunk_reader(self) -> File {
+        self.into_inner()
+    }
+}
+
+impl<T, R> Decode<T, R> for ParquetCodec
+where
+    T: ParquetSchema + TryFrom<Row, Error = CodecError>,
+    R: ParquetSource,
+{
+    fn decode_subset(&self, reader: R) -> Result<Vec<T>, CodecError> {
+        let reader = SerializedFileReader::new(reader.into_chunk_reader())?;
+        read_rows(&reader, self.schema::<T>())
+    }
+
+    /// Only `SerializedFileReader::new` counts as opening, it reads and parses the footer.
+    fn decode_subset_opened(&self, reader: R) -> Result<(Vec<T>, Duration), CodecError> {
+        let reader = reader.into_chunk_reader();
+        let start = Instant::now();
+        let reader = SerializedFileReader::new(reader)?;
+        let open = start.elapsed();
+        Ok((read_rows(&reader, self.schema::<T>())?, open))
+    }
+
+    /// The file itself is still read into memory, only the rows are converted one at a time.
+    fn decode_subset_each(&self, reader: R, f: impl FnMut(T)) -> Result<(), CodecError> {
+        let reader = SerializedFileReader::new(reader.into_chunk_reader())?;
+        read_rows_each(&reader, self.schema::<T>(), f)
+    }
+}
+
+/// `schema` is the one the codec wrote `T` with.
+fn read_rows<T>(reader: &impl FileReader, schema: Type) -> Result<Vec<T>, CodecError>
+where
+    T: TryFrom<Row, Error = CodecError>,
+{
+    let mut decoded = vec![];
+    read_rows_each(reader, schema, |row| decoded.push(row))?;
+    Ok(decoded)
+}
+
+fn read_rows_each<T>(
+    reader: &impl FileReader,
+    schema: Type,
+    mut f: impl FnMut(T),
+) -> Result<(), CodecError>
+where
+    T: TryFrom<Row, Error = CodecError>,
+{
+    check_schema(&schema, reader)?;
+    for row in reader.get_row_iter(Some(schema))? {
+        f(T::try_from(row?)?);
+    }
+    Ok(())
+}
+
+/// The projection `get_row_iter` is given only has to name columns the file has, a file of
+/// another type sharing a few column names would be read without complaint. So the columns stored
+/// in the footer have to match `schema` one to one, by name, repetition and physical type.
+/// Fixed lengths aren't compared, a hash of the wrong length is reported by the row conversion.
+fn check_schema(schema: &Type, reader: &impl FileReader) -> Result<(), CodecError> {
+    let expected = schema
+        .get_fields()
+        .iter()
+        .map(|field| describe_column(field));
+    let found = reader
+        .metadata()
+        .file_metadata()
+        .schema()
+        .get_fields()
+        .iter()
+        .map(|field| describe_column(field));
+    let mismatch = expected
+        .zip_longest(found)
+        .map(|columns| columns.map_any(Some, Some).or(None, None))
+        .find(|(expected, found)| expected != found);
+    match mismatch {
+        None => Ok(()),
+        Some((expected, found)) => {
+            let none = || "none".to_string();
+            Err(CodecError::SchemaMismatch {
+                schema: schema.name().to_string(),
+                expected: expected.unwrap_or_else(none),
+                found: found.unwrap_or_else(none),
+            })
+        }
+    }
+}
+
+/// A column the way parquet's schema syntax writes it, e.g. `REQUIRED INT64 amount`.
+fn describe_column(field: &Type) -> String {
+    let info = field.get_basic_info();
+    let repetition = if info.has_repetition() {
+        info.repetition().to_string()
+    } else {
+        String::new()
+    };
+    let physical = if field.is_primitive() {
+        field.get_physical_type().to_string()
+    } else {
+        "group".to_string()
+    };
+    format!("{repetition} {physical} {}", info.name())
+}
+
+impl ParquetCodec {
+    /// Same as `decode_subset` but reads the file at `path` directly. Only the footer and the
+    /// pages currently being decoded are held in memory, not the whole file.
+    pub fn decode_file<T>(&self, path: impl AsRef<Path>) -> Result<Vec<T>, CodecError>
+    where
+        T: ParquetSchema + TryFrom<Row, Error = CodecError>,
+    {
+        self.decode_subset(File::open(path)?)
+    }
+
+    /// Writes every row group as a complete parquet file of its own, pushed onto `writers`, so
+    /// the shards can be handed to different workers. Nothing is pushed for an empty subset.
+    pub fn encode_sharded<T, W>(&self, data: &[T], writers: &mut Vec<W>) -> Result<(), CodecError>
+    where
+        [T]: ColumnEncoder<ElementT = T>,
+        T: ParquetSchema + Clone,
+        W: std::io::Write + Send + Default,
+    {
+        for shard in data.chunks(self.row_group_size) {
+            let mut writer = W::default();
+            self.encode_subset(shard, &mut writer)?;
+            writers.push(writer);
+        }
+        Ok(())
+    }
+
+    /// Decodes the shards written by `encode_sharded` one after the other, in order.
+    pub fn decode_shards<T>(&self, shards: Vec<Cursor<Vec<u8>>>) -> Result<Vec<T>, CodecError>
+    where
+        T: ParquetSchema + TryFrom<Row, Error = CodecError>,
+    {
+        let mut decoded = vec![];
+        for shard in shards {
+            decoded.extend(Decode::<T, _>::decode_subset(self, shard)?);
+        }
+        Ok(decoded)
+    }
+
+    /// Same as `decode_shards` but every shard is decoded on its own rayon task. The entries
+    /// still come back in shard order.
+    #[cfg(feature = "parallel")]
+    pub fn decode_shards_parallel<T>(
+        &self,
+        shards: Vec<Cursor<Vec<u8>>>,
+    ) -> Result<Vec<T>, CodecError>
+    where
+        T: ParquetSchema + TryFrom<Row, Error = CodecError> + Send,
+    {
+        use rayon::prelude::*;
+
+        let decoded = shards
+            .into_par_iter()
+            .map(|shard| Decode::<T, _>::decode_subset(self, shard))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(decoded.into_iter().flatten().collect())
+    }
+
+    /// Decodes a subset of `T`s like `decode_subset` does, but assembles all of its `Row`s before
+    /// converting any of them into a `T`. Returns the time spent on the rows, opening the file
+    /// included, and the time spent on the conversion, telling parquet's own decoding apart from
+    /// the conversion into the configs.
+    pub fn decode_rows_timed<T>(
+        &self,
+        reader: impl ParquetSource,
+    ) -> Result<(Duration, Duration), CodecError>
+    where
+        T: ParquetSchema + TryFrom<Row, Error = CodecError>,
+    {
+        let reader = reader.into_chunk_reader();
+        let start = Instant::now();
+        let reader = SerializedFileReader::new(reader)?;
+        let schema = self.schema::<T>();
+        check_schema(&schema, &reader)?;
+        let rows = reader
+            .get_row_iter(Some(schema))?
+            .collect::<Result<Vec<_>, _>>()?;
+        let raw_rows = start.elapsed();
+
+        let start = Instant::now();
+        for row in rows {
+            T::try_from(row)?;
+        }
+        Ok((raw_rows, start.elapsed()))
+    }
+
+    /// Rows in the file, summed up from the row group metadata in the footer. No page is read.
+    pub fn count_rows(&self, reader: Cursor<Vec<u8>>) -> Result<usize, CodecError> {
+        let reader = SerializedFileReader::new(Bytes::from(reader.into_inner()))?;
+        let rows: i64 = reader
+            .metadata()
+            .row_groups()
+            .iter()
+            .map(|group| group.num_rows())
+            .sum();
+        Ok(rows as usize)
+    }
+
+    /// Reads only the columns named in `projection`, the rest are never decompressed or decoded.
+    /// Rows come back raw since a partial `T` can't be built, their fields follow the order of
+    /// `T::schema()`.
+    pub fn decode_columns<T: ParquetSchema>(
+        &self,
+        reader: Cursor<Vec<u8>>,
+        projection: &[&str],
+    ) -> Result<Vec<Row>, CodecError> {
+        let schema = self.schema::<T>();
+        if let Some(unknown) = projection
+            .iter()
+            .find(|name| !schema.get_fields().iter().any(|f| f.name() == **name))
+        {
+            return Err(CodecError::UnknownColumn {
+                name: unknown.to_string(),
+            });
+        }
+        let fields = schema
+            .get_fields()
+            .iter()
+            .filter(|field| projection.contains(&field.name()))
+            .cloned()
+            .collect_vec();
+        let projection = Type::group_type_builder(schema.name())
+            .with_fields(fields)
+            .build()?;
+
+        let reader = SerializedFileReader::new(Bytes::from(reader.into_inner()))?;
+        Ok(reader.get_row_iter(Some(projection))?.try_collect()?)
+    }
+
+    /// All coins belonging to `owner`. Row groups whose statistics or bloom filter rule the owner
+    /// out are never decoded, the rest are scanned.
+    pub fn find_by_owner(
+        &self,
+        reader: Cursor<Vec<u8>>,
+        owner: &Address,
+    ) -> Result<Vec<CoinConfig>, CodecError> {
+        self.lookup_owner(reader, owner, true)
+            .map(|(coins, _)| coins)
+    }
+
+    /// Same as `find_by_owner` but decodes every row group, the baseline for the pruning.
+    pub fn scan_for_owner(
+        &self,
+        reader: Cursor<Vec<u8>>,
+        owner: &Address,
+    ) -> Result<Vec<CoinConfig>, CodecError> {
+        self.lookup_owner(reader, owner, false)
+            .map(|(coins, _)| coins)
+    }
+
+    /// The coins of `owner` along with the number of row groups decoded to find them.
+    fn lookup_owner(
+        &self,
+        reader: Cursor<Vec<u8>>,
+        owner: &Address,
+        prune: bool,
+    ) -> Result<(Vec<CoinConfig>, usize), CodecError> {
+        let schema = self.schema::<CoinConfig>();
+        let owner_column = schema
+            .get_fields()
+            .iter()
+            .position(|field| field.name() == "owner")
+            .expect("coins have an owner column");
+
+        let options = ReadOptionsBuilder::new()
+            .with_reader_properties(
+                ReaderProperties::builder()
+                    .set_read_bloom_filter(true)
+                    .build(),
+            )
+            .build();
+        let reader =
+            SerializedFileReader::new_with_options(Bytes::from(reader.into_inner()), options)?;
+
+        let mut found = vec![];
+        let mut decoded = 0;
+        for index in 0..reader.num_row_groups() {
+            let statistics = reader
+                .metadata()
+                .row_group(index)
+                .column(owner_column)
+                .statistics();
+            if prune && !may_contain(statistics, owner.as_ref()) {
+                continue;
+            }
+            let group = reader.get_row_group(index)?;
+            let filtered_out = group
+                .get_column_bloom_filter(owner_column)
+                .is_some_and(|filter| !filter.check(&owner.to_vec()));
+            if prune && filtered_out {
+                continue;
+            }
+            decoded += 1;
+            for row in group.get_row_iter(Some(schema.clone()))? {
+                let coin = CoinConfig::try_from(row?)?;
+                if coin.owner == *owner {
+                    found.push(coin);
+                }
+            }
+        }
+        Ok((found, decoded))
+    }
+}
+
+/// Whether a column chunk can hold `value` going by its min/max. Missing statistics can't rule
+/// anything out.
+fn may_contain(statistics: Option<&Statistics>, value: &[u8]) -> bool {
+    match statistics {
+        Some(statistics) if statistics.has_min_max_set() => {
+            statistics.min_bytes() <= value && value <= statistics.max_bytes()
+        }
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+
+    use super::*;
+    use crate::{
+        serde_types::{ContractBalance, ContractConfig, ContractState, MessageConfig},
+        test_support::{coins, contracts, messages, seeded_rng},
+    };
+
+    #[test]
+    fn rejects_hashes_of_the_wrong_length() {
+        // given
+        let schema = parquet::schema::parser::parse_message_type(
+            "message ContractState {
+                REQUIRED FIXED_LEN_BYTE_ARRAY (16) key;
+                REQUIRED FIXED_LEN_BYTE_ARRAY (32) value;
+            }",
+        )
+        .unwrap();
+        let mut buffer = vec![];
+        let mut writer =
+            SerializedFileWriter::new(&mut buffer, Arc::new(schema), Default::default()).unwrap();
+        let mut group = writer.next_row_group().unwrap();
+        for len in [16, 32] {
+            let mut column = group.next_column().unwrap().unwrap();
+            column
+                .typed::<FixedLenByteArrayType>()
+                .write_batch(&[vec![1; len].into()], None, None)
                 .unwrap();
-        let maturity = Type::primitive_type_builder("maturity", PhysicalType::INT32)
-            .with_converted_type(parquet::basic::ConvertedType::UINT_32)
-            .with_repetition(Repetition::OPTIONAL)
-            .build()
+            column.close().unwrap();
+        }
+        group.close().unwrap();
+        writer.close().unwrap();
+        let reader = SerializedFileReader::new(Bytes::from(buffer)).unwrap();
+        let row = reader.get_row_iter(None).unwrap().next().unwrap().unwrap();
+
+        // when
+        let result = ContractState::try_from(row);
+
+        // then
+        let err = result.unwrap_err();
+        assert!(
+            matches!(
+                &err,
+                CodecError::BadFieldLength { field, expected: 32, got: 16 } if field == "key"
+            ),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn unsigned_integers_survive_at_their_extremes() {
+        // given
+        let mut rng = seeded_rng(1);
+        let extremes = |coin: CoinConfig, max: bool| CoinConfig {
+            output_index: Some(if max { u8::MAX } else { 0 }),
+            tx_pointer_block_height: Some(BlockHeight::new(if max { u32::MAX } else { 0 })),
+            tx_pointer_tx_idx: Some(if max { u16::MAX } else { 0 }),
+            maturity: Some(BlockHeight::new(if max { u32::MAX } else { 0 })),
+            amount: if max { u64::MAX } else { 0 },
+            ..coin
+        };
+        let coins = vec![
+            extremes(CoinConfig::random(&mut rng), true),
+            extremes(CoinConfig::random(&mut rng), false),
+            CoinConfig {
+                amount: i64::MAX as u64 + 1,
+                ..CoinConfig::random(&mut rng)
+            },
+        ];
+        let messages = vec![MessageConfig {
+            amount: u64::MAX,
+            da_height: DaBlockHeight(u64::MAX),
+            ..MessageConfig::random(&mut rng)
+        }];
+        let contracts = vec![ContractConfig {
+            output_index: Some(u8::MAX),
+            tx_pointer_block_height: Some(BlockHeight::new(u32::MAX)),
+            tx_pointer_tx_idx: Some(u16::MAX),
+            ..ContractConfig::random(&mut rng)
+        }];
+        let codec = ParquetCodec::new(10, ParquetCompression::Uncompressed);
+        let (mut coins_buf, mut messages_buf, mut contracts_buf) = (vec![], vec![], vec![]);
+
+        // when
+        codec.encode_subset(&coins, &mut coins_buf).unwrap();
+        codec.encode_subset(&messages, &mut messages_buf).unwrap();
+        codec.encode_subset(&contracts, &mut contracts_buf).unwrap();
+
+        // then
+        let decoded_coins: Vec<CoinConfig> = codec.decode_subset(Cursor::new(coins_buf)).unwrap();
+        let decoded_messages: Vec<MessageConfig> =
+            codec.decode_subset(Cursor::new(messages_buf)).unwrap();
+        let decoded_contracts: Vec<ContractConfig> =
+            codec.decode_subset(Cursor::new(contracts_buf)).unwrap();
+        pretty_assertions::assert_eq!(decoded_coins, coins);
+        pretty_assertions::assert_eq!(decoded_messages, messages);
+        pretty_assertions::assert_eq!(decoded_contracts, contracts);
+    }
+
+    #[test]
+    fn gzip_levels_are_checked_up_front() {
+        // given
+        let coins = coins(1, 2);
+
+        // when
+        let highest = ParquetCodec::try_new(10, ParquetCompression::Gzip(10));
+        let too_high = ParquetCodec::try_new(10, ParquetCompression::Gzip(11));
+        let unchecked =
+            ParquetCodec::new(10, ParquetCompression::Gzip(11)).encode_subset(&coins, &mut vec![]);
+
+        // then
+        let mut buffer = vec![];
+        highest.unwrap().encode_subset(&coins, &mut buffer).unwrap();
+        assert!(!buffer.is_empty());
+        assert!(too_high.is_err());
+        assert!(unchecked.is_err());
+    }
+
+    #[test]
+    fn brotli_pages_round_trip() {
+        // given
+        let coins = coins(250, 5);
+        let codec = ParquetCodec::try_new(100, ParquetCompression::Brotli(11)).unwrap();
+        let mut buffer = vec![];
+
+        // when
+        codec.encode_subset(&coins, &mut buffer).unwrap();
+
+        // then
+        let decoded: Vec<CoinConfig> = codec.decode_subset(Cursor::new(buffer)).unwrap();
+        pretty_assertions::assert_eq!(decoded, coins);
+        assert!(ParquetCodec::try_new(100, ParquetCompression::Brotli(12)).is_err());
+    }
+
+    #[test]
+    fn empty_batches_and_out_of_range_scales_are_errors() {
+        // given
+        let codec = || ParquetCodec::new(5, ParquetCompression::Uncompressed);
+
+        // when
+        let zero_batch = ParquetCodec::try_new(0, ParquetCompression::Uncompressed);
+        let zero_row_group = codec().with_row_group_size(0);
+        let scale = codec().with_decimal_amounts(DECIMAL_PRECISION + 1);
+
+        // then
+        assert!(matches!(
+            zero_batch,
+            Err(CodecError::EmptyBatch {
+                setting: "batch size"
+            })
+        ));
+        assert!(matches!(
+            zero_row_group,
+            Err(CodecError::EmptyBatch {
+                setting: "row group size"
+            })
+        ));
+        assert!(matches!(
+            scale,
+            Err(CodecError::DecimalScale { scale: 39, .. })
+        ));
+    }
+
+    #[test]
+    fn decodes_straight_from_a_file() {
+        // given
+        let coins = coins(250, 3);
+        let codec = ParquetCodec::new(100, ParquetCompression::Zstd(1));
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        codec.encode_subset(&coins, file.as_file_mut()).unwrap();
+
+        // when
+        let decoded: Vec<CoinConfig> = codec.decode_file(file.path()).unwrap();
+
+        // then
+        pretty_assertions::assert_eq!(decoded, coins);
+    }
+
+    #[test]
+    fn decodes_from_the_same_file_readers_as_the_stream_codecs() {
+        use std::io::Seek;
+
+        use crate::{
+            encoding::{AnyCodec, BincodeCodec, PayloadCodec},
+            test_support::full_payload,
+            util::Data,
+        };
+
+        // given
+        let payload = full_payload(Data::from_array([30, 20, 10, 40, 20, 10]), 4);
+        let codecs = [
+            AnyCodec::Parquet(ParquetCodec::new(16, ParquetCompression::Zstd(1))),
+            AnyCodec::Bincode(BincodeCodec::default()),
+        ];
+
+        for codec in codecs {
+            let mut files =
+                Data::from_array(std::array::from_fn(|_| tempfile::tempfile().unwrap()));
+            PayloadCodec::<BufReader<File>, _>::encode(&codec, &payload, &mut files).unwrap();
+            let readers = files.map(|mut file| {
+                file.rewind().unwrap();
+                BufReader::new(file)
+            });
+
+            // when
+            let decoded = PayloadCodec::<_, File>::decode(&codec, readers).unwrap();
+
+            // then
+            pretty_assertions::assert_eq!(decoded, payload);
+        }
+    }
+
+    #[test]
+    fn decodes_from_borrowed_and_shared_bytes() {
+        use crate::{
+            encoding::{AnyCodec, BincodeCodec, PayloadCodec},
+            test_support::full_payload,
+            util::Data,
+        };
+
+        // given
+        let payload = full_payload(Data::from_array([30, 20, 10, 40, 20, 10]), 5);
+        let codecs = [
+            AnyCodec::Parquet(ParquetCodec::new(16, ParquetCompression::Zstd(1))),
+            AnyCodec::Bincode(BincodeCodec::default()),
+        ];
+
+        for codec in codecs {
+            let mut data = Data::with_capacity(0);
+            PayloadCodec::<Cursor<Vec<u8>>, _>::encode(&codec, &payload, &mut data).unwrap();
+
+            // when
+            let borrowed = PayloadCodec::<&[u8], Vec<u8>>::decode(&codec, data.as_ref()).unwrap();
+
+            // then
+            pretty_assertions::assert_eq!(borrowed, payload);
+        }
+        let codec = ParquetCodec::new(16, ParquetCompression::Zstd(1));
+        let mut buffer = vec![];
+        codec.encode_subset(&payload.coins, &mut buffer).unwrap();
+        let shared: Vec<CoinConfig> = codec.decode_subset(Bytes::from(buffer)).unwrap();
+        pretty_assertions::assert_eq!(shared, payload.coins);
+    }
+
+    #[test]
+    fn files_of_another_type_are_rejected_before_any_row_is_read() {
+        // given
+        let coins = coins(10, 6);
+        let mut buffer = vec![];
+        let codec = ParquetCodec::new(5, ParquetCompression::Gzip(1));
+        codec.encode_subset(&coins, &mut buffer).unwrap();
+
+        // when
+        // every column of a balance is also a coin column, the projection alone would pass
+        let result: Result<Vec<ContractBalance>, _> = codec.decode_subset(Cursor::new(buffer));
+
+        // then
+        let err = result.unwrap_err();
+        assert!(
+            matches!(&err, CodecError::SchemaMismatch { schema, .. } if schema == "ContractBalance"),
+            "{err}"
+        );
+        pretty_assertions::assert_eq!(
+            err.to_string(),
+            "file doesn't have the ContractBalance schema, expected column \
+             REQUIRED FIXED_LEN_BYTE_ARRAY asset_id, found OPTIONAL FIXED_LEN_BYTE_ARRAY tx_id"
+        );
+    }
+
+    #[test]
+    fn derived_schema_follows_the_fields() {
+        // when
+        let schema = CoinConfig::schema();
+
+        // then
+        let columns = schema
+            .get_fields()
+            .iter()
+            .map(|field| {
+                let info = field.get_basic_info();
+                (
+                    info.name().to_string(),
+                    info.repetition(),
+                    field.get_physical_type(),
+                )
+            })
+            .collect_vec();
+        pretty_assertions::assert_eq!(
+            columns,
+            vec![
+                (
+                    "tx_id".to_string(),
+                    Repetition::OPTIONAL,
+                    PhysicalType::FIXED_LEN_BYTE_ARRAY
+                ),
+                (
+                    "output_index".to_string(),
+                    Repetition::OPTIONAL,
+                    PhysicalType::INT32
+                ),
+                (
+                    "tx_pointer_block_height".to_string(),
+                    Repetition::OPTIONAL,
+                    PhysicalType::INT32
+                ),
+                (
+                    "tx_pointer_tx_idx".to_string(),
+                    Repetition::OPTIONAL,
+                    PhysicalType::INT32
+                ),
+                (
+                    "maturity".to_string(),
+                    Repetition::OPTIONAL,
+                    PhysicalType::INT32
+                ),
+                (
+                    "owner".to_string(),
+                    Repetition::REQUIRED,
+                    PhysicalType::FIXED_LEN_BYTE_ARRAY
+                ),
+                (
+                    "amount".to_string(),
+                    Repetition::REQUIRED,
+                    PhysicalType::INT64
+                ),
+                (
+                    "asset_id".to_string(),
+                    Repetition::REQUIRED,
+                    PhysicalType::FIXED_LEN_BYTE_ARRAY
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn message_schema_is_named_after_message_config() {
+        // given
+        let messages = messages(10, 4);
+        let mut buffer = vec![];
+        ParquetCodec::new(5, ParquetCompression::Gzip(1))
+            .encode_subset(&messages, &mut buffer)
             .unwrap();
-        let owner = Type::primitive_type_builder("owner", PhysicalType::FIXED_LEN_BYTE_ARRAY)
-            .with_length(32)
-            .with_repetition(Repetition::REQUIRED)
-            .build()
+
+        // when
+        let reader = SerializedFileReader::new(Bytes::from(buffer)).unwrap();
+
+        // then
+        let schema = reader.metadata().file_metadata().schema();
+        assert_eq!(schema.name(), "MessageConfig");
+    }
+
+    #[test]
+    fn row_groups_span_several_batches() {
+        // given
+        let coins = coins(25, 5);
+        let codec = ParquetCodec::new(5, ParquetCompression::Gzip(1))
+            .with_row_group_size(10)
             .unwrap();
-        let amount = Type::primitive_type_builder("amount", PhysicalType::INT64)
-            .with_converted_type(parquet::basic::ConvertedType::UINT_64)
-            .with_repetition(Repetition::REQUIRED)
-            .build()
+        let mut buffer = vec![];
+
+        // when
+        codec.encode_subset(&coins, &mut buffer).unwrap();
+
+        // then
+        let reader = SerializedFileReader::new(Bytes::from(buffer.clone())).unwrap();
+        let rows_per_group = reader
+            .metadata()
+            .row_groups()
+            .iter()
+            .map(|group| group.num_rows())
+            .collect_vec();
+        pretty_assertions::assert_eq!(rows_per_group, vec![10, 10, 5]);
+        let decoded: Vec<CoinConfig> = codec.decode_subset(Cursor::new(buffer)).unwrap();
+        pretty_assertions::assert_eq!(decoded, coins);
+    }
+
+    #[test]
+    fn rows_are_counted_from_the_metadata() {
+        // given
+        let coins = coins(1234, 14);
+        let codec = ParquetCodec::new(100, ParquetCompression::Gzip(1));
+        let mut buffer = vec![];
+        codec.encode_subset(&coins, &mut buffer).unwrap();
+
+        // when
+        let rows = codec.count_rows(Cursor::new(buffer)).unwrap();
+
+        // then
+        pretty_assertions::assert_eq!(rows, 1234);
+    }
+
+    #[test]
+    fn shards_hold_a_row_group_each() {
+        // given
+        let coins = coins(25, 15);
+        let codec = ParquetCodec::new(10, ParquetCompression::Gzip(1));
+        let mut shards: Vec<Vec<u8>> = vec![];
+
+        // when
+        codec.encode_sharded(&coins, &mut shards).unwrap();
+
+        // then
+        let rows = shards
+            .iter()
+            .map(|shard| codec.count_rows(Cursor::new(shard.clone())).unwrap())
+            .collect_vec();
+        pretty_assertions::assert_eq!(rows, vec![10, 10, 5]);
+        let decoded: Vec<CoinConfig> = codec
+            .decode_shards(shards.into_iter().map(Cursor::new).collect())
             .unwrap();
-        let asset_id = Type::primitive_type_builder("asset_id", PhysicalType::FIXED_LEN_BYTE_ARRAY)
-            .with_length(32)
-            .with_repetition(Repetition::REQUIRED)
-            .build()
+        pretty_assertions::assert_eq!(decoded, coins);
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn shards_decoded_in_parallel_keep_their_order() {
+        // given
+        let coins = coins(95, 16);
+        let codec = ParquetCodec::new(10, ParquetCompression::Gzip(1));
+        let mut shards: Vec<Vec<u8>> = vec![];
+        codec.encode_sharded(&coins, &mut shards).unwrap();
+
+        // when
+        let decoded: Vec<CoinConfig> = codec
+            .decode_shards_parallel(shards.into_iter().map(Cursor::new).collect())
             .unwrap();
 
-        parquet::schema::types::Type::group_type_builder("CoinConfig")
-            .with_fields(
-                [
-                    tx_id,
-                    output_index,
-                    tx_pointer_block_height,
-                    tx_pointer_tx_idx,
-                    maturity,
-                    owner,
-                    amount,
-                    asset_id,
-                ]
-                .map(Arc::new)
-                .to_vec(),
-            )
-            .build()
-            .unwrap()
+        // then
+        pretty_assertions::assert_eq!(decoded, coins);
     }
-}
 
-impl ParquetSchema for MessageConfig {
-    fn schema() -> Type {
-        use parquet::basic::Type as PhysicalType;
-        let sender = Type::primitive_type_builder("sender", PhysicalType::FIXED_LEN_BYTE_ARRAY)
-            .with_length(32)
-            .with_repetition(Repetition::REQUIRED)
-            .build()
+    #[test]
+    fn dictionary_encoding_can_be_turned_off() {
+        // given
+        let coin = coins(1, 6).remove(0);
+        let coins = vec![coin; 100];
+        let encode = |codec: ParquetCodec| {
+            let mut buffer = vec![];
+            codec.encode_subset(&coins, &mut buffer).unwrap();
+            buffer.len()
+        };
+
+        // when
+        let with_dictionary = encode(ParquetCodec::new(100, ParquetCompression::Uncompressed));
+        let without_dictionary =
+            encode(ParquetCodec::new(100, ParquetCompression::Uncompressed).with_dictionary(false));
+
+        // then
+        assert!(with_dictionary < without_dictionary);
+    }
+
+    #[test]
+    fn columns_can_be_compressed_differently() {
+        // given
+        let contracts = contracts(10, 7);
+        let codec = ParquetCodec::new(10, ParquetCompression::Uncompressed)
+            .with_column_compression("code", ParquetCompression::Zstd(1))
+            .with_column_encoding("contract_id", Encoding::PLAIN)
+            .with_dictionary(false);
+        let mut buffer = vec![];
+
+        // when
+        codec.encode_subset(&contracts, &mut buffer).unwrap();
+
+        // then
+        let reader = SerializedFileReader::new(Bytes::from(buffer.clone())).unwrap();
+        let group = reader.metadata().row_group(0);
+        let compression_of = |name: &str| {
+            group
+                .columns()
+                .iter()
+                .find(|column| column.column_path().string() == name)
+                .unwrap()
+                .compression()
+        };
+        assert!(matches!(compression_of("code"), Compression::ZSTD(_)));
+        pretty_assertions::assert_eq!(compression_of("contract_id"), Compression::UNCOMPRESSED);
+        let decoded: Vec<ContractConfig> = codec.decode_subset(Cursor::new(buffer)).unwrap();
+        pretty_assertions::assert_eq!(decoded, contracts);
+    }
+
+    #[test]
+    fn finds_coins_by_owner() {
+        // given
+        let mut coins = coins(100, 8);
+        let owner = coins[7].owner;
+        coins[42].owner = owner;
+        coins.sort_by_key(|coin| coin.owner);
+        let codec = ParquetCodec::new(10, ParquetCompression::Uncompressed)
+            .with_row_group_size(10)
+            .unwrap()
+            .with_bloom_filter("owner");
+        let mut buffer = vec![];
+        codec.encode_subset(&coins, &mut buffer).unwrap();
+
+        // when
+        let (found, pruned_groups) = codec
+            .lookup_owner(Cursor::new(buffer.clone()), &owner, true)
             .unwrap();
-        let recipient =
-            Type::primitive_type_builder("recipient", PhysicalType::FIXED_LEN_BYTE_ARRAY)
-                .with_length(32)
-                .with_repetition(Repetition::REQUIRED)
-                .build()
-                .unwrap();
-        let nonce = Type::primitive_type_builder("nonce", PhysicalType::FIXED_LEN_BYTE_ARRAY)
-            .with_length(32)
-            .with_repetition(Repetition::REQUIRED)
-            .build()
+
+        // then
+        let expected = coins
+            .into_iter()
+            .filter(|coin| coin.owner == owner)
+            .collect_vec();
+        pretty_assertions::assert_eq!(found, expected);
+        let (scanned, scanned_groups) = codec
+            .lookup_owner(Cursor::new(buffer), &owner, false)
             .unwrap();
-        let amount = Type::primitive_type_builder("amount", PhysicalType::INT64)
-            .with_converted_type(parquet::basic::ConvertedType::UINT_64)
-            .with_repetition(Repetition::REQUIRED)
-            .build()
+        pretty_assertions::assert_eq!(scanned, expected);
+        pretty_assertions::assert_eq!(scanned_groups, 10);
+        // sorted by owner, both coins sit in one row group or two neighbouring ones
+        assert!(
+            (1..=2).contains(&pruned_groups),
+            "decoded {pruned_groups} row groups"
+        );
+    }
+
+    #[test]
+    fn appending_keeps_the_existing_row_groups_and_their_bloom_filters() {
+        // given
+        let coins = coins(50, 11);
+        let codec = ParquetCodec::new(8, ParquetCompression::Gzip(1))
+            .with_row_group_size(16)
+            .unwrap()
+            .with_bloom_filter("owner");
+        let mut buffer = vec![];
+        codec.encode_subset(&coins[..40], &mut buffer).unwrap();
+
+        // when
+        codec.append_subset(&coins[40..], &mut buffer).unwrap();
+
+        // then
+        let options = ReadOptionsBuilder::new()
+            .with_reader_properties(
+                ReaderProperties::builder()
+                    .set_read_bloom_filter(true)
+                    .build(),
+            )
+            .build();
+        let reader = SerializedFileReader::new_with_options(Bytes::from(buffer), options).unwrap();
+        let owner_column = 5;
+        let groups = (0..reader.num_row_groups())
+            .map(|index| {
+                let group = reader.get_row_group(index).unwrap();
+                (
+                    group.metadata().num_rows(),
+                    group.get_column_bloom_filter(owner_column).is_some(),
+                )
+            })
+            .collect_vec();
+        pretty_assertions::assert_eq!(groups, vec![(16, true), (16, true), (8, true), (10, true)]);
+        let decoded: Vec<CoinConfig> = read_rows(&reader, CoinConfig::schema()).unwrap();
+        pretty_assertions::assert_eq!(decoded, coins);
+    }
+
+    #[test]
+    fn owners_outside_every_row_group_are_not_found() {
+        // given
+        let coins = coins(20, 9);
+        let codec = ParquetCodec::new(10, ParquetCompression::Uncompressed);
+        let mut buffer = vec![];
+        codec.encode_subset(&coins, &mut buffer).unwrap();
+
+        // when
+        let found = codec
+            .find_by_owner(Cursor::new(buffer), &Address::new([0; 32]))
             .unwrap();
-        let data = Type::primitive_type_builder("data", PhysicalType::BYTE_ARRAY)
-            .with_repetition(Repetition::REQUIRED)
-            .build()
+
+        // then
+        assert!(found.is_empty());
+    }
+
+    #[test]
+    fn decodes_only_the_projected_columns() {
+        // given
+        let coins = coins(10, 10);
+        let mut buffer = vec![];
+        let codec = ParquetCodec::new(5, ParquetCompression::Gzip(1));
+        codec.encode_subset(&coins, &mut buffer).unwrap();
+
+        // when
+        let rows = codec
+            .decode_columns::<CoinConfig>(Cursor::new(buffer), &["amount", "owner"])
             .unwrap();
-        let da_height = Type::primitive_type_builder("da_height", PhysicalType::INT64)
-            .with_converted_type(parquet::basic::ConvertedType::UINT_64)
-            .with_repetition(Repetition::REQUIRED)
-            .build()
+
+        // then
+        let decoded = rows
+            .iter()
+            .map(|row| {
+                let columns = row
+                    .get_column_iter()
+                    .map(|(name, field)| (name.clone(), field.clone()))
+                    .collect_vec();
+                let [(owner_name, Field::Bytes(owner)), (amount_name, Field::ULong(amount))] =
+                    columns.as_slice()
+                else {
+                    panic!("unexpected columns: {columns:?}");
+                };
+                pretty_assertions::assert_eq!(
+                    (owner_name.as_str(), amount_name.as_str()),
+                    ("owner", "amount")
+                );
+                (Address::new(owner.data().try_into().unwrap()), *amount)
+            })
+            .collect_vec();
+        let expected = coins
+            .iter()
+            .map(|coin| (coin.owner, coin.amount))
+            .collect_vec();
+        pretty_assertions::assert_eq!(decoded, expected);
+    }
+
+    #[test]
+    fn projecting_an_unknown_column_fails() {
+        // given
+        let mut buffer = vec![];
+        let codec = ParquetCodec::new(5, ParquetCompression::Gzip(1));
+        codec.encode_subset(&coins(1, 11), &mut buffer).unwrap();
+
+        // when
+        let result = codec.decode_columns::<CoinConfig>(Cursor::new(buffer), &["owner", "nope"]);
+
+        // then
+        assert!(matches!(result, Err(CodecError::UnknownColumn { name }) if name == "nope"));
+    }
+
+    #[test]
+    fn rows_are_decoded_apart_from_their_conversion() {
+        // given
+        let coins = coins(12, 14);
+        let codec = ParquetCodec::new(5, ParquetCompression::Gzip(1));
+        let mut buffer = vec![];
+        codec.encode_subset(&coins, &mut buffer).unwrap();
+
+        // when
+        let times = codec.decode_rows_timed::<CoinConfig>(Cursor::new(buffer.clone()));
+        let mismatch = codec.decode_rows_timed::<MessageConfig>(Cursor::new(buffer));
+
+        // then
+        assert!(times.is_ok());
+        assert!(matches!(
+            mismatch,
+            Err(CodecError::SchemaMismatch { schema, .. }) if schema == "MessageConfig"
+        ));
+    }
+
+    #[test]
+    fn amounts_round_trip_as_decimals() {
+        // given
+        let mut rng = seeded_rng(12);
+        let coins = [0, u64::MAX].map(|amount| CoinConfig {
+            amount,
+            ..CoinConfig::random(&mut rng)
+        });
+        let balances = [1, i64::MAX as u64 + 1].map(|amount| ContractBalance {
+            amount,
+            asset_id: AssetId::new([amount as u8; 32]),
+        });
+        let codec = ParquetCodec::new(5, ParquetCompression::Gzip(1))
+            .with_decimal_amounts(9)
             .unwrap();
+        let (mut coins_buf, mut balances_buf) = (vec![], vec![]);
+
+        // when
+        codec.encode_subset(&coins, &mut coins_buf).unwrap();
+        codec.encode_subset(&balances, &mut balances_buf).unwrap();
 
-        parquet::schema::types::Type::group_type_builder("CoinConfig")
-            .with_fields(
-                [sender, recipient, nonce, amount, data, da_height]
-                    .map(Arc::new)
-                    .to_vec(),
+        // then
+        let reader = SerializedFileReader::new(Bytes::from(coins_buf.clone())).unwrap();
+        let schema = reader.metadata().file_metadata().schema();
+        let amount = schema
+            .get_fields()
+            .iter()
+            .find(|field| field.name() == "amount")
+            .unwrap();
+        pretty_assertions::assert_eq!(
+            (
+                amount.get_physical_type(),
+                amount.get_basic_info().logical_type()
+            ),
+            (
+                PhysicalType::FIXED_LEN_BYTE_ARRAY,
+                Some(LogicalType::Decimal {
+                    scale: 9,
+                    precision: 38
+                })
             )
-            .build()
+        );
+        let decoded_coins: Vec<CoinConfig> = codec.decode_subset(Cursor::new(coins_buf)).unwrap();
+        let decoded_balances: Vec<ContractBalance> =
+            codec.decode_subset(Cursor::new(balances_buf)).unwrap();
+        pretty_assertions::assert_eq!(decoded_coins, coins);
+        pretty_assertions::assert_eq!(decoded_balances, balances);
+    }
+
+    #[test]
+    fn decimal_amounts_need_a_codec_that_expects_them() {
+        // given
+        let coins = coins(3, 13);
+        let mut buffer = vec![];
+        ParquetCodec::new(5, ParquetCompression::Gzip(1))
+            .with_decimal_amounts(0)
             .unwrap()
+            .encode_subset(&coins, &mut buffer)
+            .unwrap();
+
+        // when
+        let result: Result<Vec<CoinConfig>, _> =
+            ParquetCodec::new(5, ParquetCompression::Gzip(1)).decode_subset(Cursor::new(buffer));
+
+        // then
+        let err = result.unwrap_err();
+        assert!(
+            matches!(&err, CodecError::SchemaMismatch { found, .. } if found.contains("FIXED_LEN_BYTE_ARRAY amount")),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn decimals_of_any_width_are_read_as_amounts() {
+        // given
+        let narrow = 256i32.to_be_bytes();
+        let padded = [vec![0], 7u64.to_be_bytes().to_vec()].concat();
+        let widest = decimal_bytes(u64::MAX);
+        let negative = (-1i64).to_be_bytes();
+        let too_large = [vec![1], vec![0; 8]].concat();
+
+        // when
+        let amounts = [
+            narrow.as_slice(),
+            padded.as_slice(),
+            widest.data(),
+            negative.as_slice(),
+            too_large.as_slice(),
+        ]
+        .map(u64_from_decimal);
+
+        // then
+        pretty_assertions::assert_eq!(amounts, [Some(256), Some(7), Some(u64::MAX), None, None]);
     }
 }
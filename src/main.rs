@@ -1,87 +1,232 @@
-// pub mod api;
+pub mod api;
+pub mod baseline;
+pub mod cli;
+pub mod compression;
 pub mod encoding;
 pub mod measurements;
+pub mod memory;
+pub mod plan;
 pub mod serde_types;
+pub mod snapshot;
+pub mod summary;
 pub mod util;
 
-use std::{iter::zip, path::Path};
+#[cfg(test)]
+mod test_support;
 
-use encoding::{BincodeCodec, BsonCodec, JsonCodec, ParquetCodec};
+use std::{
+    fs::File,
+    io::{BufWriter, Cursor, Write},
+    iter::zip,
+    ops::RangeInclusive,
+    path::Path,
+    time::Duration,
+};
+
+use anyhow::Context;
+use baseline::Baseline;
+use clap::Parser;
+use cli::{Cli, CodecName, Command, Phase};
+use compression::CompressionAlgo;
+use encoding::{
+    AnyCodec, AvroCodec, BincodeCodec, BsonCodec, CborCodec, CodecError, ColumnarBincodeCodec,
+    CsvCodec, JsonCodec, MessagePackCodec, Mismatch, ParquetCodec, ParquetCompression,
+    PayloadCodec,
+};
+use flate2::Compression;
 use itertools::Itertools;
-use measurements::{EncodeMeasurement, LinearRegression, MeasurementRunner};
+use measurements::{
+    write_json, AppendMeasurement, CollectToCsv, EncodeMeasurement, HistogramBucket,
+    LinearRegression, MeasurementRunner, PerTypeMeasurement, PolynomialRegression, Prediction,
+    SweepMeasurement, TimingStats, Timings, ToCsv,
+};
+use plan::{chart_file, output_file, ChartGroup, Plan};
 use plotters::{
-    prelude::{ChartBuilder, Circle, IntoDrawingArea, PathElement, SVGBackend},
+    chart::ChartContext,
+    coord::{
+        combinators::IntoLogRange,
+        ranged1d::{Ranged, ValueFormatter},
+    },
+    prelude::{
+        Cartesian2d, ChartBuilder, Circle, Cross, DrawingBackend, EmptyElement, ErrorBar,
+        IntoDrawingArea, PathElement, Rectangle, SVGBackend, TriangleMarker,
+    },
     series::{LineSeries, PointSeries},
     style::{Color, IntoFont, RGBColor, WHITE},
 };
-use rand::Rng;
+use summary::RunSummary;
+use util::{payload_with, Data, Payload, PayloadConfig, PayloadRng};
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 enum Shape {
     Line,
     Circle,
+    Cross,
+    Square,
+    Triangle,
+}
+
+/// Point series without a pinned shape take the next one from here, so overlapping series can
+/// be told apart even where the colors are close.
+const MARKERS: [Shape; 4] = [Shape::Circle, Shape::Cross, Shape::Square, Shape::Triangle];
+
+fn marker_shape(idx: usize) -> Shape {
+    MARKERS[idx % MARKERS.len()]
+}
+
+/// Series without a pinned color take the next one from here, in the order they were added.
+const PALETTE: [(u8, u8, u8); 10] = [
+    (31, 119, 180),
+    (255, 127, 14),
+    (44, 160, 44),
+    (214, 39, 40),
+    (148, 103, 189),
+    (140, 86, 75),
+    (227, 119, 194),
+    (127, 127, 127),
+    (188, 189, 34),
+    (23, 190, 207),
+];
+
+fn palette_color(idx: usize) -> (u8, u8, u8) {
+    PALETTE[idx % PALETTE.len()]
 }
 
 #[derive(Debug, Clone)]
 struct PlotSettings {
     label: String,
-    color: (u8, u8, u8),
-    shape: Shape,
+    color: Option<(u8, u8, u8)>,
+    shape: Option<Shape>,
 }
 
 impl PlotSettings {
     pub fn normal(label: &str) -> Self {
-        let mut rng = rand::thread_rng();
         Self {
             label: label.to_string(),
-            color: (
-                rng.gen_range(0..65),
-                rng.gen_range(0..65),
-                rng.gen_range(0..65),
-            ),
-            shape: Shape::Circle,
+            color: None,
+            shape: None,
         }
     }
     pub fn predicted(label: &str) -> Self {
         Self {
             label: label.to_string(),
-            color: (rand::random(), rand::random(), rand::random()),
-            shape: Shape::Line,
+            color: None,
+            shape: Some(Shape::Line),
         }
     }
+    /// Pins the color instead of taking the next one from the palette.
+    pub fn with_color(mut self, color: (u8, u8, u8)) -> Self {
+        self.color = Some(color);
+        self
+    }
+    /// Pins the shape instead of taking the next marker.
+    pub fn with_shape(mut self, shape: Shape) -> Self {
+        self.shape = Some(shape);
+        self
+    }
+}
+
+/// Which axes of a plot use a logarithmic scale.
+#[derive(Debug, Clone, Copy, Default)]
+struct LogScale {
+    x: bool,
+    y: bool,
+}
+
+impl LogScale {
+    pub const X: Self = Self { x: true, y: false };
+    pub const Y: Self = Self { x: false, y: true };
 }
 
 fn draw_measurements(
     title: &str,
     x_desc: &str,
     y_desc: &str,
-    measurement_sets: Vec<(Vec<(f64, f64)>, PlotSettings)>,
+    measurement_sets: Vec<(Vec<Point>, PlotSettings)>,
+    log_scale: LogScale,
     path: impl AsRef<Path>,
-) -> anyhow::Result<()> {
-    let max_x = measurement_sets
-        .iter()
-        .flat_map(|m| &m.0)
+) -> anyhow::Result<usize> {
+    // zero (e.g. the `num_elements == 0` measurement) has no place on a log axis
+    let measurement_sets = measurement_sets
+        .into_iter()
+        .map(|(data, settings)| {
+            let data = data
+                .into_iter()
+                .filter(|&(x, y, _)| (!log_scale.x || x > 0f64) && (!log_scale.y || y > 0f64))
+                .collect_vec();
+            (data, settings)
+        })
+        .collect_vec();
+    let points = || measurement_sets.iter().flat_map(|m| &m.0);
+
+    let max_x = points()
         .map(|m| m.0)
         .max_by(|a, b| a.total_cmp(b))
-        .unwrap();
-
-    let max_y = measurement_sets
-        .iter()
-        .flat_map(|m| &m.0)
-        .map(|m| m.1)
+        .unwrap_or(1f64);
+    let max_y = points()
+        .map(|m| m.1 + m.2)
         .max_by(|a, b| a.total_cmp(b))
-        .unwrap();
+        .unwrap_or(1f64);
+    let min_x = points()
+        .map(|m| m.0)
+        .min_by(|a, b| a.total_cmp(b))
+        .unwrap_or(1f64);
+    let min_y = points()
+        .map(|m| m.1)
+        .min_by(|a, b| a.total_cmp(b))
+        .unwrap_or(1f64);
 
     let root = SVGBackend::new(path.as_ref(), (1980, 1200)).into_drawing_area();
     root.fill(&WHITE)?;
 
-    let mut chart = ChartBuilder::on(&root)
+    let mut builder = ChartBuilder::on(&root);
+    builder
         .x_label_area_size(70)
         .y_label_area_size(70)
         .margin(5)
-        .caption(title, ("sans-serif", 50.0).into_font())
-        .build_cartesian_2d(0f64..max_x, 0f64..max_y)?;
+        .caption(title, ("sans-serif", 50.0).into_font());
+
+    let labels = (x_desc, y_desc);
+    let drawn = match (log_scale.x, log_scale.y) {
+        (false, false) => {
+            let chart = builder.build_cartesian_2d(0f64..max_x, 0f64..max_y)?;
+            draw_series(chart, labels, measurement_sets, 0f64)?
+        }
+        (true, false) => {
+            let chart = builder.build_cartesian_2d((min_x..max_x).log_scale(), 0f64..max_y)?;
+            draw_series(chart, labels, measurement_sets, 0f64)?
+        }
+        (false, true) => {
+            let chart = builder.build_cartesian_2d(0f64..max_x, (min_y..max_y).log_scale())?;
+            draw_series(chart, labels, measurement_sets, min_y)?
+        }
+        (true, true) => {
+            let chart = builder
+                .build_cartesian_2d((min_x..max_x).log_scale(), (min_y..max_y).log_scale())?;
+            draw_series(chart, labels, measurement_sets, min_y)?
+        }
+    };
+
+    // To avoid the IO failure being ignored silently, we manually call the present function
+    root.present().expect("Unable to write result to file");
+
+    Ok(drawn)
+}
 
+/// Returns how many series were drawn. Error bars are cut off at `min_y` so they stay on a
+/// log scaled axis.
+fn draw_series<'a, DB, X, Y>(
+    mut chart: ChartContext<'a, DB, Cartesian2d<X, Y>>,
+    (x_desc, y_desc): (&str, &str),
+    measurement_sets: Vec<(Vec<Point>, PlotSettings)>,
+    min_y: f64,
+) -> anyhow::Result<usize>
+where
+    DB: DrawingBackend + 'a,
+    DB::ErrorType: 'static,
+    X: Ranged<ValueType = f64> + ValueFormatter<f64>,
+    Y: Ranged<ValueType = f64> + ValueFormatter<f64>,
+{
     chart
         .configure_mesh()
         .x_desc(x_desc)
@@ -90,34 +235,72 @@ fn draw_measurements(
         .y_labels(50)
         .draw()?;
 
-    for (data, details) in measurement_sets {
-        let color = RGBColor(details.color.0, details.color.1, details.color.2);
-        if let Shape::Circle = details.shape {
-            chart
-                .draw_series(PointSeries::<_, _, Circle<_, _>, _>::new(
-                    data.iter().copied(),
-                    3,
-                    color.clone().filled(),
-                ))?
-                .label(&details.label)
-                .legend(move |(x, y)| Circle::new((x + 10, y), 3, color.clone().filled()));
-        } else {
-            chart
-                .draw_series(LineSeries::new(data.iter().copied(), color))?
-                .label(&details.label)
-                .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color));
+    let mut drawn = 0;
+    for (idx, (data, details)) in measurement_sets.into_iter().enumerate() {
+        let (r, g, b) = details.color.unwrap_or_else(|| palette_color(idx));
+        let color = RGBColor(r, g, b);
+        let shape = details.shape.unwrap_or_else(|| marker_shape(idx));
+        let points = data.iter().map(|&(x, y, _)| (x, y));
+        match shape {
+            Shape::Line => {
+                chart
+                    .draw_series(LineSeries::new(points, color))?
+                    .label(&details.label)
+                    .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color));
+            }
+            Shape::Circle => {
+                chart
+                    .draw_series(PointSeries::<_, _, Circle<_, _>, _>::new(
+                        points,
+                        3,
+                        color.filled(),
+                    ))?
+                    .label(&details.label)
+                    .legend(move |(x, y)| Circle::new((x + 10, y), 3, color.filled()));
+            }
+            Shape::Cross => {
+                chart
+                    .draw_series(PointSeries::<_, _, Cross<_, _>, _>::new(points, 4, color))?
+                    .label(&details.label)
+                    .legend(move |(x, y)| Cross::new((x + 10, y), 4, color));
+            }
+            Shape::Square => {
+                chart
+                    .draw_series(points.map(|point| {
+                        EmptyElement::at(point) + Rectangle::new([(-3, -3), (3, 3)], color.filled())
+                    }))?
+                    .label(&details.label)
+                    .legend(move |(x, y)| {
+                        Rectangle::new([(x + 7, y - 3), (x + 13, y + 3)], color.filled())
+                    });
+            }
+            Shape::Triangle => {
+                chart
+                    .draw_series(PointSeries::<_, _, TriangleMarker<_, _>, _>::new(
+                        points,
+                        4,
+                        color.filled(),
+                    ))?
+                    .label(&details.label)
+                    .legend(move |(x, y)| TriangleMarker::new((x + 10, y), 4, color.filled()));
+            }
         }
-
-        chart
-            .configure_series_labels()
-            .background_style(RGBColor(128, 128, 128))
-            .draw()?;
+        if shape != Shape::Line {
+            chart.draw_series(data.iter().filter(|(_, _, err)| *err > 0f64).map(
+                |&(x, y, err)| {
+                    ErrorBar::new_vertical(x, (y - err).max(min_y), y, y + err, color, 6)
+                },
+            ))?;
+        }
+        drawn += 1;
     }
 
-    // To avoid the IO failure being ignored silently, we manually call the present function
-    root.present().expect("Unable to write result to file");
+    chart
+        .configure_series_labels()
+        .background_style(RGBColor(128, 128, 128))
+        .draw()?;
 
-    Ok(())
+    Ok(drawn)
 }
 
 #[derive(Debug, Clone, Copy, Default)]
@@ -146,13 +329,20 @@ impl Scale {
     }
 }
 
+/// x, y and the spread drawn as an error bar around y.
+type Point = (f64, f64, f64);
+
 #[derive(Debug, Default)]
 struct PlotMerger {
     storage_scale: Scale,
     x_scale: Scale,
-    bytes: Vec<(Vec<(f64, f64)>, PlotSettings)>,
-    encode_time: Vec<(Vec<(f64, f64)>, PlotSettings)>,
-    decode_time: Vec<(Vec<(f64, f64)>, PlotSettings)>,
+    log_scale: LogScale,
+    bytes: Vec<(Vec<Point>, PlotSettings)>,
+    encode_time: Vec<(Vec<Point>, PlotSettings)>,
+    decode_time: Vec<(Vec<Point>, PlotSettings)>,
+    throughput: Vec<(Vec<Point>, PlotSettings)>,
+    peak_memory: Vec<(Vec<Point>, PlotSettings)>,
+    compression_ratio: Vec<(Vec<Point>, PlotSettings)>,
 }
 
 impl PlotMerger {
@@ -164,157 +354,1705 @@ impl PlotMerger {
         }
     }
 
+    /// Axes are linear unless set otherwise here.
+    pub fn with_log_scale(mut self, log_scale: LogScale) -> Self {
+        self.log_scale = log_scale;
+        self
+    }
+
     pub fn add(&mut self, settings: PlotSettings, measurement: &[EncodeMeasurement]) -> &mut Self {
+        // resolved here so a series keeps its color and shape even in charts that skip some series
+        let settings = PlotSettings {
+            color: Some(
+                settings
+                    .color
+                    .unwrap_or_else(|| palette_color(self.bytes.len())),
+            ),
+            shape: Some(
+                settings
+                    .shape
+                    .unwrap_or_else(|| marker_shape(self.bytes.len())),
+            ),
+            ..settings
+        };
         let x_axis = measurement
             .iter()
             .map(|m| m.num_elements as f64 / self.x_scale.divider())
             .collect_vec();
 
+        let points = |y: Vec<(f64, f64)>| {
+            zip(x_axis.clone(), y)
+                .map(|(x, (y, err))| (x, y, err))
+                .collect()
+        };
+
         let bytes = measurement
             .iter()
-            .map(|m| m.bytes as f64 / self.storage_scale.divider());
-        self.bytes
-            .push((zip(x_axis.clone(), bytes).collect(), settings.clone()));
+            .map(|m| (m.bytes as f64 / self.storage_scale.divider(), 0f64))
+            .collect();
+        self.bytes.push((points(bytes), settings.clone()));
 
-        let encode_time = measurement.iter().map(|m| m.encode_time.as_secs_f64());
+        let encode_time = measurement
+            .iter()
+            .map(|m| {
+                (
+                    m.encode_time.as_secs_f64(),
+                    m.encode_stats.stddev.as_secs_f64(),
+                )
+            })
+            .collect();
         self.encode_time
-            .push((zip(x_axis.clone(), encode_time).collect(), settings.clone()));
+            .push((points(encode_time), settings.clone()));
+
+        let throughput = measurement
+            .iter()
+            .map(|m| (m.encode_throughput_elems() / self.x_scale.divider(), 0f64))
+            .collect();
+        self.throughput.push((points(throughput), settings.clone()));
+
+        // a decode phase that wasn't measured, e.g. by `run_encode_only`, is left out instead of
+        // drawn as a flat line at zero
+        let decode_time = measurement
+            .iter()
+            .map(|m| {
+                Some((
+                    m.decode_time?.as_secs_f64(),
+                    m.decode_stats?.stddev.as_secs_f64(),
+                ))
+            })
+            .collect::<Option<Vec<_>>>();
+        if let Some(decode_time) = decode_time {
+            self.decode_time
+                .push((points(decode_time), settings.clone()));
+        }
+
+        let peak_memory = zip(&x_axis, measurement)
+            .filter_map(|(&x, m)| {
+                let peak = m.peak_bytes? as f64 / self.storage_scale.divider();
+                Some((x, peak, 0f64))
+            })
+            .collect_vec();
+        if !peak_memory.is_empty() {
+            self.peak_memory.push((peak_memory, settings.clone()));
+        }
+
+        self
+    }
 
-        let decode_time = measurement.iter().map(|m| m.decode_time.as_secs_f64());
-        self.decode_time
-            .push((zip(x_axis, decode_time).collect(), settings.clone()));
+    /// Adds the measured points along with the line predicted from them, in the same color so the
+    /// fit can be judged by eye. The line is labeled `<label>_predicted`.
+    pub fn add_with_prediction(
+        &mut self,
+        label: &str,
+        measured: &[EncodeMeasurement],
+        predicted: &[EncodeMeasurement],
+    ) -> &mut Self {
+        let color = palette_color(self.bytes.len());
+        self.add(PlotSettings::normal(label).with_color(color), measured);
+        self.add(
+            PlotSettings::predicted(&format!("{label}_predicted")).with_color(color),
+            predicted,
+        )
+    }
 
+    /// Adds a series of `(num_elements, ratio)` as given by `measurements::compression_ratio`.
+    /// Drawn by `plot_ratio_with` only.
+    pub fn add_ratio(&mut self, settings: PlotSettings, ratio: &[(usize, f64)]) -> &mut Self {
+        let settings = PlotSettings {
+            color: Some(
+                settings
+                    .color
+                    .unwrap_or_else(|| palette_color(self.compression_ratio.len())),
+            ),
+            ..settings
+        };
+        let points = ratio
+            .iter()
+            .map(|&(elements, ratio)| (elements as f64 / self.x_scale.divider(), ratio, 0f64))
+            .collect();
+        self.compression_ratio.push((points, settings));
         self
     }
 
-    pub fn plot(self, dir: impl AsRef<Path>) -> anyhow::Result<()> {
+    /// Adds one series per config type, labeled `<label>_<type>`.
+    pub fn add_per_type(&mut self, label: &str, measurements: &[PerTypeMeasurement]) -> &mut Self {
+        let Some(first) = measurements.first() else {
+            return self;
+        };
+        for (type_idx, (type_label, _)) in first.by_type().into_iter().enumerate() {
+            let series = measurements
+                .iter()
+                .map(|m| m.by_type()[type_idx].1.clone())
+                .collect_vec();
+            self.add(
+                PlotSettings::normal(&format!("{label}_{type_label}")),
+                &series,
+            );
+        }
+        self
+    }
+
+    /// Every chart goes into `dir` as `<prefix>_<chart>.svg`, or just `<chart>.svg` if the prefix
+    /// is empty.
+    pub fn plot_with(self, dir: impl AsRef<Path>, prefix: &str) -> anyhow::Result<()> {
         let dir = dir.as_ref();
         std::fs::create_dir_all(dir)?;
+        let file = |chart: &str| chart_file(dir, prefix, chart);
 
         draw_measurements(
             "storage requirements",
             &format!("{} elements", self.x_scale.label()),
             &format!("{}Bs", self.storage_scale.label()),
             self.bytes,
-            dir.join("storage_requirements.svg"),
+            self.log_scale,
+            file("storage_requirements"),
         )?;
 
+        if !self.encode_time.is_empty() {
+            draw_measurements(
+                "encoding time",
+                &format!("{} elements", self.x_scale.label()),
+                "s",
+                self.encode_time,
+                self.log_scale,
+                file("encoding_time"),
+            )?;
+        }
+        if !self.decode_time.is_empty() {
+            draw_measurements(
+                "decoding time",
+                &format!("{} elements", self.x_scale.label()),
+                "s",
+                self.decode_time,
+                self.log_scale,
+                file("decoding_time"),
+            )?;
+        }
+        if !self.throughput.is_empty() {
+            draw_measurements(
+                "encoding throughput",
+                &format!("{} elements", self.x_scale.label()),
+                &format!("{} elements/s", self.x_scale.label()),
+                self.throughput,
+                self.log_scale,
+                file("throughput"),
+            )?;
+        }
+        if !self.peak_memory.is_empty() {
+            draw_measurements(
+                "peak memory",
+                &format!("{} elements", self.x_scale.label()),
+                &format!("{}Bs", self.storage_scale.label()),
+                self.peak_memory,
+                self.log_scale,
+                file("peak_memory"),
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// The file is named like `plot_with` names them.
+    pub fn plot_ratio_with(self, dir: impl AsRef<Path>, prefix: &str) -> anyhow::Result<()> {
+        let dir = dir.as_ref();
+        std::fs::create_dir_all(dir)?;
+
         draw_measurements(
-            "encoding time",
+            "compression ratio",
             &format!("{} elements", self.x_scale.label()),
-            "s",
-            self.encode_time,
-            dir.join("encoding_time.svg"),
+            "uncompressed / compressed",
+            self.compression_ratio,
+            self.log_scale,
+            chart_file(dir, prefix, "compression_ratio"),
         )?;
-        draw_measurements(
-            "decoding time",
-            &format!("{} elements", self.x_scale.label()),
-            "s",
-            self.decode_time,
-            dir.join("decoding_time.svg"),
+        Ok(())
+    }
+}
+
+fn write_results(
+    dir: impl AsRef<Path>,
+    prefix: &str,
+    label: &str,
+    measurements: &[EncodeMeasurement],
+) -> anyhow::Result<()> {
+    let dir = dir.as_ref();
+    std::fs::create_dir_all(dir)?;
+    let file = BufWriter::new(File::create(output_file(
+        dir,
+        prefix,
+        &format!("{label}.csv"),
+    ))?);
+    measurements.iter().collect_csv(file)?;
+    write_json(
+        output_file(dir, prefix, &format!("{label}.json")),
+        label,
+        measurements,
+    )?;
+    Ok(())
+}
+
+/// Writes every run to `{label}.csv` and draws its timings against the payload size into
+/// `chart`. Measurements with a single timing are drawn under the run's label, those with several
+/// under the names of their timings, prefixed by the label if there's more than one run.
+fn plot_timings<M: Timings + ToCsv>(
+    dir: impl AsRef<Path>,
+    prefix: &str,
+    title: &str,
+    chart: &str,
+    runs: &[(&str, Vec<M>)],
+) -> anyhow::Result<()> {
+    let dir = dir.as_ref();
+    std::fs::create_dir_all(dir)?;
+    let names = M::series();
+    let mut series = vec![];
+    for (label, measurements) in runs {
+        let file = BufWriter::new(File::create(output_file(
+            dir,
+            prefix,
+            &format!("{label}.csv"),
+        ))?);
+        measurements.iter().collect_csv(file)?;
+
+        for (idx, name) in names.iter().enumerate() {
+            let name = match (names.len(), runs.len()) {
+                (1, _) => label.to_string(),
+                (_, 1) => name.to_string(),
+                _ => format!("{label} {name}"),
+            };
+            let timings = measurements
+                .iter()
+                .map(|m| (m.num_elements(), m.timings()[idx]))
+                .collect_vec();
+            series.push((name, timings));
+        }
+    }
+    draw_timings(title, series, chart_file(dir, prefix, chart))
+}
+
+/// `run_append` for the codecs that can be appended to, `None` for the rest.
+fn run_append(
+    runner: &mut MeasurementRunner,
+    codec: AnyCodec,
+    increment: usize,
+) -> Option<Result<Vec<AppendMeasurement>, CodecError>> {
+    Some(match codec {
+        AnyCodec::Json(codec) => runner.run_append(&codec, increment),
+        AnyCodec::Bson(codec) => runner.run_append(&codec, increment),
+        AnyCodec::Bincode(codec) => runner.run_append(&codec, increment),
+        AnyCodec::Parquet(codec) => runner.run_append(&codec, increment),
+        AnyCodec::MessagePack(codec) => runner.run_append(&codec, increment),
+        AnyCodec::Cbor(codec) => runner.run_append(&codec, increment),
+        AnyCodec::ColumnarBincode(codec) => runner.run_append(&codec, increment),
+        AnyCodec::Avro(_) | AnyCodec::Csv(_) => return None,
+    })
+}
+
+/// Size and decode time of the same payload against the swept parameter. The parameters usually
+/// grow by orders of magnitude so the x axis is logarithmic.
+fn plot_sweep(
+    dir: impl AsRef<Path>,
+    prefix: &str,
+    label: &str,
+    parameter: &str,
+    measurements: &[SweepMeasurement],
+) -> anyhow::Result<()> {
+    let dir = dir.as_ref();
+    std::fs::create_dir_all(dir)?;
+    let file = BufWriter::new(File::create(output_file(
+        dir,
+        prefix,
+        &format!("{label}.csv"),
+    ))?);
+    measurements.iter().collect_csv(file)?;
+
+    let storage_scale = Scale::M;
+    let series = |pick: &dyn Fn(&EncodeMeasurement) -> (f64, f64)| {
+        let points = measurements
+            .iter()
+            .map(|m| {
+                let (y, err) = pick(&m.measurement);
+                (m.parameter as f64, y, err)
+            })
+            .collect_vec();
+        vec![(points, PlotSettings::normal(label))]
+    };
+    draw_measurements(
+        "storage requirements",
+        parameter,
+        &format!("{}Bs", storage_scale.label()),
+        series(&|m| (m.bytes as f64 / storage_scale.divider(), 0f64)),
+        LogScale::X,
+        chart_file(dir, prefix, "storage_requirements"),
+    )?;
+    draw_measurements(
+        "decoding time",
+        parameter,
+        "s",
+        series(&|m| {
+            (
+                m.decode_time.unwrap_or_default().as_secs_f64(),
+                m.decode_stats
+                    .map(|stats| stats.stddev)
+                    .unwrap_or_default()
+                    .as_secs_f64(),
+            )
+        }),
+        LogScale::X,
+        chart_file(dir, prefix, "decoding_time"),
+    )?;
+    Ok(())
+}
+
+/// Plots every series of `(elements, time)` on a linear scale.
+fn draw_timings(
+    title: &str,
+    series: Vec<(String, Vec<(usize, Duration)>)>,
+    path: impl AsRef<Path>,
+) -> anyhow::Result<()> {
+    let x_scale = Scale::M;
+    let sets = series
+        .into_iter()
+        .map(|(label, timings)| {
+            let points = timings
+                .into_iter()
+                .map(|(elements, time)| {
+                    (
+                        elements as f64 / x_scale.divider(),
+                        time.as_secs_f64(),
+                        0f64,
+                    )
+                })
+                .collect_vec();
+            (points, PlotSettings::normal(&label))
+        })
+        .collect();
+    draw_measurements(
+        title,
+        &format!("{} elements", x_scale.label()),
+        "s",
+        sets,
+        LogScale::default(),
+        path,
+    )?;
+    Ok(())
+}
+
+/// Buckets of the per record histograms.
+const HISTOGRAM_BUCKETS: usize = 50;
+
+fn plot_per_record(
+    dir: impl AsRef<Path>,
+    prefix: &str,
+    label: &str,
+    times: &[Duration],
+) -> anyhow::Result<()> {
+    let dir = dir.as_ref();
+    std::fs::create_dir_all(dir)?;
+    let buckets = measurements::histogram(times, HISTOGRAM_BUCKETS);
+    let mut file = BufWriter::new(File::create(output_file(
+        dir,
+        prefix,
+        &format!("{label}.csv"),
+    ))?);
+    writeln!(file, "start,end,count")?;
+    for bucket in &buckets {
+        writeln!(
+            file,
+            "{},{},{}",
+            bucket.start.as_nanos(),
+            bucket.end.as_nanos(),
+            bucket.count
         )?;
+    }
+    if let Some(max) = times.iter().max() {
+        let stats = TimingStats::from_samples(times);
+        println!(
+            "{label} per record: median {:?}, mean {:?}, max {max:?}",
+            stats.median, stats.mean
+        );
+    }
+    draw_histogram(
+        &format!("{label} encode time per record"),
+        &buckets,
+        chart_file(dir, prefix, label),
+    )
+}
 
-        Ok(())
+/// The counts go on a log axis, a handful of slow records would be invisible next to the bulk
+/// otherwise.
+fn draw_histogram(
+    title: &str,
+    buckets: &[HistogramBucket],
+    path: impl AsRef<Path>,
+) -> anyhow::Result<()> {
+    let micros = |time: Duration| time.as_secs_f64() * 1e6;
+    let min_x = buckets.first().map_or(0f64, |b| micros(b.start));
+    let max_x = buckets.last().map_or(1f64, |b| micros(b.end));
+    let max_y = buckets.iter().map(|b| b.count).max().unwrap_or(1) as f64;
+    // empty buckets stay empty instead of reaching down to zero, which a log axis can't show
+    let floor = 0.5f64;
+
+    let root = SVGBackend::new(path.as_ref(), (1980, 1200)).into_drawing_area();
+    root.fill(&WHITE)?;
+    let mut chart = ChartBuilder::on(&root)
+        .x_label_area_size(70)
+        .y_label_area_size(70)
+        .margin(5)
+        .caption(title, ("sans-serif", 50.0).into_font())
+        .build_cartesian_2d(min_x..max_x, (floor..max_y * 2f64).log_scale())?;
+    chart
+        .configure_mesh()
+        .x_desc("µs")
+        .y_desc("records")
+        .draw()?;
+
+    let (r, g, b) = palette_color(0);
+    let color = RGBColor(r, g, b);
+    chart.draw_series(
+        buckets
+            .iter()
+            .filter(|bucket| bucket.count > 0)
+            .map(|bucket| {
+                Rectangle::new(
+                    [
+                        (micros(bucket.start), floor),
+                        (micros(bucket.end), bucket.count as f64),
+                    ],
+                    color.filled(),
+                )
+            }),
+    )?;
+
+    root.present().expect("Unable to write result to file");
+    Ok(())
+}
+
+/// Fits below this R² get flagged, extrapolating them is likely misleading.
+const POOR_FIT_RSQUARED: f64 = 0.95;
+
+fn report_fit<M>(label: &str, prediction: &Prediction<M>) {
+    for (quantity, summary) in &prediction.summaries {
+        let warning = if !summary.is_fitted() {
+            " <- too few points to fit, prediction is flat"
+        } else if summary.rsquared < POOR_FIT_RSQUARED {
+            " <- poor fit"
+        } else {
+            ""
+        };
+        println!(
+            "{label} {quantity}: R² = {:.4}, residual std error = {:.4e}{warning}",
+            summary.rsquared, summary.residual_std_error
+        );
+    }
+}
+
+/// The compressed variants benchmarked for `codec`, labelled like their result files.
+fn run_compressed_variants(
+    runner: &mut MeasurementRunner,
+    codec: CodecName,
+) -> Result<Vec<(String, Vec<EncodeMeasurement>)>, CodecError> {
+    plan::compressed_variants(codec)
+        .into_iter()
+        .map(|(label, algo)| {
+            runner.set_label(&label);
+            let measurements = match codec {
+                CodecName::Json => runner.run_compressed(&JsonCodec::Lines, algo)?,
+                CodecName::JsonArray => runner.run_compressed(&JsonCodec::Array, algo)?,
+                CodecName::Bson => runner.run_compressed(&BsonCodec, algo)?,
+                CodecName::Bincode => runner.run_compressed(&BincodeCodec::default(), algo)?,
+                CodecName::Msgpack => runner.run_compressed(&MessagePackCodec, algo)?,
+                CodecName::Cbor => runner.run_compressed(&CborCodec, algo)?,
+                CodecName::Avro => runner.run_compressed(&AvroCodec, algo)?,
+                CodecName::Csv => runner.run_compressed(&CsvCodec, algo)?,
+                CodecName::BincodeColumnar => {
+                    runner.run_compressed(&ColumnarBincodeCodec::Columns, algo)?
+                }
+                // parquet compresses its pages itself
+                CodecName::Parquet => {
+                    runner.run(&ParquetCodec::try_new(50000, parquet_compression(algo))?)?
+                }
+            };
+            Ok((label, measurements))
+        })
+        .collect()
+}
+
+/// The page compression parquet uses for `algo`.
+fn parquet_compression(algo: CompressionAlgo) -> ParquetCompression {
+    match algo {
+        CompressionAlgo::Gzip(level) => ParquetCompression::Gzip(level.level()),
+        CompressionAlgo::Zstd(level) => ParquetCompression::Zstd(level),
+        CompressionAlgo::Brotli(quality) => ParquetCompression::Brotli(quality),
+        CompressionAlgo::Snappy => ParquetCompression::Snappy,
+    }
+}
+
+fn verify_round_trip(
+    codec: &impl PayloadCodec<Cursor<Vec<u8>>, Vec<u8>>,
+    payload: &Payload,
+) -> Result<(), Mismatch> {
+    let mut data = Data::with_capacity(0);
+    codec.encode(payload, &mut data)?;
+    codec.decode_and_verify(data.wrap_in_cursor(), payload)
+}
+
+/// The baseline at `path`, `None` if there's none yet.
+fn load_baseline(path: &Path) -> anyhow::Result<Option<Baseline>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let baseline = Baseline::load(path)
+        .with_context(|| format!("couldn't load baseline {}", path.display()))?;
+    Ok(Some(baseline))
+}
+
+/// Compares `normal`, measured on the payloads of `seed`, against `baseline`, or records it at
+/// `path` if there's none yet.
+fn check_baseline(
+    path: &Path,
+    baseline: Option<&Baseline>,
+    seed: u64,
+    normal: &[(CodecName, Vec<EncodeMeasurement>)],
+    tolerance: f64,
+) -> anyhow::Result<()> {
+    let mut current = Baseline::new(seed);
+    for (name, measurements) in normal {
+        current.add(name.label(), measurements);
+    }
+    let Some(baseline) = baseline else {
+        current.save(path)?;
+        println!("saved baseline to {}", path.display());
+        return Ok(());
+    };
+
+    let regressions = baseline::compare(&current, baseline, tolerance);
+    for regression in &regressions {
+        println!("regression: {regression}");
+    }
+    if !regressions.is_empty() {
+        anyhow::bail!(
+            "{} regression(s) beyond {tolerance}% of the baseline",
+            regressions.len()
+        );
+    }
+    Ok(())
+}
+
+/// Builds one payload of `num_elements`, from `rng` and `seed` if given, runs `phase` on it once with the
+/// codec and prints the result. No runner, no warmup and no samples, every call in a profile
+/// belongs to the codec.
+fn run_single(
+    name: CodecName,
+    num_elements: usize,
+    phase: Phase,
+    code_len: RangeInclusive<usize>,
+    rng: PayloadRng,
+    seed: Option<u64>,
+) -> anyhow::Result<()> {
+    let mut config = PayloadConfig::new(num_elements)
+        .with_rng(rng)
+        .with_code_len(code_len);
+    if let Some(seed) = seed {
+        config = config.with_seed(seed);
+    }
+    print_payload_source(&config);
+    let payload = payload_with(&config);
+    let codec = name.codec()?;
+    let mut data = Data::with_capacity(0);
+    let label = name.label();
+    match phase {
+        Phase::Encode => {
+            let measurement = measurements::measure_encode_only(&codec, &mut data, &payload)?;
+            println!(
+                "{label}: encoded {} entries into {} bytes in {:?}",
+                measurement.num_elements, measurement.bytes, measurement.encode_time
+            );
+        }
+        Phase::Decode => {
+            PayloadCodec::<Cursor<Vec<u8>>, _>::encode(&codec, &payload, &mut data)?;
+            let measurement = measurements::measure_decode_only(&codec, &data)?;
+            println!(
+                "{label}: decoded {} entries from {} bytes in {:?}",
+                measurement.num_elements, measurement.bytes, measurement.decode_time
+            );
+        }
+        Phase::Both => {
+            let measurement = measurements::measure_normal(&codec, &mut data, &payload)?;
+            println!(
+                "{label}: {} entries, {} bytes, encoded in {:?}, decoded in {:?}",
+                measurement.num_elements,
+                measurement.bytes,
+                measurement.encode_time,
+                measurement.decode_time.unwrap_or_default()
+            );
+        }
+    }
+    Ok(())
+}
+
+/// The seed to pass to `--seed` to measure the same payloads again.
+fn print_payload_source(config: &PayloadConfig) {
+    match config.rng {
+        PayloadRng::Std => println!("payload seed: {}", config.seed),
+        PayloadRng::Step => println!("payloads from StepRng, the seed is unused"),
     }
 }
 
 fn main() -> anyhow::Result<()> {
-    let mut measurement_runner = MeasurementRunner::new(200_000, 10_000);
-    let prediction_storage_scale = Scale::G;
-    let prediction_x_scale = Scale::M;
-
-    let prediction_max = 1_000_000_000usize;
-    let prediction_step = prediction_max;
-    let prediction_start = 0usize;
-
-    let parquet_codec = ParquetCodec::new(50000, 0);
-    let parquet_codec_w_compression = ParquetCodec::new(50000, 1);
-
-    let normal_json = measurement_runner.run(&JsonCodec);
-    // let normal_bson = measurement_runner.run(&BsonCodec);
-    let normal_bincode = measurement_runner.run(&BincodeCodec);
-    let normal_parquet = measurement_runner.run(&parquet_codec);
-    let mut merger = PlotMerger::new(Scale::M, Scale::M);
-    merger.add(PlotSettings::normal("serde_json"), &normal_json);
-    merger.add(PlotSettings::normal("bincode"), &normal_bincode);
-    // merger.add(PlotSettings::normal("bson"), &normal_bson);
-    merger.add(PlotSettings::normal("parquet"), &normal_parquet);
-    merger.plot("normal")?;
-
-    let normal_json_predicted =
-        normal_json.linear_regression(prediction_start, prediction_step, prediction_max);
-    // let normal_bson_predicted =
-    //     normal_bson.linear_regression(prediction_start, prediction_step, prediction_max);
-    let normal_bincode_predicted =
-        normal_bincode.linear_regression(prediction_start, prediction_step, prediction_max);
-    let normal_parquet_predicted =
-        normal_parquet.linear_regression(prediction_start, prediction_step, prediction_max);
-    let mut merger = PlotMerger::new(prediction_storage_scale, prediction_x_scale);
-    merger.add(
-        PlotSettings::predicted("serde_json"),
-        &normal_json_predicted,
-    );
-    merger.add(
-        PlotSettings::predicted("parquet"),
-        &normal_parquet_predicted,
-    );
-    // merger.add(PlotSettings::predicted("bson"), &normal_bson_predicted);
-    merger.add(
-        PlotSettings::predicted("bincode"),
-        &normal_bincode_predicted,
-    );
-    merger.plot("normal_predicted")?;
-
-    let json_compressed = measurement_runner.run_compressed(&JsonCodec);
-    // let bson_compressed = measurement_runner.run_compressed(&BsonCodec);
-    let bincode_compressed = measurement_runner.run_compressed(&BincodeCodec);
-    let parquet_compressed = measurement_runner.run(&parquet_codec_w_compression);
-    let mut merger = PlotMerger::default();
-    // merger.add(PlotSettings::normal("serde_json"), &json_compressed);
-    merger.add(PlotSettings::normal("parquet"), &parquet_compressed);
-    // merger.add(PlotSettings::normal("bson"), &bson_compressed);
-    merger.add(PlotSettings::normal("bincode"), &bincode_compressed);
-    merger.plot("compressed")?;
-
-    let json_compressed_predicted =
-        json_compressed.linear_regression(prediction_start, prediction_step, prediction_max);
-    // let bson_compressed_predicted =
-    //     bson_compressed.linear_regression(prediction_start, prediction_step, prediction_max);
-    let bincode_compressed_predicted =
-        bincode_compressed.linear_regression(prediction_start, prediction_step, prediction_max);
-    let parquet_compressed_predicted =
-        parquet_compressed.linear_regression(prediction_start, prediction_step, prediction_max);
-    let mut merger = PlotMerger::new(prediction_storage_scale, prediction_x_scale);
-    merger.add(
-        PlotSettings::predicted("serde_json_compressed"),
-        &json_compressed_predicted,
-    );
-    merger.add(
-        PlotSettings::predicted("bincode_compressed"),
-        &bincode_compressed_predicted,
-    );
-    merger.add(
-        PlotSettings::predicted("bincode"),
-        &normal_bincode_predicted,
-    );
-    merger.add(
-        PlotSettings::predicted("parquet_compressed"),
-        &parquet_compressed_predicted,
-    );
-    merger.add(
-        PlotSettings::predicted("parquet"),
-        &normal_parquet_predicted,
-    );
-    // merger.add(
-    //     PlotSettings::predicted("bson_compressed"),
-    //     &bson_compressed_predicted,
-    // );
-    merger.add(
-        PlotSettings::predicted("serde_json"),
-        &normal_json_predicted,
-    );
-    // merger.add(PlotSettings::predicted("bson"), &normal_bson_predicted);
-    merger.plot("compressed_predicted")?;
+    let cli = Cli::parse();
+    if let Some(Command::BenchOne {
+        codec,
+        num_elements,
+        phase,
+    }) = cli.command
+    {
+        return run_single(
+            codec,
+            num_elements,
+            phase,
+            cli.code_len.clone(),
+            cli.payload_rng(),
+            cli.seed,
+        );
+    }
+    if cli.dry_run {
+        print!("{}", Plan::new(&cli));
+        return Ok(());
+    }
+    let mut summary = RunSummary::new();
+    let result = run_charts(&cli, &mut summary);
+    // also when a run failed, what it got through may tell why
+    print!("{summary}");
+    result
+}
 
+/// Every chart the flags ask for, a group at a time in the order `Plan` lists them.
+fn run_charts(cli: &Cli, summary: &mut RunSummary) -> anyhow::Result<()> {
+    let baseline = cli
+        .baseline
+        .as_deref()
+        .map(load_baseline)
+        .transpose()?
+        .flatten();
+    let mut runner = MeasurementRunner::new(cli.max, cli.step, 1)
+        .with_samples(5)
+        .with_rng(cli.payload_rng())
+        .with_code_len(cli.code_len.clone())
+        .with_progress(|label, measured, total| {
+            eprint!("\r{label}: {measured}/{total}");
+            if measured == total {
+                eprintln!();
+            }
+        });
+    // the timings of other payloads than the baseline's would tell nothing about the codecs
+    if cli.step_rng && baseline.is_some() {
+        anyhow::bail!("a baseline only records its seed, it can't be compared against --step-rng");
+    }
+    let seed = match (cli.seed, &baseline) {
+        (Some(seed), Some(baseline)) if seed != baseline.seed() => anyhow::bail!(
+            "--seed {seed} differs from the seed {} the baseline was measured with",
+            baseline.seed()
+        ),
+        (_, Some(baseline)) => Some(baseline.seed()),
+        (seed, None) => seed,
+    };
+    if let Some(seed) = seed {
+        runner = runner.with_seed(seed);
+    }
+    print_payload_source(&runner.payload_config(cli.step));
+    let mut charts = Charts {
+        cli,
+        summary,
+        runner,
+        chart_prefix: cli.chart_prefix(),
+        baseline,
+        normal: vec![],
+    };
+    for group in plan::chart_groups(cli) {
+        charts.run(group)?;
+    }
     Ok(())
 }
+
+/// The predictions are drawn in GB over millions of entries.
+const PREDICTION_STORAGE_SCALE: Scale = Scale::G;
+const PREDICTION_X_SCALE: Scale = Scale::M;
+
+/// What the chart groups of a run share, each group is drawn by a method of its own.
+struct Charts<'a> {
+    cli: &'a Cli,
+    summary: &'a mut RunSummary,
+    runner: MeasurementRunner,
+    chart_prefix: String,
+    baseline: Option<Baseline>,
+    /// The plain measurements of every codec, most other groups are compared against them.
+    normal: Vec<(CodecName, Vec<EncodeMeasurement>)>,
+}
+
+impl Charts<'_> {
+    fn run(&mut self, group: ChartGroup) -> anyhow::Result<()> {
+        match group {
+            ChartGroup::Verify => self.verify(),
+            ChartGroup::EncodeOnly => self.encode_only(),
+            ChartGroup::Normal => self.normal(),
+            ChartGroup::DecodeOnly => self.decode_only(),
+            ChartGroup::EntryStream => self.entry_stream(),
+            ChartGroup::Columnar => self.columnar(),
+            #[cfg(feature = "parallel")]
+            ChartGroup::Parallel => self.parallel(),
+            ChartGroup::Seek => self.seek(),
+            ChartGroup::FileSeek => self.file_seek(),
+            ChartGroup::Append => self.append(),
+            ChartGroup::PerType => self.per_type(),
+            ChartGroup::PerRecord => self.per_record(),
+            ChartGroup::ParquetSettings => self.parquet_settings(),
+            ChartGroup::Predicted => self.predicted(),
+            ChartGroup::Compressed => self.compressed(),
+            ChartGroup::CompressionLevels => self.compression_levels(),
+            ChartGroup::BincodeConfigs => self.bincode_configs(),
+            ChartGroup::ParquetBrotliLevels => self.parquet_brotli_levels(),
+        }
+    }
+
+    fn out_dir(&self, chart: &str) -> std::path::PathBuf {
+        self.cli.out_dir.join(chart)
+    }
+
+    fn in_depth(&self) -> Vec<CodecName> {
+        self.cli
+            .codecs
+            .iter()
+            .copied()
+            .filter(|name| plan::compared_in_depth(*name))
+            .collect()
+    }
+
+    fn normal_of(&self, codec: CodecName) -> Option<&Vec<EncodeMeasurement>> {
+        self.normal
+            .iter()
+            .find(|(name, _)| *name == codec)
+            .map(|(_, measurements)| measurements)
+    }
+
+    /// The plain measurements of every codec extrapolated to `--predict-to`.
+    fn normal_predicted(&self) -> Vec<(String, Prediction<EncodeMeasurement>)> {
+        self.normal
+            .iter()
+            .map(|(name, measurements)| {
+                let predicted = self.extrapolate(measurements);
+                (name.label().to_string(), predicted)
+            })
+            .collect()
+    }
+
+    fn extrapolate(&self, measurements: &Vec<EncodeMeasurement>) -> Prediction<EncodeMeasurement> {
+        measurements.linear_regression(0, self.cli.predict_to, self.cli.predict_to)
+    }
+
+    fn verify(&mut self) -> anyhow::Result<()> {
+        let payload = payload_with(&self.runner.payload_config(self.cli.step));
+        for name in &self.cli.codecs {
+            verify_round_trip(&name.codec()?, &payload)
+                .with_context(|| format!("{} didn't round trip", name.label()))?;
+        }
+        Ok(())
+    }
+
+    fn encode_only(&mut self) -> anyhow::Result<()> {
+        let mut merger = PlotMerger::new(Scale::M, Scale::M);
+        for name in &self.cli.codecs {
+            self.runner
+                .set_label(format!("{}_encode_only", name.label()));
+            let measurements = self.summary.track(name.label(), "encode_only", || {
+                self.runner.run_encode_only(&name.codec()?)
+            })?;
+            write_results(
+                self.out_dir("encode_only"),
+                &self.chart_prefix,
+                name.label(),
+                &measurements,
+            )?;
+            merger.add(PlotSettings::normal(name.label()), &measurements);
+        }
+        merger.plot_with(self.out_dir("encode_only"), &self.chart_prefix)
+    }
+
+    fn normal(&mut self) -> anyhow::Result<()> {
+        for name in &self.cli.codecs {
+            self.runner.set_label(name.label());
+            let measurements = self.summary.track(name.label(), "normal", || {
+                if self.cli.geometric {
+                    self.runner.run_geometric(&name.codec()?)
+                } else {
+                    self.runner.run(&name.codec()?)
+                }
+            })?;
+            write_results(
+                self.out_dir("normal"),
+                &self.chart_prefix,
+                name.label(),
+                &measurements,
+            )?;
+            self.normal.push((*name, measurements));
+        }
+        let mut merger = PlotMerger::new(Scale::M, Scale::M);
+        for (name, measurements) in &self.normal {
+            merger.add(PlotSettings::normal(name.label()), measurements);
+        }
+        merger.plot_with(self.out_dir("normal"), &self.chart_prefix)?;
+        if let Some(path) = &self.cli.baseline {
+            check_baseline(
+                path,
+                self.baseline.as_ref(),
+                self.runner.seed(),
+                &self.normal,
+                self.cli.tolerance,
+            )?;
+        }
+        Ok(())
+    }
+
+    fn decode_only(&mut self) -> anyhow::Result<()> {
+        let mut decode_only = vec![];
+        for name in &self.cli.codecs {
+            self.runner
+                .set_label(format!("{}_decode_only", name.label()));
+            let measurements = self.summary.track_time(name.label(), "decode_only", || {
+                self.runner.run_decode_only(&name.codec()?)
+            })?;
+            decode_only.push((name.label(), measurements));
+        }
+        plot_timings(
+            self.out_dir("decode_only"),
+            &self.chart_prefix,
+            "decoding a pre-encoded payload",
+            "decoding_time",
+            &decode_only,
+        )
+    }
+
+    /// A single stream of tagged entries against a stream per type, the gap is the cost of the
+    /// tags.
+    fn entry_stream(&mut self) -> anyhow::Result<()> {
+        let mut merger = PlotMerger::new(Scale::M, Scale::M);
+        let mut any_entries = false;
+        for (name, per_type) in self
+            .normal
+            .iter()
+            .filter(|(name, _)| plan::streams_entries(*name))
+        {
+            let label = plan::entries_label(*name);
+            self.runner.set_label(&label);
+            let runner = &mut self.runner;
+            let entries = self
+                .summary
+                .track(name.label(), "entry_stream", || match name {
+                    CodecName::Json => runner.run_entries(&JsonCodec::Lines),
+                    CodecName::JsonArray => runner.run_entries(&JsonCodec::Array),
+                    CodecName::Bincode => runner.run_entries(&BincodeCodec::default()),
+                    _ => unreachable!("{} doesn't stream entries", name.label()),
+                })?;
+            write_results(
+                self.out_dir("entry_stream"),
+                &self.chart_prefix,
+                &label,
+                &entries,
+            )?;
+            merger.add(PlotSettings::normal(name.label()), per_type);
+            merger.add(PlotSettings::normal(&label), &entries);
+            any_entries = true;
+        }
+        if any_entries {
+            merger.plot_with(self.out_dir("entry_stream"), &self.chart_prefix)?;
+        }
+        Ok(())
+    }
+
+    /// The same values entry by entry, the gap to the columns is the layout alone and the gap to
+    /// uncompressed parquet what its container costs or saves on top.
+    fn columnar(&mut self) -> anyhow::Result<()> {
+        let mut merger = PlotMerger::new(Scale::M, Scale::M);
+        if let Some(columns) = self.normal_of(CodecName::BincodeColumnar) {
+            merger.add(
+                PlotSettings::normal(CodecName::BincodeColumnar.label()),
+                columns,
+            );
+        }
+        let baselines = [
+            (
+                plan::COLUMNAR_ROWS,
+                AnyCodec::ColumnarBincode(ColumnarBincodeCodec::Rows),
+            ),
+            (
+                plan::PARQUET_UNCOMPRESSED,
+                AnyCodec::Parquet(ParquetCodec::try_new(
+                    50000,
+                    ParquetCompression::Uncompressed,
+                )?),
+            ),
+        ];
+        for (label, codec) in baselines {
+            self.runner.set_label(label);
+            let measurements = self
+                .summary
+                .track(label, "columnar", || self.runner.run(&codec))?;
+            write_results(
+                self.out_dir("columnar"),
+                &self.chart_prefix,
+                label,
+                &measurements,
+            )?;
+            merger.add(PlotSettings::normal(label), &measurements);
+        }
+        if let Some(bincode) = self.normal_of(CodecName::Bincode) {
+            merger.add(PlotSettings::normal(CodecName::Bincode.label()), bincode);
+        }
+        merger.plot_with(self.out_dir("columnar"), &self.chart_prefix)
+    }
+
+    #[cfg(feature = "parallel")]
+    fn parallel(&mut self) -> anyhow::Result<()> {
+        let mut merger = PlotMerger::new(Scale::M, Scale::M);
+        let mut speedups = vec![];
+        for (name, serial) in self
+            .normal
+            .iter()
+            .filter(|(name, _)| plan::compared_in_depth(*name))
+        {
+            self.runner.set_label(format!("{}_parallel", name.label()));
+            let parallel = self.summary.track(name.label(), "parallel", || {
+                self.runner.run_parallel(&name.codec()?)
+            })?;
+            speedups.push(format!(
+                "{} {:.2}x",
+                name.label(),
+                measurements::encode_speedup(serial, &parallel)
+            ));
+            write_results(
+                self.out_dir("parallel"),
+                &self.chart_prefix,
+                name.label(),
+                &parallel,
+            )?;
+            merger.add(PlotSettings::normal(name.label()), serial);
+            merger.add(
+                PlotSettings::normal(&format!("{}_parallel", name.label())),
+                &parallel,
+            );
+        }
+        if !speedups.is_empty() {
+            println!("parallel encode speedup: {}", speedups.join(", "));
+            merger.plot_with(self.out_dir("parallel"), &self.chart_prefix)?;
+        }
+        Ok(())
+    }
+
+    fn seek(&mut self) -> anyhow::Result<()> {
+        let seek = self
+            .summary
+            .track_time("coins", "seek", || self.runner.run_seek(1_000))?;
+        plot_timings(
+            self.out_dir("seek"),
+            &self.chart_prefix,
+            "seek to last batch",
+            "seek_time",
+            &[("seek", seek)],
+        )
+    }
+
+    fn file_seek(&mut self) -> anyhow::Result<()> {
+        for name in self.in_depth() {
+            let gzip = CompressionAlgo::Gzip(Compression::new(1));
+            let seek = self.summary.track_time(name.label(), "file_seek", || {
+                self.runner.run_file_seek(&name.codec()?, gzip)
+            })?;
+            plot_timings(
+                self.out_dir(&plan::file_seek_dir(name)),
+                &self.chart_prefix,
+                "seek to end of file",
+                "seek_time",
+                &[("seek", seek)],
+            )?;
+        }
+        Ok(())
+    }
+
+    /// A step worth of entries on top of every corpus of the sweep.
+    fn append(&mut self) -> anyhow::Result<()> {
+        for name in self.in_depth() {
+            let codec = name.codec()?;
+            let append = self.summary.track_time(name.label(), "append", || {
+                run_append(&mut self.runner, codec, self.cli.step)
+            });
+            if let Some(append) = append {
+                plot_timings(
+                    self.out_dir(&plan::append_dir(name)),
+                    &self.chart_prefix,
+                    "adding to an existing corpus",
+                    "append_time",
+                    &[("append", append?)],
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    fn per_type(&mut self) -> anyhow::Result<()> {
+        // contract code dwarfs balances, so the sizes only fit on a log axis
+        let mut merger = PlotMerger::new(Scale::M, Scale::M).with_log_scale(LogScale::Y);
+        let in_depth = self.in_depth();
+        for name in &in_depth {
+            self.runner.set_label(format!("{}_per_type", name.label()));
+            let per_type = self.summary.track_time(name.label(), "per_type", || {
+                self.runner.run_per_type(&name.codec()?)
+            })?;
+            // the decode time of every type next to how much of it went into opening the subset
+            std::fs::create_dir_all(self.out_dir("per_type"))?;
+            let file = File::create(output_file(
+                &self.out_dir("per_type"),
+                &self.chart_prefix,
+                &format!("{}.csv", name.label()),
+            ))?;
+            per_type.iter().collect_csv(BufWriter::new(file))?;
+            merger.add_per_type(name.label(), &per_type);
+        }
+        if !in_depth.is_empty() {
+            merger.plot_with(self.out_dir("per_type"), &self.chart_prefix)?;
+        }
+        Ok(())
+    }
+
+    fn per_record(&mut self) -> anyhow::Result<()> {
+        // contracts vary the most in size, their code dominates
+        let contracts = payload_with(&self.runner.payload_config(self.cli.max)).contracts;
+        for name in self
+            .cli
+            .codecs
+            .iter()
+            .filter(|name| plan::timed_per_record(**name))
+        {
+            let times = self.summary.track_time(name.label(), "per_record", || {
+                measurements::measure_per_record(&name.codec()?, &contracts)
+            })?;
+            plot_per_record(
+                self.out_dir("per_record"),
+                &self.chart_prefix,
+                name.label(),
+                &times,
+            )?;
+        }
+        Ok(())
+    }
+
+    fn parquet_settings(&mut self) -> anyhow::Result<()> {
+        let (_, parquet) = self
+            .normal
+            .iter()
+            .find(|(name, _)| *name == CodecName::Parquet)
+            .context("parquet's settings are compared against its plain measurements")?;
+        self.runner.set_label(plan::PARQUET_NO_DICTIONARY);
+        let no_dictionary =
+            self.summary
+                .track(plan::PARQUET_NO_DICTIONARY, "dictionary", || {
+                    self.runner.run(
+                        &ParquetCodec::try_new(50000, ParquetCompression::Gzip(0))?
+                            .with_dictionary(false),
+                    )
+                })?;
+        write_results(
+            self.out_dir("dictionary"),
+            &self.chart_prefix,
+            plan::PARQUET_NO_DICTIONARY,
+            &no_dictionary,
+        )?;
+        let mut merger = PlotMerger::new(Scale::M, Scale::M);
+        merger.add(PlotSettings::normal("parquet"), parquet);
+        merger.add(
+            PlotSettings::normal(plan::PARQUET_NO_DICTIONARY),
+            &no_dictionary,
+        );
+        merger.plot_with(self.out_dir("dictionary"), &self.chart_prefix)?;
+
+        // contract code is the bulk of the data, the hashes barely compress anyway
+        self.runner.set_label(plan::PARQUET_ZSTD_CODE);
+        let zstd_code =
+            self.summary
+                .track(plan::PARQUET_ZSTD_CODE, "column_compression", || {
+                    self.runner.run(
+                        &ParquetCodec::try_new(50000, ParquetCompression::Gzip(0))?
+                            .with_column_compression("code", ParquetCompression::Zstd(1))
+                            .with_column_compression(
+                                "contract_id",
+                                ParquetCompression::Uncompressed,
+                            ),
+                    )
+                })?;
+        write_results(
+            self.out_dir("column_compression"),
+            &self.chart_prefix,
+            plan::PARQUET_ZSTD_CODE,
+            &zstd_code,
+        )?;
+        let mut merger = PlotMerger::new(Scale::M, Scale::M);
+        merger.add(PlotSettings::normal("parquet"), parquet);
+        merger.add(PlotSettings::normal(plan::PARQUET_ZSTD_CODE), &zstd_code);
+        merger.plot_with(self.out_dir("column_compression"), &self.chart_prefix)?;
+
+        // sorted by owner, small row groups give the statistics something to skip
+        let lookup_codec =
+            ParquetCodec::try_new(5000, ParquetCompression::Gzip(0))?.with_bloom_filter("owner");
+        let lookup = self.summary.track_time("parquet", "lookup", || {
+            self.runner.run_lookup(&lookup_codec)
+        })?;
+        plot_timings(
+            self.out_dir("lookup"),
+            &self.chart_prefix,
+            "lookup by owner",
+            "lookup_time",
+            &[("lookup", lookup)],
+        )?;
+
+        // the rows parquet assembles against the configs built from them
+        let conversion = self.summary.track_time("parquet", "row_conversion", || {
+            self.runner
+                .run_row_conversion(&ParquetCodec::try_new(50000, ParquetCompression::Gzip(0))?)
+        })?;
+        if let Some(largest) = conversion.last() {
+            println!(
+                "parquet row conversion: {:.0}% of decoding",
+                largest.conversion_share() * 100f64
+            );
+        }
+        plot_timings(
+            self.out_dir("row_conversion"),
+            &self.chart_prefix,
+            "parquet decode",
+            "row_conversion_time",
+            &[("row_conversion", conversion)],
+        )?;
+
+        // on the largest payload, the smaller ones fit in a single row group at most sizes
+        let batch_sizes = self.summary.track_time("parquet", "batch_size", || {
+            self.runner
+                .run_sweep(&plan::PARQUET_BATCH_SIZES, |batch_size| {
+                    ParquetCodec::try_new(batch_size, ParquetCompression::Gzip(0))
+                })
+        })?;
+        plot_sweep(
+            self.out_dir("batch_size"),
+            &self.chart_prefix,
+            "parquet",
+            "batch size",
+            &batch_sizes,
+        )?;
+
+        #[cfg(feature = "parallel")]
+        {
+            let sharded = self.summary.track_time("parquet", "sharded", || {
+                self.runner.run_sharded(
+                    &ParquetCodec::try_new(5000, ParquetCompression::Gzip(0))?,
+                    rayon::current_num_threads(),
+                )
+            })?;
+            if let Some(largest) = sharded.last() {
+                println!(
+                    "parallel shard decode speedup: {:.2}x over {} shards",
+                    largest.speedup(),
+                    largest.shards
+                );
+            }
+            plot_timings(
+                self.out_dir("sharded"),
+                &self.chart_prefix,
+                "decoding a payload split into shards",
+                "decoding_time",
+                &[("sharded", sharded)],
+            )?;
+        }
+        Ok(())
+    }
+
+    fn predicted(&mut self) -> anyhow::Result<()> {
+        let normal_parquet_quadratic = self.normal_of(CodecName::Parquet).map(|measurements| {
+            measurements.polynomial_regression(2, 0, self.cli.predict_to, self.cli.predict_to)
+        });
+        let mut merger = PlotMerger::new(PREDICTION_STORAGE_SCALE, PREDICTION_X_SCALE);
+        // same order as the `normal` chart so every codec keeps its color
+        for (label, predicted) in &self.normal_predicted() {
+            report_fit(label, predicted);
+            merger.add(PlotSettings::predicted(label), predicted);
+        }
+        if let Some(quadratic) = &normal_parquet_quadratic {
+            report_fit("parquet_quadratic", quadratic);
+            merger.add(PlotSettings::predicted("parquet_quadratic"), quadratic);
+        }
+        merger.plot_with(self.out_dir("normal_predicted"), &self.chart_prefix)?;
+
+        // fitted over the measured range only, so the points aren't squashed into a corner
+        let mut merger = PlotMerger::new(Scale::M, Scale::M);
+        for (name, measurements) in &self.normal {
+            let fitted = measurements.linear_regression(0, self.cli.step, self.cli.max);
+            merger.add_with_prediction(name.label(), measurements, &fitted);
+        }
+        merger.plot_with(self.out_dir("normal_fit"), &self.chart_prefix)
+    }
+
+    fn compressed(&mut self) -> anyhow::Result<()> {
+        let mut compressed = vec![];
+        let mut ratios = PlotMerger::new(Scale::M, Scale::M);
+        // every codec in a color of its own, the uncompressed measurements as a plain line under
+        // the markers of the compressed variants so the savings can be read off directly
+        let mut merger = PlotMerger::default();
+        for (idx, (name, uncompressed)) in self.normal.iter().enumerate() {
+            let color = palette_color(idx);
+            merger.add(
+                PlotSettings::normal(&format!("{} (raw)", name.label()))
+                    .with_color(color)
+                    .with_shape(Shape::Line),
+                uncompressed,
+            );
+            let (elapsed, variants) =
+                measurements::track_time(|| run_compressed_variants(&mut self.runner, *name));
+            let variants = variants?;
+            let bytes = variants
+                .iter()
+                .flat_map(|(_, measurements)| measurements)
+                .map(|m| m.bytes)
+                .sum();
+            self.summary
+                .add(name.label(), "compressed", elapsed, Some(bytes));
+            for (label, measurements) in variants {
+                let ratio = measurements::compression_ratio(uncompressed, &measurements);
+                ratios.add_ratio(PlotSettings::normal(&label), &ratio);
+                merger.add(
+                    PlotSettings::normal(&label).with_color(color),
+                    &measurements,
+                );
+                compressed.push((label, measurements));
+            }
+        }
+        ratios.plot_ratio_with(self.out_dir("compression_ratio"), &self.chart_prefix)?;
+        for (label, measurements) in &compressed {
+            write_results(
+                self.out_dir("compressed"),
+                &self.chart_prefix,
+                label,
+                measurements,
+            )?;
+        }
+        merger.plot_with(self.out_dir("compressed"), &self.chart_prefix)?;
+
+        let mut merger = PlotMerger::new(PREDICTION_STORAGE_SCALE, PREDICTION_X_SCALE);
+        for (label, measurements) in &compressed {
+            let label = format!("{label}_compressed");
+            let predicted = self.extrapolate(measurements);
+            report_fit(&label, &predicted);
+            merger.add(PlotSettings::predicted(&label), &predicted);
+        }
+        // the uncompressed predictions for reference
+        for (label, predicted) in &self.normal_predicted() {
+            merger.add(PlotSettings::predicted(label), predicted);
+        }
+        merger.plot_with(self.out_dir("compressed_predicted"), &self.chart_prefix)
+    }
+
+    fn compression_levels(&mut self) -> anyhow::Result<()> {
+        let mut merger = PlotMerger::default();
+        for level in plan::GZIP_LEVELS {
+            let label = plan::gzip_level_label(level);
+            self.runner.set_label(&label);
+            let bincode_compressed = self.summary.track(&label, "compression_levels", || {
+                self.runner.run_compressed(
+                    &BincodeCodec::default(),
+                    CompressionAlgo::Gzip(Compression::new(level)),
+                )
+            })?;
+            write_results(
+                self.out_dir("compression_levels"),
+                &self.chart_prefix,
+                &label,
+                &bincode_compressed,
+            )?;
+            merger.add(PlotSettings::normal(&label), &bincode_compressed);
+        }
+        merger.plot_with(self.out_dir("compression_levels"), &self.chart_prefix)
+    }
+
+    fn bincode_configs(&mut self) -> anyhow::Result<()> {
+        let mut merger = PlotMerger::default();
+        for (label, codec) in plan::bincode_configs() {
+            self.runner.set_label(label);
+            let measurements = self
+                .summary
+                .track(label, "bincode_configs", || self.runner.run(&codec))?;
+            write_results(
+                self.out_dir("bincode_configs"),
+                &self.chart_prefix,
+                label,
+                &measurements,
+            )?;
+            merger.add(PlotSettings::normal(label), &measurements);
+        }
+        merger.plot_with(self.out_dir("bincode_configs"), &self.chart_prefix)
+    }
+
+    /// The encode time chart is the point here, the top levels cost far more than they save.
+    fn parquet_brotli_levels(&mut self) -> anyhow::Result<()> {
+        let mut merger = PlotMerger::default();
+        for level in 1..=self.cli.max_brotli_level {
+            let label = plan::brotli_level_label(level);
+            self.runner.set_label(&label);
+            let measurements = self.summary.track(&label, "parquet_brotli_levels", || {
+                self.runner.run(&ParquetCodec::try_new(
+                    50000,
+                    ParquetCompression::Brotli(level),
+                )?)
+            })?;
+            write_results(
+                self.out_dir("parquet_brotli_levels"),
+                &self.chart_prefix,
+                &label,
+                &measurements,
+            )?;
+            merger.add(PlotSettings::normal(&label), &measurements);
+        }
+        merger.plot_with(self.out_dir("parquet_brotli_levels"), &self.chart_prefix)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    fn files_in(dir: &Path) -> Vec<PathBuf> {
+        std::fs::read_dir(dir)
+            .unwrap()
+            .flat_map(|entry| {
+                let path = entry.unwrap().path();
+                if path.is_dir() {
+                    files_in(&path)
+                } else {
+                    vec![path]
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn a_seed_other_than_the_baselines_is_rejected() {
+        // given
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("baseline.json");
+        Baseline::new(1).save(&path).unwrap();
+        let cli = Cli::parse_from([
+            "regenesis_encoding",
+            "--seed",
+            "2",
+            "--baseline",
+            path.to_str().unwrap(),
+            "--out-dir",
+            dir.path().to_str().unwrap(),
+        ]);
+
+        // when
+        let result = run_charts(&cli, &mut RunSummary::new());
+
+        // then
+        let err = result.unwrap_err().to_string();
+        assert!(err.contains("--seed 2 differs from the seed 1"), "{err}");
+    }
+
+    #[test]
+    fn the_step_rng_cant_be_compared_against_a_baseline() {
+        // given
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("baseline.json");
+        Baseline::new(1).save(&path).unwrap();
+        let cli = Cli::parse_from([
+            "regenesis_encoding",
+            "--step-rng",
+            "--baseline",
+            path.to_str().unwrap(),
+            "--out-dir",
+            dir.path().to_str().unwrap(),
+        ]);
+
+        // when
+        let result = run_charts(&cli, &mut RunSummary::new());
+
+        // then
+        let err = result.unwrap_err().to_string();
+        assert!(err.contains("--step-rng"), "{err}");
+    }
+
+    #[test]
+    fn a_run_writes_the_files_of_its_plan() {
+        // given
+        let dir = tempfile::tempdir().unwrap();
+        let cli = Cli::parse_from([
+            "regenesis_encoding",
+            "--codecs",
+            "serde_json,bincode,parquet,bincode_columnar",
+            "--max",
+            "20",
+            "--step",
+            "10",
+            "--max-brotli-level",
+            "1",
+            "--per-record",
+            "--out-dir",
+            dir.path().to_str().unwrap(),
+        ]);
+
+        // when
+        run_charts(&cli, &mut RunSummary::new()).unwrap();
+
+        // then
+        let written = files_in(dir.path()).into_iter().sorted().collect_vec();
+        let planned = Plan::new(&cli).files.into_iter().sorted().collect_vec();
+        pretty_assertions::assert_eq!(written, planned);
+    }
+
+    #[test]
+    fn every_series_is_drawn() {
+        // given
+        let dir = tempfile::tempdir().unwrap();
+        let measurement_sets = vec![
+            (vec![(1f64, 1f64, 0f64)], PlotSettings::normal("a")),
+            (vec![(2f64, 2f64, 0.5f64)], PlotSettings::normal("b")),
+            (vec![(1f64, 3f64, 0f64)], PlotSettings::predicted("c")),
+            (vec![(3f64, 1f64, 0f64)], PlotSettings::normal("d")),
+            (
+                vec![(3f64, 2f64, 0f64)],
+                PlotSettings::normal("e").with_shape(Shape::Square),
+            ),
+        ];
+
+        // when
+        let drawn = draw_measurements(
+            "title",
+            "x",
+            "y",
+            measurement_sets,
+            LogScale::default(),
+            dir.path().join("plot.svg"),
+        )
+        .unwrap();
+
+        // then
+        pretty_assertions::assert_eq!(drawn, 5);
+    }
+
+    #[test]
+    fn prediction_shares_the_color_of_its_points() {
+        // given
+        let measured = vec![EncodeMeasurement::new(
+            10,
+            100,
+            Duration::from_secs(1),
+            Duration::from_secs(1),
+        )];
+        let mut merger = PlotMerger::default();
+        merger.add(PlotSettings::normal("other"), &measured);
+
+        // when
+        merger.add_with_prediction("bincode", &measured, &measured);
+
+        // then
+        let series = merger
+            .bytes
+            .iter()
+            .map(|(_, settings)| (settings.label.as_str(), settings.color))
+            .collect_vec();
+        let expected = vec![
+            ("other", Some(palette_color(0))),
+            ("bincode", Some(palette_color(1))),
+            ("bincode_predicted", Some(palette_color(1))),
+        ];
+        pretty_assertions::assert_eq!(series, expected);
+    }
+
+    #[test]
+    fn point_series_cycle_through_the_markers() {
+        // given
+        let measured = vec![EncodeMeasurement::new(
+            10,
+            100,
+            Duration::from_secs(1),
+            Duration::from_secs(1),
+        )];
+        let mut merger = PlotMerger::default();
+
+        // when
+        for label in ["a", "b", "c", "d", "e"] {
+            merger.add(PlotSettings::normal(label), &measured);
+        }
+        merger.add(PlotSettings::predicted("f"), &measured);
+
+        // then
+        let shapes = merger
+            .bytes
+            .iter()
+            .map(|(_, settings)| settings.shape)
+            .collect_vec();
+        let expected = [
+            Shape::Circle,
+            Shape::Cross,
+            Shape::Square,
+            Shape::Triangle,
+            Shape::Circle,
+            Shape::Line,
+        ]
+        .map(Some)
+        .to_vec();
+        pretty_assertions::assert_eq!(shapes, expected);
+    }
+
+    #[test]
+    fn prefixed_charts_leave_unprefixed_ones_alone() {
+        // given
+        let dir = tempfile::tempdir().unwrap();
+        let measured = [EncodeMeasurement::new(
+            100,
+            100,
+            Duration::from_secs(1),
+            Duration::from_secs(1),
+        )];
+        let merger = || {
+            let mut merger = PlotMerger::default();
+            merger.add(PlotSettings::normal("bincode"), &measured);
+            merger
+        };
+
+        // when
+        merger().plot_with(dir.path(), "").unwrap();
+        merger()
+            .plot_with(dir.path(), "bincode_uncompressed")
+            .unwrap();
+
+        // then
+        let files = std::fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .sorted()
+            .collect_vec();
+        let expected = [
+            "bincode_uncompressed_decoding_time.svg",
+            "bincode_uncompressed_encoding_time.svg",
+            "bincode_uncompressed_storage_requirements.svg",
+            "bincode_uncompressed_throughput.svg",
+            "decoding_time.svg",
+            "encoding_time.svg",
+            "storage_requirements.svg",
+            "throughput.svg",
+        ];
+        pretty_assertions::assert_eq!(files, expected);
+    }
+
+    #[test]
+    fn prefixed_results_leave_unprefixed_ones_alone() {
+        // given
+        let dir = tempfile::tempdir().unwrap();
+        let measured = [EncodeMeasurement::new(
+            100,
+            100,
+            Duration::from_secs(1),
+            Duration::from_secs(1),
+        )];
+
+        // when
+        write_results(dir.path(), "", "bincode", &measured).unwrap();
+        write_results(dir.path(), "bincode_uncompressed", "bincode", &measured).unwrap();
+
+        // then
+        let files = std::fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .sorted()
+            .collect_vec();
+        let expected = [
+            "bincode.csv",
+            "bincode.json",
+            "bincode_uncompressed_bincode.csv",
+            "bincode_uncompressed_bincode.json",
+        ];
+        pretty_assertions::assert_eq!(files, expected);
+    }
+
+    #[test]
+    fn raw_and_compressed_series_share_a_color() {
+        // given
+        let measured = [EncodeMeasurement::new(
+            100,
+            100,
+            Duration::from_secs(1),
+            Duration::from_secs(1),
+        )];
+        let color = palette_color(3);
+        let mut merger = PlotMerger::default();
+
+        // when
+        merger.add(
+            PlotSettings::normal("bincode (raw)")
+                .with_color(color)
+                .with_shape(Shape::Line),
+            &measured,
+        );
+        merger.add(
+            PlotSettings::normal("bincode_gzip").with_color(color),
+            &measured,
+        );
+
+        // then
+        let settings = merger
+            .bytes
+            .iter()
+            .map(|(_, settings)| (settings.color, settings.shape))
+            .collect_vec();
+        pretty_assertions::assert_eq!(
+            settings,
+            vec![
+                (Some(color), Some(Shape::Line)),
+                (Some(color), Some(Shape::Cross))
+            ]
+        );
+    }
+
+    #[test]
+    fn per_record_histogram_is_written() {
+        // given
+        let dir = tempfile::tempdir().unwrap();
+        let times = [10, 11, 11, 12, 400].map(Duration::from_micros);
+
+        // when
+        plot_per_record(dir.path(), "", "bincode", &times).unwrap();
+
+        // then
+        let csv = std::fs::read_to_string(dir.path().join("bincode.csv")).unwrap();
+        pretty_assertions::assert_eq!(csv.lines().count(), HISTOGRAM_BUCKETS + 1);
+        assert!(dir.path().join("bincode.svg").exists());
+    }
+
+    #[test]
+    fn ratio_chart_is_written_next_to_nothing_else() {
+        // given
+        let dir = tempfile::tempdir().unwrap();
+        let mut merger = PlotMerger::default();
+        merger.add_ratio(
+            PlotSettings::normal("bincode_gzip"),
+            &[(10, 2f64), (20, 2.5f64)],
+        );
+
+        // when
+        merger.plot_ratio_with(dir.path(), "").unwrap();
+
+        // then
+        let files = std::fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect_vec();
+        pretty_assertions::assert_eq!(files, vec!["compression_ratio.svg"]);
+    }
+}
@@ -1,77 +1,525 @@
 use std::{
-    io::{BufReader, Cursor, Write},
+    fs::File,
+    io::{BufReader, BufWriter, Cursor, Seek, SeekFrom, Write},
+    iter::zip,
+    ops::{Deref, RangeInclusive},
+    path::Path,
     time::{Duration, Instant},
 };
 
-use flate2::{read::GzDecoder, write::GzEncoder, Compression};
 use itertools::Itertools;
-use linregress::{FormulaRegressionBuilder, RegressionDataBuilder};
+use linregress::{FormulaRegressionBuilder, RegressionDataBuilder, RegressionModel};
+use serde::{de::DeserializeOwned, Deserialize, Deserializer, Serialize, Serializer};
 
 use crate::{
-    encoding::PayloadCodec,
-    util::{payload, Data, Payload},
+    api::{BatchCodec, BatchIndex, GzipBatchCodec, InMemorySource, StateReader},
+    compression::{CompressionAlgo, Compressor, Decompressor},
+    encoding::{AppendCodec, CodecError, EncodeEntries, EntryCodec, ParquetCodec, PayloadCodec},
+    memory::track_peak,
+    serde_types::{
+        CoinConfig, ContractBalance, ContractConfig, ContractState, ContractStateRoot,
+        MessageConfig, DEFAULT_CODE_LEN,
+    },
+    util::{payload_with, Data, Payload, PayloadConfig, PayloadRng},
 };
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EncodeMeasurement {
+    #[serde(rename = "elements")]
     pub num_elements: usize,
     pub bytes: usize,
+    /// Median of `encode_stats`.
+    #[serde(
+        serialize_with = "serialize_nanos",
+        deserialize_with = "deserialize_nanos"
+    )]
     pub encode_time: Duration,
-    pub decode_time: Duration,
+    /// Median of `decode_stats`, `None` if nothing was decoded.
+    #[serde(
+        serialize_with = "serialize_optional_nanos",
+        deserialize_with = "deserialize_optional_nanos"
+    )]
+    pub decode_time: Option<Duration>,
+    pub encode_stats: TimingStats,
+    pub decode_stats: Option<TimingStats>,
+    /// Largest heap growth seen while encoding or decoding, `None` if memory isn't tracked.
+    pub peak_bytes: Option<usize>,
+}
+
+impl EncodeMeasurement {
+    /// A measurement taken from a single encode/decode cycle.
+    pub fn new(
+        num_elements: usize,
+        bytes: usize,
+        encode_time: Duration,
+        decode_time: Duration,
+    ) -> Self {
+        Self {
+            num_elements,
+            bytes,
+            encode_time,
+            decode_time: Some(decode_time),
+            encode_stats: TimingStats::exact(encode_time),
+            decode_stats: Some(TimingStats::exact(decode_time)),
+            peak_bytes: None,
+        }
+    }
+
+    /// Nothing was decoded, the decode phase is left out.
+    pub fn encode_only(num_elements: usize, bytes: usize, encode_time: Duration) -> Self {
+        Self {
+            decode_time: None,
+            decode_stats: None,
+            ..Self::new(num_elements, bytes, encode_time, Duration::ZERO)
+        }
+    }
+
+    pub fn with_peak_bytes(mut self, peak_bytes: Option<usize>) -> Self {
+        self.peak_bytes = peak_bytes;
+        self
+    }
+
+    pub fn encode_throughput_elems(&self) -> f64 {
+        per_sec(self.num_elements, self.encode_time)
+    }
+
+    /// Zero if nothing was decoded.
+    pub fn decode_throughput_elems(&self) -> f64 {
+        self.decode_time
+            .map_or(0f64, |time| per_sec(self.num_elements, time))
+    }
+
+    /// Encoded bytes produced per second of encoding.
+    pub fn bytes_per_sec(&self) -> f64 {
+        per_sec(self.bytes, self.encode_time)
+    }
+
+    /// Folds repeated measurements of the same payload into one. The decode phase is left out
+    /// unless every sample has one.
+    ///
+    /// Panics if `samples` is empty.
+    pub fn from_samples(samples: &[EncodeMeasurement]) -> Self {
+        let first = samples.first().expect("at least one sample");
+        let encode_times = samples.iter().map(|m| m.encode_time).collect_vec();
+        let decode_times = samples
+            .iter()
+            .map(|m| m.decode_time)
+            .collect::<Option<Vec<_>>>();
+        let encode_stats = TimingStats::from_samples(&encode_times);
+        let decode_stats = decode_times.map(|times| TimingStats::from_samples(&times));
+
+        Self {
+            num_elements: first.num_elements,
+            bytes: first.bytes,
+            encode_time: encode_stats.median,
+            decode_time: decode_stats.map(|stats| stats.median),
+            encode_stats,
+            decode_stats,
+            peak_bytes: samples.iter().map(|m| m.peak_bytes).max().flatten(),
+        }
+    }
+}
+
+/// Separate measurements for every config type of a payload.
+#[derive(Debug, Clone)]
+pub struct PerTypeMeasurement {
+    pub coins: EncodeMeasurement,
+    pub messages: EncodeMeasurement,
+    pub contracts: EncodeMeasurement,
+    pub contract_state: EncodeMeasurement,
+    pub contract_balance: EncodeMeasurement,
+    pub contract_state_root: EncodeMeasurement,
+    /// The part of every type's decode time spent opening its subset, see `DecodeTime`. Fixed
+    /// per subset, so it's what bends the decode curve of parquet at small sizes.
+    pub decode_open: Data<Duration>,
+}
+
+impl PerTypeMeasurement {
+    pub fn by_type(&self) -> [(&'static str, &EncodeMeasurement); 6] {
+        let measurements = [
+            &self.coins,
+            &self.messages,
+            &self.contracts,
+            &self.contract_state,
+            &self.contract_balance,
+            &self.contract_state_root,
+        ];
+        std::array::from_fn(|idx| (Data::LABELS[idx], measurements[idx]))
+    }
+
+    /// Panics if `samples` is empty.
+    pub fn from_samples(samples: &[PerTypeMeasurement]) -> Self {
+        let fold = |pick: fn(&PerTypeMeasurement) -> &EncodeMeasurement| {
+            EncodeMeasurement::from_samples(&samples.iter().map(pick).cloned().collect_vec())
+        };
+        Self {
+            coins: fold(|m| &m.coins),
+            messages: fold(|m| &m.messages),
+            contracts: fold(|m| &m.contracts),
+            contract_state: fold(|m| &m.contract_state),
+            contract_balance: fold(|m| &m.contract_balance),
+            contract_state_root: fold(|m| &m.contract_state_root),
+            decode_open: Data::from_array(std::array::from_fn(|idx| {
+                median(samples, |m| *m.decode_open.as_array()[idx])
+            })),
+        }
+    }
+}
+
+/// The decode time of every type next to the part of it spent opening the subset.
+impl ToCsv for PerTypeMeasurement {
+    fn headers() -> Vec<String> {
+        std::iter::once("elements".to_string())
+            .chain(
+                Data::LABELS
+                    .iter()
+                    .flat_map(|name| [format!("{name}_decode_time"), format!("{name}_open_time")]),
+            )
+            .collect()
+    }
+
+    fn to_csv(&self, mut writer: impl Write) -> std::io::Result<()> {
+        let elements: usize = self.by_type().iter().map(|(_, m)| m.num_elements).sum();
+        let times = zip(self.by_type(), self.decode_open.as_array())
+            .map(|((_, m), open)| {
+                let decode_time = m.decode_time.unwrap_or_default();
+                format!("{},{}", decode_time.as_nanos(), open.as_nanos())
+            })
+            .join(",");
+        writer.write_all(format!("{elements},{times}\n").as_bytes())
+    }
+}
+
+/// How many times faster `parallel` encoded than `serial`, averaged over the sizes both measured.
+pub fn encode_speedup(serial: &[EncodeMeasurement], parallel: &[EncodeMeasurement]) -> f64 {
+    let ratios = zip(serial, parallel)
+        .filter(|(_, parallel)| !parallel.encode_time.is_zero())
+        .map(|(serial, parallel)| {
+            serial.encode_time.as_secs_f64() / parallel.encode_time.as_secs_f64()
+        })
+        .collect_vec();
+    if ratios.is_empty() {
+        return 1f64;
+    }
+    ratios.iter().sum::<f64>() / ratios.len() as f64
+}
+
+/// `(num_elements, uncompressed / compressed bytes)` for every size measured by both. Sizes where
+/// either side wrote nothing, like the empty payload at zero elements, have no ratio and are
+/// skipped.
+pub fn compression_ratio(
+    uncompressed: &[EncodeMeasurement],
+    compressed: &[EncodeMeasurement],
+) -> Vec<(usize, f64)> {
+    compressed
+        .iter()
+        .filter_map(|compressed| {
+            let uncompressed = uncompressed
+                .iter()
+                .find(|m| m.num_elements == compressed.num_elements)?;
+            (uncompressed.bytes > 0 && compressed.bytes > 0).then(|| {
+                (
+                    compressed.num_elements,
+                    uncompressed.bytes as f64 / compressed.bytes as f64,
+                )
+            })
+        })
+        .collect()
+}
+
+/// The middle of the times `pick` takes from `samples`, the mean of the two middle ones for an
+/// even count.
+///
+/// Panics if `samples` is empty.
+fn median<T>(samples: &[T], pick: impl Fn(&T) -> Duration) -> Duration {
+    let times = samples.iter().map(pick).sorted().collect_vec();
+    assert!(!times.is_empty(), "need at least one sample");
+    let mid = times.len() / 2;
+    if times.len() % 2 == 0 {
+        (times[mid - 1] + times[mid]) / 2
+    } else {
+        times[mid]
+    }
+}
+
+/// A phase that wasn't timed is an empty CSV cell, same as untracked peak bytes.
+fn nanos_or_empty(time: Option<Duration>) -> String {
+    time.map(|time| time.as_nanos().to_string())
+        .unwrap_or_default()
+}
+
+/// Zero when nothing was timed, so plots and CSVs never see infinities.
+fn per_sec(amount: usize, time: Duration) -> f64 {
+    if time.is_zero() {
+        0f64
+    } else {
+        amount as f64 * 1e9 / time.as_nanos() as f64
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TimingStats {
+    #[serde(
+        serialize_with = "serialize_nanos",
+        deserialize_with = "deserialize_nanos"
+    )]
+    pub min: Duration,
+    #[serde(
+        serialize_with = "serialize_nanos",
+        deserialize_with = "deserialize_nanos"
+    )]
+    pub median: Duration,
+    #[serde(
+        serialize_with = "serialize_nanos",
+        deserialize_with = "deserialize_nanos"
+    )]
+    pub mean: Duration,
+    #[serde(
+        serialize_with = "serialize_nanos",
+        deserialize_with = "deserialize_nanos"
+    )]
+    pub stddev: Duration,
+}
+
+impl TimingStats {
+    pub fn exact(time: Duration) -> Self {
+        Self {
+            min: time,
+            median: time,
+            mean: time,
+            stddev: Duration::ZERO,
+        }
+    }
+
+    /// Panics if `samples` is empty.
+    pub fn from_samples(samples: &[Duration]) -> Self {
+        let min = *samples.iter().min().expect("at least one sample");
+        let median = median(samples, |time| *time);
+
+        let secs = samples.iter().map(Duration::as_secs_f64).collect_vec();
+        let mean = secs.iter().sum::<f64>() / secs.len() as f64;
+        let variance = secs.iter().map(|s| (s - mean).powi(2)).sum::<f64>() / secs.len() as f64;
+
+        Self {
+            min,
+            median,
+            mean: Duration::from_secs_f64(mean),
+            stddev: Duration::from_secs_f64(variance.sqrt()),
+        }
+    }
+}
+
+/// Durations are exported as nanoseconds, same as the CSV rows.
+fn serialize_nanos<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u128(duration.as_nanos())
+}
+
+/// Reads back what `serialize_nanos` wrote, e.g. a baseline saved by an earlier run.
+fn deserialize_nanos<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+    u64::deserialize(deserializer).map(Duration::from_nanos)
+}
+
+/// Same as `serialize_nanos`, a phase that wasn't timed is `null`.
+fn serialize_optional_nanos<S: Serializer>(
+    duration: &Option<Duration>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    duration
+        .map(|duration| duration.as_nanos())
+        .serialize(serializer)
+}
+
+fn deserialize_optional_nanos<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Duration>, D::Error> {
+    Option::<u64>::deserialize(deserializer).map(|nanos| nanos.map(Duration::from_nanos))
+}
+
+#[derive(Serialize)]
+struct CodecMeasurements<'a> {
+    codec: &'a str,
+    points: &'a [EncodeMeasurement],
+}
+
+pub fn write_json(
+    path: impl AsRef<Path>,
+    codec: &str,
+    points: &[EncodeMeasurement],
+) -> std::io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    serde_json::to_writer(&mut writer, &CodecMeasurements { codec, points })?;
+    writer.flush()
 }
 
 pub trait ToCsv {
     fn headers() -> Vec<String>;
-    fn to_csv(&self, writer: impl Write);
+    fn to_csv(&self, writer: impl Write) -> std::io::Result<()>;
 }
 
 pub trait CollectToCsv {
-    fn collect_csv(self, writer: impl Write);
+    /// Flushes `writer` once every row is written.
+    fn collect_csv(self, writer: impl Write) -> std::io::Result<()>;
+}
+
+/// How well a fitted curve matches the measured points.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RegressionSummary {
+    pub rsquared: f64,
+    pub residual_std_error: f64,
+}
+
+impl RegressionSummary {
+    /// Reported when there weren't enough points to fit a curve.
+    pub const UNFITTED: Self = Self {
+        rsquared: f64::NAN,
+        residual_std_error: f64::NAN,
+    };
+
+    pub fn is_fitted(&self) -> bool {
+        !self.rsquared.is_nan()
+    }
+
+    fn of(model: &RegressionModel) -> Self {
+        Self {
+            rsquared: model.rsquared(),
+            residual_std_error: model.scale().sqrt(),
+        }
+    }
+}
+
+/// Predicted measurements along with the quality of every fit that produced them.
+#[derive(Debug, Clone)]
+pub struct Prediction<M> {
+    pub points: Vec<M>,
+    /// One entry per predicted quantity, e.g. `("bytes", ..)`.
+    pub summaries: Vec<(&'static str, RegressionSummary)>,
+}
+
+impl<M> Prediction<M> {
+    pub fn worst_rsquared(&self) -> f64 {
+        self.summaries
+            .iter()
+            .map(|(_, summary)| summary.rsquared)
+            .min_by(|a, b| a.total_cmp(b))
+            .unwrap_or(1f64)
+    }
+}
+
+impl<M> Deref for Prediction<M> {
+    type Target = [M];
+
+    fn deref(&self) -> &Self::Target {
+        &self.points
+    }
 }
 
 pub trait LinearRegression {
     type Measurement;
-    fn linear_regression(&self, start: usize, step: usize, end: usize) -> Vec<Self::Measurement>;
+    fn linear_regression(
+        &self,
+        start: usize,
+        step: usize,
+        end: usize,
+    ) -> Prediction<Self::Measurement>;
 }
 
 impl LinearRegression for &[SeekMeasurement] {
     type Measurement = SeekMeasurement;
 
-    fn linear_regression(&self, start: usize, step: usize, end: usize) -> Vec<Self::Measurement> {
+    fn linear_regression(
+        &self,
+        start: usize,
+        step: usize,
+        end: usize,
+    ) -> Prediction<Self::Measurement> {
         let x = self.iter().map(|m| m.num_elements as f64).collect_vec();
         let regress = move |extract_y: fn(&SeekMeasurement) -> f64| {
             let y = self.iter().map(extract_y).collect_vec();
             gen_lin_function(x.clone(), y)
         };
 
-        let params = [
-            regress(|m| m.normal.as_secs_f64()),
-            regress(|m| m.compressed.as_secs_f64()),
-        ];
+        let (normal, normal_summary) = regress(|m| m.normal.as_secs_f64());
+        let (compressed, compressed_summary) = regress(|m| m.compressed.as_secs_f64());
 
-        (start..=end)
+        let points = (start..=end)
             .step_by(step)
             .map(|num_elements| SeekMeasurement {
                 num_elements,
-                normal: Duration::from_secs_f64(no_negatives(params[0](num_elements))),
-                compressed: Duration::from_secs_f64(no_negatives(params[1](num_elements))),
+                normal: Duration::from_secs_f64(no_negatives(normal(num_elements))),
+                compressed: Duration::from_secs_f64(no_negatives(compressed(num_elements))),
             })
-            .collect()
+            .collect();
+
+        Prediction {
+            points,
+            summaries: vec![
+                ("normal", normal_summary),
+                ("compressed", compressed_summary),
+            ],
+        }
     }
 }
 
-fn gen_lin_function(x: Vec<f64>, y: Vec<f64>) -> impl Fn(usize) -> f64 {
+fn gen_lin_function(x: Vec<f64>, y: Vec<f64>) -> (impl Fn(usize) -> f64, RegressionSummary) {
+    gen_poly_function(x, y, 1)
+}
+
+/// Fits `Y ~ X1 + ... + Xn` where `Xp` is `x` raised to the power `p`.
+///
+/// A fit of degree `n` needs at least `n + 1` distinct x values. With fewer, or whenever
+/// `linregress` can't compute the fit, the prediction falls back to a flat line at the mean of
+/// `y` and the summary is `RegressionSummary::UNFITTED`.
+fn gen_poly_function(
+    x: Vec<f64>,
+    y: Vec<f64>,
+    degree: u32,
+) -> (impl Fn(usize) -> f64, RegressionSummary) {
+    let distinct_x = x.iter().map(|x| x.to_bits()).unique().count();
+    let model = if distinct_x > degree as usize {
+        fit_polynomial(&x, y.clone(), degree)
+    } else {
+        None
+    };
+
+    let (params, summary) = match model {
+        Some(model) => (model.parameters().to_vec(), RegressionSummary::of(&model)),
+        None => {
+            let mean = if y.is_empty() {
+                0f64
+            } else {
+                y.iter().sum::<f64>() / y.len() as f64
+            };
+            (vec![mean], RegressionSummary::UNFITTED)
+        }
+    };
+
+    let predict = move |x: usize| {
+        params
+            .iter()
+            .enumerate()
+            .map(|(power, param)| param * (x as f64).powi(power as i32))
+            .sum()
+    };
+    (predict, summary)
+}
+
+fn fit_polynomial(x: &[f64], y: Vec<f64>, degree: u32) -> Option<RegressionModel> {
+    let powers = (1..=degree)
+        .map(|power| {
+            let column = x.iter().map(|x| x.powi(power as i32)).collect_vec();
+            (format!("X{power}"), column)
+        })
+        .collect_vec();
+    let formula = format!("Y ~ {}", powers.iter().map(|(name, _)| name).join(" + "));
+
     let data = RegressionDataBuilder::new()
-        .build_from(vec![("Y", y), ("X", x)])
-        .unwrap();
-    let model = FormulaRegressionBuilder::new()
+        .build_from(std::iter::once(("Y".to_string(), y)).chain(powers))
+        .ok()?;
+    FormulaRegressionBuilder::new()
         .data(&data)
-        .formula("Y ~ X")
+        .formula(formula)
         .fit()
-        .unwrap();
-    let params = model.parameters();
-    let (b, a) = (params[0], params[1]);
-
-    move |x: usize| (a * x as f64 + b)
+        .ok()
 }
 
 fn no_negatives(val: f64) -> f64 {
@@ -84,130 +532,408 @@ fn no_negatives(val: f64) -> f64 {
 impl LinearRegression for Vec<SeekMeasurement> {
     type Measurement = SeekMeasurement;
 
-    fn linear_regression(&self, start: usize, step: usize, end: usize) -> Vec<Self::Measurement> {
+    fn linear_regression(
+        &self,
+        start: usize,
+        step: usize,
+        end: usize,
+    ) -> Prediction<Self::Measurement> {
         self.as_slice().linear_regression(start, step, end)
     }
 }
 impl LinearRegression for Vec<EncodeMeasurement> {
     type Measurement = EncodeMeasurement;
 
-    fn linear_regression(&self, start: usize, step: usize, end: usize) -> Vec<Self::Measurement> {
+    fn linear_regression(
+        &self,
+        start: usize,
+        step: usize,
+        end: usize,
+    ) -> Prediction<Self::Measurement> {
         self.as_slice().linear_regression(start, step, end)
     }
 }
 
 impl LinearRegression for &[EncodeMeasurement] {
     type Measurement = EncodeMeasurement;
-    fn linear_regression(&self, start: usize, step: usize, end: usize) -> Vec<Self::Measurement> {
-        let x = self.iter().map(|m| m.num_elements as f64).collect_vec();
-        let regress = |extract_y: fn(&EncodeMeasurement) -> f64| {
-            let y = self.iter().map(extract_y).collect_vec();
-            gen_lin_function(x.clone(), y)
-        };
+    fn linear_regression(
+        &self,
+        start: usize,
+        step: usize,
+        end: usize,
+    ) -> Prediction<Self::Measurement> {
+        predict_encode_measurements(self, gen_lin_function, start, step, end)
+    }
+}
 
-        let params = [
-            regress(|m| m.bytes as f64),
-            regress(|m| m.encode_time.as_secs_f64()),
-            regress(|m| m.decode_time.as_secs_f64()),
-        ];
+pub trait PolynomialRegression {
+    type Measurement;
+    fn polynomial_regression(
+        &self,
+        degree: u32,
+        start: usize,
+        step: usize,
+        end: usize,
+    ) -> Prediction<Self::Measurement>;
+}
 
-        (start..=end)
-            .step_by(step)
-            .map(|num_elements| EncodeMeasurement {
-                num_elements,
-                bytes: no_negatives(params[0](num_elements)) as usize,
-                encode_time: Duration::from_secs_f64(no_negatives(params[1](num_elements))),
-                decode_time: Duration::from_secs_f64(no_negatives(params[2](num_elements))),
-            })
-            .collect()
+impl PolynomialRegression for &[EncodeMeasurement] {
+    type Measurement = EncodeMeasurement;
+    fn polynomial_regression(
+        &self,
+        degree: u32,
+        start: usize,
+        step: usize,
+        end: usize,
+    ) -> Prediction<Self::Measurement> {
+        let fit = |x, y| gen_poly_function(x, y, degree);
+        predict_encode_measurements(self, fit, start, step, end)
+    }
+}
+
+impl PolynomialRegression for Vec<EncodeMeasurement> {
+    type Measurement = EncodeMeasurement;
+    fn polynomial_regression(
+        &self,
+        degree: u32,
+        start: usize,
+        step: usize,
+        end: usize,
+    ) -> Prediction<Self::Measurement> {
+        self.as_slice()
+            .polynomial_regression(degree, start, step, end)
+    }
+}
+
+fn predict_encode_measurements<F: Fn(usize) -> f64>(
+    measurements: &[EncodeMeasurement],
+    fit: impl Fn(Vec<f64>, Vec<f64>) -> (F, RegressionSummary),
+    start: usize,
+    step: usize,
+    end: usize,
+) -> Prediction<EncodeMeasurement> {
+    let x = measurements
+        .iter()
+        .map(|m| m.num_elements as f64)
+        .collect_vec();
+    let regress = |extract_y: fn(&EncodeMeasurement) -> f64| {
+        let y = measurements.iter().map(extract_y).collect_vec();
+        fit(x.clone(), y)
+    };
+
+    let (bytes, bytes_summary) = regress(|m| m.bytes as f64);
+    let (encode_time, encode_summary) = regress(|m| m.encode_time.as_secs_f64());
+    // measurements without a decode phase predict none either
+    let decoded = measurements.iter().all(|m| m.decode_time.is_some());
+    let (decode_time, decode_summary) =
+        regress(|m| m.decode_time.unwrap_or_default().as_secs_f64());
+
+    let points = (start..=end)
+        .step_by(step)
+        .map(|num_elements| {
+            let bytes = no_negatives(bytes(num_elements)) as usize;
+            let encode_time = Duration::from_secs_f64(no_negatives(encode_time(num_elements)));
+            if decoded {
+                let decode_time = Duration::from_secs_f64(no_negatives(decode_time(num_elements)));
+                EncodeMeasurement::new(num_elements, bytes, encode_time, decode_time)
+            } else {
+                EncodeMeasurement::encode_only(num_elements, bytes, encode_time)
+            }
+        })
+        .collect();
+
+    let mut summaries = vec![("bytes", bytes_summary), ("encode_time", encode_summary)];
+    if decoded {
+        summaries.push(("decode_time", decode_summary));
     }
+    Prediction { points, summaries }
 }
 
 impl ToCsv for EncodeMeasurement {
-    fn to_csv(&self, mut writer: impl Write) {
-        writer
-            .write_all(
-                format!(
-                    "{},{},{},{}\n",
-                    self.num_elements,
-                    self.bytes,
-                    self.encode_time.as_nanos(),
-                    self.decode_time.as_nanos()
-                )
-                .as_bytes(),
+    fn to_csv(&self, mut writer: impl Write) -> std::io::Result<()> {
+        writer.write_all(
+            format!(
+                "{},{},{},{},{},{},{},{},{},{},{},{},{},{}\n",
+                self.num_elements,
+                self.bytes,
+                self.encode_time.as_nanos(),
+                nanos_or_empty(self.decode_time),
+                self.encode_stats.min.as_nanos(),
+                self.encode_stats.mean.as_nanos(),
+                self.encode_stats.stddev.as_nanos(),
+                nanos_or_empty(self.decode_stats.map(|stats| stats.min)),
+                nanos_or_empty(self.decode_stats.map(|stats| stats.mean)),
+                nanos_or_empty(self.decode_stats.map(|stats| stats.stddev)),
+                self.encode_throughput_elems(),
+                self.decode_time
+                    .map(|_| self.decode_throughput_elems().to_string())
+                    .unwrap_or_default(),
+                self.bytes_per_sec(),
+                self.peak_bytes.map(|b| b.to_string()).unwrap_or_default(),
             )
-            .unwrap();
+            .as_bytes(),
+        )
     }
 
     fn headers() -> Vec<String> {
-        ["elements", "bytes", "encode_time", "decode_time"]
-            .map(|e| e.to_string())
-            .to_vec()
+        [
+            "elements",
+            "bytes",
+            "encode_time",
+            "decode_time",
+            "encode_min",
+            "encode_mean",
+            "encode_stddev",
+            "decode_min",
+            "decode_mean",
+            "decode_stddev",
+            "encode_elems_per_sec",
+            "decode_elems_per_sec",
+            "bytes_per_sec",
+            "peak_bytes",
+        ]
+        .map(|e| e.to_string())
+        .to_vec()
     }
 }
 
+/// Encodes into `data`, which should be empty, and decodes a copy of the output so the caller
+/// keeps the buffers. The copy is made outside of the timed sections.
 pub fn measure_normal<C: PayloadCodec<Cursor<Vec<u8>>, Vec<u8>>>(
     codec: &C,
-    mut data: Data<Vec<u8>>,
-    entries: Payload,
-) -> EncodeMeasurement {
+    data: &mut Data<Vec<u8>>,
+    entries: &Payload,
+) -> Result<EncodeMeasurement, CodecError> {
     let num_elements = entries.num_entries();
-    let encode_time = track_time(|| codec.encode(entries, &mut data)).0;
+    let (encode_peak, (encode_time, encoded)) =
+        track_peak(|| track_time(|| codec.encode(entries, data)));
+    encoded?;
     let bytes = data.len();
-    let decode_time = track_time(|| codec.decode(data.wrap_in_cursor())).0;
-    EncodeMeasurement {
-        bytes,
-        encode_time,
-        decode_time,
-        num_elements,
-    }
+    let encoded = data.as_ref().map(|bytes| Cursor::new(bytes.to_vec()));
+    let (decode_peak, (decode_time, decoded)) = track_peak(|| track_time(|| codec.decode(encoded)));
+    let _decoded = decoded?;
+    Ok(
+        EncodeMeasurement::new(num_elements, bytes, encode_time, decode_time)
+            .with_peak_bytes(encode_peak.max(decode_peak)),
+    )
+}
+
+/// The encode half of `measure_normal`, nothing is decoded. For profiling the encoder on its own
+/// or for codecs whose decoding is broken.
+pub fn measure_encode_only<C: PayloadCodec<Cursor<Vec<u8>>, Vec<u8>>>(
+    codec: &C,
+    data: &mut Data<Vec<u8>>,
+    entries: &Payload,
+) -> Result<EncodeMeasurement, CodecError> {
+    let num_elements = entries.num_entries();
+    let (encode_peak, (encode_time, encoded)) =
+        track_peak(|| track_time(|| codec.encode(entries, data)));
+    encoded?;
+    Ok(
+        EncodeMeasurement::encode_only(num_elements, data.len(), encode_time)
+            .with_peak_bytes(encode_peak),
+    )
+}
+
+/// Like `measure_normal` but the whole payload goes through `EntryCodec` as a single stream of
+/// tagged entries. The entries are collected before the clock starts.
+pub fn measure_entries<C: EntryCodec<Cursor<Vec<u8>>, Vec<u8>>>(
+    codec: &C,
+    entries: &Payload,
+) -> Result<EncodeMeasurement, CodecError> {
+    let num_elements = entries.num_entries();
+    let stream = entries.clone().into_entries().collect_vec();
+    let mut data = vec![];
+    let (encode_peak, (encode_time, encoded)) =
+        track_peak(|| track_time(|| codec.encode_entries(stream, &mut data)));
+    encoded?;
+    let bytes = data.len();
+    let (decode_peak, (decode_time, decoded)) =
+        track_peak(|| track_time(|| codec.decode_entries(Cursor::new(data))));
+    let _decoded = decoded?;
+    Ok(
+        EncodeMeasurement::new(num_elements, bytes, encode_time, decode_time)
+            .with_peak_bytes(encode_peak.max(decode_peak)),
+    )
+}
+
+/// Like `measure_normal` but encodes the subsets concurrently.
+#[cfg(feature = "parallel")]
+pub fn measure_normal_parallel<C: PayloadCodec<Cursor<Vec<u8>>, Vec<u8>> + Sync>(
+    codec: &C,
+    data: &mut Data<Vec<u8>>,
+    entries: &Payload,
+) -> Result<EncodeMeasurement, CodecError> {
+    let num_elements = entries.num_entries();
+    let (encode_peak, (encode_time, encoded)) =
+        track_peak(|| track_time(|| codec.encode_parallel(entries, data)));
+    encoded?;
+    let bytes = data.len();
+    let encoded = data.as_ref().map(|bytes| Cursor::new(bytes.to_vec()));
+    let (decode_peak, (decode_time, decoded)) = track_peak(|| track_time(|| codec.decode(encoded)));
+    let _decoded = decoded?;
+    Ok(
+        EncodeMeasurement::new(num_elements, bytes, encode_time, decode_time)
+            .with_peak_bytes(encode_peak.max(decode_peak)),
+    )
+}
+
+/// Like `measure_normal` but times and sizes every config type on its own.
+pub fn measure_normal_per_type<C: PayloadCodec<Cursor<Vec<u8>>, Vec<u8>>>(
+    codec: &C,
+    data: &mut Data<Vec<u8>>,
+    entries: &Payload,
+) -> Result<PerTypeMeasurement, CodecError> {
+    let elements = Data {
+        coins: entries.coins.len(),
+        messages: entries.messages.len(),
+        contracts: entries.contracts.len(),
+        contract_state: entries.contract_state.len(),
+        contract_balance: entries.contract_balance.len(),
+        contract_state_root: entries.contract_state_root.len(),
+    };
+    let encode_times = codec.encode_timed(entries, data)?;
+    let bytes = data.each_ref().map(Vec::len);
+    let (_decoded, decode_times) =
+        codec.decode_timed(data.as_ref().map(|bytes| Cursor::new(bytes.to_vec())))?;
+
+    Ok(PerTypeMeasurement {
+        coins: EncodeMeasurement::new(
+            elements.coins,
+            bytes.coins,
+            encode_times.coins,
+            decode_times.coins.total,
+        ),
+        messages: EncodeMeasurement::new(
+            elements.messages,
+            bytes.messages,
+            encode_times.messages,
+            decode_times.messages.total,
+        ),
+        contracts: EncodeMeasurement::new(
+            elements.contracts,
+            bytes.contracts,
+            encode_times.contracts,
+            decode_times.contracts.total,
+        ),
+        contract_state: EncodeMeasurement::new(
+            elements.contract_state,
+            bytes.contract_state,
+            encode_times.contract_state,
+            decode_times.contract_state.total,
+        ),
+        contract_balance: EncodeMeasurement::new(
+            elements.contract_balance,
+            bytes.contract_balance,
+            encode_times.contract_balance,
+            decode_times.contract_balance.total,
+        ),
+        contract_state_root: EncodeMeasurement::new(
+            elements.contract_state_root,
+            bytes.contract_state_root,
+            encode_times.contract_state_root,
+            decode_times.contract_state_root.total,
+        ),
+        decode_open: decode_times.map(|time| time.open),
+    })
 }
 
 pub fn measure_compressed<
-    C: for<'a> PayloadCodec<BufReader<GzDecoder<&'a [u8]>>, GzEncoder<&'a mut Vec<u8>>>,
+    C: for<'a> PayloadCodec<BufReader<Decompressor<&'a [u8]>>, Compressor<&'a mut Vec<u8>>>,
 >(
     codec: &C,
     data: &mut Data<Vec<u8>>,
-    entries: Payload,
-) -> EncodeMeasurement {
+    entries: &Payload,
+    algo: CompressionAlgo,
+) -> Result<EncodeMeasurement, CodecError> {
     let num_elements = entries.num_entries();
     data.clear();
-    let (encode_time, data) = track_time(|| {
-        let mut data = data.wrap_in_compressor(Compression::new(1));
-        codec.encode(entries, &mut data);
-        data.finish().unwrap()
+    let (encode_peak, (encode_time, data)) = track_peak(|| {
+        track_time(|| {
+            let mut data = data.wrap_in_compressor(algo)?;
+            codec.encode(entries, &mut data)?;
+            Ok::<_, CodecError>(data.finish()?)
+        })
     });
+    let data = data?;
     let bytes = data.len();
-    let (decode_time, _) = track_time(|| {
-        let data = data.wrap_in_buffered_decompressor();
-        codec.decode(data);
+    let (decode_peak, (decode_time, decoded)) = track_peak(|| {
+        track_time(|| {
+            let data = data.wrap_in_buffered_decompressor(algo)?;
+            codec.decode(data)
+        })
     });
+    let _decoded = decoded?;
 
-    EncodeMeasurement {
-        bytes,
-        encode_time,
-        decode_time,
-        num_elements,
-    }
+    Ok(
+        EncodeMeasurement::new(num_elements, bytes, encode_time, decode_time)
+            .with_peak_bytes(encode_peak.max(decode_peak)),
+    )
 }
 
 impl<'a, T: IntoIterator<Item = &'a K>, K: ToCsv + 'a> CollectToCsv for T {
-    fn collect_csv(self, mut writer: impl Write) {
+    fn collect_csv(self, mut writer: impl Write) -> std::io::Result<()> {
         let headers = K::headers().join(",") + "\n";
-        writer.write_all(headers.as_bytes()).unwrap();
+        writer.write_all(headers.as_bytes())?;
         for el in self.into_iter() {
-            el.to_csv(&mut writer)
+            el.to_csv(&mut writer)?;
         }
+        writer.flush()
+    }
+}
+
+/// A measurement made of a few timings of the same payload, e.g. with and without compression.
+/// Sampled into the median of every timing and plotted with a series per timing.
+pub trait Timings: Sized {
+    /// Name of every timing in the charts, in the order `timings` returns them.
+    fn series() -> Vec<&'static str>;
+    fn num_elements(&self) -> usize;
+    fn timings(&self) -> Vec<Duration>;
+    /// `self` with its timings replaced, in the order of `series`.
+    fn with_timings(&self, timings: &[Duration]) -> Self;
+
+    /// Collapses repeated measurements of the same payload into the median of every timing.
+    fn from_samples(samples: &[Self]) -> Self {
+        assert!(!samples.is_empty(), "need at least one sample");
+        let medians = (0..Self::series().len())
+            .map(|idx| median(samples, |m| m.timings()[idx]))
+            .collect_vec();
+        samples[0].with_timings(&medians)
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize)]
 pub struct SeekMeasurement {
+    #[serde(rename = "elements")]
     pub num_elements: usize,
+    #[serde(serialize_with = "serialize_nanos")]
     pub normal: Duration,
+    #[serde(serialize_with = "serialize_nanos")]
     pub compressed: Duration,
 }
 
+impl Timings for SeekMeasurement {
+    fn series() -> Vec<&'static str> {
+        vec!["normal", "gzip"]
+    }
+
+    fn num_elements(&self) -> usize {
+        self.num_elements
+    }
+
+    fn timings(&self) -> Vec<Duration> {
+        vec![self.normal, self.compressed]
+    }
+
+    fn with_timings(&self, timings: &[Duration]) -> Self {
+        Self {
+            normal: timings[0],
+            compressed: timings[1],
+            ..*self
+        }
+    }
+}
+
 impl ToCsv for SeekMeasurement {
     fn headers() -> Vec<String> {
         ["elements", "compressed", "time"]
@@ -215,127 +941,1854 @@ impl ToCsv for SeekMeasurement {
             .to_vec()
     }
 
-    fn to_csv(&self, mut writer: impl Write) {
+    fn to_csv(&self, mut writer: impl Write) -> std::io::Result<()> {
         let mut encode_row = move |compressed, time: Duration| {
-            writer
-                .write_all(
-                    format!("{},{compressed},{}\n", self.num_elements, time.as_nanos()).as_bytes(),
-                )
-                .unwrap();
+            writer.write_all(
+                format!("{},{compressed},{}\n", self.num_elements, time.as_nanos()).as_bytes(),
+            )
         };
 
-        encode_row(false, self.normal);
-        encode_row(true, self.compressed);
+        encode_row(false, self.normal)?;
+        encode_row(true, self.compressed)
     }
 }
 
-// pub fn measure_json_seek(entries: Vec<StateEntry>) -> SeekMeasurement {
-//     let num_elements = entries.len();
-//     let normal = seek_end_uncompressed(entries.clone());
-//     let compressed = seek_end_compressed(entries);
-//     SeekMeasurement {
-//         num_elements,
-//         normal,
-//         compressed,
-//     }
-// }
+/// Encodes the entries one at a time, each into the same cleared buffer, and times every one.
+/// Where the aggregate times only show the mean, this shows how far single entries stray from it,
+/// e.g. contracts with a large `code`. Only meaningful for codecs without a header or footer,
+/// those pay for theirs on every entry.
+pub fn measure_per_record<T, C: EncodeEntries<T>>(
+    codec: &C,
+    data: &[T],
+) -> Result<Vec<Duration>, CodecError> {
+    let mut buffer = vec![];
+    data.iter()
+        .map(|entry| {
+            buffer.clear();
+            let (time, encoded) =
+                track_time(|| codec.encode_entries(std::slice::from_ref(entry), &mut buffer));
+            encoded.map(|()| time)
+        })
+        .collect()
+}
 
-fn track_time<T>(action: impl FnOnce() -> T) -> (Duration, T) {
-    let start = Instant::now();
-    let ret = action();
-    (Instant::now() - start, ret)
+/// `count` of the times fell into `start..end`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HistogramBucket {
+    pub start: Duration,
+    pub end: Duration,
+    pub count: usize,
 }
 
-// fn generate_json_uncompressed(payload: impl Iterator<Item = StateEntry>, path: impl AsRef<Path>) {
-//     let file = File::create(path.as_ref()).unwrap();
-//     let mut writer = BufWriter::new(file);
-//     encode_json_payload(payload, &mut writer);
-// }
-//
-// fn generate_json_compressed(payload: impl Iterator<Item = StateEntry>, path: impl AsRef<Path>) {
-//     let file = File::create(path.as_ref()).unwrap();
-//     let mut compressor = GzEncoder::new(file, Compression::default());
-//     encode_json_payload(payload, &mut compressor);
-//     compressor.finish().unwrap();
-// }
-//
-// fn seek_end_uncompressed(payload: impl IntoIterator<Item = StateEntry>) -> std::time::Duration {
-//     let tmp = tempfile::NamedTempFile::new().unwrap();
-//     generate_json_uncompressed(payload.into_iter(), tmp.path());
-//     tmp.as_file().sync_data().unwrap();
-//
-//     let start = Instant::now();
-//     let mut file = File::open(tmp.path()).unwrap();
-//     file.seek(std::io::SeekFrom::End(0)).unwrap();
-//
-//     let duration = Instant::now() - start;
-//
-//     tmp.close().unwrap();
-//     duration
-// }
-//
-// fn seek_end_compressed(payload: impl IntoIterator<Item = StateEntry>) -> std::time::Duration {
-//     let tmp = tempfile::NamedTempFile::new().unwrap();
-//     generate_json_compressed(payload.into_iter(), tmp.path());
-//     tmp.as_file().sync_data().unwrap();
-//
-//     let start = Instant::now();
-//     let file = File::open(tmp.path()).unwrap();
-//     let mut decoder = GzDecoder::new(file);
-//
-//     std::io::copy(
-//         &mut std::io::Read::by_ref(&mut decoder),
-//         &mut std::io::sink(),
-//     )
-//     .unwrap();
-//
-//     let duration = Instant::now() - start;
-//     tmp.close().unwrap();
-//     duration
-// }
-//
-pub struct MeasurementRunner {
-    step: usize,
-    max: usize,
-    data: Data<Vec<u8>>,
+/// Splits the range from the shortest to the longest time into `buckets` equally wide buckets.
+/// The last one is stretched to end at the longest time, which it includes. Empty if there are
+/// no times.
+pub fn histogram(times: &[Duration], buckets: usize) -> Vec<HistogramBucket> {
+    assert!(buckets > 0, "need at least one bucket");
+    let (Some(&min), Some(&max)) = (times.iter().min(), times.iter().max()) else {
+        return vec![];
+    };
+    // a bucket of at least a nanosecond, so equal times still land somewhere
+    let width = ((max - min) / buckets as u32).max(Duration::from_nanos(1));
+    let mut counts = vec![0; buckets];
+    for time in times {
+        let idx = ((*time - min).as_nanos() / width.as_nanos()) as usize;
+        counts[idx.min(buckets - 1)] += 1;
+    }
+    counts
+        .into_iter()
+        .enumerate()
+        .map(|(idx, count)| HistogramBucket {
+            start: min + width * idx as u32,
+            end: if idx + 1 == buckets {
+                max
+            } else {
+                min + width * (idx + 1) as u32
+            },
+            count,
+        })
+        .collect()
 }
 
-impl MeasurementRunner {
-    pub fn new(max: usize, step: usize) -> Self {
+/// Time it takes to add a fixed increment to a corpus of `num_elements` entries, once appended to
+/// the encoded corpus and once encoding corpus and increment together from scratch.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct AppendMeasurement {
+    #[serde(rename = "elements")]
+    pub num_elements: usize,
+    #[serde(serialize_with = "serialize_nanos")]
+    pub append: Duration,
+    #[serde(serialize_with = "serialize_nanos")]
+    pub reencode: Duration,
+}
+
+impl Timings for AppendMeasurement {
+    fn series() -> Vec<&'static str> {
+        vec!["append", "re-encode"]
+    }
+
+    fn num_elements(&self) -> usize {
+        self.num_elements
+    }
+
+    fn timings(&self) -> Vec<Duration> {
+        vec![self.append, self.reencode]
+    }
+
+    fn with_timings(&self, timings: &[Duration]) -> Self {
         Self {
-            data: Data::with_capacity(5_000_000_000),
-            step,
-            max,
+            append: timings[0],
+            reencode: timings[1],
+            ..*self
         }
     }
+}
 
-    pub fn run_compressed<
-        C: for<'a> PayloadCodec<BufReader<GzDecoder<&'a [u8]>>, GzEncoder<&'a mut Vec<u8>>>,
-    >(
-        &mut self,
-        codec: &C,
-    ) -> Vec<EncodeMeasurement> {
-        (0..self.max)
-            .step_by(self.step)
-            .map(payload)
-            .map(|entries| {
-                self.data.clear();
-                measure_compressed(codec, &mut self.data, entries)
-            })
-            .collect()
+impl ToCsv for AppendMeasurement {
+    fn headers() -> Vec<String> {
+        ["elements", "appended", "time"]
+            .map(|e| e.to_string())
+            .to_vec()
     }
 
-    pub fn run<C: PayloadCodec<Cursor<Vec<u8>>, Vec<u8>>>(
-        &self,
-        codec: &C,
-    ) -> Vec<EncodeMeasurement> {
-        (0..self.max)
-            .step_by(self.step)
-            .map(payload)
-            .map(|entries| {
-                let data = Data::with_capacity(5_000_000_000);
-                measure_normal(codec, data, entries)
-            })
-            .collect()
+    fn to_csv(&self, mut writer: impl Write) -> std::io::Result<()> {
+        let mut encode_row = move |appended, time: Duration| {
+            writer.write_all(
+                format!("{},{appended},{}\n", self.num_elements, time.as_nanos()).as_bytes(),
+            )
+        };
+
+        encode_row(true, self.append)?;
+        encode_row(false, self.reencode)
+    }
+}
+
+/// Time it takes to decode a payload split into shards, each encoded on its own, once shard after
+/// shard and once with every shard on its own rayon task.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct ShardMeasurement {
+    #[serde(rename = "elements")]
+    pub num_elements: usize,
+    pub shards: usize,
+    #[serde(serialize_with = "serialize_nanos")]
+    pub serial: Duration,
+    #[serde(serialize_with = "serialize_nanos")]
+    pub parallel: Duration,
+}
+
+impl Timings for ShardMeasurement {
+    fn series() -> Vec<&'static str> {
+        vec!["serial", "parallel"]
+    }
+
+    fn num_elements(&self) -> usize {
+        self.num_elements
+    }
+
+    fn timings(&self) -> Vec<Duration> {
+        vec![self.serial, self.parallel]
+    }
+
+    fn with_timings(&self, timings: &[Duration]) -> Self {
+        Self {
+            serial: timings[0],
+            parallel: timings[1],
+            ..*self
+        }
+    }
+}
+
+impl ShardMeasurement {
+    /// How many times faster the parallel decode was, 1 if nothing was timed.
+    pub fn speedup(&self) -> f64 {
+        if self.parallel.is_zero() {
+            return 1f64;
+        }
+        self.serial.as_secs_f64() / self.parallel.as_secs_f64()
+    }
+}
+
+impl ToCsv for ShardMeasurement {
+    fn headers() -> Vec<String> {
+        ["elements", "shards", "parallel", "time"]
+            .map(|e| e.to_string())
+            .to_vec()
+    }
+
+    fn to_csv(&self, mut writer: impl Write) -> std::io::Result<()> {
+        let mut encode_row = move |parallel, time: Duration| {
+            writer.write_all(
+                format!(
+                    "{},{},{parallel},{}\n",
+                    self.num_elements,
+                    self.shards,
+                    time.as_nanos()
+                )
+                .as_bytes(),
+            )
+        };
+
+        encode_row(false, self.serial)?;
+        encode_row(true, self.parallel)
+    }
+}
+
+/// Time it takes to find the coins of one owner, once by decoding everything and once letting
+/// the row group statistics and bloom filters skip what they can.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct LookupMeasurement {
+    #[serde(rename = "elements")]
+    pub num_elements: usize,
+    #[serde(serialize_with = "serialize_nanos")]
+    pub full_scan: Duration,
+    #[serde(serialize_with = "serialize_nanos")]
+    pub pruned: Duration,
+}
+
+impl Timings for LookupMeasurement {
+    fn series() -> Vec<&'static str> {
+        vec!["full_scan", "pruned"]
+    }
+
+    fn num_elements(&self) -> usize {
+        self.num_elements
+    }
+
+    fn timings(&self) -> Vec<Duration> {
+        vec![self.full_scan, self.pruned]
+    }
+
+    fn with_timings(&self, timings: &[Duration]) -> Self {
+        Self {
+            full_scan: timings[0],
+            pruned: timings[1],
+            ..*self
+        }
+    }
+}
+
+impl ToCsv for LookupMeasurement {
+    fn headers() -> Vec<String> {
+        ["elements", "pruned", "time"]
+            .map(|e| e.to_string())
+            .to_vec()
+    }
+
+    fn to_csv(&self, mut writer: impl Write) -> std::io::Result<()> {
+        let mut encode_row = move |pruned, time: Duration| {
+            writer.write_all(
+                format!("{},{pruned},{}\n", self.num_elements, time.as_nanos()).as_bytes(),
+            )
+        };
+
+        encode_row(false, self.full_scan)?;
+        encode_row(true, self.pruned)
+    }
+}
+
+/// Time it takes parquet to decode a payload, up to assembling its `Row`s and up to converting
+/// them into the configs as well. Both come from the same decode, see
+/// `ParquetCodec::decode_rows_timed`.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct RowConversionMeasurement {
+    #[serde(rename = "elements")]
+    pub num_elements: usize,
+    #[serde(serialize_with = "serialize_nanos")]
+    pub raw_rows: Duration,
+    #[serde(serialize_with = "serialize_nanos")]
+    pub converted: Duration,
+}
+
+impl Timings for RowConversionMeasurement {
+    fn series() -> Vec<&'static str> {
+        vec!["raw_rows", "converted"]
+    }
+
+    fn num_elements(&self) -> usize {
+        self.num_elements
+    }
+
+    fn timings(&self) -> Vec<Duration> {
+        vec![self.raw_rows, self.converted]
+    }
+
+    fn with_timings(&self, timings: &[Duration]) -> Self {
+        Self {
+            raw_rows: timings[0],
+            converted: timings[1],
+            ..*self
+        }
+    }
+}
+
+impl RowConversionMeasurement {
+    /// Fraction of the full decode spent converting the rows, 0 for an empty payload.
+    pub fn conversion_share(&self) -> f64 {
+        if self.converted.is_zero() {
+            return 0f64;
+        }
+        self.converted.saturating_sub(self.raw_rows).as_secs_f64() / self.converted.as_secs_f64()
+    }
+}
+
+impl ToCsv for RowConversionMeasurement {
+    fn headers() -> Vec<String> {
+        ["elements", "converted", "time"]
+            .map(|e| e.to_string())
+            .to_vec()
+    }
+
+    fn to_csv(&self, mut writer: impl Write) -> std::io::Result<()> {
+        let mut encode_row = move |converted, time: Duration| {
+            writer.write_all(
+                format!("{},{converted},{}\n", self.num_elements, time.as_nanos()).as_bytes(),
+            )
+        };
+
+        encode_row(false, self.raw_rows)?;
+        encode_row(true, self.converted)
+    }
+}
+
+/// The same payload measured with a codec built for `parameter`, e.g. parquet's batch size.
+#[derive(Debug, Clone, Serialize)]
+pub struct SweepMeasurement {
+    pub parameter: usize,
+    #[serde(flatten)]
+    pub measurement: EncodeMeasurement,
+}
+
+impl SweepMeasurement {
+    /// The median of samples of the same parameter, see `EncodeMeasurement::from_samples`.
+    pub fn from_samples(samples: &[SweepMeasurement]) -> Self {
+        let measurements = samples.iter().map(|s| s.measurement.clone()).collect_vec();
+        Self {
+            parameter: samples[0].parameter,
+            measurement: EncodeMeasurement::from_samples(&measurements),
+        }
+    }
+}
+
+impl ToCsv for SweepMeasurement {
+    fn headers() -> Vec<String> {
+        let mut headers = vec!["parameter".to_string()];
+        headers.extend(EncodeMeasurement::headers());
+        headers
+    }
+
+    fn to_csv(&self, mut writer: impl Write) -> std::io::Result<()> {
+        writer.write_all(format!("{},", self.parameter).as_bytes())?;
+        self.measurement.to_csv(writer)
+    }
+}
+
+/// Time it takes to decode an already encoded payload, e.g. a snapshot loaded at startup.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct DecodeMeasurement {
+    #[serde(rename = "elements")]
+    pub num_elements: usize,
+    pub bytes: usize,
+    #[serde(serialize_with = "serialize_nanos")]
+    pub decode_time: Duration,
+}
+
+impl Timings for DecodeMeasurement {
+    fn series() -> Vec<&'static str> {
+        vec!["decode_time"]
+    }
+
+    fn num_elements(&self) -> usize {
+        self.num_elements
+    }
+
+    fn timings(&self) -> Vec<Duration> {
+        vec![self.decode_time]
+    }
+
+    fn with_timings(&self, timings: &[Duration]) -> Self {
+        Self {
+            decode_time: timings[0],
+            ..*self
+        }
+    }
+}
+
+impl DecodeMeasurement {
+    pub fn decode_throughput_elems(&self) -> f64 {
+        per_sec(self.num_elements, self.decode_time)
+    }
+}
+
+impl ToCsv for DecodeMeasurement {
+    fn headers() -> Vec<String> {
+        ["elements", "bytes", "decode_time", "decode_elems_per_sec"]
+            .map(|e| e.to_string())
+            .to_vec()
+    }
+
+    fn to_csv(&self, mut writer: impl Write) -> std::io::Result<()> {
+        writer.write_all(
+            format!(
+                "{},{},{},{}\n",
+                self.num_elements,
+                self.bytes,
+                self.decode_time.as_nanos(),
+                self.decode_throughput_elems()
+            )
+            .as_bytes(),
+        )
+    }
+}
+
+/// Decodes `encoded` without encoding anything first. The readers get a copy of the bytes,
+/// made before the clock starts, so the same corpus can be decoded again and again.
+pub fn measure_decode_only<C: PayloadCodec<Cursor<Vec<u8>>, Vec<u8>>>(
+    codec: &C,
+    encoded: &Data<Vec<u8>>,
+) -> Result<DecodeMeasurement, CodecError> {
+    let bytes = encoded.len();
+    let readers = encoded.as_ref().map(|bytes| Cursor::new(bytes.to_vec()));
+    let (decode_time, decoded) = track_time(|| codec.decode(readers));
+    Ok(DecodeMeasurement {
+        num_elements: decoded?.num_entries(),
+        bytes,
+        decode_time,
+    })
+}
+
+/// Looks up the owner of the middle coin. The coins are sorted by owner before being encoded, as
+/// a snapshot meant for lookups would be, otherwise every row group spans the whole key range and
+/// the statistics can't rule any of them out.
+pub fn measure_lookup(
+    codec: &ParquetCodec,
+    mut coins: Vec<CoinConfig>,
+) -> Result<LookupMeasurement, CodecError> {
+    let num_elements = coins.len();
+    coins.sort_by_key(|coin| coin.owner);
+    let Some(owner) = coins.get(num_elements / 2).map(|coin| coin.owner) else {
+        return Ok(LookupMeasurement {
+            num_elements,
+            full_scan: Duration::ZERO,
+            pruned: Duration::ZERO,
+        });
+    };
+
+    let mut data = Data::with_capacity(0);
+    let payload = Payload {
+        coins,
+        ..Payload::default()
+    };
+    PayloadCodec::<Cursor<Vec<u8>>, _>::encode(codec, &payload, &mut data)?;
+    let encoded = data.coins;
+
+    let (full_scan, found) =
+        track_time(|| codec.scan_for_owner(Cursor::new(encoded.clone()), &owner));
+    found?;
+    let (pruned, found) = track_time(|| codec.find_by_owner(Cursor::new(encoded), &owner));
+    found?;
+    Ok(LookupMeasurement {
+        num_elements,
+        full_scan,
+        pruned,
+    })
+}
+
+/// Encodes `entries` untimed, then decodes every subset with `ParquetCodec::decode_rows_timed`.
+/// The readers are set up before the clock starts.
+pub fn measure_row_conversion(
+    codec: &ParquetCodec,
+    entries: &Payload,
+) -> Result<RowConversionMeasurement, CodecError> {
+    let mut data = Data::with_capacity(0);
+    PayloadCodec::<Cursor<Vec<u8>>, _>::encode(codec, entries, &mut data)?;
+
+    let data = data.wrap_in_cursor();
+    let times = [
+        codec.decode_rows_timed::<CoinConfig>(data.coins)?,
+        codec.decode_rows_timed::<MessageConfig>(data.messages)?,
+        codec.decode_rows_timed::<ContractConfig>(data.contracts)?,
+        codec.decode_rows_timed::<ContractState>(data.contract_state)?,
+        codec.decode_rows_timed::<ContractBalance>(data.contract_balance)?,
+        codec.decode_rows_timed::<ContractStateRoot>(data.contract_state_root)?,
+    ];
+    let raw_rows = times.iter().map(|(rows, _)| *rows).sum();
+    let conversion: Duration = times.iter().map(|(_, conversion)| *conversion).sum();
+
+    Ok(RowConversionMeasurement {
+        num_elements: entries.num_entries(),
+        raw_rows,
+        converted: raw_rows + conversion,
+    })
+}
+
+/// Splits `entries` into `shards` with `Payload::split_into`, encodes every shard on its own and
+/// decodes them back into a single payload with `Payload::merge`, once shard after shard and once
+/// with every shard on its own rayon task. The readers are set up before the clock starts.
+#[cfg(feature = "parallel")]
+pub fn measure_sharded<C>(
+    codec: &C,
+    entries: &Payload,
+    shards: usize,
+) -> Result<ShardMeasurement, CodecError>
+where
+    C: PayloadCodec<Cursor<Vec<u8>>, Vec<u8>> + Sync,
+{
+    use rayon::prelude::*;
+
+    let encoded = entries
+        .clone()
+        .split_into(shards)
+        .iter()
+        .map(|shard| {
+            let mut data = Data::with_capacity(0);
+            codec.encode(shard, &mut data)?;
+            Ok(data)
+        })
+        .collect::<Result<Vec<_>, CodecError>>()?;
+    let readers = || {
+        encoded
+            .iter()
+            .cloned()
+            .map(Data::wrap_in_cursor)
+            .collect_vec()
+    };
+
+    let serial_readers = readers();
+    let (serial, decoded) = track_time(|| {
+        serial_readers
+            .into_iter()
+            .map(|shard| codec.decode(shard))
+            .try_fold(Payload::default(), |merged, shard| {
+                Ok::<_, CodecError>(merged.merge(shard?))
+            })
+    });
+    let _decoded = decoded?;
+    let parallel_readers = readers();
+    let (parallel, decoded) = track_time(|| {
+        let shards = parallel_readers
+            .into_par_iter()
+            .map(|shard| codec.decode(shard))
+            .collect::<Result<Vec<_>, CodecError>>()?;
+        Ok::<_, CodecError>(shards.into_iter().fold(Payload::default(), Payload::merge))
+    });
+    let _decoded = decoded?;
+    Ok(ShardMeasurement {
+        num_elements: entries.num_entries(),
+        shards,
+        serial,
+        parallel,
+    })
+}
+
+/// Time it takes to jump to the last batch and decode it, once with plain batches and once with
+/// every batch gzipped on its own.
+pub fn measure_seek<T: Serialize + DeserializeOwned + Clone>(
+    entries: Vec<T>,
+    batch_size: usize,
+) -> anyhow::Result<SeekMeasurement> {
+    let num_elements = entries.len();
+    let normal = seek_last_batch::<T, _>(InMemorySource::new(entries.clone(), batch_size)?)?;
+    let compressed = seek_last_batch::<T, _>(InMemorySource::with_codec(
+        entries,
+        batch_size,
+        GzipBatchCodec::default(),
+    )?)?;
+    Ok(SeekMeasurement {
+        num_elements,
+        normal,
+        compressed,
+    })
+}
+
+/// Encodes `corpus` and then times appending `increment` to it against encoding both from
+/// scratch. Everything but the encoding itself, including the encoding of the corpus, happens
+/// before the clock starts.
+pub fn measure_append<C>(
+    codec: &C,
+    corpus: &Payload,
+    increment: &Payload,
+) -> Result<AppendMeasurement, CodecError>
+where
+    C: AppendCodec + PayloadCodec<Cursor<Vec<u8>>, Vec<u8>>,
+{
+    let mut existing = Data::with_capacity(0);
+    codec.encode(corpus, &mut existing)?;
+    let (append, appended) = track_time(|| codec.append(increment, &mut existing));
+    appended?;
+
+    let combined = corpus.clone().merge(increment.clone());
+    let mut scratch = Data::with_capacity(0);
+    let (reencode, encoded) = track_time(|| codec.encode(&combined, &mut scratch));
+    encoded?;
+
+    Ok(AppendMeasurement {
+        num_elements: corpus.num_entries(),
+        append,
+        reencode,
+    })
+}
+
+fn seek_last_batch<T: DeserializeOwned, C: BatchCodec + Clone>(
+    source: InMemorySource<C>,
+) -> anyhow::Result<Duration> {
+    let Some(last) = source.batch_cursors().len().checked_sub(1) else {
+        return Ok(Duration::ZERO);
+    };
+    let codec = source.codec();
+    let mut reader = StateReader::with_codec(source, 0, codec)?;
+    let (time, batch) = track_time(|| reader.read_batch_at::<T>(last));
+    batch?;
+    Ok(time)
+}
+
+pub fn track_time<T>(action: impl FnOnce() -> T) -> (Duration, T) {
+    let start = Instant::now();
+    let ret = action();
+    (Instant::now() - start, ret)
+}
+
+/// Time it takes to reach the end of a payload written out to a real file, once as the plain
+/// encoded subsets and once compressed with `algo`. The plain file is a single `seek`, the
+/// compressed one has to be decompressed in full to find its end. Both files are synced before
+/// timing but the page cache stays warm, so this is the cost of the filesystem calls rather than
+/// of the disk.
+pub fn measure_file_seek(
+    codec: &impl PayloadCodec<Cursor<Vec<u8>>, Vec<u8>>,
+    entries: &Payload,
+    algo: CompressionAlgo,
+) -> Result<SeekMeasurement, CodecError> {
+    let mut data = Data::with_capacity(0);
+    codec.encode(entries, &mut data)?;
+
+    let mut plain = tempfile::NamedTempFile::new()?;
+    for subset in data.as_array() {
+        plain.write_all(subset)?;
+    }
+    plain.as_file().sync_data()?;
+
+    let compressed = tempfile::NamedTempFile::new()?;
+    let mut compressor = Compressor::new(compressed.reopen()?, algo)?;
+    for subset in data.as_array() {
+        compressor.write_all(subset)?;
+    }
+    compressor.finish()?.sync_data()?;
+
+    let (normal, end) = track_time(|| {
+        let mut file = File::open(plain.path())?;
+        file.seek(SeekFrom::End(0))
+    });
+    end?;
+    let (compressed_time, scanned) = track_time(|| {
+        let file = File::open(compressed.path())?;
+        let mut decompressor = Decompressor::new(BufReader::new(file), algo)?;
+        std::io::copy(&mut decompressor, &mut std::io::sink())
+    });
+    scanned?;
+
+    Ok(SeekMeasurement {
+        num_elements: entries.num_entries(),
+        normal,
+        compressed: compressed_time,
+    })
+}
+
+/// Payload sizes of a sweep, one every `step` from 0 up to `max`, exclusive.
+pub fn sweep_repeats(max: usize, step: usize) -> Vec<usize> {
+    (0..max).step_by(step).collect()
+}
+
+/// Payload sizes doubling from `step` for as long as they stay below `max`. None at all for a zero
+/// `step`, which would never grow.
+pub fn geometric_repeats(max: usize, step: usize) -> Vec<usize> {
+    if step == 0 {
+        return vec![];
+    }
+    std::iter::successors(Some(step), |repeat| repeat.checked_mul(2))
+        .take_while(|&repeat| repeat < max)
+        .collect()
+}
+
+/// Generous guess of the encoded size of a single entry. Json of a contract, the most verbose
+/// entry, is well below it.
+const ENCODED_ENTRY_ESTIMATE: usize = 512;
+
+/// Capacity of each output buffer so that the largest payload of a sweep up to `max` fits without
+/// reallocating.
+fn buffer_capacity(max: usize) -> usize {
+    let config = PayloadConfig::new(max);
+    let largest_subset = [
+        config.coins,
+        config.messages,
+        config.contracts,
+        config.contract_state,
+        config.contract_balance,
+        config.contract_state_root,
+    ]
+    .into_iter()
+    .max()
+    .unwrap_or_default();
+    largest_subset * ENCODED_ENTRY_ESTIMATE
+}
+
+/// Where the output buffers of every measurement come from.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum BufferStrategy {
+    /// One set of buffers is cleared and refilled by every measurement. Once warmed up it's
+    /// never reallocated, so the timings leave allocation out.
+    #[default]
+    Reuse,
+    /// Every measurement gets buffers of its own. They're reserved before the clock starts but
+    /// first touching the fresh pages happens while encoding, so the timings include it.
+    Fresh,
+}
+
+/// Label, payloads measured so far, payloads in the sweep.
+type ProgressFn = dyn FnMut(&str, usize, usize);
+
+pub struct MeasurementRunner {
+    step: usize,
+    max: usize,
+    seed: u64,
+    rng: PayloadRng,
+    samples: usize,
+    warmup: usize,
+    buffer_capacity: usize,
+    buffer_strategy: BufferStrategy,
+    data: Data<Vec<u8>>,
+    code_len: RangeInclusive<usize>,
+    label: String,
+    progress: Option<Box<ProgressFn>>,
+}
+
+impl MeasurementRunner {
+    /// `warmup` encode/decode cycles are run on the largest payload before every `run`/`run_compressed`
+    /// so cold caches and a fresh allocator don't skew the first points. Their output is never returned.
+    /// The output buffers are sized from `max`, see `with_buffer_capacity` to override it, and
+    /// reused by every run, see `with_buffer_strategy`.
+    pub fn new(max: usize, step: usize, warmup: usize) -> Self {
+        let buffer_capacity = buffer_capacity(max);
+        Self {
+            data: Data::with_capacity(buffer_capacity),
+            buffer_capacity,
+            buffer_strategy: BufferStrategy::default(),
+            step,
+            max,
+            seed: rand::random(),
+            rng: PayloadRng::default(),
+            samples: 1,
+            warmup,
+            code_len: DEFAULT_CODE_LEN..=DEFAULT_CODE_LEN,
+            label: String::new(),
+            progress: None,
+        }
+    }
+
+    /// How many times each payload size is measured. Must be at least 1.
+    pub fn with_samples(mut self, samples: usize) -> Self {
+        assert!(samples > 0, "need at least one sample");
+        self.samples = samples;
+        self
+    }
+
+    /// Bytes reserved up front for each of the output buffers. They still grow past it if
+    /// the estimate falls short.
+    pub fn with_buffer_capacity(mut self, bytes: usize) -> Self {
+        self.buffer_capacity = bytes;
+        self.data = Data::with_capacity(bytes);
+        self
+    }
+
+    /// Whether the encoding runs share one set of output buffers or allocate their own.
+    pub fn with_buffer_strategy(mut self, strategy: BufferStrategy) -> Self {
+        self.buffer_strategy = strategy;
+        self
+    }
+
+    /// Called with the current label, the items measured so far and the items in the sweep after
+    /// every payload, or parameter of `run_sweep`, that `sample_sweep` measures. Every `run_*`
+    /// goes through it. Nothing is reported unless set.
+    pub fn with_progress(mut self, progress: impl FnMut(&str, usize, usize) + 'static) -> Self {
+        self.progress = Some(Box::new(progress));
+        self
+    }
+
+    /// Handed to the progress callback, e.g. the name of the codec about to be measured.
+    pub fn set_label(&mut self, label: impl Into<String>) {
+        self.label = label.into();
+    }
+
+    /// Use a fixed seed for the generated payloads instead of a random one.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// The generator the payloads are drawn from, see `PayloadRng`.
+    pub fn with_rng(mut self, rng: PayloadRng) -> Self {
+        self.rng = rng;
+        self
+    }
+
+    /// Bytes of code of every generated contract, see `PayloadConfig::code_len`. The output
+    /// buffers are sized for the default, longer code makes them grow during the first run.
+    pub fn with_code_len(mut self, code_len: RangeInclusive<usize>) -> Self {
+        self.code_len = code_len;
+        self
+    }
+
+    /// The seed every payload is generated from. Pass it to `with_seed` to repeat a run.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Empty output buffers for the next measurement.
+    fn buffers(&mut self) -> &mut Data<Vec<u8>> {
+        match self.buffer_strategy {
+            BufferStrategy::Reuse => self.data.clear(),
+            BufferStrategy::Fresh => self.data = Data::with_capacity(self.buffer_capacity),
+        }
+        &mut self.data
+    }
+
+    /// The config of every payload with `repeat` the runner measures.
+    pub fn payload_config(&self, repeat: usize) -> PayloadConfig {
+        PayloadConfig::new(repeat)
+            .with_seed(self.seed)
+            .with_rng(self.rng)
+            .with_code_len(self.code_len.clone())
+    }
+
+    /// Every payload of the sweep, generated as it's iterated. Doesn't borrow the runner so the
+    /// measurements can take its buffers.
+    fn payloads(&self) -> impl DoubleEndedIterator<Item = Payload> {
+        self.payloads_of(sweep_repeats(self.max, self.step))
+    }
+
+    fn payloads_of(
+        &self,
+        repeats: impl IntoIterator<Item = usize>,
+    ) -> impl DoubleEndedIterator<Item = Payload> + ExactSizeIterator {
+        let configs = repeats
+            .into_iter()
+            .map(|repeat| self.payload_config(repeat))
+            .collect_vec();
+        configs.into_iter().map(|config| payload_with(&config))
+    }
+
+    fn largest_payload(&self) -> Option<Payload> {
+        self.payloads().next_back()
+    }
+
+    /// Measures every item `self.samples` times and collapses each item's samples into one
+    /// measurement, reporting progress after every item. `warmup` is measured `self.warmup` times
+    /// first and thrown away.
+    fn sample_sweep<I, S, M, E>(
+        &mut self,
+        warmup: Option<I>,
+        items: impl ExactSizeIterator<Item = I>,
+        mut measure: impl FnMut(&mut Data<Vec<u8>>, &I) -> Result<S, E>,
+        collapse: fn(&[S]) -> M,
+    ) -> Result<Vec<M>, E> {
+        if let Some(item) = warmup {
+            for _ in 0..self.warmup {
+                measure(self.buffers(), &item)?;
+            }
+        }
+
+        let total = items.len();
+        items
+            .enumerate()
+            .map(|(idx, item)| {
+                let samples = (0..self.samples)
+                    .map(|_| measure(self.buffers(), &item))
+                    .collect::<Result<Vec<_>, _>>()?;
+                if let Some(progress) = &mut self.progress {
+                    progress(&self.label, idx + 1, total);
+                }
+                Ok(collapse(&samples))
+            })
+            .collect()
+    }
+
+    /// `sample_sweep` over the payloads of the sweep, warming up on the largest.
+    fn sample_payloads<S, M, E>(
+        &mut self,
+        measure: impl FnMut(&mut Data<Vec<u8>>, &Payload) -> Result<S, E>,
+        collapse: fn(&[S]) -> M,
+    ) -> Result<Vec<M>, E> {
+        self.sample_repeats(sweep_repeats(self.max, self.step), measure, collapse)
+    }
+
+    /// `sample_sweep` over a payload of every size in `repeats`, warming up on the last one.
+    fn sample_repeats<S, M, E>(
+        &mut self,
+        repeats: Vec<usize>,
+        measure: impl FnMut(&mut Data<Vec<u8>>, &Payload) -> Result<S, E>,
+        collapse: fn(&[S]) -> M,
+    ) -> Result<Vec<M>, E> {
+        let warmup = self.payloads_of(repeats.last().copied()).next();
+        let payloads = self.payloads_of(repeats);
+        self.sample_sweep(warmup, payloads, measure, collapse)
+    }
+
+    pub fn run_compressed<
+        C: for<'a> PayloadCodec<BufReader<Decompressor<&'a [u8]>>, Compressor<&'a mut Vec<u8>>>,
+    >(
+        &mut self,
+        codec: &C,
+        algo: CompressionAlgo,
+    ) -> Result<Vec<EncodeMeasurement>, CodecError> {
+        self.sample_payloads(
+            |buffers, entries| measure_compressed(codec, buffers, entries, algo),
+            EncodeMeasurement::from_samples,
+        )
+    }
+
+    /// `run_compressed` with snappy's framed format. The decompressor is buffered just like the
+    /// other algorithms so the codecs' `fill_buf` loops keep working.
+    pub fn run_snappy<
+        C: for<'a> PayloadCodec<BufReader<Decompressor<&'a [u8]>>, Compressor<&'a mut Vec<u8>>>,
+    >(
+        &mut self,
+        codec: &C,
+    ) -> Result<Vec<EncodeMeasurement>, CodecError> {
+        self.run_compressed(codec, CompressionAlgo::Snappy)
+    }
+
+    pub fn run<C: PayloadCodec<Cursor<Vec<u8>>, Vec<u8>>>(
+        &mut self,
+        codec: &C,
+    ) -> Result<Vec<EncodeMeasurement>, CodecError> {
+        self.run_repeats(codec, sweep_repeats(self.max, self.step))
+    }
+
+    /// `run` over payloads doubling in size from `step` up to `max`, instead of one every `step`.
+    /// Small payloads, where parquet's curve bends, are sampled as densely as ever while the
+    /// large ones, each slower to measure than the last, thin out.
+    pub fn run_geometric<C: PayloadCodec<Cursor<Vec<u8>>, Vec<u8>>>(
+        &mut self,
+        codec: &C,
+    ) -> Result<Vec<EncodeMeasurement>, CodecError> {
+        self.run_repeats(codec, geometric_repeats(self.max, self.step))
+    }
+
+    /// Measures a payload of every size in `repeats`, warming up on the last one.
+    fn run_repeats<C: PayloadCodec<Cursor<Vec<u8>>, Vec<u8>>>(
+        &mut self,
+        codec: &C,
+        repeats: Vec<usize>,
+    ) -> Result<Vec<EncodeMeasurement>, CodecError> {
+        self.sample_repeats(
+            repeats,
+            |buffers, entries| measure_normal(codec, buffers, entries),
+            EncodeMeasurement::from_samples,
+        )
+    }
+
+    /// `run` without the decoding, see `measure_encode_only`.
+    pub fn run_encode_only<C: PayloadCodec<Cursor<Vec<u8>>, Vec<u8>>>(
+        &mut self,
+        codec: &C,
+    ) -> Result<Vec<EncodeMeasurement>, CodecError> {
+        self.sample_payloads(
+            |buffers, entries| measure_encode_only(codec, buffers, entries),
+            EncodeMeasurement::from_samples,
+        )
+    }
+
+    /// `run` with the payloads encoded as a single stream of `StateEntry`s.
+    pub fn run_entries<C: EntryCodec<Cursor<Vec<u8>>, Vec<u8>>>(
+        &mut self,
+        codec: &C,
+    ) -> Result<Vec<EncodeMeasurement>, CodecError> {
+        self.sample_payloads(
+            |_, entries| measure_entries(codec, entries),
+            EncodeMeasurement::from_samples,
+        )
+    }
+
+    #[cfg(feature = "parallel")]
+    pub fn run_parallel<C: PayloadCodec<Cursor<Vec<u8>>, Vec<u8>> + Sync>(
+        &mut self,
+        codec: &C,
+    ) -> Result<Vec<EncodeMeasurement>, CodecError> {
+        self.sample_payloads(
+            |buffers, entries| measure_normal_parallel(codec, buffers, entries),
+            EncodeMeasurement::from_samples,
+        )
+    }
+
+    pub fn run_per_type<C: PayloadCodec<Cursor<Vec<u8>>, Vec<u8>>>(
+        &mut self,
+        codec: &C,
+    ) -> Result<Vec<PerTypeMeasurement>, CodecError> {
+        self.sample_payloads(
+            |buffers, entries| measure_normal_per_type(codec, buffers, entries),
+            PerTypeMeasurement::from_samples,
+        )
+    }
+
+    /// Measures the largest payload once per parameter, encoded by `codec(parameter)`. Unlike
+    /// `run` the payload stays put and the codec changes, so the parameter's effect is all that's
+    /// left in the results. Empty if there's no payload to measure.
+    pub fn run_sweep<C: PayloadCodec<Cursor<Vec<u8>>, Vec<u8>>>(
+        &mut self,
+        parameters: &[usize],
+        codec: impl Fn(usize) -> Result<C, CodecError>,
+    ) -> Result<Vec<SweepMeasurement>, CodecError> {
+        let Some(entries) = self.largest_payload() else {
+            return Ok(vec![]);
+        };
+        self.sample_sweep(
+            parameters.last().copied(),
+            parameters.iter().copied(),
+            |buffers, &parameter| {
+                Ok(SweepMeasurement {
+                    parameter,
+                    measurement: measure_normal(&codec(parameter)?, buffers, &entries)?,
+                })
+            },
+            SweepMeasurement::from_samples,
+        )
+    }
+
+    /// Only times decoding every payload, see `measure_decode_only`. Each sample encodes the
+    /// payload again first, untimed, into the runner's buffers.
+    pub fn run_decode_only<C: PayloadCodec<Cursor<Vec<u8>>, Vec<u8>>>(
+        &mut self,
+        codec: &C,
+    ) -> Result<Vec<DecodeMeasurement>, CodecError> {
+        self.sample_payloads(
+            |encoded, entries| {
+                codec.encode(entries, encoded)?;
+                measure_decode_only(codec, encoded)
+            },
+            DecodeMeasurement::from_samples,
+        )
+    }
+
+    /// Sweeps the coins of each payload through `measure_lookup`.
+    pub fn run_lookup(
+        &mut self,
+        codec: &ParquetCodec,
+    ) -> Result<Vec<LookupMeasurement>, CodecError> {
+        self.sample_payloads(
+            |_, entries| measure_lookup(codec, entries.coins.clone()),
+            LookupMeasurement::from_samples,
+        )
+    }
+
+    pub fn run_row_conversion(
+        &mut self,
+        codec: &ParquetCodec,
+    ) -> Result<Vec<RowConversionMeasurement>, CodecError> {
+        self.sample_payloads(
+            |_, entries| measure_row_conversion(codec, entries),
+            RowConversionMeasurement::from_samples,
+        )
+    }
+
+    /// Sweeps every payload, split into `shards`, through `measure_sharded`.
+    #[cfg(feature = "parallel")]
+    pub fn run_sharded<C>(
+        &mut self,
+        codec: &C,
+        shards: usize,
+    ) -> Result<Vec<ShardMeasurement>, CodecError>
+    where
+        C: PayloadCodec<Cursor<Vec<u8>>, Vec<u8>> + Sync,
+    {
+        self.sample_payloads(
+            |_, entries| measure_sharded(codec, entries, shards),
+            ShardMeasurement::from_samples,
+        )
+    }
+
+    /// Sweeps every payload through `measure_file_seek`.
+    pub fn run_file_seek<C: PayloadCodec<Cursor<Vec<u8>>, Vec<u8>>>(
+        &mut self,
+        codec: &C,
+        algo: CompressionAlgo,
+    ) -> Result<Vec<SeekMeasurement>, CodecError> {
+        self.sample_payloads(
+            |_, entries| measure_file_seek(codec, entries, algo),
+            SeekMeasurement::from_samples,
+        )
+    }
+
+    /// Appends a payload of `increment` repeats to every payload of the sweep, see
+    /// `measure_append`. The increment is generated with the next seed so it doesn't repeat the
+    /// corpus.
+    pub fn run_append<C>(
+        &mut self,
+        codec: &C,
+        increment: usize,
+    ) -> Result<Vec<AppendMeasurement>, CodecError>
+    where
+        C: AppendCodec + PayloadCodec<Cursor<Vec<u8>>, Vec<u8>>,
+    {
+        let increment = payload_with(
+            &self
+                .payload_config(increment)
+                .with_seed(self.seed.wrapping_add(1)),
+        );
+        self.sample_payloads(
+            |_, corpus| measure_append(codec, corpus, &increment),
+            AppendMeasurement::from_samples,
+        )
+    }
+
+    /// Sweeps the coins of each payload through `measure_seek`, cut into batches of `batch_size`.
+    pub fn run_seek(&mut self, batch_size: usize) -> anyhow::Result<Vec<SeekMeasurement>> {
+        self.sample_payloads(
+            |_, entries| measure_seek(entries.coins.clone(), batch_size),
+            SeekMeasurement::from_samples,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encoding::{BincodeCodec, ParquetCompression};
+
+    #[test]
+    fn csv_starts_with_the_measurement_headers() {
+        // given
+        let measurements = [EncodeMeasurement::new(
+            10,
+            100,
+            Duration::from_nanos(5),
+            Duration::from_nanos(10),
+        )];
+        let mut csv = vec![];
+
+        // when
+        measurements.iter().collect_csv(&mut csv).unwrap();
+
+        // then
+        let csv = String::from_utf8(csv).unwrap();
+        let expected = format!(
+            "{}\n10,100,5,10,5,5,0,10,10,0,2000000000,1000000000,20000000000,\n",
+            EncodeMeasurement::headers().join(",")
+        );
+        pretty_assertions::assert_eq!(csv, expected);
+    }
+
+    #[test]
+    fn json_durations_match_the_csv_nanoseconds() {
+        // given
+        let measurement =
+            EncodeMeasurement::new(10, 100, Duration::from_nanos(5), Duration::from_nanos(7));
+
+        // when
+        let json = serde_json::to_value(&measurement).unwrap();
+
+        // then
+        let expected = serde_json::json!({
+            "elements": 10,
+            "bytes": 100,
+            "encode_time": 5,
+            "decode_time": 7,
+            "encode_stats": {"min": 5, "median": 5, "mean": 5, "stddev": 0},
+            "decode_stats": {"min": 7, "median": 7, "mean": 7, "stddev": 0},
+            "peak_bytes": null,
+        });
+        pretty_assertions::assert_eq!(json, expected);
+    }
+
+    #[test]
+    fn a_skipped_decode_is_left_empty() {
+        // given
+        let measurement = EncodeMeasurement::encode_only(10, 100, Duration::from_nanos(5));
+
+        // when
+        let mut csv = vec![];
+        measurement.to_csv(&mut csv).unwrap();
+        let json = serde_json::to_value(&measurement).unwrap();
+
+        // then
+        pretty_assertions::assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "10,100,5,,5,5,0,,,,2000000000,,20000000000,\n"
+        );
+        pretty_assertions::assert_eq!(json["decode_time"], serde_json::Value::Null);
+        pretty_assertions::assert_eq!(json["decode_stats"], serde_json::Value::Null);
+    }
+
+    #[test]
+    fn stats_over_samples() {
+        // given
+        let samples = [4, 1, 3, 2].map(Duration::from_secs);
+
+        // when
+        let stats = TimingStats::from_samples(&samples);
+
+        // then
+        let expected = TimingStats {
+            min: Duration::from_secs(1),
+            median: Duration::from_millis(2500),
+            mean: Duration::from_millis(2500),
+            stddev: Duration::from_secs_f64(1.25f64.sqrt()),
+        };
+        pretty_assertions::assert_eq!(stats, expected);
+    }
+
+    #[test]
+    fn timings_are_sampled_into_their_medians_one_by_one() {
+        // given
+        let shard = |serial, parallel| ShardMeasurement {
+            num_elements: 10,
+            shards: 2,
+            serial: Duration::from_secs(serial),
+            parallel: Duration::from_secs(parallel),
+        };
+        let samples = [shard(1, 9), shard(3, 7), shard(2, 8)];
+
+        // when
+        let sampled = ShardMeasurement::from_samples(&samples);
+
+        // then
+        pretty_assertions::assert_eq!(
+            sampled.timings(),
+            vec![Duration::from_secs(2), Duration::from_secs(8)]
+        );
+        pretty_assertions::assert_eq!((sampled.num_elements, sampled.shards), (10, 2));
+    }
+
+    #[test]
+    fn throughput_of_an_untimed_measurement_is_zero() {
+        // given
+        let measurement = EncodeMeasurement::new(10, 100, Duration::ZERO, Duration::ZERO);
+
+        // when
+        let throughput = [
+            measurement.encode_throughput_elems(),
+            measurement.decode_throughput_elems(),
+            measurement.bytes_per_sec(),
+        ];
+
+        // then
+        pretty_assertions::assert_eq!(throughput, [0f64; 3]);
+    }
+
+    #[test]
+    fn per_type_measurement_splits_the_payload() {
+        // given
+        let entries = payload_with(&PayloadConfig::new(10).with_seed(3));
+        let expected_elements = [
+            entries.coins.len(),
+            entries.messages.len(),
+            entries.contracts.len(),
+            entries.contract_state.len(),
+            entries.contract_balance.len(),
+            entries.contract_state_root.len(),
+        ];
+        let total = measure_normal(
+            &BincodeCodec::default(),
+            &mut Data::with_capacity(0),
+            &entries,
+        )
+        .unwrap();
+
+        // when
+        let measurement = measure_normal_per_type(
+            &BincodeCodec::default(),
+            &mut Data::with_capacity(0),
+            &entries,
+        )
+        .unwrap();
+
+        // then
+        let elements = measurement.by_type().map(|(_, m)| m.num_elements);
+        let bytes: usize = measurement.by_type().iter().map(|(_, m)| m.bytes).sum();
+        pretty_assertions::assert_eq!(elements, expected_elements);
+        pretty_assertions::assert_eq!(bytes, total.bytes);
+    }
+
+    #[test]
+    fn only_parquet_spends_time_opening_a_subset() {
+        // given
+        let entries = payload_with(&PayloadConfig::new(30).with_seed(3));
+        let parquet = ParquetCodec::new(100, ParquetCompression::Zstd(1));
+
+        // when
+        let parquet =
+            measure_normal_per_type(&parquet, &mut Data::with_capacity(0), &entries).unwrap();
+        let bincode = measure_normal_per_type(
+            &BincodeCodec::default(),
+            &mut Data::with_capacity(0),
+            &entries,
+        )
+        .unwrap();
+
+        // then
+        for (open, (_, measurement)) in zip(parquet.decode_open.into_array(), parquet.by_type()) {
+            assert!(!open.is_zero());
+            assert!(Some(open) <= measurement.decode_time);
+        }
+        pretty_assertions::assert_eq!(bincode.decode_open, Data::from_array([Duration::ZERO; 6]));
+        let mut csv = vec![];
+        [parquet].iter().collect_csv(&mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        pretty_assertions::assert_eq!(
+            csv.lines()
+                .map(|line| line.split(',').count())
+                .collect_vec(),
+            [13, 13]
+        );
+    }
+
+    #[test]
+    fn estimated_size_matches_the_encoded_size() {
+        // given
+        let entries = payload_with(&PayloadConfig::new(30).with_seed(3));
+        let measured = measure_normal(
+            &BincodeCodec::default(),
+            &mut Data::with_capacity(0),
+            &entries,
+        )
+        .unwrap();
+
+        // when
+        let estimate = PayloadCodec::<Cursor<Vec<u8>>, Vec<u8>>::estimate_size(
+            &BincodeCodec::default(),
+            &entries,
+        )
+        .unwrap();
+
+        // then
+        let total: usize = estimate.as_array().into_iter().sum();
+        pretty_assertions::assert_eq!(total, measured.bytes);
+    }
+
+    #[test]
+    fn runner_buffers_scale_with_max() {
+        // given
+        let small = MeasurementRunner::new(30, 10, 0);
+        let large = MeasurementRunner::new(60_000, 10, 0);
+
+        // when
+        let small_capacity = small.data.capacity();
+        let large_capacity = large.data.capacity();
+
+        // then
+        // contract state, four slots per contract, is the largest subset
+        pretty_assertions::assert_eq!(small_capacity, 6 * 40 * ENCODED_ENTRY_ESTIMATE);
+        pretty_assertions::assert_eq!(large_capacity, 6 * 80_000 * ENCODED_ENTRY_ESTIMATE);
+    }
+
+    #[test]
+    fn runner_buffer_capacity_can_be_overridden() {
+        // given
+        let runner = MeasurementRunner::new(300_000, 10, 0);
+
+        // when
+        let runner = runner.with_buffer_capacity(1024);
+
+        // then
+        pretty_assertions::assert_eq!(runner.data.capacity(), 6 * 1024);
+    }
+
+    #[test]
+    fn reused_buffers_are_cleared_between_payloads() {
+        // given
+        let mut fresh = MeasurementRunner::new(30, 10, 1)
+            .with_seed(5)
+            .with_buffer_strategy(BufferStrategy::Fresh);
+        let mut reused = MeasurementRunner::new(30, 10, 1)
+            .with_seed(5)
+            .with_samples(2)
+            .with_buffer_strategy(BufferStrategy::Reuse);
+
+        // when
+        let fresh = fresh.run(&BincodeCodec::default()).unwrap();
+        let reused = reused.run(&BincodeCodec::default()).unwrap();
+
+        // then
+        let fresh_bytes = fresh.iter().map(|m| m.bytes).collect_vec();
+        let reused_bytes = reused.iter().map(|m| m.bytes).collect_vec();
+        pretty_assertions::assert_eq!(reused_bytes, fresh_bytes);
+    }
+
+    #[test]
+    fn encode_only_runner_leaves_the_decode_phase_out() {
+        // given
+        let mut runner = MeasurementRunner::new(30, 10, 1).with_seed(5);
+        let expected = runner.run(&BincodeCodec::default()).unwrap();
+
+        // when
+        let measurements = runner.run_encode_only(&BincodeCodec::default()).unwrap();
+
+        // then
+        let sizes = measurements
+            .iter()
+            .map(|m| (m.num_elements, m.bytes, m.decode_time.is_some()))
+            .collect_vec();
+        let expected = expected
+            .iter()
+            .map(|m| (m.num_elements, m.bytes, false))
+            .collect_vec();
+        pretty_assertions::assert_eq!(sizes, expected);
+        assert!(measurements[1..].iter().all(|m| !m.encode_time.is_zero()));
+    }
+
+    #[test]
+    fn decode_only_runner_decodes_every_payload() {
+        // given
+        let mut runner = MeasurementRunner::new(30, 10, 1)
+            .with_seed(5)
+            .with_samples(3);
+        let expected = runner.run(&BincodeCodec::default()).unwrap();
+
+        // when
+        let measurements = runner.run_decode_only(&BincodeCodec::default()).unwrap();
+
+        // then
+        let sizes = measurements
+            .iter()
+            .map(|m| (m.num_elements, m.bytes))
+            .collect_vec();
+        let expected = expected
+            .iter()
+            .map(|m| (m.num_elements, m.bytes))
+            .collect_vec();
+        pretty_assertions::assert_eq!(sizes, expected);
+    }
+
+    #[test]
+    fn histogram_counts_every_time_once() {
+        // given
+        let times = [10, 12, 19, 20, 53].map(Duration::from_nanos);
+
+        // when
+        let buckets = histogram(&times, 4);
+
+        // then
+        let buckets = buckets
+            .iter()
+            .map(|b| (b.start.as_nanos(), b.end.as_nanos(), b.count))
+            .collect_vec();
+        pretty_assertions::assert_eq!(
+            buckets,
+            vec![(10, 20, 3), (20, 30, 1), (30, 40, 0), (40, 53, 1)]
+        );
+    }
+
+    #[test]
+    fn every_record_is_timed() {
+        // given
+        /// Keeps every call it gets, with what the buffer held when it got it.
+        struct Recorder(std::cell::RefCell<Vec<(Vec<ContractConfig>, usize)>>);
+        impl EncodeEntries<ContractConfig> for Recorder {
+            fn encode_entries(
+                &self,
+                data: &[ContractConfig],
+                writer: &mut Vec<u8>,
+            ) -> Result<(), CodecError> {
+                self.0.borrow_mut().push((data.to_vec(), writer.len()));
+                writer.extend_from_slice(&[0; 8]);
+                Ok(())
+            }
+        }
+        let contracts = crate::test_support::contracts(20, 1);
+        let recorder = Recorder(Default::default());
+
+        // when
+        let times = measure_per_record(&recorder, &contracts).unwrap();
+
+        // then
+        pretty_assertions::assert_eq!(times.len(), contracts.len());
+        let expected = contracts
+            .iter()
+            .map(|contract| (vec![contract.clone()], 0))
+            .collect_vec();
+        pretty_assertions::assert_eq!(recorder.0.into_inner(), expected);
+    }
+
+    #[test]
+    fn seek_runner_sweeps_the_coins() {
+        // given
+        let mut runner = MeasurementRunner::new(30, 10, 0).with_seed(5);
+
+        // when
+        let measurements = runner.run_seek(2).unwrap();
+
+        // then
+        let elements = measurements.iter().map(|m| m.num_elements).collect_vec();
+        pretty_assertions::assert_eq!(elements, vec![0, 3, 6]);
+    }
+
+    #[test]
+    fn append_runner_measures_every_corpus_size() {
+        // given
+        let mut runner = MeasurementRunner::new(30, 10, 0).with_seed(5);
+        let expected = runner
+            .run(&BincodeCodec::default())
+            .unwrap()
+            .iter()
+            .map(|m| m.num_elements)
+            .collect_vec();
+
+        // when
+        let measurements = runner.run_append(&BincodeCodec::default(), 5).unwrap();
+
+        // then
+        let elements = measurements.iter().map(|m| m.num_elements).collect_vec();
+        pretty_assertions::assert_eq!(elements, expected);
+    }
+
+    #[test]
+    fn step_rng_payloads_dont_depend_on_the_seed() {
+        // given
+        let runner = |seed| {
+            MeasurementRunner::new(30, 10, 0)
+                .with_seed(seed)
+                .with_rng(PayloadRng::Step)
+        };
+
+        // when
+        let first = runner(1).payloads().collect_vec();
+        let second = runner(2).payloads().collect_vec();
+
+        // then
+        pretty_assertions::assert_eq!(first, second);
+    }
+
+    #[test]
+    fn geometric_runner_doubles_the_payload_up_to_max() {
+        // given
+        let mut runner = MeasurementRunner::new(100, 10, 1).with_seed(5);
+
+        // when
+        let measurements = runner.run_geometric(&BincodeCodec::default()).unwrap();
+
+        // then
+        let elements = measurements.iter().map(|m| m.num_elements).collect_vec();
+        let expected = [10, 20, 40, 80]
+            .map(|repeat| payload_with(&PayloadConfig::new(repeat).with_seed(5)).num_entries())
+            .to_vec();
+        pretty_assertions::assert_eq!(elements, expected);
+    }
+
+    #[test]
+    fn geometric_sweep_of_a_zero_step_is_empty() {
+        // when
+        let repeats = geometric_repeats(10, 0);
+
+        // then
+        assert!(repeats.is_empty());
+    }
+
+    #[test]
+    fn file_seek_runner_counts_every_entry() {
+        // given
+        let mut runner = MeasurementRunner::new(30, 10, 0).with_seed(5);
+        let algo = CompressionAlgo::Zstd(1);
+
+        // when
+        let measurements = runner
+            .run_file_seek(&BincodeCodec::default(), algo)
+            .unwrap();
+
+        // then
+        let elements = measurements.iter().map(|m| m.num_elements).collect_vec();
+        let expected = [0, 10, 20]
+            .map(|repeat| payload_with(&PayloadConfig::new(repeat).with_seed(5)).num_entries())
+            .to_vec();
+        pretty_assertions::assert_eq!(elements, expected);
+    }
+
+    #[test]
+    fn lookup_runner_sweeps_the_coins() {
+        // given
+        let mut runner = MeasurementRunner::new(30, 10, 0).with_seed(5);
+        let codec = ParquetCodec::new(2, crate::encoding::ParquetCompression::Uncompressed)
+            .with_bloom_filter("owner");
+
+        // when
+        let measurements = runner.run_lookup(&codec).unwrap();
+
+        // then
+        let elements = measurements.iter().map(|m| m.num_elements).collect_vec();
+        pretty_assertions::assert_eq!(elements, vec![0, 3, 6]);
+    }
+
+    #[test]
+    fn row_conversion_runner_sweeps_the_payloads() {
+        // given
+        let mut runner = MeasurementRunner::new(30, 10, 0).with_seed(5);
+        let codec = ParquetCodec::new(2, crate::encoding::ParquetCompression::Uncompressed);
+
+        // when
+        let measurements = runner.run_row_conversion(&codec).unwrap();
+
+        // then
+        let elements = measurements.iter().map(|m| m.num_elements).collect_vec();
+        let expected = [0, 10, 20]
+            .map(|repeat| payload_with(&PayloadConfig::new(repeat).with_seed(5)).num_entries())
+            .to_vec();
+        pretty_assertions::assert_eq!(elements, expected);
+        assert!(measurements.iter().all(|m| m.raw_rows <= m.converted));
+    }
+
+    #[test]
+    fn quadratic_fit_predicts_a_quadratic() {
+        // given
+        let quadratic = |x: usize| 1000 + 2 * x + 3 * x * x;
+        let measurements = (1..=10)
+            .map(|x| {
+                let time = Duration::from_secs(quadratic(x) as u64);
+                EncodeMeasurement::new(x, quadratic(x), time, time)
+            })
+            .collect_vec();
+
+        // when
+        let predicted = measurements.polynomial_regression(2, 20, 10, 40);
+
+        // then
+        let bytes = predicted.iter().map(|m| m.bytes).collect_vec();
+        let expected = [20, 30, 40].map(quadratic).to_vec();
+        pretty_assertions::assert_eq!(bytes, expected);
+    }
+
+    #[test]
+    fn exact_line_fits_perfectly() {
+        // given
+        let measurements = (1..=10)
+            .map(|x| {
+                let time = Duration::from_millis(5 * x as u64 + 3);
+                EncodeMeasurement::new(x, 7 * x + 100, time, time)
+            })
+            .collect_vec();
+
+        // when
+        let predicted = measurements.linear_regression(0, 10, 100);
+
+        // then
+        assert!(predicted.worst_rsquared() > 0.999_999);
+        pretty_assertions::assert_eq!(predicted.summaries.len(), 3);
+    }
+
+    #[test]
+    fn single_point_predicts_flat_line() {
+        // given
+        let measurements = vec![EncodeMeasurement::new(
+            10,
+            100,
+            Duration::from_secs(1),
+            Duration::from_secs(2),
+        )];
+
+        // when
+        let predicted = measurements.linear_regression(0, 50, 100);
+
+        // then
+        let bytes = predicted.iter().map(|m| m.bytes).collect_vec();
+        pretty_assertions::assert_eq!(bytes, vec![100, 100, 100]);
+        assert!(predicted
+            .summaries
+            .iter()
+            .all(|(_, summary)| !summary.is_fitted()));
+    }
+
+    #[test]
+    fn identical_values_do_not_panic() {
+        // given
+        let measurements = (1..=5)
+            .map(|x| EncodeMeasurement::new(x, 100, Duration::ZERO, Duration::ZERO))
+            .collect_vec();
+
+        // when
+        let predicted = measurements.linear_regression(0, 50, 100);
+
+        // then
+        let bytes = predicted.iter().map(|m| m.bytes).collect_vec();
+        pretty_assertions::assert_eq!(bytes, vec![100, 100, 100]);
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn parallel_encode_matches_serial_encode() {
+        // given
+        let entries = payload_with(&PayloadConfig::new(10).with_seed(5));
+        let mut serial = Data::with_capacity(0);
+        PayloadCodec::<Cursor<Vec<u8>>, _>::encode(&BincodeCodec::default(), &entries, &mut serial)
+            .unwrap();
+
+        // when
+        let mut parallel = Data::with_capacity(0);
+        PayloadCodec::<Cursor<Vec<u8>>, _>::encode_parallel(
+            &BincodeCodec::default(),
+            &entries,
+            &mut parallel,
+        )
+        .unwrap();
+
+        // then
+        pretty_assertions::assert_eq!(
+            [
+                parallel.coins,
+                parallel.messages,
+                parallel.contracts,
+                parallel.contract_state,
+                parallel.contract_balance,
+                parallel.contract_state_root,
+            ],
+            [
+                serial.coins,
+                serial.messages,
+                serial.contracts,
+                serial.contract_state,
+                serial.contract_balance,
+                serial.contract_state_root,
+            ]
+        );
+    }
+
+    #[test]
+    fn speedup_is_the_mean_encode_time_ratio() {
+        // given
+        let measurement =
+            |secs| EncodeMeasurement::new(1, 1, Duration::from_secs(secs), Duration::from_secs(1));
+        let serial = [measurement(4), measurement(6)];
+        let parallel = [measurement(2), measurement(2)];
+
+        // when
+        let speedup = encode_speedup(&serial, &parallel);
+
+        // then
+        pretty_assertions::assert_eq!(speedup, 2.5);
+    }
+
+    #[test]
+    fn compression_ratio_pairs_sizes_and_skips_empty_ones() {
+        // given
+        let measurement = |elements, bytes| {
+            EncodeMeasurement::new(elements, bytes, Duration::ZERO, Duration::ZERO)
+        };
+        let uncompressed = [
+            measurement(0, 0),
+            measurement(10, 300),
+            measurement(20, 600),
+        ];
+        let compressed = [
+            measurement(0, 20),
+            measurement(20, 200),
+            measurement(30, 250),
+        ];
+
+        // when
+        let ratio = compression_ratio(&uncompressed, &compressed);
+
+        // then
+        pretty_assertions::assert_eq!(ratio, vec![(20, 3f64)]);
+    }
+
+    #[test]
+    fn sweep_measures_the_same_payload_once_per_parameter() {
+        // given
+        let mut runner = MeasurementRunner::new(30, 10, 0).with_seed(5);
+
+        // when
+        let sweep = runner
+            .run_sweep(&[5, 50], |batch_size| {
+                ParquetCodec::try_new(batch_size, ParquetCompression::Uncompressed)
+            })
+            .unwrap();
+
+        // then
+        let parameters = sweep.iter().map(|m| m.parameter).collect_vec();
+        pretty_assertions::assert_eq!(parameters, vec![5, 50]);
+        pretty_assertions::assert_eq!(
+            sweep[0].measurement.num_elements,
+            sweep[1].measurement.num_elements
+        );
+        // every row group repeats the column metadata
+        assert!(sweep[0].measurement.bytes > sweep[1].measurement.bytes);
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn sharded_measurement_decodes_every_shard() {
+        // given
+        let entries = payload_with(&PayloadConfig::new(10).with_seed(5));
+        let codec = ParquetCodec::new(10, ParquetCompression::Uncompressed);
+
+        // when
+        let measurement = measure_sharded(&codec, &entries, 3).unwrap();
+
+        // then
+        pretty_assertions::assert_eq!(measurement.num_elements, entries.num_entries());
+        pretty_assertions::assert_eq!(measurement.shards, 3);
+    }
+
+    #[test]
+    fn progress_is_reported_after_every_payload() {
+        // given
+        let reports = std::rc::Rc::new(std::cell::RefCell::new(vec![]));
+        let mut runner = MeasurementRunner::new(30, 10, 1)
+            .with_seed(5)
+            .with_progress({
+                let reports = reports.clone();
+                move |label: &str, measured, total| {
+                    reports
+                        .borrow_mut()
+                        .push((label.to_string(), measured, total))
+                }
+            });
+        runner.set_label("bincode");
+
+        // when
+        runner.run(&BincodeCodec::default()).unwrap();
+
+        // then
+        let expected = (1..=3)
+            .map(|measured| ("bincode".to_string(), measured, 3))
+            .collect_vec();
+        pretty_assertions::assert_eq!(*reports.borrow(), expected);
+    }
+
+    #[test]
+    fn sweep_progress_counts_the_parameters() {
+        // given
+        let reports = std::rc::Rc::new(std::cell::RefCell::new(vec![]));
+        let mut runner = MeasurementRunner::new(30, 10, 0)
+            .with_seed(5)
+            .with_progress({
+                let reports = reports.clone();
+                move |_: &str, measured, total| reports.borrow_mut().push((measured, total))
+            });
+
+        // when
+        runner
+            .run_sweep(&[10, 100], |batch_size| {
+                Ok(ParquetCodec::new(
+                    batch_size,
+                    ParquetCompression::Uncompressed,
+                ))
+            })
+            .unwrap();
+
+        // then
+        pretty_assertions::assert_eq!(*reports.borrow(), vec![(1, 2), (2, 2)]);
+    }
+
+    #[test]
+    fn geometric_progress_counts_its_own_payloads() {
+        // given
+        let reports = std::rc::Rc::new(std::cell::RefCell::new(vec![]));
+        let mut runner = MeasurementRunner::new(100, 10, 0)
+            .with_seed(5)
+            .with_progress({
+                let reports = reports.clone();
+                move |_: &str, measured, total| reports.borrow_mut().push((measured, total))
+            });
+
+        // when
+        runner.run_geometric(&BincodeCodec::default()).unwrap();
+
+        // then
+        pretty_assertions::assert_eq!(*reports.borrow(), vec![(1, 4), (2, 4), (3, 4), (4, 4)]);
     }
 }
@@ -0,0 +1,118 @@
+//! Peak heap usage tracking.
+//!
+//! With the `peak-memory` feature enabled a counting wrapper around the system allocator is
+//! installed as the global allocator. Without it `track_peak` still works but reports `None`.
+
+#[cfg(feature = "peak-memory")]
+mod tracking {
+    use std::{
+        alloc::{GlobalAlloc, Layout, System},
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    static LIVE: AtomicUsize = AtomicUsize::new(0);
+    static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+    pub struct TrackingAllocator;
+
+    unsafe impl GlobalAlloc for TrackingAllocator {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            let ptr = System.alloc(layout);
+            if !ptr.is_null() {
+                grew(layout.size());
+            }
+            ptr
+        }
+
+        unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+            let ptr = System.alloc_zeroed(layout);
+            if !ptr.is_null() {
+                grew(layout.size());
+            }
+            ptr
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            System.dealloc(ptr, layout);
+            LIVE.fetch_sub(layout.size(), Ordering::Relaxed);
+        }
+
+        unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+            let new_ptr = System.realloc(ptr, layout, new_size);
+            if !new_ptr.is_null() {
+                LIVE.fetch_sub(layout.size(), Ordering::Relaxed);
+                grew(new_size);
+            }
+            new_ptr
+        }
+    }
+
+    fn grew(size: usize) {
+        let live = LIVE.fetch_add(size, Ordering::Relaxed) + size;
+        PEAK.fetch_max(live, Ordering::Relaxed);
+    }
+
+    #[global_allocator]
+    static ALLOCATOR: TrackingAllocator = TrackingAllocator;
+
+    /// Makes the currently live bytes the new peak and returns them as the baseline.
+    pub fn reset_peak() -> usize {
+        let live = LIVE.load(Ordering::Relaxed);
+        PEAK.store(live, Ordering::Relaxed);
+        live
+    }
+
+    pub fn peak() -> usize {
+        PEAK.load(Ordering::Relaxed)
+    }
+}
+
+/// Runs `action` and reports how far the heap grew above its starting size while it ran.
+///
+/// Allocations made by other threads in the meantime are counted as well.
+pub fn track_peak<T>(action: impl FnOnce() -> T) -> (Option<usize>, T) {
+    #[cfg(feature = "peak-memory")]
+    {
+        let baseline = tracking::reset_peak();
+        let ret = action();
+        (Some(tracking::peak().saturating_sub(baseline)), ret)
+    }
+    #[cfg(not(feature = "peak-memory"))]
+    {
+        (None, action())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "peak-memory")]
+    #[test]
+    fn reports_peak_when_tracking() {
+        // given
+        // large enough that memory other tests free meanwhile can't hide it
+        let size = 32 << 20;
+
+        // when
+        let (peak, buffer) = track_peak(|| vec![0u8; size]);
+
+        // then
+        pretty_assertions::assert_eq!(buffer.len(), size);
+        let peak = peak.unwrap();
+        assert!(
+            peak >= size,
+            "peak of {peak} bytes is below the {size} allocated"
+        );
+    }
+
+    #[cfg(not(feature = "peak-memory"))]
+    #[test]
+    fn reports_nothing_without_tracking() {
+        // when
+        let (peak, _) = track_peak(|| vec![0u8; 1024]);
+
+        // then
+        assert_eq!(peak, None);
+    }
+}
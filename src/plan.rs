@@ -0,0 +1,575 @@
+//! Everything a run measures and the files it writes, worked out from the flags alone so
+//! `--dry-run` can print it without generating a single payload. `main` goes through the very same
+//! steps in the same order, taking its labels, directories and variants from the functions here.
+
+use std::{
+    fmt,
+    ops::RangeInclusive,
+    path::{Path, PathBuf},
+};
+
+use flate2::Compression;
+use itertools::Itertools;
+
+use crate::{
+    cli::{Cli, CodecName},
+    compression::CompressionAlgo,
+    encoding::BincodeCodec,
+    measurements::{geometric_repeats, sweep_repeats},
+};
+
+/// Batch sizes parquet is swept through on the largest payload.
+pub const PARQUET_BATCH_SIZES: [usize; 4] = [1_000, 10_000, 50_000, 200_000];
+/// The columnar bincode codec writing the same values row by row.
+pub const COLUMNAR_ROWS: &str = "bincode_columnar_rows";
+pub const PARQUET_UNCOMPRESSED: &str = "parquet_uncompressed";
+pub const PARQUET_NO_DICTIONARY: &str = "parquet_no_dictionary";
+/// Parquet with its contract code compressed by zstd.
+pub const PARQUET_ZSTD_CODE: &str = "parquet_zstd_code";
+/// Gzip levels bincode is swept through.
+pub const GZIP_LEVELS: RangeInclusive<u32> = 1..=9;
+
+/// The charts `PlotMerger::plot_with` draws, given every series was measured in both phases.
+const MERGED_CHARTS: [&str; 4] = [
+    "storage_requirements",
+    "encoding_time",
+    "decoding_time",
+    "throughput",
+];
+/// Drawn along with the merged charts if the measurements carry a peak.
+const PEAK_MEMORY_CHART: &str = "peak_memory";
+
+/// Only the codecs with a meaningful amount of work per subset are worth comparing in depth.
+pub fn compared_in_depth(name: CodecName) -> bool {
+    matches!(name, CodecName::Bincode | CodecName::Parquet)
+}
+
+/// Codecs that write a header or a footer would pay for it on every single record.
+pub fn timed_per_record(name: CodecName) -> bool {
+    !matches!(name, CodecName::Parquet | CodecName::Avro | CodecName::Csv)
+}
+
+/// Codecs that can write the payload as a single stream of tagged entries.
+pub fn streams_entries(name: CodecName) -> bool {
+    matches!(
+        name,
+        CodecName::Json | CodecName::JsonArray | CodecName::Bincode
+    )
+}
+
+pub fn entries_label(name: CodecName) -> String {
+    format!("{}_entries", name.label())
+}
+
+pub fn gzip_level_label(level: u32) -> String {
+    format!("bincode_gzip{level}")
+}
+
+pub fn brotli_level_label(level: u32) -> String {
+    format!("parquet_brotli{level}")
+}
+
+/// Chart directory of the seek to the end of a file written by `name`.
+pub fn file_seek_dir(name: CodecName) -> String {
+    format!("file_seek/{}", name.label())
+}
+
+/// Chart directory of appending to a corpus written by `name`.
+pub fn append_dir(name: CodecName) -> String {
+    format!("append/{}", name.label())
+}
+
+/// Bincode configs compared against each other, most fields are hashes so the integers are where
+/// varints and byte order could show.
+pub fn bincode_configs() -> [(&'static str, BincodeCodec); 3] {
+    [
+        ("bincode_varint", BincodeCodec::little_endian()),
+        ("bincode_big_endian", BincodeCodec::big_endian()),
+        ("bincode_fixint", BincodeCodec::fixint()),
+    ]
+}
+
+/// The compressed variants of `name`, labelled like their result files. Parquet compresses its
+/// pages itself with the same algorithm and level.
+pub fn compressed_variants(name: CodecName) -> Vec<(String, CompressionAlgo)> {
+    let label = name.label();
+    let gzip = CompressionAlgo::Gzip(Compression::new(1));
+    match name {
+        CodecName::Bincode | CodecName::Parquet => vec![
+            (label.to_string(), gzip),
+            (format!("{label}_zstd"), CompressionAlgo::Zstd(1)),
+            (format!("{label}_brotli"), CompressionAlgo::Brotli(1)),
+            (format!("{label}_snappy"), CompressionAlgo::Snappy),
+        ],
+        _ => vec![(label.to_string(), gzip)],
+    }
+}
+
+/// `dir/<prefix>_<name>`, or just `dir/<name>` if the prefix is empty. Every file a run writes is
+/// named this way so runs sharing an `--out-dir` don't overwrite each other.
+pub fn output_file(dir: &Path, prefix: &str, name: &str) -> PathBuf {
+    if prefix.is_empty() {
+        dir.join(name)
+    } else {
+        dir.join(format!("{prefix}_{name}"))
+    }
+}
+
+pub fn chart_file(dir: &Path, prefix: &str, chart: &str) -> PathBuf {
+    output_file(dir, prefix, &format!("{chart}.svg"))
+}
+
+/// A codec measured over a list of payload sizes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlannedRun {
+    /// Subdirectory of `--out-dir` the results go to, `None` for the round trip check.
+    pub chart: Option<String>,
+    pub label: String,
+    pub sizes: Vec<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Plan {
+    pub out_dir: PathBuf,
+    pub chart_prefix: String,
+    pub runs: Vec<PlannedRun>,
+    /// Every result and chart file the run writes.
+    pub files: Vec<PathBuf>,
+}
+
+/// A group of charts a run draws together, usually from the same measurements. `Plan::new` and
+/// `main` both go through `chart_groups`, each with a function per group.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChartGroup {
+    /// The round trip check, it draws nothing.
+    Verify,
+    EncodeOnly,
+    Normal,
+    DecodeOnly,
+    EntryStream,
+    Columnar,
+    #[cfg(feature = "parallel")]
+    Parallel,
+    Seek,
+    FileSeek,
+    Append,
+    PerType,
+    PerRecord,
+    /// Parquet against its own settings, from the dictionary to the batch size.
+    ParquetSettings,
+    /// The uncompressed measurements extrapolated to `--predict-to` and fitted over the sweep.
+    Predicted,
+    /// Every codec's compressed variants, their ratios and predictions.
+    Compressed,
+    CompressionLevels,
+    BincodeConfigs,
+    ParquetBrotliLevels,
+}
+
+/// The chart groups the flags ask for, in the order they're drawn.
+pub fn chart_groups(cli: &Cli) -> Vec<ChartGroup> {
+    let has = |name: CodecName| cli.codecs.contains(&name);
+    let mut groups = vec![];
+    if cli.verify {
+        groups.push(ChartGroup::Verify);
+    }
+    if cli.encode_only {
+        groups.push(ChartGroup::EncodeOnly);
+        return groups;
+    }
+    groups.extend([
+        ChartGroup::Normal,
+        ChartGroup::DecodeOnly,
+        ChartGroup::EntryStream,
+    ]);
+    if has(CodecName::BincodeColumnar) {
+        groups.push(ChartGroup::Columnar);
+    }
+    #[cfg(feature = "parallel")]
+    groups.push(ChartGroup::Parallel);
+    groups.extend([
+        ChartGroup::Seek,
+        ChartGroup::FileSeek,
+        ChartGroup::Append,
+        ChartGroup::PerType,
+    ]);
+    if cli.per_record {
+        groups.push(ChartGroup::PerRecord);
+    }
+    if has(CodecName::Parquet) {
+        groups.push(ChartGroup::ParquetSettings);
+    }
+    groups.push(ChartGroup::Predicted);
+    if !cli.compressed {
+        return groups;
+    }
+    groups.push(ChartGroup::Compressed);
+    if has(CodecName::Bincode) {
+        groups.extend([ChartGroup::CompressionLevels, ChartGroup::BincodeConfigs]);
+    }
+    if has(CodecName::Parquet) && cli.max_brotli_level > 0 {
+        groups.push(ChartGroup::ParquetBrotliLevels);
+    }
+    groups
+}
+
+impl Plan {
+    pub fn new(cli: &Cli) -> Self {
+        let mut plan = Self {
+            out_dir: cli.out_dir.clone(),
+            chart_prefix: cli.chart_prefix(),
+            runs: vec![],
+            files: vec![],
+        };
+        for group in chart_groups(cli) {
+            plan.add_group(cli, group);
+        }
+        plan
+    }
+
+    fn add_group(&mut self, cli: &Cli, group: ChartGroup) {
+        let sweep = sweep_repeats(cli.max, cli.step);
+        let in_depth = cli
+            .codecs
+            .iter()
+            .copied()
+            .filter(|name| compared_in_depth(*name))
+            .collect_vec();
+        let both = MERGED_CHARTS.as_slice();
+
+        match group {
+            ChartGroup::Verify => {
+                for name in &cli.codecs {
+                    self.add(None, name.label(), vec![cli.step]);
+                }
+            }
+            ChartGroup::EncodeOnly => {
+                for name in &cli.codecs {
+                    self.add(Some("encode_only"), name.label(), sweep.clone());
+                    self.results("encode_only", name.label());
+                }
+                self.merged(
+                    "encode_only",
+                    &["storage_requirements", "encoding_time", "throughput"],
+                );
+            }
+            ChartGroup::Normal => {
+                let normal = if cli.geometric {
+                    geometric_repeats(cli.max, cli.step)
+                } else {
+                    sweep
+                };
+                for name in &cli.codecs {
+                    self.add(Some("normal"), name.label(), normal.clone());
+                    self.results("normal", name.label());
+                }
+                self.merged("normal", both);
+            }
+            ChartGroup::DecodeOnly => {
+                for name in &cli.codecs {
+                    self.add(Some("decode_only"), name.label(), sweep.clone());
+                    self.file("decode_only", &format!("{}.csv", name.label()));
+                }
+                self.charts("decode_only", &["decoding_time"]);
+            }
+            ChartGroup::EntryStream => {
+                let streamed = cli
+                    .codecs
+                    .iter()
+                    .copied()
+                    .filter(|name| streams_entries(*name))
+                    .collect_vec();
+                for name in &streamed {
+                    self.add(Some("entry_stream"), entries_label(*name), sweep.clone());
+                    self.results("entry_stream", &entries_label(*name));
+                }
+                if !streamed.is_empty() {
+                    self.merged("entry_stream", both);
+                }
+            }
+            ChartGroup::Columnar => {
+                for label in [COLUMNAR_ROWS, PARQUET_UNCOMPRESSED] {
+                    self.add(Some("columnar"), label, sweep.clone());
+                    self.results("columnar", label);
+                }
+                self.merged("columnar", both);
+            }
+            #[cfg(feature = "parallel")]
+            ChartGroup::Parallel => {
+                for name in &in_depth {
+                    self.add(Some("parallel"), name.label(), sweep.clone());
+                    self.results("parallel", name.label());
+                }
+                if !in_depth.is_empty() {
+                    self.merged("parallel", both);
+                }
+            }
+            ChartGroup::Seek => {
+                self.add(Some("seek"), "seek", sweep);
+                self.file("seek", "seek.csv");
+                self.charts("seek", &["seek_time"]);
+            }
+            ChartGroup::FileSeek => {
+                for name in &in_depth {
+                    let chart = file_seek_dir(*name);
+                    self.add(Some(&chart), name.label(), sweep.clone());
+                    self.file(&chart, "seek.csv");
+                    self.charts(&chart, &["seek_time"]);
+                }
+            }
+            ChartGroup::Append => {
+                for name in &in_depth {
+                    let chart = append_dir(*name);
+                    self.add(Some(&chart), name.label(), sweep.clone());
+                    self.file(&chart, "append.csv");
+                    self.charts(&chart, &["append_time"]);
+                }
+            }
+            ChartGroup::PerType => {
+                for name in &in_depth {
+                    self.add(Some("per_type"), name.label(), sweep.clone());
+                    self.file("per_type", &format!("{}.csv", name.label()));
+                }
+                // the subsets are timed alone, no peak is tracked for them
+                if !in_depth.is_empty() {
+                    self.charts("per_type", both);
+                }
+            }
+            ChartGroup::PerRecord => {
+                for name in cli.codecs.iter().filter(|name| timed_per_record(**name)) {
+                    self.add(Some("per_record"), name.label(), vec![cli.max]);
+                    self.file("per_record", &format!("{}.csv", name.label()));
+                    self.charts("per_record", &[name.label()]);
+                }
+            }
+            ChartGroup::ParquetSettings => {
+                self.add(Some("dictionary"), PARQUET_NO_DICTIONARY, sweep.clone());
+                self.results("dictionary", PARQUET_NO_DICTIONARY);
+                self.merged("dictionary", both);
+                self.add(Some("column_compression"), PARQUET_ZSTD_CODE, sweep.clone());
+                self.results("column_compression", PARQUET_ZSTD_CODE);
+                self.merged("column_compression", both);
+                self.add(Some("lookup"), "lookup", sweep.clone());
+                self.file("lookup", "lookup.csv");
+                self.charts("lookup", &["lookup_time"]);
+                self.add(Some("row_conversion"), "row_conversion", sweep.clone());
+                self.file("row_conversion", "row_conversion.csv");
+                self.charts("row_conversion", &["row_conversion_time"]);
+                let largest = sweep.last().into_iter().copied().collect_vec();
+                for batch_size in PARQUET_BATCH_SIZES {
+                    let label = format!("parquet_batch{batch_size}");
+                    self.add(Some("batch_size"), label, largest.clone());
+                }
+                self.file("batch_size", "parquet.csv");
+                self.charts("batch_size", &["storage_requirements", "decoding_time"]);
+                if cfg!(feature = "parallel") {
+                    self.add(Some("sharded"), "sharded", sweep);
+                    self.file("sharded", "sharded.csv");
+                    self.charts("sharded", &["decoding_time"]);
+                }
+            }
+            ChartGroup::Predicted => {
+                // fitted lines carry no peak
+                self.charts("normal_predicted", both);
+                self.merged("normal_fit", both);
+            }
+            ChartGroup::Compressed => {
+                for name in &cli.codecs {
+                    for (label, _) in compressed_variants(*name) {
+                        self.add(Some("compressed"), &label, sweep.clone());
+                        self.results("compressed", &label);
+                    }
+                }
+                self.merged("compressed", both);
+                self.charts("compression_ratio", &["compression_ratio"]);
+                self.charts("compressed_predicted", both);
+            }
+            ChartGroup::CompressionLevels => {
+                for level in GZIP_LEVELS {
+                    let label = gzip_level_label(level);
+                    self.add(Some("compression_levels"), &label, sweep.clone());
+                    self.results("compression_levels", &label);
+                }
+                self.merged("compression_levels", both);
+            }
+            ChartGroup::BincodeConfigs => {
+                for (label, _) in bincode_configs() {
+                    self.add(Some("bincode_configs"), label, sweep.clone());
+                    self.results("bincode_configs", label);
+                }
+                self.merged("bincode_configs", both);
+            }
+            ChartGroup::ParquetBrotliLevels => {
+                for level in 1..=cli.max_brotli_level {
+                    let label = brotli_level_label(level);
+                    self.add(Some("parquet_brotli_levels"), &label, sweep.clone());
+                    self.results("parquet_brotli_levels", &label);
+                }
+                self.merged("parquet_brotli_levels", both);
+            }
+        }
+    }
+
+    fn add(&mut self, chart: Option<&str>, label: impl Into<String>, sizes: Vec<usize>) {
+        self.runs.push(PlannedRun {
+            chart: chart.map(str::to_string),
+            label: label.into(),
+            sizes,
+        });
+    }
+
+    fn file(&mut self, chart: &str, name: &str) {
+        let file = output_file(&self.out_dir.join(chart), &self.chart_prefix, name);
+        self.files.push(file);
+    }
+
+    /// The csv and json `write_results` exports.
+    fn results(&mut self, chart: &str, label: &str) {
+        self.file(chart, &format!("{label}.csv"));
+        self.file(chart, &format!("{label}.json"));
+    }
+
+    fn charts(&mut self, chart: &str, names: &[&str]) {
+        for name in names {
+            self.file(chart, &format!("{name}.svg"));
+        }
+    }
+
+    /// `charts` along with the peak memory, which is only tracked with the `peak-memory` feature.
+    fn merged(&mut self, chart: &str, names: &[&str]) {
+        self.charts(chart, names);
+        if cfg!(feature = "peak-memory") {
+            self.charts(chart, &[PEAK_MEMORY_CHART]);
+        }
+    }
+
+    /// Payloads generated over the whole run, counting a payload once per run it's measured in.
+    pub fn num_payloads(&self) -> usize {
+        self.runs.iter().map(|run| run.sizes.len()).sum()
+    }
+}
+
+impl fmt::Display for Plan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for run in &self.runs {
+            let dest = match &run.chart {
+                Some(chart) => self.out_dir.join(chart).display().to_string(),
+                None => "round trip check".to_string(),
+            };
+            writeln!(
+                f,
+                "{dest}: {} at sizes {}",
+                run.label,
+                run.sizes.iter().join(", ")
+            )?;
+        }
+        for file in &self.files {
+            writeln!(f, "writes {}", file.display())?;
+        }
+        writeln!(
+            f,
+            "{} runs over {} payloads writing {} files",
+            self.runs.len(),
+            self.num_payloads(),
+            self.files.len()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::*;
+
+    fn sizes_of<'a>(plan: &'a Plan, chart: &str, label: &str) -> Option<&'a [usize]> {
+        plan.runs
+            .iter()
+            .find(|run| run.chart.as_deref() == Some(chart) && run.label == label)
+            .map(|run| run.sizes.as_slice())
+    }
+
+    #[test]
+    fn plan_follows_the_flags() {
+        // given
+        let cli = Cli::parse_from([
+            "regenesis_encoding",
+            "--codecs",
+            "bincode,parquet",
+            "--max",
+            "30",
+            "--step",
+            "10",
+            "--no-compressed",
+            "--geometric",
+            "--per-record",
+        ]);
+
+        // when
+        let plan = Plan::new(&cli);
+
+        // then
+        pretty_assertions::assert_eq!(sizes_of(&plan, "normal", "bincode"), Some(&[10, 20][..]));
+        pretty_assertions::assert_eq!(
+            sizes_of(&plan, "append/parquet", "parquet"),
+            Some(&[0, 10, 20][..])
+        );
+        pretty_assertions::assert_eq!(
+            sizes_of(&plan, "batch_size", "parquet_batch1000"),
+            Some(&[20][..])
+        );
+        pretty_assertions::assert_eq!(sizes_of(&plan, "per_record", "bincode"), Some(&[30][..]));
+        pretty_assertions::assert_eq!(sizes_of(&plan, "per_record", "parquet"), None);
+        assert!(plan
+            .runs
+            .iter()
+            .all(|run| run.chart.as_deref() != Some("compressed")));
+        let file = |chart: &str, name: &str| {
+            PathBuf::from(format!("./{chart}/bincode-parquet_uncompressed_{name}"))
+        };
+        assert!(plan.files.contains(&file("normal", "bincode.json")));
+        assert!(plan
+            .files
+            .contains(&file("normal_fit", "storage_requirements.svg")));
+    }
+
+    #[test]
+    fn encode_only_plans_nothing_else() {
+        // given
+        let cli = Cli::parse_from([
+            "regenesis_encoding",
+            "--codecs",
+            "serde_json,msgpack",
+            "--max",
+            "20",
+            "--step",
+            "10",
+            "--encode-only",
+            "--verify",
+        ]);
+
+        // when
+        let plan = Plan::new(&cli);
+
+        // then
+        let runs = plan
+            .runs
+            .iter()
+            .map(|run| (run.chart.as_deref(), run.label.as_str(), run.sizes.clone()))
+            .collect_vec();
+        pretty_assertions::assert_eq!(
+            runs,
+            vec![
+                (None, "serde_json", vec![10]),
+                (None, "msgpack", vec![10]),
+                (Some("encode_only"), "serde_json", vec![0, 10]),
+                (Some("encode_only"), "msgpack", vec![0, 10]),
+            ]
+        );
+        assert!(plan
+            .files
+            .iter()
+            .all(|file| file.starts_with("./encode_only")));
+        pretty_assertions::assert_eq!(plan.num_payloads(), 6);
+    }
+}
@@ -1,8 +1,9 @@
 use core::fmt;
-use std::convert::TryFrom;
+use std::{convert::TryFrom, ops::RangeInclusive};
 
-use fuel_core_types::{blockchain::primitives::DaBlockHeight, fuel_types::bytes::WORD_SIZE};
+use fuel_core_types::blockchain::primitives::DaBlockHeight;
 use fuel_types::{Address, AssetId, BlockHeight, Bytes32, ContractId, Nonce, Salt, Word};
+use parquet_schema_derive::ParquetSchema;
 use rand::Rng;
 use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
 use serde_with::{serde_as, DeserializeAs, SerializeAs};
@@ -10,12 +11,14 @@ use serde_with::{serde_as, DeserializeAs, SerializeAs};
 use crate::util::random_bytes_32;
 
 #[serde_as]
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, ParquetSchema)]
 pub struct CoinConfig {
     /// auto-generated if None
     #[serde_as(as = "Option<HexType>")]
     #[serde(default)]
     pub tx_id: Option<Bytes32>,
+    #[serde_as(as = "Option<HexNumber>")]
+    #[serde(default)]
     pub output_index: Option<u8>,
     /// used if coin is forked from another chain to preserve id & tx_pointer
     #[serde_as(as = "Option<HexNumber>")]
@@ -23,12 +26,15 @@ pub struct CoinConfig {
     pub tx_pointer_block_height: Option<BlockHeight>,
     /// used if coin is forked from another chain to preserve id & tx_pointer
     /// The index of the originating tx within `tx_pointer_block_height`
+    #[serde_as(as = "Option<HexNumber>")]
+    #[serde(default)]
     pub tx_pointer_tx_idx: Option<u16>,
     #[serde_as(as = "Option<HexNumber>")]
     #[serde(default)]
     pub maturity: Option<BlockHeight>,
     #[serde_as(as = "HexType")]
     pub owner: Address,
+    #[serde(with = "i64_bits")]
     pub amount: u64,
     #[serde_as(as = "HexType")]
     pub asset_id: AssetId,
@@ -50,7 +56,7 @@ impl CoinConfig {
 }
 
 #[serde_as]
-#[derive(Clone, Debug, Deserialize, Serialize, Eq, PartialEq)]
+#[derive(Clone, Debug, Deserialize, Serialize, Eq, PartialEq, ParquetSchema)]
 pub struct ContractConfig {
     #[serde_as(as = "HexType")]
     pub contract_id: ContractId,
@@ -62,6 +68,8 @@ pub struct ContractConfig {
     #[serde_as(as = "Option<HexType>")]
     #[serde(default)]
     pub tx_id: Option<Bytes32>,
+    #[serde_as(as = "Option<HexNumber>")]
+    #[serde(default)]
     pub output_index: Option<u8>,
     /// TxPointer: auto-generated if None
     /// used if contract is forked from another chain to preserve id & tx_pointer
@@ -72,11 +80,13 @@ pub struct ContractConfig {
     /// TxPointer: auto-generated if None
     /// used if contract is forked from another chain to preserve id & tx_pointer
     /// The index of the originating tx within `tx_pointer_block_height`
+    #[serde_as(as = "Option<HexNumber>")]
+    #[serde(default)]
     pub tx_pointer_tx_idx: Option<u16>,
 }
 
 #[serde_as]
-#[derive(Clone, Debug, Deserialize, Serialize, Eq, PartialEq)]
+#[derive(Clone, Debug, Deserialize, Serialize, Eq, PartialEq, ParquetSchema)]
 pub struct ContractState {
     #[serde_as(as = "HexType")]
     pub key: Bytes32,
@@ -85,18 +95,53 @@ pub struct ContractState {
 }
 
 #[serde_as]
-#[derive(Clone, Debug, Deserialize, Serialize, Eq, PartialEq)]
+#[derive(Clone, Debug, Deserialize, Serialize, Eq, PartialEq, ParquetSchema)]
 pub struct ContractBalance {
     #[serde_as(as = "HexType")]
     pub asset_id: AssetId,
+    #[serde(with = "i64_bits")]
     pub amount: u64,
 }
 
+#[serde_as]
+#[derive(Clone, Debug, Deserialize, Serialize, Eq, PartialEq, ParquetSchema)]
+pub struct ContractStateRoot {
+    #[serde_as(as = "HexType")]
+    pub contract_id: ContractId,
+    #[serde_as(as = "HexType")]
+    pub root: Bytes32,
+}
+
+impl ContractStateRoot {
+    pub fn random(rng: &mut impl Rng) -> Self {
+        ContractStateRoot {
+            contract_id: ContractId::new(*random_bytes_32(rng)),
+            root: random_bytes_32(rng),
+        }
+    }
+}
+
+/// Bytes of code `ContractConfig::random` gives every contract. Real bytecode runs into the
+/// kilobytes, see `ContractConfig::random_with` for that.
+pub const DEFAULT_CODE_LEN: usize = 32;
+
 impl ContractConfig {
     pub fn random(rng: &mut impl Rng) -> Self {
+        Self::random_with(rng, DEFAULT_CODE_LEN..=DEFAULT_CODE_LEN)
+    }
+
+    /// A contract with code of a length drawn uniformly from `code_len`. A range of a single
+    /// length draws nothing for it, so `random` yields the same contracts it always has.
+    pub fn random_with(rng: &mut impl Rng, code_len: RangeInclusive<usize>) -> Self {
+        let contract_id = ContractId::new(*random_bytes_32(rng));
+        let len = if code_len.start() < code_len.end() {
+            rng.gen_range(code_len)
+        } else {
+            *code_len.start()
+        };
         ContractConfig {
-            contract_id: ContractId::new(*random_bytes_32(rng)),
-            code: (*random_bytes_32(rng)).to_vec(),
+            contract_id,
+            code: (0..len).map(|_| rng.gen::<u8>()).collect(),
             salt: Salt::new(*random_bytes_32(rng)),
             tx_id: Some(random_bytes_32(rng)),
             output_index: Some(rng.gen()),
@@ -107,7 +152,7 @@ impl ContractConfig {
 }
 
 #[serde_as]
-#[derive(Clone, Debug, Default, Deserialize, Serialize, Eq, PartialEq)]
+#[derive(Clone, Debug, Default, Deserialize, Serialize, Eq, PartialEq, ParquetSchema)]
 pub struct MessageConfig {
     #[serde_as(as = "HexType")]
     pub sender: Address,
@@ -115,6 +160,7 @@ pub struct MessageConfig {
     pub recipient: Address,
     #[serde_as(as = "HexType")]
     pub nonce: Nonce,
+    #[serde(with = "i64_bits")]
     pub amount: Word,
     pub data: Vec<u8>,
     /// The block height from the parent da layer that originated this message
@@ -135,6 +181,18 @@ impl MessageConfig {
     }
 }
 
+/// A single entry of any of the state types, for feeding a snapshot one entry at a time.
+/// Serialized externally tagged, see `EntryCodec`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StateEntry {
+    Coin(CoinConfig),
+    Message(MessageConfig),
+    Contract(ContractConfig),
+    ContractState(ContractState),
+    ContractBalance(ContractBalance),
+    ContractStateRoot(ContractStateRoot),
+}
+
 // ------------ Other stuff --------------
 
 /// Used for primitive number types which don't implement AsRef or TryFrom<&[u8]>
@@ -180,18 +238,23 @@ impl<'de> DeserializeAs<'de, DaBlockHeight> for HexNumber {
     }
 }
 
-pub struct HexType;
+/// Hex string, `0x` prefixed unless `PREFIX` is false. Deserialization is strict about it: a
+/// prefixed `HexType` rejects bare hex and `HexTypeNoPrefix` rejects a prefix.
+pub struct HexType<const PREFIX: bool = true>;
+
+/// `HexType` for consumers that don't accept the `0x` prefix.
+pub type HexTypeNoPrefix = HexType<false>;
 
-impl<T: AsRef<[u8]>> SerializeAs<T> for HexType {
+impl<T: AsRef<[u8]>, const PREFIX: bool> SerializeAs<T> for HexType<PREFIX> {
     fn serialize_as<S>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: Serializer,
     {
-        serde_hex::serialize(value, serializer)
+        serde_hex::serialize_with(value, PREFIX, serializer)
     }
 }
 
-impl<'de, T, E> DeserializeAs<'de, T> for HexType
+impl<'de, T, E, const PREFIX: bool> DeserializeAs<'de, T> for HexType<PREFIX>
 where
     for<'a> T: TryFrom<&'a [u8], Error = E>,
     E: fmt::Display,
@@ -200,7 +263,7 @@ where
     where
         D: Deserializer<'de>,
     {
-        serde_hex::deserialize(deserializer)
+        serde_hex::deserialize_with(deserializer, PREFIX)
     }
 }
 
@@ -211,27 +274,66 @@ pub mod serde_hex {
     use hex::{FromHex, ToHex};
     use serde::{de::Error, Deserializer, Serializer};
 
+    const PREFIX: &str = "0x";
+
     pub fn serialize<T, S>(target: T, ser: S) -> Result<S::Ok, S::Error>
     where
         S: Serializer,
         T: ToHex,
     {
-        let s = format!("0x{}", target.encode_hex::<String>());
-        ser.serialize_str(&s)
+        serialize_with(target, true, ser)
     }
 
     pub fn deserialize<'de, T, E, D>(des: D) -> Result<T, D::Error>
+    where
+        D: Deserializer<'de>,
+        for<'a> T: TryFrom<&'a [u8], Error = E>,
+        E: fmt::Display,
+    {
+        deserialize_with(des, true)
+    }
+
+    pub fn serialize_with<T, S>(target: T, prefix: bool, ser: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+        T: ToHex,
+    {
+        ser.serialize_str(&encode(target, prefix))
+    }
+
+    pub fn deserialize_with<'de, T, E, D>(des: D, prefix: bool) -> Result<T, D::Error>
     where
         D: Deserializer<'de>,
         for<'a> T: TryFrom<&'a [u8], Error = E>,
         E: fmt::Display,
     {
         let raw_string: String = serde::Deserialize::deserialize(des)?;
-        let stripped_prefix = raw_string.trim_start_matches("0x");
-        let bytes: Vec<u8> = FromHex::from_hex(stripped_prefix).map_err(D::Error::custom)?;
+        let bytes = decode(&raw_string, prefix).map_err(D::Error::custom)?;
         let result = T::try_from(bytes.as_slice()).map_err(D::Error::custom)?;
         Ok(result)
     }
+
+    /// The hex string `serialize_with` writes, for formats that don't go through serde.
+    pub fn encode(target: impl ToHex, prefix: bool) -> String {
+        let hex = target.encode_hex::<String>();
+        if prefix {
+            format!("{PREFIX}{hex}")
+        } else {
+            hex
+        }
+    }
+
+    /// Reverse of `encode`, fails if the `0x` prefix is missing when `prefix` is set or present
+    /// when it isn't.
+    pub fn decode(hex: &str, prefix: bool) -> Result<Vec<u8>, String> {
+        let digits = match (hex.strip_prefix(PREFIX), prefix) {
+            (Some(digits), true) => digits,
+            (None, false) => hex,
+            (Some(_), false) => return Err(format!("unexpected {PREFIX} prefix in {hex:?}")),
+            (None, true) => return Err(format!("missing {PREFIX} prefix in {hex:?}")),
+        };
+        FromHex::from_hex(digits).map_err(|e: hex::FromHexError| e.to_string())
+    }
 }
 
 macro_rules! impl_hex_number {
@@ -252,15 +354,22 @@ macro_rules! impl_hex_number {
                 D: Deserializer<'de>,
             {
                 const SIZE: usize = core::mem::size_of::<$i>();
-                let mut bytes: Vec<u8> = serde_hex::deserialize(deserializer)?;
+                let hex: String = Deserialize::deserialize(deserializer)?;
+                // numbers may leave out the leading zero of their top byte, e.g. `0x1`
+                let hex = match hex.strip_prefix("0x") {
+                    Some(digits) if digits.len() % 2 == 1 => format!("0x0{digits}"),
+                    _ => hex,
+                };
+                let mut bytes = serde_hex::decode(&hex, true).map_err(D::Error::custom)?;
                 match bytes.len() {
                     len if len > SIZE => {
+                        let unit = if SIZE == 1 { "byte" } else { "bytes" };
                         return Err(D::Error::custom(format!(
-                            "value cant exceed {WORD_SIZE} bytes, got {len}"
+                            "value cant exceed {SIZE} {unit}, got {len}"
                         )));
                     }
                     len if len < SIZE => {
-                        // pad if length < word size
+                        // pad if length < SIZE
                         bytes = (0..SIZE - len)
                             .map(|_| 0u8)
                             .chain(bytes.into_iter())
@@ -268,7 +377,7 @@ macro_rules! impl_hex_number {
                     }
                     _ => {}
                 }
-                // We've already verified the bytes.len == WORD_SIZE, force the conversion here.
+                // We've already verified the bytes.len == SIZE, force the conversion here.
                 Ok($i::from_be_bytes(
                     bytes.try_into().expect("byte lengths checked"),
                 ))
@@ -282,8 +391,253 @@ impl_hex_number!(u16);
 impl_hex_number!(u32);
 impl_hex_number!(u64);
 
+/// bson has no unsigned 64 bit integers and refuses any `u64` above `i64::MAX`, so binary formats
+/// get the `i64` of the same bits. Human readable ones keep the plain number.
+pub mod i64_bits {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(value: &u64, ser: S) -> Result<S::Ok, S::Error> {
+        if ser.is_human_readable() {
+            value.serialize(ser)
+        } else {
+            (*value as i64).serialize(ser)
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(des: D) -> Result<u64, D::Error> {
+        if des.is_human_readable() {
+            u64::deserialize(des)
+        } else {
+            i64::deserialize(des).map(|bits| bits as u64)
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use super::*;
+    use crate::test_support::seeded_rng;
+
+    #[serde_as]
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Prefixed {
+        #[serde_as(as = "HexType")]
+        address: Address,
+    }
+
+    #[serde_as]
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Bare {
+        #[serde_as(as = "HexTypeNoPrefix")]
+        address: Address,
+    }
+
+    #[test]
+    fn prefixed_hex_round_trips() {
+        // given
+        let original = Prefixed {
+            address: Address::new([0xab; 32]),
+        };
+
+        // when
+        let json = serde_json::to_string(&original).unwrap();
+        let decoded: Prefixed = serde_json::from_str(&json).unwrap();
+
+        // then
+        pretty_assertions::assert_eq!(json, format!(r#"{{"address":"0x{}"}}"#, "ab".repeat(32)));
+        pretty_assertions::assert_eq!(decoded, original);
+    }
+
+    #[test]
+    fn bare_hex_round_trips() {
+        // given
+        let original = Bare {
+            address: Address::new([0xab; 32]),
+        };
+
+        // when
+        let json = serde_json::to_string(&original).unwrap();
+        let decoded: Bare = serde_json::from_str(&json).unwrap();
+
+        // then
+        pretty_assertions::assert_eq!(json, format!(r#"{{"address":"{}"}}"#, "ab".repeat(32)));
+        pretty_assertions::assert_eq!(decoded, original);
+    }
+
+    #[test]
+    fn json_keeps_amounts_as_plain_numbers() {
+        // given
+        let balance = ContractBalance {
+            asset_id: AssetId::default(),
+            amount: u64::MAX,
+        };
+
+        // when
+        let json = serde_json::to_value(&balance).unwrap();
+        let decoded: ContractBalance = serde_json::from_value(json.clone()).unwrap();
+
+        // then
+        pretty_assertions::assert_eq!(json["amount"], serde_json::json!(u64::MAX));
+        pretty_assertions::assert_eq!(decoded, balance);
+    }
+
+    fn hex_number_error<T>(hex: &str) -> String
+    where
+        HexNumber: for<'de> DeserializeAs<'de, T>,
+        T: fmt::Debug,
+    {
+        let mut deserializer = serde_json::Deserializer::from_str(hex);
+        let result: Result<T, _> = HexNumber::deserialize_as(&mut deserializer);
+        result.unwrap_err().to_string()
+    }
+
+    fn hex_number<T>(hex: &str) -> T
+    where
+        HexNumber: for<'de> DeserializeAs<'de, T>,
+    {
+        let mut deserializer = serde_json::Deserializer::from_str(hex);
+        HexNumber::deserialize_as(&mut deserializer).unwrap()
+    }
+
+    #[test]
+    fn short_hex_numbers_are_padded_with_leading_zeros() {
+        // when
+        let empty = (
+            hex_number::<u8>(r#""0x""#),
+            hex_number::<u16>(r#""0x""#),
+            hex_number::<u32>(r#""0x""#),
+            hex_number::<u64>(r#""0x""#),
+        );
+        let one_digit = (
+            hex_number::<u8>(r#""0x0""#),
+            hex_number::<u16>(r#""0x1""#),
+            hex_number::<u32>(r#""0xa""#),
+            hex_number::<u64>(r#""0x0""#),
+        );
+        let two_bytes = (
+            hex_number::<u8>(r#""0xff""#),
+            hex_number::<u16>(r#""0x00ff""#),
+            hex_number::<u32>(r#""0x00ff""#),
+            hex_number::<u64>(r#""0x00ff""#),
+        );
+        let full_width = (
+            hex_number::<u8>(r#""0x80""#),
+            hex_number::<u16>(r#""0x8001""#),
+            hex_number::<u32>(r#""0x80000001""#),
+            hex_number::<u64>(r#""0x8000000000000001""#),
+        );
+
+        // then
+        pretty_assertions::assert_eq!(empty, (0, 0, 0, 0));
+        pretty_assertions::assert_eq!(one_digit, (0, 1, 10, 0));
+        pretty_assertions::assert_eq!(two_bytes, (255, 255, 255, 255));
+        pretty_assertions::assert_eq!(
+            full_width,
+            (0x80, 0x8001, 0x8000_0001, 0x8000_0000_0000_0001)
+        );
+    }
+
+    #[serde_as]
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Heights {
+        #[serde_as(as = "HexNumber")]
+        block: BlockHeight,
+        #[serde_as(as = "HexNumber")]
+        da: DaBlockHeight,
+    }
+
+    #[test]
+    fn block_heights_round_trip_as_their_numbers() {
+        // given
+        let original = Heights {
+            block: BlockHeight::new(0x0102_0304),
+            da: DaBlockHeight(u64::MAX),
+        };
+
+        // when
+        let json = serde_json::to_string(&original).unwrap();
+        let decoded: Heights = serde_json::from_str(&json).unwrap();
+        let short: Heights = serde_json::from_str(r#"{"block":"0x1","da":"0x00ff"}"#).unwrap();
+
+        // then
+        pretty_assertions::assert_eq!(json, r#"{"block":"0x01020304","da":"0xffffffffffffffff"}"#);
+        pretty_assertions::assert_eq!(decoded, original);
+        pretty_assertions::assert_eq!(
+            short,
+            Heights {
+                block: BlockHeight::new(1),
+                da: DaBlockHeight(255),
+            }
+        );
+    }
+
+    #[test]
+    fn coins_and_contracts_encode_output_index_alike() {
+        // given
+        let mut rng = seeded_rng(1);
+        let coin = CoinConfig {
+            output_index: Some(7),
+            tx_pointer_tx_idx: Some(0x1234),
+            ..CoinConfig::random(&mut rng)
+        };
+        let contract = ContractConfig {
+            output_index: Some(7),
+            tx_pointer_tx_idx: Some(0x1234),
+            ..ContractConfig::random(&mut rng)
+        };
+
+        // when
+        let coin = serde_json::to_value(coin).unwrap();
+        let contract = serde_json::to_value(contract).unwrap();
+
+        // then
+        pretty_assertions::assert_eq!(coin["output_index"], contract["output_index"]);
+        pretty_assertions::assert_eq!(coin["output_index"], "0x07");
+        pretty_assertions::assert_eq!(coin["tx_pointer_tx_idx"], contract["tx_pointer_tx_idx"]);
+        pretty_assertions::assert_eq!(coin["tx_pointer_tx_idx"], "0x1234");
+    }
+
+    #[test]
+    fn oversized_hex_numbers_name_the_target_width() {
+        // given
+        let nine_bytes = format!(r#""0x{}""#, "01".repeat(9));
+
+        // when
+        let errors = [
+            hex_number_error::<u8>(r#""0x010203""#),
+            hex_number_error::<u16>(r#""0x010203""#),
+            hex_number_error::<u32>(r#""0x0102030405""#),
+            hex_number_error::<u64>(&nine_bytes),
+        ];
+
+        // then
+        pretty_assertions::assert_eq!(
+            errors,
+            [
+                "value cant exceed 1 byte, got 3",
+                "value cant exceed 2 bytes, got 3",
+                "value cant exceed 4 bytes, got 5",
+                "value cant exceed 8 bytes, got 9",
+            ]
+        );
+    }
+
+    #[test]
+    fn hex_deserialization_is_strict_about_the_prefix() {
+        // given
+        let bare = format!(r#"{{"address":"{}"}}"#, "ab".repeat(32));
+        let prefixed = format!(r#"{{"address":"0x{}"}}"#, "ab".repeat(32));
+
+        // when
+        let prefixed_from_bare = serde_json::from_str::<Prefixed>(&bare);
+        let bare_from_prefixed = serde_json::from_str::<Bare>(&prefixed);
+
+        // then
+        let err = prefixed_from_bare.unwrap_err().to_string();
+        assert!(err.contains("missing 0x prefix"), "{err}");
+        let err = bare_from_prefixed.unwrap_err().to_string();
+        assert!(err.contains("unexpected 0x prefix"), "{err}");
+    }
 
     #[test]
     fn heyhay() {
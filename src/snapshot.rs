@@ -0,0 +1,338 @@
+use std::{
+    io::{Cursor, ErrorKind, Read, Write},
+    ops::Range,
+};
+
+use crate::{
+    encoding::{CodecError, PayloadCodec},
+    util::{Data, Payload},
+};
+
+/// First bytes of every snapshot file.
+pub const MAGIC: [u8; 4] = *b"RGSN";
+pub const VERSION: u8 = 2;
+/// Magic, version, codec id, checksum id and then an offset, a length and a checksum, all little
+/// endian `u64`s, per subset in `Data` order.
+pub const HEADER_LEN: usize = MAGIC.len() + 3 + 6 * 3 * 8;
+
+/// Hashes every subset for the header. The id is stored along with the checksums so a reader set
+/// up with another algorithm says so instead of reporting every subset as corrupt.
+pub trait Checksum {
+    fn id(&self) -> u8;
+    fn checksum(&self, bytes: &[u8]) -> u64;
+}
+
+/// The default checksum.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Crc32;
+
+impl Checksum for Crc32 {
+    fn id(&self) -> u8 {
+        0
+    }
+
+    fn checksum(&self, bytes: &[u8]) -> u64 {
+        crc32fast::hash(bytes).into()
+    }
+}
+
+/// Writes a payload as a single file: the header followed by the encoded subsets back to back.
+/// The codec itself isn't stored, only the id given here, e.g. `AnyCodec::id`.
+pub struct SnapshotWriter<W: Write, C = Crc32> {
+    dest: W,
+    codec_id: u8,
+    checksum: C,
+}
+
+impl<W: Write> SnapshotWriter<W> {
+    pub fn new(dest: W, codec_id: u8) -> Self {
+        Self::with_checksum(dest, codec_id, Crc32)
+    }
+}
+
+impl<W: Write, C: Checksum> SnapshotWriter<W, C> {
+    pub fn with_checksum(dest: W, codec_id: u8, checksum: C) -> Self {
+        Self {
+            dest,
+            codec_id,
+            checksum,
+        }
+    }
+
+    /// The subsets are encoded in memory first, the header needs their lengths up front.
+    pub fn write(
+        mut self,
+        codec: &impl PayloadCodec<Cursor<Vec<u8>>, Vec<u8>>,
+        payload: &Payload,
+    ) -> Result<W, CodecError> {
+        let mut data = Data::with_capacity(0);
+        codec.encode(payload, &mut data)?;
+
+        self.dest.write_all(&MAGIC)?;
+        self.dest
+            .write_all(&[VERSION, self.codec_id, self.checksum.id()])?;
+        let mut offset = HEADER_LEN as u64;
+        for subset in data.as_array() {
+            let len = subset.len() as u64;
+            self.dest.write_all(&offset.to_le_bytes())?;
+            self.dest.write_all(&len.to_le_bytes())?;
+            self.dest
+                .write_all(&self.checksum.checksum(subset).to_le_bytes())?;
+            offset += len;
+        }
+        for subset in data.as_array() {
+            self.dest.write_all(subset)?;
+        }
+        self.dest.flush()?;
+        Ok(self.dest)
+    }
+}
+
+/// Reads back what `SnapshotWriter` wrote. The header and the checksums are checked up front, so
+/// every subset is known to lie within the file and to be intact before anything is decoded.
+pub struct SnapshotReader {
+    codec_id: u8,
+    subsets: Data<Range<usize>>,
+    bytes: Vec<u8>,
+}
+
+impl SnapshotReader {
+    pub fn new(bytes: Vec<u8>) -> Result<Self, CodecError> {
+        Self::with_checksum(bytes, &Crc32)
+    }
+
+    /// For snapshots written with `SnapshotWriter::with_checksum`.
+    pub fn with_checksum(bytes: Vec<u8>, checksum: &impl Checksum) -> Result<Self, CodecError> {
+        if bytes.len() < HEADER_LEN {
+            return Err(invalid_data(format!(
+                "snapshot of {} bytes is too short for its header",
+                bytes.len()
+            )));
+        }
+        if bytes[..MAGIC.len()] != MAGIC {
+            return Err(invalid_data("not a snapshot file".to_string()));
+        }
+        let version = bytes[MAGIC.len()];
+        if version != VERSION {
+            return Err(invalid_data(format!(
+                "snapshot version {version} isn't supported"
+            )));
+        }
+        let codec_id = bytes[MAGIC.len() + 1];
+        let checksum_id = bytes[MAGIC.len() + 2];
+        if checksum_id != checksum.id() {
+            return Err(invalid_data(format!(
+                "snapshot checksums have id {checksum_id}, expected {}",
+                checksum.id()
+            )));
+        }
+
+        let mut fields = bytes[MAGIC.len() + 3..HEADER_LEN]
+            .chunks_exact(8)
+            .map(|field| u64::from_le_bytes(field.try_into().expect("chunks are 8 bytes")));
+        let mut next_field = || fields.next().expect("header has three fields per subset");
+        let fields: [_; 6] = std::array::from_fn(|idx| {
+            (Data::LABELS[idx], next_field(), next_field(), next_field())
+        });
+        let ranges = Data::from_array(fields).try_map(|(subset, offset, len, expected)| {
+            if offset < HEADER_LEN as u64 {
+                return Err(invalid_data(format!(
+                    "{subset} at {offset} starts inside the header"
+                )));
+            }
+            let range = offset
+                .checked_add(len)
+                .filter(|&end| end <= bytes.len() as u64)
+                .map(|end| offset as usize..end as usize)
+                .ok_or_else(|| {
+                    invalid_data(format!(
+                        "{subset} at {offset} of {len} bytes runs past the end of the snapshot"
+                    ))
+                })?;
+            Ok((subset, range, expected))
+        })?;
+        check_overlap(ranges.each_ref().into_array())?;
+        let subsets = ranges.try_map(|(subset, range, expected)| {
+            if checksum.checksum(&bytes[range.clone()]) != expected {
+                return Err(CodecError::ChecksumMismatch { subset });
+            }
+            Ok(range)
+        })?;
+
+        Ok(Self {
+            codec_id,
+            subsets,
+            bytes,
+        })
+    }
+
+    pub fn from_reader(mut source: impl Read) -> Result<Self, CodecError> {
+        let mut bytes = vec![];
+        source.read_to_end(&mut bytes)?;
+        Self::new(bytes)
+    }
+
+    /// Id of the codec the snapshot was written with, pick the codec to `decode` with by it.
+    pub fn codec_id(&self) -> u8 {
+        self.codec_id
+    }
+
+    /// Encoded bytes of every subset.
+    pub fn subset_lens(&self) -> Data<usize> {
+        self.subsets.each_ref().map(|range| range.len())
+    }
+
+    /// The stream codecs read the subsets in place, parquet copies each one it decodes.
+    pub fn decode(
+        &self,
+        codec: &impl for<'a> PayloadCodec<&'a [u8], Vec<u8>>,
+    ) -> Result<Payload, CodecError> {
+        let readers = self
+            .subsets
+            .each_ref()
+            .map(|range| &self.bytes[range.clone()]);
+        codec.decode(readers)
+    }
+}
+
+/// Every subset has its own bytes, none of them is read as part of another.
+fn check_overlap(subsets: [&(&str, Range<usize>, u64); 6]) -> Result<(), CodecError> {
+    let mut subsets = subsets
+        .into_iter()
+        .filter(|(_, range, _)| !range.is_empty())
+        .collect::<Vec<_>>();
+    subsets.sort_by_key(|(_, range, _)| range.start);
+    for pair in subsets.windows(2) {
+        let [(first, first_range, _), (second, second_range, _)] = pair else {
+            unreachable!("windows of two");
+        };
+        if second_range.start < first_range.end {
+            return Err(invalid_data(format!("{second} overlaps {first}")));
+        }
+    }
+    Ok(())
+}
+
+fn invalid_data(msg: String) -> CodecError {
+    std::io::Error::new(ErrorKind::InvalidData, msg).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        encoding::{AnyCodec, BincodeCodec, ParquetCodec, ParquetCompression},
+        test_support::full_payload,
+    };
+
+    #[test]
+    fn payload_round_trips_through_a_snapshot() {
+        // given
+        let payload = full_payload(Data::from_array([3, 2, 4, 10, 5, 4]), 1);
+        let codec = AnyCodec::Parquet(ParquetCodec::new(2, ParquetCompression::Zstd(1)));
+
+        // when
+        let file = SnapshotWriter::new(vec![], codec.id())
+            .write(&codec, &payload)
+            .unwrap();
+
+        // then
+        let reader = SnapshotReader::from_reader(file.as_slice()).unwrap();
+        pretty_assertions::assert_eq!(reader.codec_id(), codec.id());
+        pretty_assertions::assert_eq!(
+            reader.subset_lens().into_array().iter().sum::<usize>(),
+            file.len() - HEADER_LEN
+        );
+        pretty_assertions::assert_eq!(reader.decode(&codec).unwrap(), payload);
+    }
+
+    #[test]
+    fn truncated_snapshots_are_rejected_before_decoding() {
+        // given
+        let payload = full_payload(Data::from_array([3, 0, 0, 0, 0, 0]), 2);
+        let mut file = SnapshotWriter::new(vec![], 2)
+            .write(&BincodeCodec::default(), &payload)
+            .unwrap();
+        file.pop();
+
+        // when
+        let result = SnapshotReader::new(file);
+
+        // then
+        let err = result.err().unwrap().to_string();
+        assert!(err.contains("runs past the end"), "{err}");
+    }
+
+    /// Overwrites the offset `SnapshotWriter` wrote for the subset at `idx` in `Data` order.
+    fn set_offset(file: &mut [u8], idx: usize, offset: u64) {
+        let start = MAGIC.len() + 3 + idx * 3 * 8;
+        file[start..start + 8].copy_from_slice(&offset.to_le_bytes());
+    }
+
+    #[test]
+    fn subsets_starting_inside_the_header_are_rejected() {
+        // given
+        let payload = full_payload(Data::from_array([3, 0, 0, 0, 0, 0]), 2);
+        let mut file = SnapshotWriter::new(vec![], 2)
+            .write(&BincodeCodec::default(), &payload)
+            .unwrap();
+        set_offset(&mut file, 0, 0);
+
+        // when
+        let result = SnapshotReader::new(file);
+
+        // then
+        let err = result.err().unwrap().to_string();
+        assert!(err.contains("coins at 0 starts inside the header"), "{err}");
+    }
+
+    #[test]
+    fn overlapping_subsets_are_rejected() {
+        // given
+        let payload = full_payload(Data::from_array([3, 2, 0, 0, 0, 0]), 2);
+        let mut file = SnapshotWriter::new(vec![], 2)
+            .write(&BincodeCodec::default(), &payload)
+            .unwrap();
+        set_offset(&mut file, 1, HEADER_LEN as u64 + 1);
+
+        // when
+        let result = SnapshotReader::new(file);
+
+        // then
+        let err = result.err().unwrap().to_string();
+        assert!(err.contains("messages overlaps coins"), "{err}");
+    }
+
+    #[test]
+    fn corrupted_subsets_fail_their_checksum() {
+        // given
+        let payload = full_payload(Data::from_array([3, 2, 0, 0, 0, 0]), 3);
+        let mut file = SnapshotWriter::new(vec![], 2)
+            .write(&BincodeCodec::default(), &payload)
+            .unwrap();
+        let messages = SnapshotReader::new(file.clone()).unwrap().subsets.messages;
+        file[messages.start + 1] ^= 0xff;
+
+        // when
+        let result = SnapshotReader::new(file);
+
+        // then
+        assert!(matches!(
+            result,
+            Err(CodecError::ChecksumMismatch { subset: "messages" })
+        ));
+    }
+
+    #[test]
+    fn other_files_are_not_mistaken_for_snapshots() {
+        // given
+        let bytes = vec![0; HEADER_LEN];
+
+        // when
+        let result = SnapshotReader::new(bytes);
+
+        // then
+        let err = result.err().unwrap().to_string();
+        assert!(err.contains("not a snapshot"), "{err}");
+    }
+}
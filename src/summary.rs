@@ -0,0 +1,176 @@
+use std::{
+    fmt,
+    time::{Duration, Instant},
+};
+
+use crate::measurements::{track_time, EncodeMeasurement};
+
+/// How long every codec took in every mode of a run, to tell which one a slow run spent its time
+/// on. Printed as a table once the run is over.
+pub struct RunSummary {
+    start: Instant,
+    rows: Vec<SummaryRow>,
+}
+
+struct SummaryRow {
+    codec: String,
+    mode: String,
+    elapsed: Duration,
+    /// Encoded over the whole sweep, `None` for runs that don't encode a payload per size.
+    bytes: Option<usize>,
+}
+
+impl Default for RunSummary {
+    fn default() -> Self {
+        Self {
+            start: Instant::now(),
+            rows: vec![],
+        }
+    }
+}
+
+impl RunSummary {
+    /// The total runtime is counted from here.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Times `run` and adds up the bytes of every measurement it returns.
+    pub fn track<E>(
+        &mut self,
+        codec: impl Into<String>,
+        mode: impl Into<String>,
+        run: impl FnOnce() -> Result<Vec<EncodeMeasurement>, E>,
+    ) -> Result<Vec<EncodeMeasurement>, E> {
+        let (elapsed, measurements) = track_time(run);
+        let measurements = measurements?;
+        let bytes = measurements.iter().map(|m| m.bytes).sum();
+        self.add(codec, mode, elapsed, Some(bytes));
+        Ok(measurements)
+    }
+
+    /// Times `run` alone, for the runs that don't produce encoded bytes worth adding up.
+    pub fn track_time<T>(
+        &mut self,
+        codec: impl Into<String>,
+        mode: impl Into<String>,
+        run: impl FnOnce() -> T,
+    ) -> T {
+        let (elapsed, ret) = track_time(run);
+        self.add(codec, mode, elapsed, None);
+        ret
+    }
+
+    pub fn add(
+        &mut self,
+        codec: impl Into<String>,
+        mode: impl Into<String>,
+        elapsed: Duration,
+        bytes: Option<usize>,
+    ) {
+        self.rows.push(SummaryRow {
+            codec: codec.into(),
+            mode: mode.into(),
+            elapsed,
+            bytes,
+        });
+    }
+
+    fn table(&self, total: Duration) -> Vec<[String; 4]> {
+        let header = ["codec", "mode", "elapsed", "bytes"].map(str::to_string);
+        let rows = self.rows.iter().map(|row| {
+            [
+                row.codec.clone(),
+                row.mode.clone(),
+                format!("{:.2?}", row.elapsed),
+                row.bytes.map_or_else(|| "-".to_string(), |b| b.to_string()),
+            ]
+        });
+        let total = [
+            "total".to_string(),
+            String::new(),
+            format!("{total:.2?}"),
+            String::new(),
+        ];
+        std::iter::once(header)
+            .chain(rows)
+            .chain(std::iter::once(total))
+            .collect()
+    }
+
+    fn write_table(&self, f: &mut impl fmt::Write, total: Duration) -> fmt::Result {
+        let table = self.table(total);
+        let widths: [usize; 4] = std::array::from_fn(|col| {
+            table
+                .iter()
+                .map(|row| row[col].chars().count())
+                .max()
+                .unwrap_or_default()
+        });
+        for [codec, mode, elapsed, bytes] in &table {
+            let line = format!(
+                "{codec:<w0$}  {mode:<w1$}  {elapsed:>w2$}  {bytes:>w3$}",
+                w0 = widths[0],
+                w1 = widths[1],
+                w2 = widths[2],
+                w3 = widths[3],
+            );
+            writeln!(f, "{}", line.trim_end())?;
+        }
+        Ok(())
+    }
+}
+
+/// The total is the time elapsed until the summary is printed.
+impl fmt::Display for RunSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write_table(f, self.start.elapsed())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn runs_are_lined_up_in_columns() {
+        // given
+        let mut summary = RunSummary::new();
+        summary.add("bincode", "normal", Duration::from_millis(1500), Some(1234));
+        summary.add("serde_json", "seek", Duration::from_micros(20), None);
+
+        // when
+        let mut table = String::new();
+        summary
+            .write_table(&mut table, Duration::from_secs(2))
+            .unwrap();
+
+        // then
+        pretty_assertions::assert_eq!(
+            table.lines().collect::<Vec<_>>(),
+            vec![
+                "codec       mode    elapsed  bytes",
+                "bincode     normal    1.50s   1234",
+                "serde_json  seek    20.00µs      -",
+                "total                 2.00s",
+            ]
+        );
+    }
+
+    #[test]
+    fn tracked_runs_add_up_their_bytes() {
+        // given
+        let mut summary = RunSummary::new();
+        let measurements = [10, 20]
+            .map(|bytes| EncodeMeasurement::new(bytes, bytes, Duration::ZERO, Duration::ZERO));
+
+        // when
+        let returned = summary
+            .track("bincode", "normal", || Ok::<_, ()>(measurements.to_vec()))
+            .unwrap();
+
+        // then
+        pretty_assertions::assert_eq!(returned.len(), 2);
+        pretty_assertions::assert_eq!(summary.rows[0].bytes, Some(30));
+    }
+}
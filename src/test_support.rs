@@ -0,0 +1,53 @@
+//! Seeded fixtures shared by the tests. Every builder takes the seed explicitly so a failing
+//! round trip can be replayed by rerunning it with the same seed.
+
+use std::iter::repeat_with;
+
+use rand::{rngs::StdRng, SeedableRng};
+
+use crate::{
+    serde_types::{CoinConfig, ContractConfig, MessageConfig, DEFAULT_CODE_LEN},
+    util::{payload_with, Data, Payload, PayloadConfig, PayloadRng},
+};
+
+/// For tests that tweak single entries, e.g. `..CoinConfig::random(&mut rng)`.
+pub fn seeded_rng(seed: u64) -> StdRng {
+    StdRng::seed_from_u64(seed)
+}
+
+pub fn coins(n: usize, seed: u64) -> Vec<CoinConfig> {
+    let mut rng = seeded_rng(seed);
+    repeat_with(|| CoinConfig::random(&mut rng))
+        .take(n)
+        .collect()
+}
+
+pub fn messages(n: usize, seed: u64) -> Vec<MessageConfig> {
+    let mut rng = seeded_rng(seed);
+    repeat_with(|| MessageConfig::random(&mut rng))
+        .take(n)
+        .collect()
+}
+
+pub fn contracts(n: usize, seed: u64) -> Vec<ContractConfig> {
+    let mut rng = seeded_rng(seed);
+    repeat_with(|| ContractConfig::random(&mut rng))
+        .take(n)
+        .collect()
+}
+
+/// A payload with `sizes` entries in the matching subsets, generated the same way the
+/// benchmarks generate theirs.
+pub fn full_payload(sizes: Data<usize>, seed: u64) -> Payload {
+    payload_with(&PayloadConfig {
+        seed,
+        rng: PayloadRng::Std,
+        coins: sizes.coins,
+        messages: sizes.messages,
+        contracts: sizes.contracts,
+        contract_state: sizes.contract_state,
+        contract_balance: sizes.contract_balance,
+        contract_state_root: sizes.contract_state_root,
+        code_len: DEFAULT_CODE_LEN..=DEFAULT_CODE_LEN,
+    })
+}
@@ -1,14 +1,21 @@
 use std::{
     io::{BufReader, Cursor},
     iter::repeat_with,
+    ops::RangeInclusive,
 };
 
-use flate2::{read::GzDecoder, write::GzEncoder, Compression};
 use fuel_types::{AssetId, Bytes32};
-use rand::Rng;
+use rand::{
+    rngs::{mock::StepRng, StdRng},
+    Rng, SeedableRng,
+};
 
-use crate::serde_types::{
-    CoinConfig, ContractBalance, ContractConfig, ContractState, MessageConfig,
+use crate::{
+    compression::{CompressionAlgo, Compressor, Decompressor},
+    serde_types::{
+        CoinConfig, ContractBalance, ContractConfig, ContractState, ContractStateRoot,
+        MessageConfig, StateEntry, DEFAULT_CODE_LEN,
+    },
 };
 
 pub fn random_bytes_32(rng: &mut impl Rng) -> Bytes32 {
@@ -32,165 +39,376 @@ impl std::io::Write for CountingSink {
     }
 }
 
+/// Adding a state type means a field here and in `Data`, `PayloadConfig` and `StateEntry`, a
+/// line in every `PayloadCodec` method and in `PerTypeMeasurement`, and a record listing for the
+/// avro and csv codecs. The parquet schema is derived.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
 pub struct Payload {
     pub coins: Vec<CoinConfig>,
     pub messages: Vec<MessageConfig>,
     pub contracts: Vec<ContractConfig>,
     pub contract_state: Vec<ContractState>,
     pub contract_balance: Vec<ContractBalance>,
+    pub contract_state_root: Vec<ContractStateRoot>,
 }
 
 impl Payload {
     pub fn num_entries(&self) -> usize {
-        self.coins.len() + self.messages.len() + self.contracts.len()
+        self.coins.len()
+            + self.messages.len()
+            + self.contracts.len()
+            + self.contract_state.len()
+            + self.contract_balance.len()
+            + self.contract_state_root.len()
+    }
+
+    /// Every entry as a `StateEntry`, one type after the other.
+    pub fn into_entries(self) -> impl Iterator<Item = StateEntry> {
+        let coins = self.coins.into_iter().map(StateEntry::Coin);
+        let messages = self.messages.into_iter().map(StateEntry::Message);
+        let contracts = self.contracts.into_iter().map(StateEntry::Contract);
+        let contract_state = self
+            .contract_state
+            .into_iter()
+            .map(StateEntry::ContractState);
+        let contract_balance = self
+            .contract_balance
+            .into_iter()
+            .map(StateEntry::ContractBalance);
+        let contract_state_root = self
+            .contract_state_root
+            .into_iter()
+            .map(StateEntry::ContractStateRoot);
+        coins
+            .chain(messages)
+            .chain(contracts)
+            .chain(contract_state)
+            .chain(contract_balance)
+            .chain(contract_state_root)
+    }
+
+    /// The entries of `other` after those of this payload, subset by subset.
+    pub fn merge(mut self, other: Payload) -> Payload {
+        self.coins.extend(other.coins);
+        self.messages.extend(other.messages);
+        self.contracts.extend(other.contracts);
+        self.contract_state.extend(other.contract_state);
+        self.contract_balance.extend(other.contract_balance);
+        self.contract_state_root.extend(other.contract_state_root);
+        self
+    }
+
+    /// Every subset cut into `k` consecutive shards whose lengths differ by one entry at most.
+    /// Merging the shards back in order gives the payload again. Panics if `k` is 0.
+    pub fn split_into(self, k: usize) -> Vec<Payload> {
+        const SHARD: &str = "split_evenly yields exactly k chunks";
+        let mut coins = split_evenly(self.coins, k);
+        let mut messages = split_evenly(self.messages, k);
+        let mut contracts = split_evenly(self.contracts, k);
+        let mut contract_state = split_evenly(self.contract_state, k);
+        let mut contract_balance = split_evenly(self.contract_balance, k);
+        let mut contract_state_root = split_evenly(self.contract_state_root, k);
+        (0..k)
+            .map(|_| Payload {
+                coins: coins.next().expect(SHARD),
+                messages: messages.next().expect(SHARD),
+                contracts: contracts.next().expect(SHARD),
+                contract_state: contract_state.next().expect(SHARD),
+                contract_balance: contract_balance.next().expect(SHARD),
+                contract_state_root: contract_state_root.next().expect(SHARD),
+            })
+            .collect()
     }
 }
 
+/// `items` in `k` consecutive chunks, the first `len % k` of them one item longer than the rest.
+fn split_evenly<T>(items: Vec<T>, k: usize) -> impl Iterator<Item = Vec<T>> {
+    assert!(k > 0, "a payload can't be split into 0 shards");
+    let (len, extra) = (items.len() / k, items.len() % k);
+    let mut items = items.into_iter();
+    (0..k).map(move |idx| {
+        items
+            .by_ref()
+            .take(len + usize::from(idx < extra))
+            .collect()
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Data<T> {
     pub coins: T,
     pub messages: T,
     pub contracts: T,
     pub contract_state: T,
     pub contract_balance: T,
+    pub contract_state_root: T,
 }
 
-impl Data<&mut Vec<u8>> {
+impl Data<()> {
+    /// Names of the fields, in the order of `from_array`.
+    pub const LABELS: [&'static str; 6] = [
+        "coins",
+        "messages",
+        "contracts",
+        "contract_state",
+        "contract_balance",
+        "contract_state_root",
+    ];
+}
+
+impl<T> Data<T> {
+    /// Fields in declaration order: coins, messages, contracts, contract state, contract balance,
+    /// contract state root.
+    pub fn from_array(
+        [coins, messages, contracts, contract_state, contract_balance, contract_state_root]: [T; 6],
+    ) -> Self {
+        Self {
+            coins,
+            messages,
+            contracts,
+            contract_state,
+            contract_balance,
+            contract_state_root,
+        }
+    }
+
+    /// Reverse of `from_array`.
+    pub fn into_array(self) -> [T; 6] {
+        [
+            self.coins,
+            self.messages,
+            self.contracts,
+            self.contract_state,
+            self.contract_balance,
+            self.contract_state_root,
+        ]
+    }
+
+    pub fn as_array(&self) -> [&T; 6] {
+        self.each_ref().into_array()
+    }
+
+    pub fn each_ref(&self) -> Data<&T> {
+        Data {
+            coins: &self.coins,
+            messages: &self.messages,
+            contracts: &self.contracts,
+            contract_state: &self.contract_state,
+            contract_balance: &self.contract_balance,
+            contract_state_root: &self.contract_state_root,
+        }
+    }
+
+    pub fn each_mut(&mut self) -> Data<&mut T> {
+        Data {
+            coins: &mut self.coins,
+            messages: &mut self.messages,
+            contracts: &mut self.contracts,
+            contract_state: &mut self.contract_state,
+            contract_balance: &mut self.contract_balance,
+            contract_state_root: &mut self.contract_state_root,
+        }
+    }
+
+    /// Applies `f` to every field, in the order of `into_array`.
+    pub fn map<U>(self, f: impl FnMut(T) -> U) -> Data<U> {
+        Data::from_array(self.into_array().map(f))
+    }
+
+    /// Same as `map` but stops at the first error.
+    pub fn try_map<U, E>(self, mut f: impl FnMut(T) -> Result<U, E>) -> Result<Data<U>, E> {
+        let [coins, messages, contracts, contract_state, contract_balance, contract_state_root] =
+            self.into_array();
+        Ok(Data {
+            coins: f(coins)?,
+            messages: f(messages)?,
+            contracts: f(contracts)?,
+            contract_state: f(contract_state)?,
+            contract_balance: f(contract_balance)?,
+            contract_state_root: f(contract_state_root)?,
+        })
+    }
+}
+
+/// Every state that holds the bytes themselves, owned, borrowed or mutably borrowed while a
+/// compressor writes into them.
+impl<T: AsRef<[u8]>> Data<T> {
+    /// Bytes across all the subsets.
     #[must_use]
     pub fn len(&self) -> usize {
-        self.coins.len()
-            + self.messages.len()
-            + self.contracts.len()
-            + self.contract_state.len()
-            + self.contract_balance.len()
+        self.as_array()
+            .iter()
+            .map(|buffer| buffer.as_ref().len())
+            .sum()
     }
 
     #[must_use]
     pub fn is_empty(&self) -> bool {
         self.len() == 0
     }
-    pub fn wrap_in_buffered_decompressor(&self) -> Data<BufReader<GzDecoder<&[u8]>>> {
-        Data {
-            coins: BufReader::new(GzDecoder::new(self.coins.as_slice())),
-            messages: BufReader::new(GzDecoder::new(self.messages.as_slice())),
-            contracts: BufReader::new(GzDecoder::new(self.contracts.as_slice())),
-            contract_state: BufReader::new(GzDecoder::new(self.contract_state.as_slice())),
-            contract_balance: BufReader::new(GzDecoder::new(self.contract_balance.as_slice())),
-        }
+}
+
+impl Data<&mut Vec<u8>> {
+    pub fn wrap_in_buffered_decompressor(
+        &self,
+        algo: CompressionAlgo,
+    ) -> std::io::Result<Data<BufReader<Decompressor<&[u8]>>>> {
+        self.each_ref()
+            .try_map(|buffer| Ok(BufReader::new(Decompressor::new(buffer.as_slice(), algo)?)))
     }
 }
 impl Data<Vec<u8>> {
     pub fn with_capacity(cap: usize) -> Self {
-        Self {
-            coins: Vec::with_capacity(cap),
-            messages: Vec::with_capacity(cap),
-            contracts: Vec::with_capacity(cap),
-            contract_state: Vec::with_capacity(cap),
-            contract_balance: Vec::with_capacity(cap),
-        }
+        Data::from_array(std::array::from_fn(|_| Vec::with_capacity(cap)))
     }
-    pub fn clear(&mut self) {
-        self.coins.clear();
-        self.messages.clear();
-        self.contracts.clear();
-        self.contract_state.clear();
-        self.contract_balance.clear();
+
+    /// Bytes reserved across all the buffers.
+    pub fn capacity(&self) -> usize {
+        self.as_array().iter().map(|buffer| buffer.capacity()).sum()
     }
 
-    pub fn len(&self) -> usize {
-        self.coins.len()
-            + self.messages.len()
-            + self.contracts.len()
-            + self.contract_state.len()
-            + self.contract_balance.len()
+    pub fn clear(&mut self) {
+        self.each_mut().map(Vec::clear);
     }
 
     pub fn as_ref(&self) -> Data<&[u8]> {
-        Data {
-            coins: self.coins.as_slice(),
-            messages: self.messages.as_slice(),
-            contracts: self.contracts.as_slice(),
-            contract_state: self.contract_state.as_slice(),
-            contract_balance: self.contract_balance.as_slice(),
-        }
+        self.each_ref().map(Vec::as_slice)
     }
 
-    pub fn wrap_in_compressor(&mut self, level: Compression) -> Data<GzEncoder<&mut Vec<u8>>> {
-        Data {
-            coins: GzEncoder::new(&mut self.coins, level),
-            messages: GzEncoder::new(&mut self.messages, level),
-            contracts: GzEncoder::new(&mut self.contracts, level),
-            contract_state: GzEncoder::new(&mut self.contract_state, level),
-            contract_balance: GzEncoder::new(&mut self.contract_balance, level),
-        }
+    pub fn wrap_in_compressor(
+        &mut self,
+        algo: CompressionAlgo,
+    ) -> std::io::Result<Data<Compressor<&mut Vec<u8>>>> {
+        self.each_mut()
+            .try_map(|buffer| Compressor::new(buffer, algo))
     }
 
     pub fn wrap_in_cursor(self) -> Data<Cursor<Vec<u8>>> {
-        Data {
-            coins: Cursor::new(self.coins),
-            messages: Cursor::new(self.messages),
-            contracts: Cursor::new(self.contracts),
-            contract_state: Cursor::new(self.contract_state),
-            contract_balance: Cursor::new(self.contract_balance),
-        }
+        self.map(Cursor::new)
     }
 }
 
-impl<'a> Data<GzEncoder<&'a mut Vec<u8>>> {
+impl Data<Cursor<Vec<u8>>> {
+    /// Reverse of `wrap_in_cursor`, the positions are dropped.
+    pub fn into_inner(self) -> Data<Vec<u8>> {
+        self.map(Cursor::into_inner)
+    }
+}
+
+impl<'a> Data<Compressor<&'a mut Vec<u8>>> {
     pub fn finish(self) -> std::io::Result<Data<&'a mut Vec<u8>>> {
-        Ok(Data {
-            coins: self.coins.finish()?,
-            messages: self.messages.finish()?,
-            contracts: self.contracts.finish()?,
-            contract_state: self.contract_state.finish()?,
-            contract_balance: self.contract_balance.finish()?,
-        })
+        self.try_map(Compressor::finish)
     }
 }
-impl Data<&mut Vec<u8>> {}
 
-pub fn payload(repeat: usize) -> Payload {
-    // let mut rng = rand::rngs::mock::StepRng::new(0, 1);
-    let mut rng = rand::thread_rng();
+/// Which generator `payload_with` draws the random fields from.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum PayloadRng {
+    /// A `StdRng` seeded with `PayloadConfig::seed`. Produces random looking bytes, similar to
+    /// real chain state.
+    #[default]
+    Std,
+    /// `StepRng::new(0, 1)`. Every field becomes an almost monotonic byte pattern which
+    /// compresses far better than real data would. Only here to reproduce older results.
+    Step,
+}
 
-    let coins = {
-        let mut rng = rng.clone();
-        repeat_with(move || CoinConfig::random(&mut rng))
-            .take(repeat / 3)
-            .collect()
-    };
-    let messages = {
-        let mut rng = rng.clone();
-        repeat_with(move || MessageConfig::random(&mut rng))
-            .take(repeat / 3)
-            .collect()
-    };
+/// Storage slots `PayloadConfig::new` gives every contract. Not taken from a real chain, picked so
+/// that contract state is the largest subset, as it is on chain, while a payload stays at about
+/// three times `repeat` entries.
+pub const STATE_SLOTS_PER_CONTRACT: usize = 4;
+/// Asset balances `PayloadConfig::new` gives every contract.
+pub const BALANCES_PER_CONTRACT: usize = 1;
 
-    let contracts = {
-        let mut rng_clone = rng.clone();
-        repeat_with(move || ContractConfig::random(&mut rng_clone))
-            .take(repeat / 3)
-            .collect()
-    };
+/// Controls how many entries of each kind `payload_with` generates.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PayloadConfig {
+    /// Seed for the `PayloadRng::Std` generator, so that the same config always yields the same
+    /// payload.
+    pub seed: u64,
+    pub rng: PayloadRng,
+    pub coins: usize,
+    pub messages: usize,
+    pub contracts: usize,
+    pub contract_state: usize,
+    pub contract_balance: usize,
+    pub contract_state_root: usize,
+    /// Bytes of code per contract, see `ContractConfig::random_with`.
+    pub code_len: RangeInclusive<usize>,
+}
 
-    let contract_state = {
-        let mut rng_clone = rng.clone();
-        // TODO: this number needs to be fixed to be per contract
-        repeat_with(move || ContractState {
-            key: random_bytes_32(&mut rng_clone),
-            value: random_bytes_32(&mut rng_clone),
-        })
-        .take(10_000)
-        .collect()
-    };
-    let contract_balance = {
-        // TODO: this number needs to be fixed to be per contract
-        repeat_with(|| ContractBalance {
-            asset_id: AssetId::new(*random_bytes_32(&mut rng)),
-            amount: rng.gen(),
-        })
-        .take(100)
-        .collect()
-    };
+impl PayloadConfig {
+    /// Splits `repeat` evenly between coins, messages and contracts. Every contract gets a state
+    /// root, `STATE_SLOTS_PER_CONTRACT` storage slots and `BALANCES_PER_CONTRACT` balances, so
+    /// every subset grows in proportion to `repeat`. The seed is picked at random, read it back
+    /// from `seed` to reproduce the payload.
+    pub fn new(repeat: usize) -> Self {
+        let contracts = repeat / 3;
+        Self {
+            seed: rand::random(),
+            rng: PayloadRng::default(),
+            coins: repeat / 3,
+            messages: repeat / 3,
+            contracts,
+            contract_state: contracts * STATE_SLOTS_PER_CONTRACT,
+            contract_balance: contracts * BALANCES_PER_CONTRACT,
+            contract_state_root: contracts,
+            code_len: DEFAULT_CODE_LEN..=DEFAULT_CODE_LEN,
+        }
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    pub fn with_rng(mut self, rng: PayloadRng) -> Self {
+        self.rng = rng;
+        self
+    }
+
+    pub fn with_code_len(mut self, code_len: RangeInclusive<usize>) -> Self {
+        self.code_len = code_len;
+        self
+    }
+}
+
+pub fn payload(repeat: usize) -> Payload {
+    payload_with(&PayloadConfig::new(repeat))
+}
+
+pub fn payload_with(config: &PayloadConfig) -> Payload {
+    match config.rng {
+        PayloadRng::Std => generate_payload(config, &mut StdRng::seed_from_u64(config.seed)),
+        PayloadRng::Step => generate_payload(config, &mut StepRng::new(0, 1)),
+    }
+}
+
+fn generate_payload(config: &PayloadConfig, rng: &mut impl Rng) -> Payload {
+    let coins = repeat_with(|| CoinConfig::random(rng))
+        .take(config.coins)
+        .collect();
+    let messages = repeat_with(|| MessageConfig::random(rng))
+        .take(config.messages)
+        .collect();
+    let contracts = repeat_with(|| ContractConfig::random_with(rng, config.code_len.clone()))
+        .take(config.contracts)
+        .collect();
+    let contract_state = repeat_with(|| ContractState {
+        key: random_bytes_32(rng),
+        value: random_bytes_32(rng),
+    })
+    .take(config.contract_state)
+    .collect();
+    let contract_balance = repeat_with(|| ContractBalance {
+        asset_id: AssetId::new(*random_bytes_32(rng)),
+        amount: rng.gen(),
+    })
+    .take(config.contract_balance)
+    .collect();
+    let contract_state_root = repeat_with(|| ContractStateRoot::random(rng))
+        .take(config.contract_state_root)
+        .collect();
 
     Payload {
         coins,
@@ -198,5 +416,224 @@ pub fn payload(repeat: usize) -> Payload {
         contracts,
         contract_state,
         contract_balance,
+        contract_state_root,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use flate2::Compression;
+
+    use super::*;
+    use crate::{
+        encoding::{BincodeCodec, PayloadCodec},
+        test_support::full_payload,
+    };
+
+    #[test]
+    fn data_map_keeps_the_fields_apart() {
+        // given
+        let data = Data::from_array([1, 2, 3, 4, 5, 6]);
+
+        // when
+        let mapped = data.map(|n| n * 10);
+
+        // then
+        pretty_assertions::assert_eq!(mapped.coins, 10);
+        pretty_assertions::assert_eq!(mapped.contract_state_root, 60);
+        pretty_assertions::assert_eq!(mapped.into_array(), [10, 20, 30, 40, 50, 60]);
+    }
+
+    #[test]
+    fn data_try_map_stops_at_the_first_error() {
+        // given
+        let data = Data::from_array([1, 2, 3, 4, 5, 6]);
+        let mut visited = vec![];
+
+        // when
+        let result = data.try_map(|n| {
+            visited.push(n);
+            if n == 3 {
+                Err(n)
+            } else {
+                Ok(n)
+            }
+        });
+
+        // then
+        assert!(matches!(result, Err(3)));
+        pretty_assertions::assert_eq!(visited, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn num_entries_counts_every_subset() {
+        // given
+        let payload = full_payload(Data::from_array([1, 2, 3, 4, 5, 6]), 1);
+
+        // when
+        let num_entries = payload.num_entries();
+
+        // then
+        assert_eq!(num_entries, 21);
+    }
+
+    #[test]
+    fn len_is_the_same_in_every_byte_state() {
+        // given
+        let mut data = Data::from_array([1, 2, 3, 4, 5, 6].map(|len| vec![0u8; len]));
+
+        // when
+        let borrowed = data.each_mut().len();
+        let sliced = data.as_ref().len();
+        let owned = data.len();
+        let unwrapped = data.wrap_in_cursor().into_inner().len();
+
+        // then
+        pretty_assertions::assert_eq!([borrowed, sliced, owned, unwrapped], [21; 4]);
+    }
+
+    #[test]
+    fn every_subset_grows_with_repeat() {
+        // when
+        let small = PayloadConfig::new(300);
+        let large = PayloadConfig::new(3_000);
+
+        // then
+        let counts = |config: &PayloadConfig| {
+            [
+                config.coins,
+                config.messages,
+                config.contracts,
+                config.contract_state,
+                config.contract_balance,
+                config.contract_state_root,
+            ]
+        };
+        pretty_assertions::assert_eq!(counts(&small), [100, 100, 100, 400, 100, 100]);
+        pretty_assertions::assert_eq!(counts(&large), counts(&small).map(|count| count * 10));
+    }
+
+    #[test]
+    fn payload_with_respects_counts_and_seed() {
+        // given
+        let config = PayloadConfig {
+            seed: 42,
+            rng: PayloadRng::Std,
+            coins: 1,
+            messages: 2,
+            contracts: 3,
+            contract_state: 1_000,
+            contract_balance: 0,
+            contract_state_root: 4,
+            code_len: 5..=9,
+        };
+
+        // when
+        let first = payload_with(&config);
+        let second = payload_with(&config);
+
+        // then
+        assert_eq!(first.coins.len(), 1);
+        assert_eq!(first.messages.len(), 2);
+        assert_eq!(first.contracts.len(), 3);
+        assert_eq!(first.contract_state.len(), 1_000);
+        assert!(first.contract_balance.is_empty());
+        assert_eq!(first.contract_state_root.len(), 4);
+        assert!(first
+            .contracts
+            .iter()
+            .all(|contract| (5..=9).contains(&contract.code.len())));
+        pretty_assertions::assert_eq!(first.coins, second.coins);
+        pretty_assertions::assert_eq!(first.contract_state, second.contract_state);
+    }
+
+    fn compression_round_trip(algo: CompressionAlgo) {
+        // given
+        let config = PayloadConfig {
+            seed: 7,
+            rng: PayloadRng::Std,
+            coins: 10,
+            messages: 10,
+            contracts: 10,
+            contract_state: 10,
+            contract_balance: 10,
+            contract_state_root: 10,
+            code_len: DEFAULT_CODE_LEN..=DEFAULT_CODE_LEN,
+        };
+        let expected = payload_with(&config);
+        let mut data = Data::with_capacity(0);
+
+        // when
+        let mut compressor = data.wrap_in_compressor(algo).unwrap();
+        PayloadCodec::<BufReader<Decompressor<&[u8]>>, _>::encode(
+            &BincodeCodec::default(),
+            &expected,
+            &mut compressor,
+        )
+        .unwrap();
+        let compressed = compressor.finish().unwrap();
+        let decoded = PayloadCodec::<_, Compressor<&mut Vec<u8>>>::decode(
+            &BincodeCodec::default(),
+            compressed.wrap_in_buffered_decompressor(algo).unwrap(),
+        )
+        .unwrap();
+
+        // then
+        pretty_assertions::assert_eq!(expected, decoded);
+    }
+
+    #[test]
+    fn merged_payloads_split_back_into_even_shards() {
+        // given
+        let first = full_payload(Data::from_array([7, 3, 2, 8, 2, 2]), 1);
+        let second = full_payload(Data::from_array([3, 0, 1, 4, 1, 1]), 2);
+
+        // when
+        let merged = first.clone().merge(second.clone());
+        let shards = merged.clone().split_into(4);
+
+        // then
+        let lens = |payload: &Payload| {
+            [
+                payload.coins.len(),
+                payload.messages.len(),
+                payload.contracts.len(),
+                payload.contract_state.len(),
+                payload.contract_balance.len(),
+                payload.contract_state_root.len(),
+            ]
+        };
+        pretty_assertions::assert_eq!(merged.coins, [first.coins, second.coins].concat());
+        pretty_assertions::assert_eq!(
+            shards.iter().map(lens).collect::<Vec<_>>(),
+            vec![
+                [3, 1, 1, 3, 1, 1],
+                [3, 1, 1, 3, 1, 1],
+                [2, 1, 1, 3, 1, 1],
+                [2, 0, 0, 3, 0, 0],
+            ]
+        );
+        let rejoined = shards.into_iter().reduce(Payload::merge).unwrap();
+        pretty_assertions::assert_eq!(rejoined, merged);
+    }
+
+    #[test]
+    fn gzip_round_trip() {
+        compression_round_trip(CompressionAlgo::Gzip(Compression::new(1)));
+    }
+
+    #[test]
+    fn zstd_round_trip() {
+        compression_round_trip(CompressionAlgo::Zstd(3));
+    }
+
+    #[test]
+    fn brotli_round_trip() {
+        compression_round_trip(CompressionAlgo::Brotli(5));
+    }
+
+    #[test]
+    fn snappy_round_trip() {
+        compression_round_trip(CompressionAlgo::Snappy);
     }
 }